@@ -0,0 +1,225 @@
+/*
+ * C API for ttspico-rs, the Rust bindings to the Pico TTS engine.
+ * Licensed under the Apache License, Version 2.0.
+ */
+
+#ifndef TTSPICO_H
+#define TTSPICO_H
+
+/* Generated by cbindgen from ttspico-capi; do not edit by hand. */
+
+#include <stddef.h>
+#include <stdint.h>
+
+/*
+ * Sample rate of Pico's output, in Hz.
+ */
+#define TTSPICO_SAMPLE_RATE 16000
+
+/*
+ * Headerless 16-bit signed little-endian PCM, mono, at [`TTSPICO_SAMPLE_RATE`].
+ */
+#define TTSPICO_FORMAT_RAW 0
+
+/*
+ * A WAV file (16-bit signed PCM, mono, at [`TTSPICO_SAMPLE_RATE`]), whose header gives an unknown length.
+ */
+#define TTSPICO_FORMAT_WAV 1
+
+/*
+ * Success.
+ */
+#define TTSPICO_OK 0
+
+/*
+ * An error of the Rust bindings themselves (i.e. invalid SSML).
+ */
+#define TTSPICO_ERR_BINDINGS -1
+
+/*
+ * An argument is invalid, i.e. `NULL` or not UTF-8.
+ */
+#define TTSPICO_ERR_INVALID_ARGUMENT -1001
+
+/*
+ * There is no voice for the requested language.
+ */
+#define TTSPICO_ERR_NO_VOICE -1002
+
+/*
+ * The write callback stopped the synthesis, by returning non-zero.
+ */
+#define TTSPICO_ERR_ABORTED -1003
+
+/*
+ * A bug in ttspico-capi (which should be reported).
+ */
+#define TTSPICO_ERR_INTERNAL -1004
+
+/*
+ * Flag of `ttspico_synth_speak()`: the text is SSML (instead of Pico's markup).
+ */
+#define TTSPICO_SPEAK_SSML 1
+
+/*
+ * The voices installed in some directories, one per language.
+ */
+typedef struct TtspicoCatalog TtspicoCatalog;
+
+/*
+ * A synthesizer, speaking with the voices of a catalog.
+ *
+ * Its Pico system lives on a thread of its own, so synthesizers can be used from any thread (even concurrently,
+ * their requests being spoken one at a time) and a process can have any number of them.
+ */
+typedef struct TtspicoSynth TtspicoSynth;
+
+/*
+ * The outcome of a call: [`TTSPICO_OK`], or a negative error code.
+ *
+ * Errors raised by Pico keep its status codes (the `PICO_EXC_*` and `PICO_ERR_*` of `picodefs.h`, i.e. -30 if
+ * it is out of memory); the others are the `TTSPICO_ERR_*` below.
+ */
+typedef int32_t TtspicoStatus;
+
+/*
+ * An output format: one of the `TTSPICO_FORMAT_*` below.
+ */
+typedef uint32_t TtspicoFormat;
+
+/*
+ * Called with each chunk of the encoded speech, as it is synthesized; returns 0 to go on, or non-zero to stop
+ * the synthesis.
+ */
+typedef int32_t (*TtspicoWriteCallback)(void *user_data, const uint8_t *data, size_t len);
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/*
+ * Lists the voices installed in `lang_dir`, one per language.
+ *
+ * If `lang_dir` is `NULL`, voices are looked for in `$TTSPICO_LANG_DIR`, `/usr/share/pico/lang`, then the ones
+ * bundled with Pico's sources. On success, `*out_catalog` is set to the catalog, to be freed with
+ * `ttspico_catalog_free()`.
+ *
+ * # Safety
+ * `lang_dir` must be `NULL` or a NUL-terminated string; `out_catalog` must be a valid pointer.
+ */
+TtspicoStatus ttspico_catalog_new(const char *lang_dir, TtspicoCatalog **out_catalog);
+
+/*
+ * Returns the number of voices in `catalog` (0 if it is `NULL`).
+ *
+ * # Safety
+ * `catalog` must be `NULL` or a catalog returned by `ttspico_catalog_new()`.
+ */
+size_t ttspico_catalog_len(const TtspicoCatalog *catalog);
+
+/*
+ * Returns the language of the `index`-th voice of `catalog` (i.e. `"en-US"`), or `NULL` if there is no such
+ * voice. Voices are sorted by language.
+ *
+ * The string is valid until the catalog is freed.
+ *
+ * # Safety
+ * `catalog` must be `NULL` or a catalog returned by `ttspico_catalog_new()`.
+ */
+const char *ttspico_catalog_language(const TtspicoCatalog *catalog, size_t index);
+
+/*
+ * Returns the speaker of the `index`-th voice of `catalog` (i.e. `"lh0"`), or `NULL` if there is no such voice.
+ *
+ * The string is valid until the catalog is freed.
+ *
+ * # Safety
+ * `catalog` must be `NULL` or a catalog returned by `ttspico_catalog_new()`.
+ */
+const char *ttspico_catalog_speaker(const TtspicoCatalog *catalog, size_t index);
+
+/*
+ * Frees `catalog` (nothing if it is `NULL`).
+ *
+ * # Safety
+ * `catalog` must be `NULL` or a catalog returned by `ttspico_catalog_new()`, not freed before.
+ */
+void ttspico_catalog_free(TtspicoCatalog *catalog);
+
+/*
+ * Returns the message of the last error on the calling thread, or an empty string if its last call succeeded.
+ *
+ * The string is valid until the next call returning a [`TtspicoStatus`] on the thread.
+ */
+const char *ttspico_last_error(void);
+
+/*
+ * Converts `ssml` to Pico's markup (see `ttspico::ssml_to_markup()` for what is supported).
+ *
+ * On success, `*out_markup` is set to the markup, to be freed with `ttspico_string_free()`.
+ *
+ * # Safety
+ * `ssml` must be a NUL-terminated string; `out_markup` must be a valid pointer.
+ */
+TtspicoStatus ttspico_ssml_to_markup(const char *ssml, char **out_markup);
+
+/*
+ * Frees a string returned by ttspico-capi (nothing if it is `NULL`).
+ *
+ * # Safety
+ * `string` must be `NULL` or a string returned by ttspico-capi to be freed, not freed before.
+ */
+void ttspico_string_free(char *string);
+
+/*
+ * Creates a synthesizer with the voices of `catalog`, loading all of them.
+ *
+ * `default_language` is the language to speak when none is given (or, if `NULL`, the first of the catalog).
+ * On success, `*out_synth` is set to the synthesizer, to be freed with `ttspico_synth_free()`; `catalog` can be
+ * freed right away.
+ *
+ * # Safety
+ * `catalog` must be a catalog returned by `ttspico_catalog_new()`; `default_language` must be `NULL` or a
+ * NUL-terminated string; `out_synth` must be a valid pointer.
+ */
+TtspicoStatus ttspico_synth_new(const TtspicoCatalog *catalog,
+                                const char *default_language,
+                                TtspicoSynth **out_synth);
+
+/*
+ * Speaks `text` with the voice of `language` (or of a dialect of it; if `NULL`, the synthesizer's default
+ * language), calling `write` with each chunk of the speech in `format` as it is synthesized, and `user_data`.
+ *
+ * `text` has Pico's markup, or is SSML with the [`TTSPICO_SPEAK_SSML`] flag. Returns once the speech is over,
+ * or with [`TTSPICO_ERR_ABORTED`] as soon as `write` returns non-zero.
+ *
+ * # Safety
+ * `synth` must be a synthesizer returned by `ttspico_synth_new()`; `language` must be `NULL` or a
+ * NUL-terminated string, and `text` one; `write` must be safe to call with `user_data`.
+ */
+TtspicoStatus ttspico_synth_speak(const TtspicoSynth *synth,
+                                  const char *language,
+                                  const char *text,
+                                  uint32_t flags,
+                                  TtspicoFormat format,
+                                  TtspicoWriteCallback write,
+                                  void *user_data);
+
+/*
+ * Frees `synth` (nothing if it is `NULL`), waiting for the synthesis in progress (if any) to stop.
+ *
+ * # Safety
+ * `synth` must be `NULL` or a synthesizer returned by `ttspico_synth_new()`, not freed before nor in use.
+ */
+void ttspico_synth_free(TtspicoSynth *synth);
+
+/*
+ * Returns the version of ttspico-capi (i.e. `"0.1.5"`), as a static string.
+ */
+const char *ttspico_version(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* TTSPICO_H */
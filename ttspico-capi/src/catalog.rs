@@ -0,0 +1,122 @@
+//! The catalog of the installed voices.
+
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::error::{self, TtspicoStatus};
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::path::Path;
+use ttspico::VoiceCatalog;
+
+/// The voices installed in some directories, one per language.
+pub struct TtspicoCatalog {
+    pub(crate) catalog: VoiceCatalog,
+    /// The language and speaker of each voice, as returned to C.
+    names: Vec<(CString, CString)>,
+}
+
+impl TtspicoCatalog {
+    pub(crate) fn new(catalog: VoiceCatalog) -> Self {
+        // (File names cannot have NULs in them)
+        let names = catalog
+            .voices()
+            .iter()
+            .map(|voice| {
+                (
+                    CString::new(voice.language.as_str()).unwrap(),
+                    CString::new(voice.speaker.as_str()).unwrap(),
+                )
+            })
+            .collect();
+        TtspicoCatalog { catalog, names }
+    }
+}
+
+/// Lists the voices installed in `lang_dir`, one per language.
+///
+/// If `lang_dir` is `NULL`, voices are looked for in `$TTSPICO_LANG_DIR`, `/usr/share/pico/lang`, then the ones
+/// bundled with Pico's sources. On success, `*out_catalog` is set to the catalog, to be freed with
+/// `ttspico_catalog_free()`.
+///
+/// # Safety
+/// `lang_dir` must be `NULL` or a NUL-terminated string; `out_catalog` must be a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn ttspico_catalog_new(
+    lang_dir: *const c_char,
+    out_catalog: *mut *mut TtspicoCatalog,
+) -> TtspicoStatus {
+    error::guard(|| {
+        error::out_arg(out_catalog, "out_catalog")?;
+        let lang_dir = error::opt_str_arg(lang_dir, "lang_dir")?.map(Path::new);
+        let dirs = ttspico::lang_search_dirs(lang_dir);
+        *out_catalog = Box::into_raw(Box::new(TtspicoCatalog::new(VoiceCatalog::new(dirs))));
+        Ok(())
+    })
+}
+
+/// Returns the number of voices in `catalog` (0 if it is `NULL`).
+///
+/// # Safety
+/// `catalog` must be `NULL` or a catalog returned by `ttspico_catalog_new()`.
+#[no_mangle]
+pub unsafe extern "C" fn ttspico_catalog_len(catalog: *const TtspicoCatalog) -> usize {
+    catalog.as_ref().map_or(0, |catalog| catalog.names.len())
+}
+
+/// Returns the language of the `index`-th voice of `catalog` (i.e. `"en-US"`), or `NULL` if there is no such
+/// voice. Voices are sorted by language.
+///
+/// The string is valid until the catalog is freed.
+///
+/// # Safety
+/// `catalog` must be `NULL` or a catalog returned by `ttspico_catalog_new()`.
+#[no_mangle]
+pub unsafe extern "C" fn ttspico_catalog_language(
+    catalog: *const TtspicoCatalog,
+    index: usize,
+) -> *const c_char {
+    catalog
+        .as_ref()
+        .and_then(|catalog| catalog.names.get(index))
+        .map_or(std::ptr::null(), |(language, _)| language.as_ptr())
+}
+
+/// Returns the speaker of the `index`-th voice of `catalog` (i.e. `"lh0"`), or `NULL` if there is no such voice.
+///
+/// The string is valid until the catalog is freed.
+///
+/// # Safety
+/// `catalog` must be `NULL` or a catalog returned by `ttspico_catalog_new()`.
+#[no_mangle]
+pub unsafe extern "C" fn ttspico_catalog_speaker(
+    catalog: *const TtspicoCatalog,
+    index: usize,
+) -> *const c_char {
+    catalog
+        .as_ref()
+        .and_then(|catalog| catalog.names.get(index))
+        .map_or(std::ptr::null(), |(_, speaker)| speaker.as_ptr())
+}
+
+/// Frees `catalog` (nothing if it is `NULL`).
+///
+/// # Safety
+/// `catalog` must be `NULL` or a catalog returned by `ttspico_catalog_new()`, not freed before.
+#[no_mangle]
+pub unsafe extern "C" fn ttspico_catalog_free(catalog: *mut TtspicoCatalog) {
+    if !catalog.is_null() {
+        drop(Box::from_raw(catalog));
+    }
+}
@@ -0,0 +1,58 @@
+//! Encoding of synthesized speech in the supported output formats, as a stream.
+
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::error::Error;
+
+/// Sample rate of Pico's output, in Hz.
+pub const TTSPICO_SAMPLE_RATE: u32 = 16000;
+// (`ttspico::SAMPLE_RATE`, spelled out so that cbindgen can write its value)
+const _: () = assert!(TTSPICO_SAMPLE_RATE == ttspico::SAMPLE_RATE);
+
+/// An output format: one of the `TTSPICO_FORMAT_*` below.
+pub type TtspicoFormat = u32;
+
+/// Headerless 16-bit signed little-endian PCM, mono, at [`TTSPICO_SAMPLE_RATE`].
+pub const TTSPICO_FORMAT_RAW: TtspicoFormat = 0;
+/// A WAV file (16-bit signed PCM, mono, at [`TTSPICO_SAMPLE_RATE`]), whose header gives an unknown length.
+pub const TTSPICO_FORMAT_WAV: TtspicoFormat = 1;
+
+/// Checks that `format` is supported.
+pub(crate) fn check_format(format: TtspicoFormat) -> Result<(), Error> {
+    match format {
+        TTSPICO_FORMAT_RAW | TTSPICO_FORMAT_WAV => Ok(()),
+        _ => Err(Error::invalid_argument(format!(
+            "unknown format {}",
+            format
+        ))),
+    }
+}
+
+/// Returns the bytes starting a stream in `format`, before any samples.
+pub(crate) fn header(format: TtspicoFormat) -> Vec<u8> {
+    match format {
+        // (The length of the speech is not known in advance)
+        TTSPICO_FORMAT_WAV => ttspico::wav_header(None).to_vec(),
+        _ => Vec::new(),
+    }
+}
+
+/// Encodes `samples` (both supported formats store them the same way).
+pub(crate) fn samples(samples: &[i16]) -> Vec<u8> {
+    samples
+        .iter()
+        .flat_map(|sample| sample.to_le_bytes())
+        .collect()
+}
@@ -0,0 +1,134 @@
+//! Status codes, and the last error message of each thread.
+
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use ttspico::PicoError;
+
+/// The outcome of a call: [`TTSPICO_OK`], or a negative error code.
+///
+/// Errors raised by Pico keep its status codes (the `PICO_EXC_*` and `PICO_ERR_*` of `picodefs.h`, i.e. -30 if
+/// it is out of memory); the others are the `TTSPICO_ERR_*` below.
+pub type TtspicoStatus = i32;
+
+/// Success.
+pub const TTSPICO_OK: TtspicoStatus = 0;
+/// An error of the Rust bindings themselves (i.e. invalid SSML).
+pub const TTSPICO_ERR_BINDINGS: TtspicoStatus = -1;
+/// An argument is invalid, i.e. `NULL` or not UTF-8.
+pub const TTSPICO_ERR_INVALID_ARGUMENT: TtspicoStatus = -1001;
+/// There is no voice for the requested language.
+pub const TTSPICO_ERR_NO_VOICE: TtspicoStatus = -1002;
+/// The write callback stopped the synthesis, by returning non-zero.
+pub const TTSPICO_ERR_ABORTED: TtspicoStatus = -1003;
+/// A bug in ttspico-capi (which should be reported).
+pub const TTSPICO_ERR_INTERNAL: TtspicoStatus = -1004;
+
+/// An error, as returned to C.
+#[derive(Debug)]
+pub(crate) struct Error {
+    pub status: TtspicoStatus,
+    pub message: String,
+}
+
+impl Error {
+    pub fn new(status: TtspicoStatus, message: impl Into<String>) -> Self {
+        Error {
+            status,
+            message: message.into(),
+        }
+    }
+
+    pub fn invalid_argument(message: impl Into<String>) -> Self {
+        Error::new(TTSPICO_ERR_INVALID_ARGUMENT, message)
+    }
+}
+
+impl From<PicoError> for Error {
+    fn from(err: PicoError) -> Self {
+        Error {
+            status: err.code,
+            message: err.descr,
+        }
+    }
+}
+
+thread_local! {
+    /// The message of the last error on this thread (empty if the last call succeeded).
+    static LAST_ERROR: RefCell<CString> = RefCell::new(CString::default());
+}
+
+/// Runs the body of an exported function, returning its status and setting the thread's last error.
+pub(crate) fn guard(body: impl FnOnce() -> Result<(), Error>) -> TtspicoStatus {
+    // (Unwinding into C would abort)
+    let result = panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or_else(|_| {
+        Err(Error::new(
+            TTSPICO_ERR_INTERNAL,
+            "ttspico-capi panicked (this is a bug)",
+        ))
+    });
+    let (status, message) = match result {
+        Ok(()) => (TTSPICO_OK, String::new()),
+        Err(err) => (err.status, err.message),
+    };
+    LAST_ERROR.with(|last_error| {
+        // (Pico's messages, like any other, cannot have NULs in them; just in case, they are cut there)
+        let message = message.split('\0').next().unwrap_or("");
+        *last_error.borrow_mut() = CString::new(message).unwrap_or_default();
+    });
+    status
+}
+
+/// Returns the string argument `arg` (named `name`), which must not be `NULL`.
+pub(crate) unsafe fn str_arg<'a>(arg: *const c_char, name: &str) -> Result<&'a str, Error> {
+    if arg.is_null() {
+        return Err(Error::invalid_argument(format!("`{}` is NULL", name)));
+    }
+    CStr::from_ptr(arg)
+        .to_str()
+        .map_err(|err| Error::invalid_argument(format!("`{}` is not UTF-8: {}", name, err)))
+}
+
+/// Returns the string argument `arg` (named `name`), or `None` if it is `NULL`.
+pub(crate) unsafe fn opt_str_arg<'a>(
+    arg: *const c_char,
+    name: &str,
+) -> Result<Option<&'a str>, Error> {
+    if arg.is_null() {
+        Ok(None)
+    } else {
+        str_arg(arg, name).map(Some)
+    }
+}
+
+/// Checks that the output argument `out` (named `name`) is not `NULL`.
+pub(crate) fn out_arg<T>(out: *mut T, name: &str) -> Result<(), Error> {
+    if out.is_null() {
+        Err(Error::invalid_argument(format!("`{}` is NULL", name)))
+    } else {
+        Ok(())
+    }
+}
+
+/// Returns the message of the last error on the calling thread, or an empty string if its last call succeeded.
+///
+/// The string is valid until the next call returning a [`TtspicoStatus`] on the thread.
+#[no_mangle]
+pub extern "C" fn ttspico_last_error() -> *const c_char {
+    LAST_ERROR.with(|last_error| last_error.borrow().as_ptr())
+}
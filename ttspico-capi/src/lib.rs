@@ -0,0 +1,46 @@
+//! A C API for [`ttspico`]: a catalog of the installed voices, synthesizers streaming speech (as PCM or WAV) to a
+//! callback, and the conversion of SSML to Pico's markup.
+//!
+//! Its header is `include/ttspico.h`, generated by cbindgen (see `cbindgen.toml`). Objects are opaque handles,
+//! created by `ttspico_*_new()` and freed by `ttspico_*_free()`; fallible functions return a [`TtspicoStatus`],
+//! with a message in [`ttspico_last_error`]. The crate builds as both a shared and a static library.
+
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod catalog;
+pub use catalog::*;
+
+mod encode;
+pub use encode::{TtspicoFormat, TTSPICO_FORMAT_RAW, TTSPICO_FORMAT_WAV, TTSPICO_SAMPLE_RATE};
+
+mod error;
+pub use error::{
+    ttspico_last_error, TtspicoStatus, TTSPICO_ERR_ABORTED, TTSPICO_ERR_BINDINGS,
+    TTSPICO_ERR_INTERNAL, TTSPICO_ERR_INVALID_ARGUMENT, TTSPICO_ERR_NO_VOICE, TTSPICO_OK,
+};
+
+mod markup;
+pub use markup::*;
+
+mod synth;
+pub use synth::*;
+
+use std::os::raw::c_char;
+
+/// Returns the version of ttspico-capi (i.e. `"0.1.5"`), as a static string.
+#[no_mangle]
+pub extern "C" fn ttspico_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const c_char
+}
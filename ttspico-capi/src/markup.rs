@@ -0,0 +1,51 @@
+//! Conversion of SSML to Pico's markup, and the strings returned to C.
+
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::error::{self, Error, TtspicoStatus};
+use std::ffi::CString;
+use std::os::raw::c_char;
+
+/// Converts `ssml` to Pico's markup (see `ttspico::ssml_to_markup()` for what is supported).
+///
+/// On success, `*out_markup` is set to the markup, to be freed with `ttspico_string_free()`.
+///
+/// # Safety
+/// `ssml` must be a NUL-terminated string; `out_markup` must be a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn ttspico_ssml_to_markup(
+    ssml: *const c_char,
+    out_markup: *mut *mut c_char,
+) -> TtspicoStatus {
+    error::guard(|| {
+        error::out_arg(out_markup, "out_markup")?;
+        let markup = ttspico::ssml_to_markup(error::str_arg(ssml, "ssml")?)?;
+        let markup = CString::new(markup)
+            .map_err(|_| Error::invalid_argument("`ssml` decodes to a NUL character"))?;
+        *out_markup = markup.into_raw();
+        Ok(())
+    })
+}
+
+/// Frees a string returned by ttspico-capi (nothing if it is `NULL`).
+///
+/// # Safety
+/// `string` must be `NULL` or a string returned by ttspico-capi to be freed, not freed before.
+#[no_mangle]
+pub unsafe extern "C" fn ttspico_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
@@ -0,0 +1,246 @@
+//! Synthesizers, each with a thread owning its Pico system.
+
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::catalog::TtspicoCatalog;
+use crate::encode::{self, TtspicoFormat};
+use crate::error::{self, Error, TtspicoStatus};
+use crate::error::{TTSPICO_ERR_ABORTED, TTSPICO_ERR_INTERNAL, TTSPICO_ERR_NO_VOICE};
+use std::ffi::c_void;
+use std::os::raw::c_char;
+use std::sync::mpsc;
+use ttspico::{MultiVoiceSynthesizer, PicoError, SynthesizerPool, VoiceCatalog};
+
+/// Flag of `ttspico_synth_speak()`: the text is SSML (instead of Pico's markup).
+pub const TTSPICO_SPEAK_SSML: u32 = 1;
+
+/// Called with each chunk of the encoded speech, as it is synthesized; returns 0 to go on, or non-zero to stop
+/// the synthesis.
+pub type TtspicoWriteCallback =
+    Option<unsafe extern "C" fn(user_data: *mut c_void, data: *const u8, len: usize) -> i32>;
+
+/// Number of chunks of samples the synthesis thread can be ahead of the write callback.
+const MAX_PENDING_CHUNKS: usize = 16;
+
+/// What the synthesis thread sends back while speaking.
+enum Event {
+    /// The next samples.
+    Audio(Vec<i16>),
+    /// The end of the speech (or its error).
+    Done(Result<(), PicoError>),
+}
+
+/// A request to speak.
+struct Job {
+    /// The voice (i.e. language) to speak with.
+    language: String,
+    /// The text to speak, with Pico's markup.
+    markup: String,
+    events: mpsc::SyncSender<Event>,
+}
+
+/// Speaks `job`, sending its samples as they are synthesized, until done or the receiver is gone.
+fn speak(synth: &mut MultiVoiceSynthesizer, job: &Job) -> Result<(), PicoError> {
+    // (Whatever a stopped synthesis left in the engine is discarded by a full reset)
+    let engine = synth.engine(&job.language)?;
+    let mut input = job.markup.as_bytes();
+    let mut pcm_buf = [0i16; 1024];
+    loop {
+        let (n_samples, done) = engine.step(&mut input, &mut pcm_buf[..])?;
+        if n_samples > 0
+            && job
+                .events
+                .send(Event::Audio(pcm_buf[..n_samples].to_vec()))
+                .is_err()
+        {
+            // (The write callback stopped the synthesis)
+            return Ok(());
+        }
+        if done {
+            return Ok(());
+        }
+    }
+}
+
+/// A synthesizer, speaking with the voices of a catalog.
+///
+/// Its Pico system lives on a thread of its own, so synthesizers can be used from any thread (even concurrently,
+/// their requests being spoken one at a time) and a process can have any number of them.
+pub struct TtspicoSynth {
+    /// The synthesizer's voices.
+    catalog: VoiceCatalog,
+    default_language: String,
+    jobs: SynthesizerPool<Job>,
+}
+
+impl TtspicoSynth {
+    fn new(catalog: &TtspicoCatalog, default_language: Option<&str>) -> Result<Self, Error> {
+        let catalog = catalog.catalog.clone();
+        let default_language = match default_language {
+            Some(language) => catalog.find_voice(language),
+            None => catalog.voices().first(),
+        }
+        .ok_or_else(|| {
+            Error::new(
+                TTSPICO_ERR_NO_VOICE,
+                format!(
+                    "no voice for {}",
+                    default_language.unwrap_or("any language")
+                ),
+            )
+        })?
+        .language
+        .clone();
+
+        let memsz = catalog.system_builder().measure()?;
+        let jobs = SynthesizerPool::start(1, memsz, &catalog, |job: Job, synth| {
+            let result = speak(synth, &job);
+            let _ = job.events.send(Event::Done(result));
+        })?;
+        Ok(TtspicoSynth {
+            catalog,
+            default_language,
+            jobs,
+        })
+    }
+
+    /// Speaks `markup` with the voice of `language`, writing it to `write` in `format` as it is synthesized.
+    fn speak(
+        &self,
+        language: &str,
+        markup: String,
+        format: TtspicoFormat,
+        mut write: impl FnMut(&[u8]) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let (events_tx, events_rx) = mpsc::sync_channel(MAX_PENDING_CHUNKS);
+        let job = Job {
+            language: language.to_string(),
+            markup,
+            events: events_tx,
+        };
+        self.jobs.send(job).map_err(|_| stopped())?;
+        write(&encode::header(format))?;
+        // (On errors, `events_rx` is dropped, which stops the synthesis)
+        loop {
+            match events_rx.recv().map_err(|_| stopped())? {
+                Event::Audio(samples) => write(&encode::samples(&samples))?,
+                Event::Done(result) => return result.map_err(Error::from),
+            }
+        }
+    }
+}
+
+fn stopped() -> Error {
+    Error::new(TTSPICO_ERR_INTERNAL, "the synthesis thread stopped")
+}
+
+/// Creates a synthesizer with the voices of `catalog`, loading all of them.
+///
+/// `default_language` is the language to speak when none is given (or, if `NULL`, the first of the catalog).
+/// On success, `*out_synth` is set to the synthesizer, to be freed with `ttspico_synth_free()`; `catalog` can be
+/// freed right away.
+///
+/// # Safety
+/// `catalog` must be a catalog returned by `ttspico_catalog_new()`; `default_language` must be `NULL` or a
+/// NUL-terminated string; `out_synth` must be a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn ttspico_synth_new(
+    catalog: *const TtspicoCatalog,
+    default_language: *const c_char,
+    out_synth: *mut *mut TtspicoSynth,
+) -> TtspicoStatus {
+    error::guard(|| {
+        error::out_arg(out_synth, "out_synth")?;
+        let catalog = catalog
+            .as_ref()
+            .ok_or_else(|| Error::invalid_argument("`catalog` is NULL"))?;
+        let default_language = error::opt_str_arg(default_language, "default_language")?;
+        *out_synth = Box::into_raw(Box::new(TtspicoSynth::new(catalog, default_language)?));
+        Ok(())
+    })
+}
+
+/// Speaks `text` with the voice of `language` (or of a dialect of it; if `NULL`, the synthesizer's default
+/// language), calling `write` with each chunk of the speech in `format` as it is synthesized, and `user_data`.
+///
+/// `text` has Pico's markup, or is SSML with the [`TTSPICO_SPEAK_SSML`] flag. Returns once the speech is over,
+/// or with [`TTSPICO_ERR_ABORTED`] as soon as `write` returns non-zero.
+///
+/// # Safety
+/// `synth` must be a synthesizer returned by `ttspico_synth_new()`; `language` must be `NULL` or a
+/// NUL-terminated string, and `text` one; `write` must be safe to call with `user_data`.
+#[no_mangle]
+pub unsafe extern "C" fn ttspico_synth_speak(
+    synth: *const TtspicoSynth,
+    language: *const c_char,
+    text: *const c_char,
+    flags: u32,
+    format: TtspicoFormat,
+    write: TtspicoWriteCallback,
+    user_data: *mut c_void,
+) -> TtspicoStatus {
+    error::guard(|| {
+        let synth = synth
+            .as_ref()
+            .ok_or_else(|| Error::invalid_argument("`synth` is NULL"))?;
+        let text = error::str_arg(text, "text")?;
+        let write = write.ok_or_else(|| Error::invalid_argument("`write` is NULL"))?;
+        if flags & !TTSPICO_SPEAK_SSML != 0 {
+            return Err(Error::invalid_argument(format!(
+                "unknown flags {:#x}",
+                flags
+            )));
+        }
+        encode::check_format(format)?;
+        let language = match error::opt_str_arg(language, "language")? {
+            Some(language) => synth
+                .catalog
+                .find_voice(language)
+                .ok_or_else(|| {
+                    Error::new(TTSPICO_ERR_NO_VOICE, format!("no voice for {}", language))
+                })?
+                .language
+                .as_str(),
+            None => &synth.default_language,
+        };
+        let markup = if flags & TTSPICO_SPEAK_SSML != 0 {
+            ttspico::ssml_to_markup(text)?
+        } else {
+            text.to_string()
+        };
+
+        synth.speak(language, markup, format, |bytes| {
+            if bytes.is_empty() || write(user_data, bytes.as_ptr(), bytes.len()) == 0 {
+                Ok(())
+            } else {
+                Err(Error::new(
+                    TTSPICO_ERR_ABORTED,
+                    "the write callback stopped the synthesis",
+                ))
+            }
+        })
+    })
+}
+
+/// Frees `synth` (nothing if it is `NULL`), waiting for the synthesis in progress (if any) to stop.
+///
+/// # Safety
+/// `synth` must be `NULL` or a synthesizer returned by `ttspico_synth_new()`, not freed before nor in use.
+#[no_mangle]
+pub unsafe extern "C" fn ttspico_synth_free(synth: *mut TtspicoSynth) {
+    if !synth.is_null() {
+        drop(Box::from_raw(synth));
+    }
+}
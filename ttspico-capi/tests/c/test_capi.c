@@ -0,0 +1,154 @@
+/*
+ * Tests the C API, given the directory of the language packs bundled with Pico's sources.
+ * Built and run by tests/c_api.rs.
+ *
+ * Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+ * Licensed under the Apache License, Version 2.0.
+ */
+
+#include "ttspico.h"
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+static int n_failed = 0;
+
+#define CHECK(cond)                                                                                \
+    do {                                                                                           \
+        if (!(cond)) {                                                                             \
+            fprintf(stderr, "%s:%d: check failed: %s (last error: \"%s\")\n", __FILE__, __LINE__,  \
+                    #cond, ttspico_last_error());                                                  \
+            n_failed++;                                                                            \
+        }                                                                                          \
+    } while (0)
+
+/* Bytes written by `ttspico_synth_speak()`. */
+typedef struct {
+    uint8_t *data;
+    size_t len;
+    size_t n_calls;
+    /* Stops the synthesis after this many calls, if not 0. */
+    size_t max_calls;
+} Buffer;
+
+static int32_t write_buffer(void *user_data, const uint8_t *data, size_t len) {
+    Buffer *buffer = (Buffer *)user_data;
+    buffer->data = (uint8_t *)realloc(buffer->data, buffer->len + len);
+    memcpy(buffer->data + buffer->len, data, len);
+    buffer->len += len;
+    buffer->n_calls++;
+    return buffer->max_calls != 0 && buffer->n_calls >= buffer->max_calls;
+}
+
+static TtspicoStatus speak(const TtspicoSynth *synth, const char *language, const char *text,
+                           uint32_t flags, TtspicoFormat format, Buffer *buffer) {
+    memset(buffer, 0, sizeof(*buffer));
+    return ttspico_synth_speak(synth, language, text, flags, format, write_buffer, buffer);
+}
+
+static void test_catalog(const char *lang_dir) {
+    TtspicoCatalog *catalog = NULL;
+    CHECK(ttspico_catalog_new(lang_dir, NULL) == TTSPICO_ERR_INVALID_ARGUMENT);
+    CHECK(strlen(ttspico_last_error()) > 0);
+
+    CHECK(ttspico_catalog_new(lang_dir, &catalog) == TTSPICO_OK);
+    CHECK(strcmp(ttspico_last_error(), "") == 0);
+    CHECK(ttspico_catalog_len(catalog) == 6);
+    CHECK(strcmp(ttspico_catalog_language(catalog, 0), "de-DE") == 0);
+    CHECK(strcmp(ttspico_catalog_language(catalog, 1), "en-GB") == 0);
+    CHECK(strcmp(ttspico_catalog_language(catalog, 2), "en-US") == 0);
+    CHECK(strcmp(ttspico_catalog_speaker(catalog, 2), "lh0") == 0);
+    CHECK(ttspico_catalog_language(catalog, 6) == NULL);
+    CHECK(ttspico_catalog_speaker(catalog, 6) == NULL);
+    ttspico_catalog_free(catalog);
+
+    CHECK(ttspico_catalog_new("/nonexistent", &catalog) == TTSPICO_OK);
+    CHECK(ttspico_catalog_len(catalog) == 0);
+    ttspico_catalog_free(catalog);
+    CHECK(ttspico_catalog_len(NULL) == 0);
+}
+
+static void test_synth(const char *lang_dir) {
+    TtspicoCatalog *catalog = NULL;
+    TtspicoSynth *synth = NULL;
+    Buffer raw, wav, buffer;
+
+    CHECK(ttspico_catalog_new(lang_dir, &catalog) == TTSPICO_OK);
+    CHECK(ttspico_synth_new(catalog, "xx", &synth) == TTSPICO_ERR_NO_VOICE);
+    CHECK(ttspico_synth_new(catalog, "en-US", &synth) == TTSPICO_OK);
+    ttspico_catalog_free(catalog);
+
+    CHECK(speak(synth, NULL, "Hello world", 0, TTSPICO_FORMAT_RAW, &raw) == TTSPICO_OK);
+    CHECK(raw.len > TTSPICO_SAMPLE_RATE / 2 && raw.len % 2 == 0);
+    CHECK(raw.n_calls > 1);
+
+    /* (The same samples, after a streamed WAV header) */
+    CHECK(speak(synth, "en-US", "Hello world", 0, TTSPICO_FORMAT_WAV, &wav) == TTSPICO_OK);
+    CHECK(wav.len == 44 + raw.len);
+    CHECK(memcmp(wav.data, "RIFF", 4) == 0 && memcmp(wav.data + 8, "WAVE", 4) == 0);
+    CHECK(memcmp(wav.data + 44, raw.data, raw.len) == 0);
+    free(wav.data);
+
+    CHECK(speak(synth, "it", "Ciao mondo", 0, TTSPICO_FORMAT_RAW, &buffer) == TTSPICO_OK);
+    CHECK(buffer.len > 0);
+    free(buffer.data);
+
+    CHECK(speak(synth, NULL, "<speak>Hello <break time=\"500ms\"/> world</speak>", TTSPICO_SPEAK_SSML,
+                TTSPICO_FORMAT_RAW, &buffer) == TTSPICO_OK);
+    CHECK(buffer.len >= raw.len + TTSPICO_SAMPLE_RATE);
+    free(buffer.data);
+
+    /* (Stopping a synthesis does not affect the next one) */
+    memset(&buffer, 0, sizeof(buffer));
+    buffer.max_calls = 1;
+    CHECK(ttspico_synth_speak(synth, NULL, "Hello world", 0, TTSPICO_FORMAT_RAW, write_buffer,
+                              &buffer) == TTSPICO_ERR_ABORTED);
+    CHECK(buffer.n_calls == 1);
+    free(buffer.data);
+    CHECK(speak(synth, NULL, "Hello world", 0, TTSPICO_FORMAT_RAW, &buffer) == TTSPICO_OK);
+    CHECK(buffer.len == raw.len && memcmp(buffer.data, raw.data, raw.len) == 0);
+    free(buffer.data);
+
+    CHECK(speak(synth, "xx", "Hello", 0, TTSPICO_FORMAT_RAW, &buffer) == TTSPICO_ERR_NO_VOICE);
+    CHECK(strcmp(ttspico_last_error(), "no voice for xx") == 0);
+    CHECK(speak(synth, NULL, "<speak>Hello</s>", TTSPICO_SPEAK_SSML, TTSPICO_FORMAT_RAW, &buffer) ==
+          TTSPICO_ERR_BINDINGS);
+    CHECK(speak(synth, NULL, "Hello", 0, 7, &buffer) == TTSPICO_ERR_INVALID_ARGUMENT);
+    CHECK(speak(synth, NULL, "Hello", 0x10, TTSPICO_FORMAT_RAW, &buffer) ==
+          TTSPICO_ERR_INVALID_ARGUMENT);
+    CHECK(speak(synth, NULL, NULL, 0, TTSPICO_FORMAT_RAW, &buffer) == TTSPICO_ERR_INVALID_ARGUMENT);
+    CHECK(ttspico_synth_speak(synth, NULL, "Hello", 0, TTSPICO_FORMAT_RAW, NULL, NULL) ==
+          TTSPICO_ERR_INVALID_ARGUMENT);
+    CHECK(buffer.n_calls == 0);
+
+    free(raw.data);
+    ttspico_synth_free(synth);
+    ttspico_synth_free(NULL);
+}
+
+static void test_markup(void) {
+    char *markup = NULL;
+    CHECK(ttspico_ssml_to_markup("<speak>Hello <break time=\"500ms\"/> world</speak>", &markup) ==
+          TTSPICO_OK);
+    CHECK(markup != NULL && strstr(markup, "Hello") != NULL && strstr(markup, "<break") != NULL);
+    ttspico_string_free(markup);
+
+    markup = NULL;
+    CHECK(ttspico_ssml_to_markup("<speak>Hello</s>", &markup) == TTSPICO_ERR_BINDINGS);
+    CHECK(markup == NULL);
+    CHECK(ttspico_ssml_to_markup(NULL, &markup) == TTSPICO_ERR_INVALID_ARGUMENT);
+    ttspico_string_free(NULL);
+}
+
+int main(int argc, char **argv) {
+    if (argc != 2) {
+        fprintf(stderr, "usage: %s LANG_DIR\n", argv[0]);
+        return 2;
+    }
+    CHECK(strlen(ttspico_version()) > 0);
+    test_catalog(argv[1]);
+    test_synth(argv[1]);
+    test_markup();
+    return n_failed == 0 ? 0 : 1;
+}
@@ -0,0 +1,58 @@
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Builds the C tests in `tests/c/` with `cc`, linking them to the shared library, and runs them.
+
+#![cfg(unix)]
+
+use std::path::Path;
+use std::process::Command;
+use ttspico_sys as native;
+
+#[test]
+fn c_api() {
+    // (Cargo puts the library next to the test executables)
+    let exe = std::env::current_exe().unwrap();
+    let lib_dir = exe.parent().unwrap();
+    let test_exe = lib_dir.join("ttspico_capi_test");
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+
+    let compiler = cc::Build::new()
+        .target(env!("TTSPICO_CAPI_TARGET"))
+        .host(env!("TTSPICO_CAPI_TARGET"))
+        .opt_level(0)
+        .cargo_metadata(false)
+        .get_compiler();
+    let status = compiler
+        .to_command()
+        .args(["-std=c99", "-Wall"])
+        .arg("-I")
+        .arg(manifest_dir.join("include"))
+        .arg(manifest_dir.join("tests/c/test_capi.c"))
+        .arg("-o")
+        .arg(&test_exe)
+        .arg("-L")
+        .arg(lib_dir)
+        .arg("-lttspico_capi")
+        .arg(format!("-Wl,-rpath,{}", lib_dir.display()))
+        .status()
+        .expect("Failed to run the C compiler");
+    assert!(status.success(), "Failed to build the C tests");
+
+    let status = Command::new(&test_exe)
+        .arg(native::BUNDLED_LANG_DIR)
+        .status()
+        .unwrap();
+    assert!(status.success(), "The C tests failed");
+}
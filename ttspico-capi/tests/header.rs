@@ -0,0 +1,264 @@
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Checks that the checked-in `include/ttspico.h` declares the C API in `src/` as cbindgen would: each public
+//! constant, type and function, with its documentation.
+//!
+//! (The order of the declarations and their line breaks, which are up to cbindgen, are not checked)
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use syn::{Expr, FnArg, GenericArgument, Item, Lit, Pat, PathArguments, ReturnType, Type, UnOp};
+
+const HEADER: &str = include_str!("../include/ttspico.h");
+
+/// Collapses the whitespace in `code` into single spaces.
+fn collapse(code: &str) -> String {
+    code.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Returns the documentation in `attrs`, one trimmed line per `///` line.
+fn docs(attrs: &[syn::Attribute]) -> String {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            syn::Meta::NameValue(syn::MetaNameValue {
+                value:
+                    Expr::Lit(syn::ExprLit {
+                        lit: Lit::Str(doc), ..
+                    }),
+                ..
+            }) => Some(doc.value().trim().to_string()),
+            _ => None,
+        })
+        .collect();
+    lines.join("\n")
+}
+
+/// Returns the C spelling of the Rust type `ty`.
+fn c_type(ty: &Type) -> String {
+    match ty {
+        Type::Path(path) => {
+            let name = path.path.segments.last().unwrap().ident.to_string();
+            match name.as_str() {
+                "i8" | "i16" | "i32" | "i64" => format!("int{}_t", &name[1..]),
+                "u8" | "u16" | "u32" | "u64" => format!("uint{}_t", &name[1..]),
+                "usize" => "size_t".to_string(),
+                "isize" => "ptrdiff_t".to_string(),
+                "c_char" => "char".to_string(),
+                "c_void" => "void".to_string(),
+                _ => name,
+            }
+        }
+        Type::Ptr(ptr) => {
+            let pointee = c_type(&ptr.elem);
+            match (pointee.ends_with('*'), ptr.const_token.is_some()) {
+                (true, false) => format!("{}*", pointee),
+                (true, true) => format!("{}const *", pointee),
+                (false, false) => format!("{} *", pointee),
+                (false, true) => format!("const {} *", pointee),
+            }
+        }
+        Type::Tuple(tuple) if tuple.elems.is_empty() => "void".to_string(),
+        _ => panic!("unexpected type in the C API"),
+    }
+}
+
+/// Returns the C declaration of `name` with the Rust type `ty`, i.e. `const char *name`.
+fn c_decl(ty: &str, name: &str) -> String {
+    if ty.ends_with('*') {
+        format!("{}{}", ty, name)
+    } else {
+        format!("{} {}", ty, name)
+    }
+}
+
+/// Returns the C parameter list of the given parameters.
+fn c_params<'a>(params: impl Iterator<Item = (String, &'a Type)>) -> String {
+    let params: Vec<_> = params
+        .map(|(name, ty)| c_decl(&c_type(ty), &name))
+        .collect();
+    if params.is_empty() {
+        "void".to_string()
+    } else {
+        params.join(", ")
+    }
+}
+
+/// Returns the C return type of `output`.
+fn c_return(output: &ReturnType) -> String {
+    match output {
+        ReturnType::Default => "void".to_string(),
+        ReturnType::Type(_, ty) => c_type(ty),
+    }
+}
+
+/// Returns the C spelling of the integer literal `expr`.
+fn c_value(expr: &Expr) -> String {
+    match expr {
+        Expr::Lit(syn::ExprLit {
+            lit: Lit::Int(int), ..
+        }) => int.base10_digits().to_string(),
+        Expr::Unary(unary) if matches!(unary.op, UnOp::Neg(_)) => {
+            format!("-{}", c_value(&unary.expr))
+        }
+        _ => panic!("unexpected constant in the C API"),
+    }
+}
+
+/// Returns the bare function type of an `Option<extern "C" fn(...)>` callback, if `ty` is one.
+fn callback(ty: &Type) -> Option<&syn::TypeBareFn> {
+    let Type::Path(path) = ty else { return None };
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match args.args.first()? {
+        GenericArgument::Type(Type::BareFn(bare_fn)) => Some(bare_fn),
+        _ => None,
+    }
+}
+
+/// Returns the declarations that cbindgen writes for the public items of `file`, with their documentation.
+fn source_declarations(file: &syn::File) -> Vec<(String, String)> {
+    let public = |vis: &syn::Visibility| matches!(vis, syn::Visibility::Public(_));
+    file.items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Const(item) if public(&item.vis) => Some((
+                format!("#define {} {}", item.ident, c_value(&item.expr)),
+                docs(&item.attrs),
+            )),
+            // (Structs are opaque to C)
+            Item::Struct(item) if public(&item.vis) => Some((
+                format!("typedef struct {} {};", item.ident, item.ident),
+                docs(&item.attrs),
+            )),
+            Item::Type(item) if public(&item.vis) => {
+                let decl = match callback(&item.ty) {
+                    Some(bare_fn) => format!(
+                        "typedef {} (*{})({});",
+                        c_return(&bare_fn.output),
+                        item.ident,
+                        c_params(bare_fn.inputs.iter().map(|arg| {
+                            let (name, _) = arg.name.as_ref().expect("unnamed callback parameter");
+                            (name.to_string(), &arg.ty)
+                        }))
+                    ),
+                    None => format!(
+                        "typedef {};",
+                        c_decl(&c_type(&item.ty), &item.ident.to_string())
+                    ),
+                };
+                Some((decl, docs(&item.attrs)))
+            }
+            Item::Fn(item)
+                if public(&item.vis)
+                    && item
+                        .attrs
+                        .iter()
+                        .any(|attr| attr.path().is_ident("no_mangle")) =>
+            {
+                let params = c_params(item.sig.inputs.iter().map(|arg| match arg {
+                    FnArg::Typed(arg) => match &*arg.pat {
+                        Pat::Ident(name) => (name.ident.to_string(), &*arg.ty),
+                        _ => panic!("unexpected parameter in the C API"),
+                    },
+                    FnArg::Receiver(_) => panic!("unexpected receiver in the C API"),
+                }));
+                let name = format!("{}({})", item.sig.ident, params);
+                Some((
+                    format!("{};", c_decl(&c_return(&item.sig.output), &name)),
+                    docs(&item.attrs),
+                ))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Returns the declarations in `header` (constants, types and functions), with their documentation.
+fn header_declarations(header: &str) -> Vec<(String, String)> {
+    let mut declarations = Vec::new();
+    let mut doc: Vec<&str> = Vec::new();
+    let mut in_doc = false;
+    // (A declaration spanning several lines, until its `;`)
+    let mut decl = String::new();
+    for line in header.lines() {
+        if in_doc {
+            if line.trim() == "*/" {
+                in_doc = false;
+            } else {
+                doc.push(line.trim().trim_start_matches('*').trim());
+            }
+        } else if line.trim() == "/*" {
+            in_doc = true;
+            doc.clear();
+        } else if !decl.is_empty() || line.starts_with("typedef ") || line.starts_with("const ") {
+            decl.push_str(line);
+            decl.push(' ');
+        } else if line.starts_with("#define ") && line.split_whitespace().count() == 3 {
+            declarations.push((collapse(line), doc.join("\n")));
+            doc.clear();
+        } else if line.starts_with(|c: char| c.is_ascii_alphabetic()) && line.contains('(') {
+            // (A function, returning a named type)
+            decl.push_str(line);
+            decl.push(' ');
+        } else {
+            // (Include guards, `extern "C"` and the like, which do not take documentation)
+            doc.clear();
+        }
+        if decl.trim_end().ends_with(';') {
+            declarations.push((collapse(&decl), doc.join("\n")));
+            decl.clear();
+            doc.clear();
+        }
+    }
+    declarations
+}
+
+#[test]
+fn header_matches_sources() {
+    let src_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+    let mut expected = BTreeMap::new();
+    for entry in std::fs::read_dir(&src_dir).unwrap() {
+        let path = entry.unwrap().path();
+        let file = syn::parse_file(&std::fs::read_to_string(&path).unwrap())
+            .unwrap_or_else(|err| panic!("Failed to parse {}: {}", path.display(), err));
+        expected.extend(source_declarations(&file));
+    }
+    let actual: BTreeMap<_, _> = header_declarations(HEADER).into_iter().collect();
+    assert!(actual.len() > 20, "Failed to parse the header");
+
+    let mut mismatches = Vec::new();
+    for (decl, doc) in &expected {
+        match actual.get(decl) {
+            Some(header_doc) if header_doc == doc => {}
+            Some(_) => mismatches.push(format!("the documentation of `{}` differs", decl)),
+            None => mismatches.push(format!("`{}` is missing", decl)),
+        }
+    }
+    for decl in actual.keys().filter(|decl| !expected.contains_key(*decl)) {
+        mismatches.push(format!("`{}` is not in the sources", decl));
+    }
+    assert!(
+        mismatches.is_empty(),
+        "include/ttspico.h is out of date; regenerate it with cbindgen (see cbindgen.toml):\n{}",
+        mismatches.join("\n")
+    );
+}
@@ -0,0 +1,463 @@
+//! Batch synthesis of the entries of a manifest file, skipping those that did not change since the last run.
+//!
+//! A manifest lists entries with an `id` (naming the output file), a `voice` (a language, i.e. `en-US`), the
+//! `text` to speak and optional parameters: `speed`, `pitch` and `volume` (levels in percent, as in Pico's
+//! markup), `ssml` (to read the text as SSML) and `format` (`wav` or `raw`). It can be:
+//! - a CSV file with a header naming the columns;
+//! - a JSON file containing an array of entries;
+//! - a TOML file containing an `[[entry]]` table for each entry.
+//!
+//! Entries are synthesized in parallel, with one Pico [`System`] per worker thread. A JSON report of the audio
+//! durations and Pico warnings of all entries is written after each run; it also records a hash of each entry's
+//! voice, text and parameters, so that the next run can skip the entries whose hash is unchanged.
+
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::output::{self, Format};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use ttspico::{Engine, EngineResetMode, PicoError, System, Voice, SAMPLE_RATE};
+
+/// An entry of a manifest.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Entry {
+    /// Identifier of the entry, which names its output file.
+    pub id: String,
+    /// Language of the voice to speak with (by default the one given with `--lang`).
+    #[serde(default)]
+    pub voice: Option<String>,
+    /// Text to speak, with Pico's markup (or SSML, if `ssml` is set).
+    pub text: String,
+    /// Speed level, in percent of the default.
+    #[serde(default)]
+    pub speed: Option<u32>,
+    /// Pitch level, in percent of the default.
+    #[serde(default)]
+    pub pitch: Option<u32>,
+    /// Volume level, in percent of the default.
+    #[serde(default)]
+    pub volume: Option<u32>,
+    /// Whether `text` is SSML.
+    #[serde(default)]
+    pub ssml: Option<bool>,
+    /// Output format (WAV by default).
+    #[serde(default)]
+    pub format: Option<Format>,
+}
+
+impl Entry {
+    /// Returns the hash of the voice, text and parameters of the entry, as a hex string.
+    fn hash(&self, voice: &str) -> String {
+        let fields = [
+            voice.to_string(),
+            self.text.clone(),
+            format!("{:?}", self.speed),
+            format!("{:?}", self.pitch),
+            format!("{:?}", self.volume),
+            format!("{:?}", self.ssml.unwrap_or(false)),
+            format!("{:?}", self.format.unwrap_or(Format::Wav)),
+        ];
+        // (FNV-1a, which unlike `DefaultHasher` is stable across Rust versions; each field is length-prefixed)
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for field in &fields {
+            let len = (field.len() as u64).to_le_bytes();
+            for &byte in len.iter().chain(field.as_bytes()) {
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(0x0100_0000_01b3);
+            }
+        }
+        format!("{:016x}", hash)
+    }
+
+    /// Returns the text to put into Pico: `text` (converted from SSML if needed) with the prosody parameters
+    /// applied via Pico's markup.
+    fn markup(&self) -> Result<String, String> {
+        let mut markup = if self.ssml.unwrap_or(false) {
+            ttspico::ssml_to_markup(&self.text).map_err(|err| err.descr)?
+        } else {
+            self.text.clone()
+        };
+        for (tag, level) in [
+            ("speed", self.speed),
+            ("pitch", self.pitch),
+            ("volume", self.volume),
+        ] {
+            if let Some(level) = level {
+                markup = format!("<{} level=\"{}\">{}</{}>", tag, level, markup, tag);
+            }
+        }
+        Ok(markup)
+    }
+}
+
+/// A TOML manifest.
+#[derive(Debug, Deserialize)]
+struct TomlManifest {
+    #[serde(default)]
+    entry: Vec<Entry>,
+}
+
+/// Reads the entries of the manifest at `path`, whose format is given by its extension.
+pub fn read_manifest(path: &Path) -> Result<Vec<Entry>, String> {
+    let err = |err: &dyn std::fmt::Display| format!("failed to read {}: {}", path.display(), err);
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("csv") => {
+            let mut reader = csv::Reader::from_path(path).map_err(|e| err(&e))?;
+            reader
+                .deserialize()
+                .collect::<Result<Vec<Entry>, _>>()
+                .map_err(|e| err(&e))
+        }
+        Some("json") => {
+            let json = fs::read_to_string(path).map_err(|e| err(&e))?;
+            serde_json::from_str(&json).map_err(|e| err(&e))
+        }
+        Some("toml") => {
+            let toml = fs::read_to_string(path).map_err(|e| err(&e))?;
+            toml::from_str::<TomlManifest>(&toml)
+                .map(|manifest| manifest.entry)
+                .map_err(|e| err(&e))
+        }
+        _ => Err(format!(
+            "unknown manifest format for {} (expected a .csv, .json or .toml file)",
+            path.display()
+        )),
+    }
+}
+
+/// A warning raised by Pico, in a [`Report`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Warning {
+    pub code: i32,
+    pub message: String,
+}
+
+/// What happened to an entry in a batch run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    /// The entry was synthesized.
+    Synthesized,
+    /// The entry was skipped, since it did not change since the last run.
+    Unchanged,
+    /// The entry could not be synthesized.
+    Failed,
+}
+
+/// The outcome of an entry, in a [`Report`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportEntry {
+    pub id: String,
+    /// Path of the output file.
+    pub file: PathBuf,
+    /// Hash of the entry's voice, text and parameters.
+    pub hash: String,
+    pub status: Status,
+    /// Duration of the synthesized speech, in milliseconds.
+    pub duration_ms: u64,
+    pub warnings: Vec<Warning>,
+    /// Why the entry could not be synthesized, if it failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// The report of a batch run, with the entries in the manifest's order.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Report {
+    pub entries: Vec<ReportEntry>,
+}
+
+impl Report {
+    /// Returns the number of entries with the given `status`.
+    pub fn count(&self, status: Status) -> usize {
+        self.entries
+            .iter()
+            .filter(|entry| entry.status == status)
+            .count()
+    }
+}
+
+/// Options of a batch run.
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// Directory to write the output files to.
+    pub out_dir: PathBuf,
+    /// Path of the report, whose previous version is used to skip unchanged entries.
+    pub report: PathBuf,
+    /// Directories to search for language packs, in order.
+    pub lang_dirs: Vec<PathBuf>,
+    /// Voice of the entries that do not specify one.
+    pub default_voice: String,
+    /// Number of worker threads.
+    pub jobs: usize,
+    /// Whether to synthesize all entries, even unchanged ones.
+    pub force: bool,
+}
+
+/// An entry to be synthesized by a worker.
+struct Job<'a> {
+    index: usize,
+    entry: &'a Entry,
+    voice: String,
+    file: PathBuf,
+    hash: String,
+}
+
+/// A worker thread's Pico system, with a voice for each language pack and an engine for (at most) one of them.
+struct Worker {
+    voices: HashMap<String, Rc<RefCell<Voice>>>,
+    engine: Option<(String, Engine)>,
+}
+
+impl Worker {
+    fn new(
+        memsz: usize,
+        language_packs: &BTreeMap<String, (String, String)>,
+    ) -> Result<Self, PicoError> {
+        let sys = System::builder().memory_size(memsz).build()?;
+        let mut voices = HashMap::new();
+        for (language, (ta_path, sg_path)) in language_packs {
+            let ta_res = System::load_resource(Rc::clone(&sys), ta_path)?;
+            let sg_res = System::load_resource(Rc::clone(&sys), sg_path)?;
+            let voice = System::create_voice(Rc::clone(&sys), language)?;
+            voice.borrow_mut().add_resource(ta_res)?;
+            voice.borrow_mut().add_resource(sg_res)?;
+            voices.insert(language.clone(), voice);
+        }
+        Ok(Worker {
+            voices,
+            engine: None,
+        })
+    }
+
+    /// Synthesizes `job`, returning the samples and warnings.
+    fn synthesize(&mut self, job: &Job) -> Result<(Vec<i16>, Vec<PicoError>), String> {
+        let markup = job.entry.markup()?;
+        if self.engine.as_ref().map(|(voice, _)| voice) != Some(&job.voice) {
+            // (The system only has memory for one engine at a time)
+            self.engine = None;
+            let voice = Rc::clone(&self.voices[&job.voice]);
+            // SAFETY: both a TA and a SG resource were added to the voice in `Worker::new`.
+            #[allow(unused_mut)]
+            let mut engine =
+                unsafe { Voice::create_engine(voice) }.map_err(|err| err.to_string())?;
+            // (The manifest comes from the user running the tool, like the text of a single synthesis)
+            #[cfg(not(feature = "system"))]
+            engine.set_file_policy(ttspico::FilePolicy::allow_all());
+            self.engine = Some((job.voice.clone(), engine));
+        }
+        let (_, engine) = self.engine.as_mut().unwrap();
+        // (So that the speech does not depend on the jobs the engine synthesized before)
+        engine
+            .reset(EngineResetMode::Full)
+            .map_err(|err| err.to_string())?;
+        crate::speak(engine, &markup).map_err(|err| {
+            // (Start over with a new engine for the next job, whatever state this one was left in)
+            self.engine = None;
+            err.to_string()
+        })
+    }
+}
+
+/// Writes `samples` to `path` in the given `format`.
+fn write_file(path: &Path, format: Format, samples: &[i16]) -> std::io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    output::write(&mut file, format, samples)?;
+    file.flush()
+}
+
+/// Synthesizes the `jobs` on the current thread, taking them from `next_job` in order.
+fn work(
+    jobs: &[Job],
+    next_job: &AtomicUsize,
+    memsz: usize,
+    language_packs: &BTreeMap<String, (String, String)>,
+) -> Vec<(usize, ReportEntry)> {
+    let mut worker = Worker::new(memsz, language_packs).map_err(|err| err.to_string());
+    let mut outcomes = Vec::new();
+    loop {
+        let job = match jobs.get(next_job.fetch_add(1, Ordering::Relaxed)) {
+            Some(job) => job,
+            None => return outcomes,
+        };
+        let mut report_entry = ReportEntry {
+            id: job.entry.id.clone(),
+            file: job.file.clone(),
+            hash: job.hash.clone(),
+            status: Status::Synthesized,
+            duration_ms: 0,
+            warnings: Vec::new(),
+            error: None,
+        };
+        let result = worker
+            .as_mut()
+            .map_err(|err| err.clone())
+            .and_then(|worker| worker.synthesize(job))
+            .and_then(|(samples, warnings)| {
+                let format = job.entry.format.unwrap_or(Format::Wav);
+                write_file(&job.file, format, &samples)
+                    .map_err(|err| format!("failed to write {}: {}", job.file.display(), err))?;
+                report_entry.duration_ms = samples.len() as u64 * 1000 / SAMPLE_RATE as u64;
+                report_entry.warnings = warnings
+                    .into_iter()
+                    .map(|warning| Warning {
+                        code: warning.code,
+                        message: warning.descr,
+                    })
+                    .collect();
+                Ok(())
+            });
+        if let Err(err) = result {
+            report_entry.status = Status::Failed;
+            report_entry.error = Some(err);
+        }
+        outcomes.push((job.index, report_entry));
+    }
+}
+
+/// Reads the report at `path`, if any.
+fn read_report(path: &Path) -> Option<Report> {
+    let json = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&json).ok()
+}
+
+/// Returns whether `id` can be used as a file name.
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty() && !id.starts_with('.') && !id.contains(['/', '\\', '\0'])
+}
+
+/// Synthesizes the `entries` of a manifest according to `options`, writing the report.
+/// Returns the report, or an error if the manifest is invalid or the report could not be written.
+pub fn run(entries: &[Entry], options: &Options) -> Result<Report, String> {
+    let mut ids = HashSet::new();
+    for entry in entries {
+        if !is_valid_id(&entry.id) {
+            return Err(format!("invalid entry id {:?}", entry.id));
+        }
+        if !ids.insert(&entry.id) {
+            return Err(format!("duplicate entry id {:?}", entry.id));
+        }
+    }
+    fs::create_dir_all(&options.out_dir)
+        .map_err(|err| format!("failed to create {}: {}", options.out_dir.display(), err))?;
+
+    let previous: HashMap<String, ReportEntry> = match read_report(&options.report) {
+        Some(report) if !options.force => report
+            .entries
+            .into_iter()
+            .map(|entry| (entry.id.clone(), entry))
+            .collect(),
+        _ => HashMap::new(),
+    };
+
+    // Resolve the entries' voices and skip the unchanged entries
+    let mut report_entries: Vec<Option<ReportEntry>> = vec![None; entries.len()];
+    let mut language_packs = BTreeMap::new();
+    let mut jobs = Vec::new();
+    for (index, entry) in entries.iter().enumerate() {
+        let voice = entry
+            .voice
+            .clone()
+            .unwrap_or_else(|| options.default_voice.clone());
+        let hash = entry.hash(&voice);
+        let format = entry.format.unwrap_or(Format::Wav);
+        let extension = match format {
+            Format::Wav => "wav",
+            Format::Raw => "raw",
+        };
+        let file = options.out_dir.join(format!("{}.{}", entry.id, extension));
+
+        match previous.get(&entry.id) {
+            Some(prev) if prev.hash == hash && prev.status != Status::Failed && file.is_file() => {
+                report_entries[index] = Some(ReportEntry {
+                    status: Status::Unchanged,
+                    ..prev.clone()
+                });
+                continue;
+            }
+            _ => {}
+        }
+        if !language_packs.contains_key(&voice) {
+            let language_pack = options
+                .lang_dirs
+                .iter()
+                .find_map(|dir| ttspico::find_language_pack_in(dir, &voice));
+            match language_pack {
+                Some(language_pack) => {
+                    language_packs.insert(voice.clone(), language_pack);
+                }
+                None => {
+                    report_entries[index] = Some(ReportEntry {
+                        id: entry.id.clone(),
+                        file,
+                        hash,
+                        status: Status::Failed,
+                        duration_ms: 0,
+                        warnings: Vec::new(),
+                        error: Some(format!("no language pack for {}", voice)),
+                    });
+                    continue;
+                }
+            }
+        }
+        jobs.push(Job {
+            index,
+            entry,
+            voice,
+            file,
+            hash,
+        });
+    }
+
+    if !jobs.is_empty() {
+        // (Group the jobs by voice, so that workers rarely have to switch engines)
+        jobs.sort_by(|a, b| a.voice.cmp(&b.voice));
+
+        let mut builder = System::builder();
+        for (ta_path, sg_path) in language_packs.values() {
+            builder = builder.language_pack(ta_path.as_str(), sg_path.as_str());
+        }
+        let memsz = builder.measure().map_err(|err| err.to_string())?;
+
+        let next_job = AtomicUsize::new(0);
+        let n_workers = options.jobs.clamp(1, jobs.len());
+        let outcomes = std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..n_workers)
+                .map(|_| scope.spawn(|| work(&jobs, &next_job, memsz, &language_packs)))
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().expect("batch worker panicked"))
+                .collect::<Vec<_>>()
+        });
+        for (index, report_entry) in outcomes {
+            report_entries[index] = Some(report_entry);
+        }
+    }
+
+    let report = Report {
+        entries: report_entries.into_iter().map(Option::unwrap).collect(),
+    };
+    let json = serde_json::to_string_pretty(&report).unwrap();
+    fs::write(&options.report, json + "\n")
+        .map_err(|err| format!("failed to write {}: {}", options.report.display(), err))?;
+    Ok(report)
+}
@@ -0,0 +1,259 @@
+//! A `pico2wave`-compatible command-line tool for the Pico TTS engine.
+//!
+//! Takes the same `-w`/`--wave` and `-l`/`--lang` options as `pico2wave`, and can additionally read text from
+//! the standard input, convert SSML input to Pico's markup, write raw PCM and list the available voices.
+//! With `--batch`, it synthesizes all the entries of a manifest file instead (see [`batch`]).
+
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod batch;
+mod output;
+
+use clap::Parser;
+use output::Format;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::rc::Rc;
+use ttspico::{Engine, PicoError, System, Voice};
+
+/// Synthesizes speech from text with Pico TTS (compatible with `pico2wave`).
+#[derive(Debug, Parser)]
+#[command(name = "ttspico-cli", version)]
+struct Args {
+    /// Write the speech to FILE ('-' for the standard output)
+    #[arg(
+        short = 'w',
+        long = "wave",
+        value_name = "FILE",
+        required_unless_present_any = ["list_voices", "batch"]
+    )]
+    wave: Option<PathBuf>,
+
+    /// Language of the text (and of the manifest entries that do not specify a voice)
+    #[arg(
+        short = 'l',
+        long = "lang",
+        value_name = "LANG",
+        default_value = "en-US"
+    )]
+    lang: String,
+
+    /// Directory of the language packs, instead of the default ones
+    #[arg(long, value_name = "DIR")]
+    lang_dir: Option<PathBuf>,
+
+    /// Output format (by default `raw` for `.raw` and `.pcm` files, `wav` otherwise)
+    #[arg(short = 'f', long, value_enum)]
+    format: Option<Format>,
+
+    /// Read the text as SSML, instead of as text with Pico's markup
+    #[arg(long)]
+    ssml: bool,
+
+    /// List the available voices and exit
+    #[arg(long)]
+    list_voices: bool,
+
+    /// Synthesize the entries of MANIFEST (a CSV, JSON or TOML file) instead of TEXT
+    #[arg(
+        long,
+        value_name = "MANIFEST",
+        conflicts_with_all = ["wave", "format", "ssml", "list_voices", "text"]
+    )]
+    batch: Option<PathBuf>,
+
+    /// Directory to write the files synthesized from the manifest to (by default the manifest's directory)
+    #[arg(long, value_name = "DIR", requires = "batch")]
+    out_dir: Option<PathBuf>,
+
+    /// Path of the batch report (by default `report.json` in the output directory)
+    #[arg(long, value_name = "FILE", requires = "batch")]
+    report: Option<PathBuf>,
+
+    /// Number of entries of the manifest to synthesize in parallel (by default the number of CPUs)
+    #[arg(short = 'j', long, value_name = "N", requires = "batch")]
+    jobs: Option<usize>,
+
+    /// Synthesize all entries of the manifest, even the ones that did not change since the last run
+    #[arg(long, requires = "batch")]
+    force: bool,
+
+    /// The text to speak (read from the standard input if omitted or '-')
+    #[arg(value_name = "TEXT")]
+    text: Vec<String>,
+}
+
+fn list_voices(dirs: &[PathBuf]) {
+    for dir in dirs {
+        for (language, _, sg_path) in ttspico::list_language_packs(dir) {
+            // (SG resources are named `<language>_<speaker>_sg.bin`)
+            let speaker = Path::new(&sg_path)
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_suffix("_sg.bin"))
+                .and_then(|name| name.strip_prefix(&language))
+                .map(|speaker| speaker.trim_start_matches('_'))
+                .unwrap_or("");
+            println!("{}\t{}\t{}", language, speaker, dir.display());
+        }
+    }
+}
+
+fn read_text(args: &Args) -> io::Result<String> {
+    if args.text.is_empty() || args.text == ["-"] {
+        let mut text = String::new();
+        io::stdin().read_to_string(&mut text)?;
+        Ok(text)
+    } else {
+        Ok(args.text.join(" "))
+    }
+}
+
+/// Speaks `text` with `engine`, returning the samples generated and the warnings raised by Pico.
+fn speak(engine: &mut Engine, text: &str) -> Result<(Vec<i16>, Vec<PicoError>), PicoError> {
+    let mut samples = Vec::new();
+    let mut warnings: Vec<PicoError> = Vec::new();
+    let mut pcm_buf = [0i16; 1024];
+    let mut input = text.as_bytes();
+    loop {
+        let (n_samples, done) = engine.step(&mut input, &mut pcm_buf[..])?;
+        samples.extend_from_slice(&pcm_buf[..n_samples]);
+        for warning in engine.warnings()? {
+            if !warnings.contains(&warning) {
+                warnings.push(warning);
+            }
+        }
+        if done {
+            return Ok((samples, warnings));
+        }
+    }
+}
+
+/// Synthesizes `text` with the language pack made of the `ta_path` and `sg_path` resources.
+fn synthesize(ta_path: &str, sg_path: &str, text: &str) -> Result<Vec<i16>, PicoError> {
+    let sys = System::builder().language_pack(ta_path, sg_path).build()?;
+    let ta_res = System::load_resource(Rc::clone(&sys), ta_path)?;
+    let sg_res = System::load_resource(Rc::clone(&sys), sg_path)?;
+    let voice = System::create_voice(sys, "ttspico-cli")?;
+    voice.borrow_mut().add_resource(ta_res)?;
+    voice.borrow_mut().add_resource(sg_res)?;
+    // SAFETY: both a TA and a SG resource were added to the voice above.
+    let mut engine = unsafe { Voice::create_engine(voice)? };
+    // (The text comes from the user running the tool, so its tags can access any file, as with pico2wave)
+    #[cfg(not(feature = "system"))]
+    engine.set_file_policy(ttspico::FilePolicy::allow_all());
+    let (samples, warnings) = speak(&mut engine, text)?;
+    for warning in warnings {
+        eprintln!("ttspico-cli: warning: {}", warning);
+    }
+    Ok(samples)
+}
+
+/// Synthesizes the entries of the `manifest`, printing a summary of the report.
+fn run_batch(args: &Args, manifest: &Path) -> Result<(), String> {
+    let entries = batch::read_manifest(manifest)?;
+    let out_dir = match &args.out_dir {
+        Some(out_dir) => out_dir.clone(),
+        None => manifest.parent().map(Path::to_path_buf).unwrap_or_default(),
+    };
+    let options = batch::Options {
+        report: args
+            .report
+            .clone()
+            .unwrap_or_else(|| out_dir.join("report.json")),
+        out_dir,
+        lang_dirs: ttspico::lang_search_dirs(args.lang_dir.as_deref()),
+        default_voice: args.lang.clone(),
+        jobs: args.jobs.unwrap_or_else(|| {
+            std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1)
+        }),
+        force: args.force,
+    };
+    let report = batch::run(&entries, &options)?;
+
+    for entry in &report.entries {
+        if let Some(err) = &entry.error {
+            eprintln!("ttspico-cli: {}: {}", entry.id, err);
+        }
+    }
+    eprintln!(
+        "ttspico-cli: {} synthesized, {} unchanged, {} failed (see {})",
+        report.count(batch::Status::Synthesized),
+        report.count(batch::Status::Unchanged),
+        report.count(batch::Status::Failed),
+        options.report.display()
+    );
+    match report.count(batch::Status::Failed) {
+        0 => Ok(()),
+        n_failed => Err(format!("{} entries failed", n_failed)),
+    }
+}
+
+fn run(args: Args) -> Result<(), String> {
+    let dirs = ttspico::lang_search_dirs(args.lang_dir.as_deref());
+    if args.list_voices {
+        list_voices(&dirs);
+        return Ok(());
+    }
+    if let Some(manifest) = &args.batch {
+        return run_batch(&args, manifest);
+    }
+
+    let (ta_path, sg_path) = dirs
+        .iter()
+        .find_map(|dir| ttspico::find_language_pack_in(dir, &args.lang))
+        .ok_or_else(|| {
+            let dirs: Vec<String> = dirs.iter().map(|dir| dir.display().to_string()).collect();
+            format!(
+                "no language pack for {} in {} (see --list-voices)",
+                args.lang,
+                dirs.join(", ")
+            )
+        })?;
+
+    let mut text = read_text(&args).map_err(|err| format!("failed to read text: {}", err))?;
+    if args.ssml {
+        text = ttspico::ssml_to_markup(&text).map_err(|err| err.descr)?;
+    }
+    let samples = synthesize(&ta_path, &sg_path, &text).map_err(|err| err.to_string())?;
+
+    let wave = args.wave.as_deref().unwrap();
+    let format = args.format.unwrap_or_else(|| Format::for_path(wave));
+    let written = if wave == Path::new("-") {
+        let stdout = io::stdout();
+        let mut stdout = stdout.lock();
+        output::write(&mut stdout, format, &samples).and_then(|()| stdout.flush())
+    } else {
+        File::create(wave).and_then(|file| {
+            let mut file = BufWriter::new(file);
+            output::write(&mut file, format, &samples).and_then(|()| file.flush())
+        })
+    };
+    written.map_err(|err| format!("failed to write {}: {}", wave.display(), err))
+}
+
+fn main() -> ExitCode {
+    match run(Args::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("ttspico-cli: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
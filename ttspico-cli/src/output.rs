@@ -0,0 +1,52 @@
+//! Writing of synthesized speech in the supported output formats.
+
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// An output format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// A WAV file (16-bit signed PCM, mono, 16kHz), like `pico2wave` writes
+    Wav,
+    /// Headerless 16-bit signed little-endian PCM, mono, 16kHz
+    Raw,
+}
+
+impl Format {
+    /// Returns the format implied by the extension of `path`.
+    pub fn for_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("raw") | Some("pcm") => Format::Raw,
+            _ => Format::Wav,
+        }
+    }
+}
+
+/// Writes `samples` to `out` in the given `format`.
+pub fn write(out: &mut dyn Write, format: Format, samples: &[i16]) -> io::Result<()> {
+    if format == Format::Wav {
+        out.write_all(&ttspico::wav_header(Some(samples.len())))?;
+    }
+    let bytes: Vec<u8> = samples
+        .iter()
+        .flat_map(|sample| sample.to_le_bytes())
+        .collect();
+    out.write_all(&bytes)
+}
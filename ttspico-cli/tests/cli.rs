@@ -0,0 +1,266 @@
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Runs the `ttspico-cli` binary with the language packs bundled with Pico's sources.
+
+use std::convert::TryInto;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+use ttspico_sys as native;
+
+/// Size of the header of the WAV files written by `ttspico-cli`.
+const WAV_HEADER_LEN: usize = 44;
+
+fn cli() -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_ttspico-cli"));
+    command.args(["--lang-dir", native::BUNDLED_LANG_DIR]);
+    command
+}
+
+/// Runs `command`, writing `stdin` to its standard input.
+fn run(mut command: Command, stdin: &str) -> Output {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to run ttspico-cli");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("ttspico-cli-{}-{}", std::process::id(), name))
+}
+
+/// Runs `ttspico-cli` with `args`, returning the raw PCM it writes to its standard output.
+fn synthesize_raw(args: &[&str], stdin: &str) -> Vec<u8> {
+    let mut command = cli();
+    command.args(["-w", "-", "-f", "raw"]).args(args);
+    let output = run(command, stdin);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(!output.stdout.is_empty());
+    output.stdout
+}
+
+#[test]
+fn pico2wave_options() {
+    let wave_path = temp_path("hello.wav");
+    let mut command = cli();
+    command.arg(format!("--wave={}", wave_path.display()));
+    command.args(["-l", "en-US", "Hello", "world"]);
+    let output = run(command, "");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let wav = fs::read(&wave_path).unwrap();
+    let _ = fs::remove_file(&wave_path);
+
+    assert_eq!(&wav[0..4], b"RIFF");
+    assert_eq!(&wav[8..16], b"WAVEfmt ");
+    assert_eq!(u32::from_le_bytes(wav[24..28].try_into().unwrap()), 16000);
+    let data_len = u32::from_le_bytes(wav[40..44].try_into().unwrap()) as usize;
+    assert_eq!(data_len, wav.len() - WAV_HEADER_LEN);
+
+    // (The same text read from the standard input, as raw PCM)
+    assert_eq!(
+        synthesize_raw(&["-l", "en-US"], "Hello world"),
+        &wav[WAV_HEADER_LEN..]
+    );
+}
+
+#[test]
+fn ssml_input() {
+    let ssml = r#"<?xml version="1.0"?>
+        <speak version="1.1" xmlns="http://www.w3.org/2001/10/synthesis" xml:lang="en-US">
+          <!-- A comment -->
+          <p><s>Hello <prosody rate="fast" volume="+50%">big <prosody pitch="high">world</prosody></prosody>.</s></p>
+          <break strength="strong"/><say-as interpret-as="characters">abc</say-as>
+          <sub alias="World Wide Web">WWW</sub> &amp; more
+        </speak>"#;
+    let markup = r#"
+
+
+          <p><s>Hello <speed level="150"><volume level="150">big <pitch level="150">world</pitch></volume></speed>.</s></p>
+          <break time="700ms"/><spell>abc</spell>
+          World Wide Web & more
+        "#;
+    assert_eq!(
+        synthesize_raw(&["--ssml"], ssml),
+        synthesize_raw(&[], markup)
+    );
+
+    let mut command = cli();
+    command.args(["--ssml", "-w", "-", "<speak><p>Unclosed</s></speak>"]);
+    let output = run(command, "");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("invalid SSML"));
+}
+
+#[test]
+fn list_voices() {
+    let mut command = cli();
+    command.arg("--list-voices");
+    let output = run(command, "");
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let voices: Vec<&str> = stdout
+        .lines()
+        .map(|line| line.rsplit_once('\t').unwrap().0)
+        .collect();
+    assert_eq!(
+        voices,
+        [
+            "de-DE\tgl0",
+            "en-GB\tkh0",
+            "en-US\tlh0",
+            "es-ES\tzl0",
+            "fr-FR\tnk0",
+            "it-IT\tcm0"
+        ]
+    );
+}
+
+#[test]
+fn unknown_language() {
+    let mut command = cli();
+    command.args(["-l", "xx-XX", "-w", "-", "Hello"]);
+    let output = run(command, "");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("no language pack for xx-XX"));
+}
+
+/// Runs `ttspico-cli --batch manifest` with `args`, returning its summary of the report.
+fn run_batch(manifest: &PathBuf, args: &[&str]) -> String {
+    let mut command = cli();
+    command.arg("--batch").arg(manifest).args(args);
+    let output = run(command, "");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    stderr.lines().last().unwrap_or("").to_string()
+}
+
+#[test]
+fn batch_manifest() {
+    let dir = temp_path("batch");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let manifest = dir.join("prompts.toml");
+    let write_manifest = |greeting: &str| {
+        let toml = format!(
+            r#"
+            [[entry]]
+            id = "greeting"
+            text = "{}"
+
+            [[entry]]
+            id = "goodbye"
+            voice = "it-IT"
+            text = "Arrivederci"
+            speed = 150
+            format = "raw"
+
+            [[entry]]
+            id = "missing-clip"
+            text = "Before <play file='/nonexistent.wav'/> after"
+            "#,
+            greeting
+        );
+        fs::write(&manifest, toml).unwrap();
+    };
+
+    write_manifest("Hello world");
+    assert_eq!(
+        run_batch(&manifest, &["-j", "2"]),
+        format!(
+            "ttspico-cli: 3 synthesized, 0 unchanged, 0 failed (see {})",
+            dir.join("report.json").display()
+        )
+    );
+    let greeting = fs::read(dir.join("greeting.wav")).unwrap();
+    assert_eq!(
+        &greeting[WAV_HEADER_LEN..],
+        synthesize_raw(&[], "Hello world")
+    );
+    assert_eq!(
+        fs::read(dir.join("goodbye.raw")).unwrap(),
+        synthesize_raw(&["-l", "it-IT"], "<speed level=\"150\">Arrivederci</speed>")
+    );
+    let report = fs::read_to_string(dir.join("report.json")).unwrap();
+    assert!(report.contains("\"duration_ms\""));
+    assert!(report.contains("file '/nonexistent.wav' not found"));
+
+    // (Only changed entries are synthesized again, unless forced to)
+    assert!(run_batch(&manifest, &[]).contains("0 synthesized, 3 unchanged, 0 failed"));
+    write_manifest("Hello again");
+    assert!(run_batch(&manifest, &[]).contains("1 synthesized, 2 unchanged, 0 failed"));
+    assert!(run_batch(&manifest, &["--force"]).contains("3 synthesized, 0 unchanged, 0 failed"));
+    fs::remove_file(dir.join("goodbye.raw")).unwrap();
+    assert!(run_batch(&manifest, &[]).contains("1 synthesized, 2 unchanged, 0 failed"));
+
+    let csv_manifest = dir.join("prompts.csv");
+    fs::write(
+        &csv_manifest,
+        "id,voice,text,pitch\nhello,en-GB,\"Hello, world\",120\nunknown,xx-XX,Hello,\n",
+    )
+    .unwrap();
+    let out_dir = dir.join("csv");
+    assert!(
+        run_batch(&csv_manifest, &["--out-dir", out_dir.to_str().unwrap()])
+            .contains("1 entries failed")
+    );
+    assert!(out_dir.join("hello.wav").is_file());
+    assert!(fs::read_to_string(out_dir.join("report.json"))
+        .unwrap()
+        .contains("no language pack for xx-XX"));
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+/// Entries synthesized one after another by the same engine are spoken the same as on their own.
+#[test]
+fn batch_reused_engine() {
+    let dir = temp_path("batch-reused");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let manifest = dir.join("prompts.csv");
+    let texts = ["Good morning, everyone!", "Hello world", "Hello world"];
+    let mut csv = "id,text,format\n".to_string();
+    for (i, text) in texts.iter().enumerate() {
+        csv += &format!("entry{},\"{}\",raw\n", i, text);
+    }
+    fs::write(&manifest, csv).unwrap();
+    assert!(run_batch(&manifest, &["-j", "1"]).contains("3 synthesized, 0 unchanged, 0 failed"));
+    for (i, text) in texts.iter().enumerate() {
+        assert_eq!(
+            fs::read(dir.join(format!("entry{}.raw", i))).unwrap(),
+            synthesize_raw(&[], text),
+            "{}",
+            text
+        );
+    }
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,218 @@
+//! A local speech synthesis daemon for the Pico TTS engine, listening on a Unix domain socket.
+//!
+//! All clients share one Pico system, which loads the language packs once at startup (instead of each client paying
+//! for it); their requests are spoken one at a time, in the order they arrive. A client that does not read its
+//! reply has its request dropped after `--write-timeout`, so that it cannot hold up the others. See [`protocol`] for
+//! the protocol.
+
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod protocol;
+mod synth;
+
+use clap::Parser;
+use protocol::{Request, Speak};
+use serde_json::{json, Value};
+use std::io::{self, BufReader, BufWriter, Write};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+use synth::{Job, Synth};
+use ttspico::VoiceCatalog;
+
+/// Serves speech synthesized with Pico TTS to local clients, over a Unix domain socket.
+#[derive(Debug, Parser)]
+#[command(name = "ttspicod", version)]
+struct Args {
+    /// Path of the socket to listen on (by default `ttspicod.sock` in `$XDG_RUNTIME_DIR`, else in `/tmp`)
+    #[arg(short = 's', long, value_name = "PATH")]
+    socket: Option<PathBuf>,
+
+    /// Voice of the requests that do not specify one
+    #[arg(
+        short = 'l',
+        long = "lang",
+        value_name = "LANG",
+        default_value = "en-US"
+    )]
+    lang: String,
+
+    /// Directory of the language packs, instead of the default ones
+    #[arg(long, value_name = "DIR")]
+    lang_dir: Option<PathBuf>,
+
+    /// Seconds to wait for a client to take the audio it is sent, before dropping its request (and connection)
+    #[arg(long, value_name = "SECS", default_value_t = 10.0)]
+    write_timeout: f64,
+}
+
+/// What all connections share.
+struct Context {
+    /// The available voices.
+    voices: VoiceCatalog,
+    default_voice: String,
+    synth: Synth,
+    /// How long writing to a client may block (i.e. keep the synthesis thread from the other clients).
+    write_timeout: Duration,
+}
+
+/// Picks the voice of `language` (or of a dialect of it), else the default one if no language is given.
+fn pick_voice<'a>(ctx: &'a Context, language: Option<&str>) -> Result<&'a str, String> {
+    match language {
+        Some(language) => ctx
+            .voices
+            .find_voice(language)
+            .map(|voice| voice.language.as_str())
+            .ok_or_else(|| format!("no voice for {}", language.replace('_', "-"))),
+        None => Ok(&ctx.default_voice),
+    }
+}
+
+/// Returns the job speaking `request`, replying to `output`.
+#[cfg(unix)]
+fn job(request: Speak, output: UnixStream, ctx: &Context) -> Result<Job, String> {
+    let voice = pick_voice(ctx, request.voice.as_deref())?.to_string();
+    let (text, words) = if request.words {
+        ttspico::mark_words(&request.text)
+    } else {
+        (request.text, Vec::new())
+    };
+    let mut markup = if request.ssml {
+        ttspico::ssml_to_markup(&text).map_err(|err| err.descr)?
+    } else {
+        text
+    };
+    for (tag, level) in [
+        ("speed", request.speed),
+        ("pitch", request.pitch),
+        ("volume", request.volume),
+    ] {
+        if let Some(level) = level {
+            markup = format!("<{} level=\"{}\">{}</{}>", tag, level, markup, tag);
+        }
+    }
+    Ok(Job {
+        voice,
+        markup,
+        words,
+        output: Box::new(BufWriter::new(output)),
+    })
+}
+
+/// Serves a connection, until the client closes it.
+#[cfg(unix)]
+fn serve(stream: UnixStream, ctx: &Context) -> io::Result<()> {
+    // (Shared by the clones of the stream, including the output of the jobs)
+    stream.set_write_timeout(Some(ctx.write_timeout))?;
+    let mut input = BufReader::new(stream.try_clone()?);
+    while let Some(request) = protocol::read_request(&mut input)? {
+        let mut output = BufWriter::new(stream.try_clone()?);
+        match serde_json::from_slice(&request) {
+            Ok(Request::Speak(request)) => match job(request, stream.try_clone()?, ctx) {
+                Ok(job) => ctx.synth.speak(job)?,
+                Err(err) => protocol::write_error(&mut output, &err)?,
+            },
+            Ok(Request::Voices) => {
+                let voices: Vec<Value> = ctx
+                    .voices
+                    .voices()
+                    .iter()
+                    .map(|voice| json!({ "language": voice.language, "speaker": voice.speaker }))
+                    .collect();
+                protocol::write_json(&mut output, protocol::VOICES, &Value::Array(voices))?
+            }
+            Err(err) => protocol::write_error(&mut output, &format!("invalid request: {}", err))?,
+        }
+        output.flush()?;
+    }
+    Ok(())
+}
+
+/// Returns the socket path used by default.
+fn default_socket() -> PathBuf {
+    std::env::var_os("XDG_RUNTIME_DIR")
+        .filter(|dir| !dir.is_empty())
+        .map_or_else(|| PathBuf::from("/tmp"), PathBuf::from)
+        .join("ttspicod.sock")
+}
+
+#[cfg(unix)]
+fn run(args: Args) -> Result<(), String> {
+    let write_timeout = Duration::try_from_secs_f64(args.write_timeout)
+        .ok()
+        .filter(|timeout| !timeout.is_zero())
+        .ok_or_else(|| format!("invalid write timeout: {}", args.write_timeout))?;
+    let voices = VoiceCatalog::new(ttspico::lang_search_dirs(args.lang_dir.as_deref()));
+    if voices.get(&args.lang).is_none() {
+        return Err(format!("no language pack for {}", args.lang));
+    }
+
+    let memsz = voices
+        .system_builder()
+        .measure()
+        .map_err(|err| err.to_string())?;
+    let synth = Synth::start(memsz, &voices)?;
+
+    let socket = args.socket.clone().unwrap_or_else(default_socket);
+    if UnixStream::connect(&socket).is_ok() {
+        return Err(format!("{} is in use by another daemon", socket.display()));
+    }
+    // (Left over by a daemon that did not quit cleanly)
+    let _ = std::fs::remove_file(&socket);
+    let listener = UnixListener::bind(&socket)
+        .map_err(|err| format!("failed to listen on {}: {}", socket.display(), err))?;
+    let ctx = Context {
+        voices,
+        default_voice: args.lang.clone(),
+        synth,
+        write_timeout,
+    };
+
+    println!("ttspicod: listening on {}", socket.display());
+    let _ = io::stdout().flush();
+    std::thread::scope(|scope| {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let ctx = &ctx;
+                    scope.spawn(move || {
+                        if let Err(err) = serve(stream, ctx) {
+                            eprintln!("ttspicod: connection failed: {}", err);
+                        }
+                    });
+                }
+                Err(err) => eprintln!("ttspicod: failed to accept a connection: {}", err),
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn run(_args: Args) -> Result<(), String> {
+    Err("Unix domain sockets are not supported on this platform".to_string())
+}
+
+fn main() -> ExitCode {
+    match run(Args::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("ttspicod: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,129 @@
+//! The daemon's protocol, over a Unix domain socket.
+//!
+//! Clients send requests, each a little-endian `u32` length followed by as many bytes of JSON:
+//! - `{"type": "speak", "text": "Hello"}` speaks `text` (with Pico's markup, or SSML if `"ssml": true`). Optional
+//!   fields: `voice` (a language, by default the daemon's `--lang`), `speed`, `pitch` and `volume` (levels in
+//!   percent, as in Pico's markup) and `words` (whether to report word boundaries);
+//! - `{"type": "voices"}` lists the available voices.
+//!
+//! The daemon replies with frames, each a kind byte, a little-endian `u32` length and as many bytes of body:
+//! - `A`: audio, as 16-bit little-endian mono PCM at 16kHz;
+//! - `W`: a word boundary, as `{"sample": N, "offset": O, "length": L}`: the word at byte `O` of the text (`L`
+//!   bytes long) starts `N` samples into the audio;
+//! - `M`: a `<mark>` of the text, as `{"sample": N, "name": "..."}`;
+//! - `E`: the end of the speech, as `{"samples": N}`;
+//! - `V`: the voices, as `[{"language": "en-US", "speaker": "lh0"}, ...]`;
+//! - `X`: an error, as `{"error": "..."}`, ending the reply to the request.
+//!
+//! Each `speak` is replied to with audio, word and mark frames (in the order they occur in the speech), then `E`
+//! or `X`; each `voices` with `V`.
+
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::io::{self, Read, Write};
+
+/// Maximum length of a request, against misbehaving clients.
+const MAX_REQUEST_LEN: u32 = 1024 * 1024;
+
+pub const AUDIO: u8 = b'A';
+pub const WORD: u8 = b'W';
+pub const MARK: u8 = b'M';
+pub const END: u8 = b'E';
+pub const VOICES: u8 = b'V';
+pub const ERROR: u8 = b'X';
+
+/// A request.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Request {
+    Speak(Speak),
+    Voices,
+}
+
+/// A `speak` request.
+#[derive(Debug, Deserialize)]
+pub struct Speak {
+    /// Text to speak, with Pico's markup (or SSML, if `ssml` is set).
+    pub text: String,
+    /// Language of the voice to speak with (by default the daemon's).
+    #[serde(default)]
+    pub voice: Option<String>,
+    /// Whether `text` is SSML.
+    #[serde(default)]
+    pub ssml: bool,
+    /// Speed level, in percent of the default.
+    #[serde(default)]
+    pub speed: Option<u32>,
+    /// Pitch level, in percent of the default.
+    #[serde(default)]
+    pub pitch: Option<u32>,
+    /// Volume level, in percent of the default.
+    #[serde(default)]
+    pub volume: Option<u32>,
+    /// Whether to report word boundaries.
+    #[serde(default)]
+    pub words: bool,
+}
+
+/// Reads a request's bytes; `None` if the client closed the connection (between requests).
+pub fn read_request(input: &mut impl Read) -> io::Result<Option<Vec<u8>>> {
+    let mut len = [0u8; 4];
+    let mut n_read = 0;
+    while n_read < len.len() {
+        match input.read(&mut len[n_read..])? {
+            0 if n_read == 0 => return Ok(None),
+            0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+            n => n_read += n,
+        }
+    }
+    let len = u32::from_le_bytes(len);
+    if len > MAX_REQUEST_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("request of {} bytes", len),
+        ));
+    }
+    let mut request = vec![0; len as usize];
+    input.read_exact(&mut request)?;
+    Ok(Some(request))
+}
+
+/// Writes a frame of the given kind.
+pub fn write_frame(output: &mut impl Write, kind: u8, body: &[u8]) -> io::Result<()> {
+    output.write_all(&[kind])?;
+    output.write_all(&(body.len() as u32).to_le_bytes())?;
+    output.write_all(body)
+}
+
+/// Writes a frame of the given kind with a JSON body.
+pub fn write_json(output: &mut impl Write, kind: u8, body: &Value) -> io::Result<()> {
+    write_frame(output, kind, body.to_string().as_bytes())
+}
+
+/// Writes an audio frame.
+pub fn write_audio(output: &mut impl Write, samples: &[i16]) -> io::Result<()> {
+    let body: Vec<u8> = samples
+        .iter()
+        .flat_map(|sample| sample.to_le_bytes())
+        .collect();
+    write_frame(output, AUDIO, &body)
+}
+
+/// Writes an error frame.
+pub fn write_error(output: &mut impl Write, message: &str) -> io::Result<()> {
+    write_json(output, ERROR, &json!({ "error": message }))
+}
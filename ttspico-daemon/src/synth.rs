@@ -0,0 +1,119 @@
+//! The synthesis thread, which owns the daemon's Pico system and speaks the requests of all clients in turn.
+
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::protocol;
+use serde_json::json;
+use std::io::{self, Write};
+use std::sync::mpsc;
+use ttspico::{MultiVoiceSynthesizer, SynthesizerPool, VoiceCatalog, Word};
+
+/// Number of samples sent to clients at a time (a tenth of a second), unless a mark comes first.
+const CHUNK_LEN: usize = ttspico::SAMPLE_RATE as usize / 10;
+
+/// A request to speak.
+pub struct Job {
+    /// The voice (i.e. language) to speak with.
+    pub voice: String,
+    /// The text to speak, with Pico's markup.
+    pub markup: String,
+    /// The words whose boundaries to report, by the index in their marks.
+    pub words: Vec<Word>,
+    /// Where the reply goes.
+    pub output: Box<dyn Write + Send>,
+}
+
+/// Speaks `job`, writing its audio and events to its output as they are synthesized.
+/// Writing errors (i.e. the client being gone, or not reading within its write timeout) stop the synthesis.
+fn speak(synth: &mut MultiVoiceSynthesizer, job: &mut Job) -> io::Result<()> {
+    let (output, words) = (&mut job.output, &job.words);
+    // (So that the speech does not depend on the requests spoken before, the engine is fully reset)
+    let engine = match synth.engine(&job.voice) {
+        Ok(engine) => engine,
+        Err(err) => return protocol::write_error(output, &err.to_string()),
+    };
+    let mut input = job.markup.as_bytes();
+    let mut samples = Vec::with_capacity(CHUNK_LEN);
+    let mut n_samples = 0;
+    let mut pcm_buf = [0i16; 1024];
+    loop {
+        let (n_step, done) = match engine.step(&mut input, &mut pcm_buf[..]) {
+            Ok(step) => step,
+            // (The audio has started already, so it can only be cut short)
+            Err(err) => return protocol::write_error(output, &err.to_string()),
+        };
+        samples.extend_from_slice(&pcm_buf[..n_step]);
+        n_samples += n_step;
+
+        // (Marks are reached by steps without audio, so the audio so far ends exactly at them)
+        for mark in engine.take_marks() {
+            if !samples.is_empty() {
+                protocol::write_audio(output, &samples)?;
+                samples.clear();
+            }
+            match ttspico::word_index(&mark.name).and_then(|i| words.get(i)) {
+                Some(word) => protocol::write_json(
+                    output,
+                    protocol::WORD,
+                    &json!({ "sample": mark.sample, "offset": word.offset, "length": word.length }),
+                )?,
+                None => protocol::write_json(
+                    output,
+                    protocol::MARK,
+                    &json!({ "sample": mark.sample, "name": mark.name }),
+                )?,
+            }
+        }
+
+        if samples.len() >= CHUNK_LEN || (done && !samples.is_empty()) {
+            protocol::write_audio(output, &samples)?;
+            samples.clear();
+        }
+        if done {
+            return protocol::write_json(output, protocol::END, &json!({ "samples": n_samples }));
+        }
+    }
+}
+
+/// The synthesis thread.
+pub struct Synth {
+    /// The jobs to speak, each with where to send the outcome of writing its reply.
+    jobs: SynthesizerPool<(Job, mpsc::Sender<io::Result<()>>)>,
+}
+
+impl Synth {
+    /// Starts the synthesis thread, with a system of `memsz` bytes loading the voices of `catalog`.
+    pub fn start(memsz: usize, catalog: &VoiceCatalog) -> Result<Self, String> {
+        let jobs = SynthesizerPool::start(
+            1,
+            memsz,
+            catalog,
+            |(mut job, done): (Job, mpsc::Sender<_>), synth| {
+                let written = speak(synth, &mut job).and_then(|_| job.output.flush());
+                let _ = done.send(written);
+            },
+        )
+        .map_err(|err| err.to_string())?;
+        Ok(Synth { jobs })
+    }
+
+    /// Speaks `job` after the ones queued before it, waiting until it is spoken; returns the outcome of writing it.
+    pub fn speak(&self, job: Job) -> io::Result<()> {
+        let (done_tx, done_rx) = mpsc::channel();
+        let stopped = || io::Error::other("the synthesis thread stopped");
+        self.jobs.send((job, done_tx)).map_err(|_| stopped())?;
+        done_rx.recv().map_err(|_| stopped())?
+    }
+}
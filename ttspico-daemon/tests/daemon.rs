@@ -0,0 +1,351 @@
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Runs `ttspicod` on a socket in a temporary directory, with the language packs bundled with Pico's sources.
+
+#![cfg(unix)]
+
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::rc::Rc;
+use std::time::Duration;
+use ttspico::{EngineStatus, System, Voice};
+use ttspico_sys as native;
+
+/// Sample rate of Pico's output.
+const SAMPLE_RATE: usize = ttspico::SAMPLE_RATE as usize;
+
+/// A running `ttspicod`, killed on drop.
+struct Daemon {
+    child: Child,
+    socket: PathBuf,
+}
+
+impl Daemon {
+    fn start(name: &str) -> Self {
+        Self::start_with(name, &[])
+    }
+
+    fn start_with(name: &str, args: &[&str]) -> Self {
+        let socket = std::env::temp_dir().join(format!(
+            "ttspicod-test-{}-{}.sock",
+            std::process::id(),
+            name
+        ));
+        let mut child = Command::new(env!("CARGO_BIN_EXE_ttspicod"))
+            .arg("--socket")
+            .arg(&socket)
+            .args(["--lang-dir", native::BUNDLED_LANG_DIR])
+            .args(args)
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("Failed to run ttspicod");
+        let mut line = String::new();
+        BufReader::new(child.stdout.take().unwrap())
+            .read_line(&mut line)
+            .unwrap();
+        assert!(line.starts_with("ttspicod: listening on"), "{}", line);
+        Daemon { child, socket }
+    }
+
+    fn connect(&self) -> Client {
+        Client(UnixStream::connect(&self.socket).unwrap())
+    }
+}
+
+impl Drop for Daemon {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = std::fs::remove_file(&self.socket);
+    }
+}
+
+/// A frame of a reply.
+#[derive(Debug, PartialEq)]
+enum Frame {
+    Audio(Vec<i16>),
+    Json(u8, Value),
+}
+
+/// A connection to the daemon.
+struct Client(UnixStream);
+
+impl Client {
+    fn send(&mut self, request: &[u8]) {
+        self.0
+            .write_all(&(request.len() as u32).to_le_bytes())
+            .unwrap();
+        self.0.write_all(request).unwrap();
+    }
+
+    fn receive(&mut self) -> Frame {
+        let mut head = [0u8; 5];
+        self.0.read_exact(&mut head).unwrap();
+        let mut body = vec![0; u32::from_le_bytes([head[1], head[2], head[3], head[4]]) as usize];
+        self.0.read_exact(&mut body).unwrap();
+        match head[0] {
+            b'A' => Frame::Audio(
+                body.chunks(2)
+                    .map(|sample| i16::from_le_bytes([sample[0], sample[1]]))
+                    .collect(),
+            ),
+            kind => Frame::Json(kind, serde_json::from_slice(&body).unwrap()),
+        }
+    }
+
+    /// Sends a `speak` request, returning its audio and the other frames of the reply (up to its end or error).
+    fn speak(&mut self, request: Value) -> (Vec<i16>, Vec<(u8, Value)>) {
+        self.send(request.to_string().as_bytes());
+        let mut samples = Vec::new();
+        let mut events = Vec::new();
+        loop {
+            match self.receive() {
+                Frame::Audio(audio) => samples.extend(audio),
+                Frame::Json(kind, body) => {
+                    events.push((kind, body));
+                    if kind == b'E' || kind == b'X' {
+                        return (samples, events);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Synthesizes `text` with a bundled language pack in this process.
+fn synthesize(ta: &str, sg: &str, text: &str) -> Vec<i16> {
+    let sys = System::new(8 * 1024 * 1024).unwrap();
+    let lang_file = |name: &str| format!("{}/{}", native::BUNDLED_LANG_DIR, name);
+    let ta_res = System::load_resource(Rc::clone(&sys), lang_file(ta)).unwrap();
+    let sg_res = System::load_resource(Rc::clone(&sys), lang_file(sg)).unwrap();
+    let voice = System::create_voice(sys, "voice").unwrap();
+    voice.borrow_mut().add_resource(ta_res).unwrap();
+    voice.borrow_mut().add_resource(sg_res).unwrap();
+    let mut engine = unsafe { Voice::create_engine(voice).unwrap() };
+
+    engine.put_text(text).unwrap();
+    engine.flush().unwrap();
+    let mut samples = Vec::new();
+    let mut pcm_buf = [0i16; 1024];
+    loop {
+        let (n_samples, status) = engine.get_data(&mut pcm_buf[..]).unwrap();
+        samples.extend_from_slice(&pcm_buf[..n_samples]);
+        if status == EngineStatus::Idle {
+            return samples;
+        }
+    }
+}
+
+#[test]
+fn speak() {
+    let daemon = Daemon::start("speak");
+    let mut client = daemon.connect();
+
+    client.send(br#"{"type": "voices"}"#);
+    match client.receive() {
+        Frame::Json(b'V', voices) => {
+            assert_eq!(voices.as_array().unwrap().len(), 6);
+            assert!(voices
+                .as_array()
+                .unwrap()
+                .contains(&json!({ "language": "en-US", "speaker": "lh0" })));
+        }
+        frame => panic!("{:?}", frame),
+    }
+
+    let hello = synthesize("en-US_ta.bin", "en-US_lh0_sg.bin", "Hello world");
+    let (samples, events) = client.speak(json!({ "type": "speak", "text": "Hello world" }));
+    assert_eq!(samples, hello);
+    assert_eq!(events, [(b'E', json!({ "samples": hello.len() }))]);
+
+    // (The voice is picked by language, a dialect if needed)
+    let (samples, _) =
+        client.speak(json!({ "type": "speak", "text": "Ciao mondo", "voice": "it" }));
+    assert_eq!(
+        samples,
+        synthesize("it-IT_ta.bin", "it-IT_cm0_sg.bin", "Ciao mondo")
+    );
+
+    let (samples, _) = client.speak(json!({
+        "type": "speak",
+        "text": "<speak>Hello <break time=\"500ms\"/> world</speak>",
+        "ssml": true,
+        "speed": 150,
+    }));
+    assert_eq!(
+        samples,
+        synthesize(
+            "en-US_ta.bin",
+            "en-US_lh0_sg.bin",
+            "<speed level=\"150\">Hello <break time=\"500ms\"/> world</speed>"
+        )
+    );
+}
+
+#[test]
+fn events() {
+    let daemon = Daemon::start("events");
+    let mut client = daemon.connect();
+
+    let text = "Hello, <mark name=\"here\"/>big world.";
+    let (samples, events) = client.speak(json!({ "type": "speak", "text": text, "words": true }));
+    let kinds: Vec<u8> = events.iter().map(|(kind, _)| *kind).collect();
+    assert_eq!(kinds, b"WMWWE");
+    let words: Vec<&str> = events
+        .iter()
+        .filter(|(kind, _)| *kind == b'W')
+        .map(|(_, word)| {
+            let offset = word["offset"].as_u64().unwrap() as usize;
+            &text[offset..offset + word["length"].as_u64().unwrap() as usize]
+        })
+        .collect();
+    assert_eq!(words, ["Hello", "big", "world"]);
+    assert_eq!(events[1].1["name"], "here");
+
+    // (Events are sent in the order they occur in the speech, which they are at sample positions of)
+    let positions: Vec<u64> = events
+        .iter()
+        .map(|(_, event)| {
+            event
+                .get("sample")
+                .unwrap_or(&event["samples"])
+                .as_u64()
+                .unwrap()
+        })
+        .collect();
+    assert!(positions.windows(2).all(|pair| pair[0] <= pair[1]));
+    assert!(positions[2] > positions[0]);
+    assert_eq!(positions[4], samples.len() as u64);
+}
+
+#[test]
+fn clients() {
+    let daemon = Daemon::start("clients");
+    let hello = synthesize("en-US_ta.bin", "en-US_lh0_sg.bin", "Hello world");
+
+    // (Clients share the daemon, their requests being spoken in turn)
+    let threads: Vec<_> = (0..3)
+        .map(|_| {
+            let mut client = daemon.connect();
+            std::thread::spawn(move || {
+                (0..2)
+                    .map(|_| {
+                        client
+                            .speak(json!({ "type": "speak", "text": "Hello world" }))
+                            .0
+                    })
+                    .collect::<Vec<_>>()
+            })
+        })
+        .collect();
+    for thread in threads {
+        assert_eq!(thread.join().unwrap(), [hello.clone(), hello.clone()]);
+    }
+
+    // (A client that is gone does not stop the daemon)
+    let mut gone = daemon.connect();
+    gone.send(
+        json!({ "type": "speak", "text": "The quick brown fox. ".repeat(20) })
+            .to_string()
+            .as_bytes(),
+    );
+    drop(gone);
+    let (samples, _) = daemon
+        .connect()
+        .speak(json!({ "type": "speak", "text": "Hello world" }));
+    assert_eq!(samples, hello);
+}
+
+#[test]
+fn stalled_client() {
+    let daemon = Daemon::start_with("stalled", &["--write-timeout", "0.5"]);
+    let hello = synthesize("en-US_ta.bin", "en-US_lh0_sg.bin", "Hello world");
+
+    // (A client that does not read its reply, which is longer than the socket can buffer, has its request dropped
+    // instead of keeping the daemon from the other clients)
+    let mut stalled = daemon.connect();
+    stalled.send(
+        json!({ "type": "speak", "text": "The quick brown fox. ".repeat(200) })
+            .to_string()
+            .as_bytes(),
+    );
+    std::thread::sleep(Duration::from_millis(200));
+    let mut client = daemon.connect();
+    client
+        .0
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    let (samples, _) = client.speak(json!({ "type": "speak", "text": "Hello world" }));
+    assert_eq!(samples, hello);
+
+    // (...and its connection closed, its reply cut short)
+    let mut reply = Vec::new();
+    stalled.0.read_to_end(&mut reply).unwrap();
+    assert!(!reply.is_empty());
+    assert!(reply.len() < 200 * SAMPLE_RATE * 2);
+}
+
+#[test]
+fn errors() {
+    let daemon = Daemon::start("errors");
+    let mut client = daemon.connect();
+
+    let error = |client: &mut Client, request: &[u8]| {
+        client.send(request);
+        match client.receive() {
+            Frame::Json(b'X', error) => error["error"].as_str().unwrap().to_string(),
+            frame => panic!("{:?}", frame),
+        }
+    };
+    assert!(error(&mut client, b"not json").starts_with("invalid request"));
+    assert!(error(&mut client, br#"{"type": "shout"}"#).starts_with("invalid request"));
+    assert_eq!(
+        error(
+            &mut client,
+            br#"{"type": "speak", "text": "Hi", "voice": "xx"}"#
+        ),
+        "no voice for xx"
+    );
+    assert!(!error(
+        &mut client,
+        br#"{"type": "speak", "text": "<speak>Hi</s>", "ssml": true}"#
+    )
+    .is_empty());
+
+    // (The connection is still usable)
+    let (samples, _) = client.speak(json!({ "type": "speak", "text": "Hello world" }));
+    assert!(!samples.is_empty());
+
+    // (So is the socket, by a second daemon)
+    let mut second = Command::new(env!("CARGO_BIN_EXE_ttspicod"))
+        .arg("--socket")
+        .arg(&daemon.socket)
+        .args(["--lang-dir", native::BUNDLED_LANG_DIR])
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    assert!(!second.wait().unwrap().success());
+    let mut stderr = String::new();
+    second
+        .stderr
+        .take()
+        .unwrap()
+        .read_to_string(&mut stderr)
+        .unwrap();
+    assert!(stderr.contains("in use"), "{}", stderr);
+}
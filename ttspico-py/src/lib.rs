@@ -0,0 +1,451 @@
+//! Python bindings to [`ttspico`], as the `ttspico` module:
+//!
+//! ```python
+//! import ttspico
+//!
+//! synth = ttspico.Synthesizer(voice="en-US")
+//! audio = synth.synthesize("Hello world")  # numpy.ndarray of int16, at ttspico.SAMPLE_RATE
+//! for chunk in synth.stream("A longer text, spoken as it is synthesized."):
+//!     ...
+//! speech = synth.synthesize_timed('Hello <mark name="here"/> world')
+//! print(speech.words, speech.marks)
+//! ```
+//!
+//! Each synthesizer has its own Pico system, on a thread of its own; the GIL is released while waiting for its
+//! speech, so Python threads with different synthesizers synthesize in parallel.
+
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod synth;
+
+use numpy::{IntoPyArray, PyArray1};
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use synth::{Event, Synth};
+use ttspico::{CatalogVoice, VoiceCatalog, SAMPLE_RATE};
+
+/// Number of samples of the chunks of a stream by default (a tenth of a second).
+const DEFAULT_CHUNK_SIZE: usize = SAMPLE_RATE as usize / 10;
+
+pyo3::create_exception!(
+    ttspico,
+    PicoError,
+    PyRuntimeError,
+    "An error raised by Pico, i.e. if a synthesizer fails to load its voice."
+);
+
+fn pico_err(err: ttspico::PicoError) -> PyErr {
+    PicoError::new_err(err.to_string())
+}
+
+/// An installed voice, i.e. a language pack.
+#[pyclass(module = "ttspico", frozen, get_all)]
+#[derive(Debug, Clone)]
+struct Voice {
+    /// Its language, i.e. `"en-US"`.
+    language: String,
+    /// Its speaker, i.e. `"lh0"`.
+    speaker: String,
+    /// Filepath of its TA (text analysis) resource.
+    ta_path: String,
+    /// Filepath of its SG (signal generation) resource.
+    sg_path: String,
+}
+
+#[pymethods]
+impl Voice {
+    fn __repr__(&self) -> String {
+        format!(
+            "Voice(language={:?}, speaker={:?})",
+            self.language, self.speaker
+        )
+    }
+}
+
+impl From<&CatalogVoice> for Voice {
+    fn from(voice: &CatalogVoice) -> Self {
+        Voice {
+            language: voice.language.clone(),
+            speaker: voice.speaker.clone(),
+            ta_path: voice.ta_path.clone(),
+            sg_path: voice.sg_path.clone(),
+        }
+    }
+}
+
+/// Returns the voices in `lang_dir` (if given), or else in `$TTSPICO_LANG_DIR`, `/usr/share/pico/lang` and the ones
+/// bundled with Pico's sources; one per language (the first directory with a language pack for it wins).
+fn voice_catalog(lang_dir: Option<PathBuf>) -> VoiceCatalog {
+    VoiceCatalog::new(ttspico::lang_search_dirs(lang_dir.as_deref()))
+}
+
+/// Lists the installed voices, sorted by language.
+///
+/// They are looked for in `lang_dir` if given, else in `$TTSPICO_LANG_DIR`, `/usr/share/pico/lang`, then the ones
+/// bundled with Pico's sources.
+#[pyfunction]
+#[pyo3(signature = (lang_dir = None))]
+fn voices(lang_dir: Option<PathBuf>) -> Vec<Voice> {
+    voice_catalog(lang_dir)
+        .voices()
+        .iter()
+        .map(Voice::from)
+        .collect()
+}
+
+/// A `<mark>` of the text, reached by the speech.
+#[pyclass(module = "ttspico", frozen, get_all)]
+#[derive(Debug, Clone)]
+struct Mark {
+    /// The mark's `name`.
+    name: String,
+    /// Position of the mark in the speech, as the number of samples before it.
+    sample: usize,
+}
+
+#[pymethods]
+impl Mark {
+    /// Position of the mark in the speech, in seconds.
+    #[getter]
+    fn time(&self) -> f64 {
+        self.sample as f64 / SAMPLE_RATE as f64
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Mark(name={:?}, sample={})", self.name, self.sample)
+    }
+}
+
+/// A word of the text, and when the speech reaches it.
+#[pyclass(module = "ttspico", frozen, get_all)]
+#[derive(Debug, Clone)]
+struct Word {
+    /// The word.
+    text: String,
+    /// Position of the word in the text, as an index into the `str`.
+    offset: usize,
+    /// Length of the word, in characters.
+    length: usize,
+    /// Position of the start of the word in the speech, as the number of samples before it.
+    sample: usize,
+}
+
+#[pymethods]
+impl Word {
+    /// Position of the start of the word in the speech, in seconds.
+    #[getter]
+    fn time(&self) -> f64 {
+        self.sample as f64 / SAMPLE_RATE as f64
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Word(text={:?}, offset={}, sample={})",
+            self.text, self.offset, self.sample
+        )
+    }
+}
+
+/// A text being spoken: its marks and words, as they are reached.
+struct Timings {
+    text: String,
+    /// The words whose marks were put into the text.
+    text_words: Vec<ttspico::Word>,
+    marks: Vec<Mark>,
+    words: Vec<Word>,
+}
+
+impl Timings {
+    /// Adds a mark reached by the speech, which is either a word's or one of the text.
+    fn add(&mut self, mark: ttspico::Mark) {
+        match ttspico::word_index(&mark.name).and_then(|i| self.text_words.get(i)) {
+            Some(text_word) => {
+                // (Python indexes strings by character)
+                let offset = text_word.offset;
+                let word = &self.text[offset..offset + text_word.length];
+                self.words.push(Word {
+                    text: word.to_string(),
+                    offset: self.text[..offset].chars().count(),
+                    length: word.chars().count(),
+                    sample: mark.sample,
+                })
+            }
+            None => self.marks.push(Mark {
+                name: mark.name,
+                sample: mark.sample,
+            }),
+        }
+    }
+}
+
+/// A synthesized speech, with its marks and word timings.
+#[pyclass(module = "ttspico", frozen)]
+struct Speech {
+    audio: Py<PyArray1<i16>>,
+    marks: Vec<Mark>,
+    words: Vec<Word>,
+}
+
+#[pymethods]
+impl Speech {
+    /// The speech's samples, as a `numpy.ndarray` of `int16` at `SAMPLE_RATE`.
+    #[getter]
+    fn audio(&self, py: Python<'_>) -> Py<PyArray1<i16>> {
+        self.audio.clone_ref(py)
+    }
+
+    /// The `<mark>`s of the text, as `Mark`s in the order the speech reaches them.
+    #[getter]
+    fn marks(&self) -> Vec<Mark> {
+        self.marks.clone()
+    }
+
+    /// The words of the text, as `Word`s in the order the speech reaches them (empty unless requested).
+    #[getter]
+    fn words(&self) -> Vec<Word> {
+        self.words.clone()
+    }
+}
+
+/// Streams the speech of a text as it is synthesized, as `numpy.ndarray`s of `int16` samples.
+///
+/// The marks and words reached so far are in `marks` and `words`.
+#[pyclass(module = "ttspico")]
+struct SpeechStream {
+    /// The events of the speech, until it is done.
+    events: Option<mpsc::Receiver<Event>>,
+    chunk_size: usize,
+    /// Samples not yet returned.
+    pending: Vec<i16>,
+    timings: Timings,
+}
+
+#[pymethods]
+impl SpeechStream {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__<'py>(&mut self, py: Python<'py>) -> PyResult<Option<Bound<'py, PyArray1<i16>>>> {
+        while self.pending.len() < self.chunk_size {
+            let events = match self.events.take() {
+                Some(events) => events,
+                None => break,
+            };
+            // (The receiver is moved to the closure, which must be `Send`)
+            let (events, event) = py.allow_threads(move || {
+                let event = events.recv();
+                (events, event)
+            });
+            match event.map_err(|_| synth::stopped()).map_err(pico_err)? {
+                Event::Audio(samples) => self.pending.extend_from_slice(&samples),
+                Event::Mark(mark) => self.timings.add(mark),
+                Event::Done(result) => {
+                    result.map_err(pico_err)?;
+                    continue;
+                }
+            }
+            self.events = Some(events);
+        }
+        if self.pending.is_empty() {
+            return Ok(None);
+        }
+        let n_samples = std::cmp::min(self.chunk_size, self.pending.len());
+        let chunk: Vec<i16> = self.pending.drain(..n_samples).collect();
+        Ok(Some(chunk.into_pyarray_bound(py)))
+    }
+
+    /// The `<mark>`s of the text reached so far.
+    #[getter]
+    fn marks(&self) -> Vec<Mark> {
+        self.timings.marks.clone()
+    }
+
+    /// The words of the text reached so far (empty unless requested).
+    #[getter]
+    fn words(&self) -> Vec<Word> {
+        self.timings.words.clone()
+    }
+}
+
+/// A speech synthesizer, speaking with one of the installed voices.
+///
+/// It speaks one text at a time (texts given while another is spoken are queued); each text is spoken from a fully
+/// reset engine, so its speech does not depend on the ones before.
+#[pyclass(module = "ttspico")]
+struct Synthesizer {
+    voice: Voice,
+    synth: Synth,
+}
+
+impl Synthesizer {
+    /// Starts speaking `text`, with a mark before each word if `words` is set.
+    fn speak(
+        &self,
+        text: &str,
+        ssml: bool,
+        words: bool,
+    ) -> PyResult<(mpsc::Receiver<Event>, Timings)> {
+        let (marked, text_words) = if words {
+            ttspico::mark_words(text)
+        } else {
+            (text.to_string(), Vec::new())
+        };
+        let markup = if ssml {
+            ttspico::ssml_to_markup(&marked).map_err(|err| PyValueError::new_err(err.descr))?
+        } else {
+            marked
+        };
+        let events = self.synth.speak(markup).map_err(pico_err)?;
+        let timings = Timings {
+            text: text.to_string(),
+            text_words,
+            marks: Vec::new(),
+            words: Vec::new(),
+        };
+        Ok((events, timings))
+    }
+
+    /// Speaks `text` to the end, returning its samples and timings.
+    fn speak_all(
+        &self,
+        py: Python<'_>,
+        text: &str,
+        ssml: bool,
+        words: bool,
+    ) -> PyResult<(Vec<i16>, Timings)> {
+        let (events, mut timings) = self.speak(text, ssml, words)?;
+        let (samples, marks) = py
+            .allow_threads(move || -> Result<_, ttspico::PicoError> {
+                let mut samples = Vec::new();
+                let mut marks = Vec::new();
+                loop {
+                    match events.recv().map_err(|_| synth::stopped())? {
+                        Event::Audio(chunk) => samples.extend_from_slice(&chunk),
+                        Event::Mark(mark) => marks.push(mark),
+                        Event::Done(result) => return result.map(|()| (samples, marks)),
+                    }
+                }
+            })
+            .map_err(pico_err)?;
+        for mark in marks {
+            timings.add(mark);
+        }
+        Ok((samples, timings))
+    }
+}
+
+#[pymethods]
+impl Synthesizer {
+    /// Creates a synthesizer with the voice of `voice` (a language, i.e. `"en-US"`, or just `"en"` for any of its
+    /// dialects), looked for as by `voices()`.
+    #[new]
+    #[pyo3(signature = (voice = "en-US", lang_dir = None))]
+    fn new(py: Python<'_>, voice: &str, lang_dir: Option<PathBuf>) -> PyResult<Self> {
+        let found = voice_catalog(lang_dir)
+            .find_voice(voice)
+            .map(Voice::from)
+            .ok_or_else(|| PyValueError::new_err(format!("no voice for {}", voice)))?;
+        let synth = py
+            .allow_threads(|| Synth::start(&found.ta_path, &found.sg_path))
+            .map_err(pico_err)?;
+        Ok(Synthesizer {
+            voice: found,
+            synth,
+        })
+    }
+
+    /// The synthesizer's voice.
+    #[getter]
+    fn voice(&self) -> Voice {
+        self.voice.clone()
+    }
+
+    /// Speaks `text` (with Pico's markup, or SSML if `ssml` is set), returning its samples as a `numpy.ndarray` of
+    /// `int16` at `SAMPLE_RATE`. Invalid SSML raises a `ValueError`.
+    #[pyo3(signature = (text, ssml = false))]
+    fn synthesize<'py>(
+        &self,
+        py: Python<'py>,
+        text: &str,
+        ssml: bool,
+    ) -> PyResult<Bound<'py, PyArray1<i16>>> {
+        let (samples, _) = self.speak_all(py, text, ssml, false)?;
+        Ok(samples.into_pyarray_bound(py))
+    }
+
+    /// Speaks `text` like `synthesize()`, returning a `Speech` with its samples, marks and (if `words` is set)
+    /// word timings.
+    ///
+    /// Word timings are reported via marks put before each word, which can change the speech slightly.
+    #[pyo3(signature = (text, ssml = false, words = true))]
+    fn synthesize_timed(
+        &self,
+        py: Python<'_>,
+        text: &str,
+        ssml: bool,
+        words: bool,
+    ) -> PyResult<Speech> {
+        let (samples, timings) = self.speak_all(py, text, ssml, words)?;
+        Ok(Speech {
+            audio: samples.into_pyarray_bound(py).unbind(),
+            marks: timings.marks,
+            words: timings.words,
+        })
+    }
+
+    /// Speaks `text` like `synthesize()`, returning a `SpeechStream` of chunks of `chunk_size` samples (but the
+    /// last) as they are synthesized, with its marks and (if `words` is set) word timings.
+    #[pyo3(signature = (text, ssml = false, words = false, chunk_size = DEFAULT_CHUNK_SIZE))]
+    fn stream(
+        &self,
+        text: &str,
+        ssml: bool,
+        words: bool,
+        chunk_size: usize,
+    ) -> PyResult<SpeechStream> {
+        if chunk_size == 0 {
+            return Err(PyValueError::new_err("chunk_size must be positive"));
+        }
+        let (events, timings) = self.speak(text, ssml, words)?;
+        Ok(SpeechStream {
+            events: Some(events),
+            chunk_size,
+            pending: Vec::with_capacity(chunk_size),
+            timings,
+        })
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Synthesizer(voice={:?})", self.voice.language)
+    }
+}
+
+#[pymodule]
+#[pyo3(name = "ttspico")]
+fn ttspico_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("SAMPLE_RATE", SAMPLE_RATE)?;
+    m.add("PicoError", m.py().get_type_bound::<PicoError>())?;
+    m.add_class::<Voice>()?;
+    m.add_class::<Mark>()?;
+    m.add_class::<Word>()?;
+    m.add_class::<Speech>()?;
+    m.add_class::<SpeechStream>()?;
+    m.add_class::<Synthesizer>()?;
+    m.add_function(wrap_pyfunction!(voices, m)?)?;
+    Ok(())
+}
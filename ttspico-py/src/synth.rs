@@ -0,0 +1,157 @@
+//! The synthesis thread of a synthesizer, which owns its Pico system.
+
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use pyo3::Python;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread::JoinHandle;
+use ttspico::{Engine, EngineResetMode, Mark, PicoError, System, Voice};
+
+/// What the synthesis thread sends back while speaking.
+pub enum Event {
+    /// The next samples.
+    Audio(Vec<i16>),
+    /// A mark reached by the speech, after the samples before it.
+    Mark(Mark),
+    /// The end of the speech (or its error).
+    Done(Result<(), PicoError>),
+}
+
+/// A request to speak.
+struct Job {
+    /// The text to speak, with Pico's markup.
+    markup: String,
+    events: mpsc::Sender<Event>,
+}
+
+/// Speaks `job` with `engine`, sending its samples and marks as they are synthesized, until done, `cancel` is set or
+/// the receiver is gone.
+fn speak(engine: &mut Engine, job: &Job, cancel: &AtomicBool) -> Result<(), PicoError> {
+    // (So that the speech does not depend on the texts spoken before)
+    engine.reset(EngineResetMode::Full)?;
+    let mut input = job.markup.as_bytes();
+    let mut pcm_buf = [0i16; 1024];
+    loop {
+        let (n_samples, done) = engine.step(&mut input, &mut pcm_buf[..])?;
+        // (The speech was dropped if the receiver is gone)
+        if n_samples > 0
+            && job
+                .events
+                .send(Event::Audio(pcm_buf[..n_samples].to_vec()))
+                .is_err()
+        {
+            return Ok(());
+        }
+        // (Marks are reached by steps without audio, so they come after all the samples before them)
+        for mark in engine.take_marks() {
+            if job.events.send(Event::Mark(mark)).is_err() {
+                return Ok(());
+            }
+        }
+        if done {
+            return Ok(());
+        }
+        if cancel.load(Ordering::Relaxed) {
+            return Err(stopped());
+        }
+    }
+}
+
+/// The synthesis thread of a synthesizer, speaking with one voice.
+pub struct Synth {
+    jobs: Option<mpsc::Sender<Job>>,
+    /// Set when the synthesizer is dropped, so that the thread drops the jobs still queued.
+    cancel: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Synth {
+    /// Starts the synthesis thread, with a system sized for the voice of the given TA and SG resources.
+    pub fn start(ta_path: &str, sg_path: &str) -> Result<Self, PicoError> {
+        let (ta_path, sg_path) = (ta_path.to_string(), sg_path.to_string());
+        let (jobs_tx, jobs_rx) = mpsc::channel::<Job>();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let init = move || -> Result<Engine, PicoError> {
+            let sys = System::builder()
+                .language_pack(ta_path.as_str(), sg_path.as_str())
+                .build()?;
+            let ta_res = System::load_resource(Rc::clone(&sys), &ta_path)?;
+            let sg_res = System::load_resource(Rc::clone(&sys), &sg_path)?;
+            let voice = System::create_voice(sys, "voice")?;
+            voice.borrow_mut().add_resource(ta_res)?;
+            voice.borrow_mut().add_resource(sg_res)?;
+            // SAFETY: both a TA and a SG resource were added to the voice above.
+            unsafe { Voice::create_engine(voice) }
+        };
+        let run = {
+            let cancel = Arc::clone(&cancel);
+            move |mut engine: Engine| {
+                for job in jobs_rx {
+                    let result = if cancel.load(Ordering::Relaxed) {
+                        Err(stopped())
+                    } else {
+                        speak(&mut engine, &job, &cancel)
+                    };
+                    let _ = job.events.send(Event::Done(result));
+                }
+            }
+        };
+        let thread = ttspico::spawn_synthesis_thread(init, run)?;
+        Ok(Synth {
+            jobs: Some(jobs_tx),
+            cancel,
+            thread: Some(thread),
+        })
+    }
+
+    /// Speaks `markup` after the texts queued before it, returning the events of its speech.
+    ///
+    /// The events are not bounded, so that a speech that is not consumed does not hold back the ones after it.
+    pub fn speak(&self, markup: String) -> Result<mpsc::Receiver<Event>, PicoError> {
+        let (events_tx, events_rx) = mpsc::channel();
+        let job = Job {
+            markup,
+            events: events_tx,
+        };
+        self.jobs
+            .as_ref()
+            .unwrap()
+            .send(job)
+            .map_err(|_| stopped())?;
+        Ok(events_rx)
+    }
+}
+
+impl Drop for Synth {
+    fn drop(&mut self) {
+        // (Dropping the sender ends the thread's loop once it has dropped the queued jobs, which frees the Pico
+        // system; the GIL is released meanwhile, so that the speech in progress does not hold up other Python threads)
+        self.cancel.store(true, Ordering::Relaxed);
+        self.jobs = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = Python::with_gil(|py| py.allow_threads(|| thread.join()));
+        }
+    }
+}
+
+/// The error of a synthesis thread that stopped (i.e. panicked).
+pub fn stopped() -> PicoError {
+    PicoError {
+        code: -1,
+        descr: "the synthesis thread stopped".to_string(),
+    }
+}
@@ -0,0 +1,108 @@
+# Tests the `ttspico` module (built with `maturin develop`) with the language packs bundled with Pico's sources.
+#
+# Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+# Licensed under the Apache License, Version 2.0.
+
+import os
+from concurrent.futures import ThreadPoolExecutor
+
+import numpy as np
+import pytest
+
+import ttspico
+
+LANG_DIR = os.path.join(os.path.dirname(__file__), "..", "..", "ttspico-sys", "build", "pico", "lang")
+
+
+@pytest.fixture(scope="module")
+def synth():
+    return ttspico.Synthesizer("en-US", lang_dir=LANG_DIR)
+
+
+def test_voices():
+    voices = ttspico.voices(LANG_DIR)
+    assert [voice.language for voice in voices] == ["de-DE", "en-GB", "en-US", "es-ES", "fr-FR", "it-IT"]
+    assert voices[2].speaker == "lh0"
+    assert voices[2].sg_path.endswith("en-US_lh0_sg.bin")
+    assert ttspico.voices("/nonexistent") == []
+
+
+def test_synthesizer_voice():
+    assert ttspico.Synthesizer("it", lang_dir=LANG_DIR).voice.language == "it-IT"
+    with pytest.raises(ValueError, match="no voice for xx"):
+        ttspico.Synthesizer("xx", lang_dir=LANG_DIR)
+
+
+def test_synthesize(synth):
+    audio = synth.synthesize("Hello world")
+    assert isinstance(audio, np.ndarray) and audio.dtype == np.int16
+    assert ttspico.SAMPLE_RATE // 2 < len(audio) < ttspico.SAMPLE_RATE * 3
+    # (Each text is spoken from a fully reset engine)
+    assert np.array_equal(synth.synthesize("Hello world"), audio)
+
+    pause = synth.synthesize('<speak>Hello <break time="500ms"/> world</speak>', ssml=True)
+    assert len(pause) >= len(audio) + ttspico.SAMPLE_RATE // 2
+    with pytest.raises(ValueError):
+        synth.synthesize("<speak>Hello</s>", ssml=True)
+
+
+def test_stream(synth):
+    audio = synth.synthesize("Hello world. How are you?")
+    chunks = list(synth.stream("Hello world. How are you?", chunk_size=1000))
+    assert all(len(chunk) == 1000 for chunk in chunks[:-1])
+    assert 0 < len(chunks[-1]) <= 1000
+    assert np.array_equal(np.concatenate(chunks), audio)
+
+    # (A stream that is not consumed does not hold back the next texts)
+    synth.stream("The quick brown fox jumps over the lazy dog.")
+    assert np.array_equal(synth.synthesize("Hello world. How are you?"), audio)
+    with pytest.raises(ValueError):
+        synth.stream("Hello", chunk_size=0)
+
+
+def test_timings(synth):
+    text = 'Hello, <mark name="here"/>big world.'
+    speech = synth.synthesize_timed(text)
+    assert [mark.name for mark in speech.marks] == ["here"]
+    assert [word.text for word in speech.words] == ["Hello", "big", "world"]
+    assert [text[word.offset:word.offset + word.length] for word in speech.words] == ["Hello", "big", "world"]
+    samples = [word.sample for word in speech.words]
+    assert samples == sorted(samples) and samples[-1] < len(speech.audio)
+    assert speech.words[0].sample <= speech.marks[0].sample <= speech.words[1].sample
+    assert speech.marks[0].time == speech.marks[0].sample / ttspico.SAMPLE_RATE
+
+    # (Words are indexed by character, as Python strings are)
+    speech = synth.synthesize_timed("Caffè latte")
+    assert [(word.text, word.offset) for word in speech.words] == [("Caffè", 0), ("latte", 6)]
+
+    speech = synth.synthesize_timed(text, words=False)
+    assert speech.words == [] and len(speech.marks) == 1
+
+    stream = synth.stream(text, words=True)
+    audio = np.concatenate(list(stream))
+    assert np.array_equal(audio, synth.synthesize_timed(text).audio)
+    assert [word.text for word in stream.words] == ["Hello", "big", "world"]
+    assert [mark.name for mark in stream.marks] == ["here"]
+
+
+def test_threads():
+    texts = ["Hello world.", "How are you?", "The quick brown fox.", "Goodbye."]
+    expected = [ttspico.Synthesizer("en-US", lang_dir=LANG_DIR).synthesize(text) for text in texts]
+
+    # (The GIL is released while synthesizing, so each thread's synthesizer runs in parallel)
+    def synthesize(text):
+        return ttspico.Synthesizer("en-US", lang_dir=LANG_DIR).synthesize(text)
+
+    with ThreadPoolExecutor(max_workers=len(texts)) as pool:
+        results = list(pool.map(synthesize, texts))
+    assert all(np.array_equal(result, audio) for result, audio in zip(results, expected))
+
+
+def test_drop():
+    synth = ttspico.Synthesizer("en-US", lang_dir=LANG_DIR)
+    streams = [synth.stream("The quick brown fox jumps over the lazy dog.") for _ in range(10)]
+    # (Dropping a synthesizer drops the texts still queued instead of speaking them)
+    del synth
+    with pytest.raises(ttspico.PicoError, match="stopped"):
+        for stream in streams:
+            list(stream)
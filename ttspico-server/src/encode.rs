@@ -0,0 +1,252 @@
+//! Encoding of synthesized speech in the supported output formats, as a stream.
+
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/// An output format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// A WAV file (16-bit signed PCM, mono, 16kHz), whose header gives an unknown length.
+    Wav,
+    /// Headerless 16-bit signed little-endian PCM, mono, 16kHz.
+    Raw,
+    /// Opus in an Ogg container.
+    #[cfg(feature = "opus")]
+    Opus,
+}
+
+impl Format {
+    /// Returns the format named `name` (as in the `format` query parameter), if supported.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "wav" => Some(Format::Wav),
+            "raw" | "pcm" => Some(Format::Raw),
+            #[cfg(feature = "opus")]
+            "opus" | "ogg" => Some(Format::Opus),
+            _ => None,
+        }
+    }
+
+    /// Returns the MIME type of the format.
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Format::Wav => "audio/wav",
+            Format::Raw => {
+                "audio/pcm;rate=16000;channels=1;encoding=signed-int;bits=16;endian=little"
+            }
+            #[cfg(feature = "opus")]
+            Format::Opus => "audio/ogg;codecs=opus",
+        }
+    }
+
+    /// Returns a new encoder for the format.
+    pub fn encoder(self) -> Result<Box<dyn Encoder>, String> {
+        match self {
+            Format::Wav => Ok(Box::new(WavEncoder)),
+            Format::Raw => Ok(Box::new(RawEncoder)),
+            #[cfg(feature = "opus")]
+            Format::Opus => Ok(Box::new(opus::OggOpusEncoder::new()?)),
+        }
+    }
+}
+
+/// Encodes a stream of samples, appending the encoded bytes to `out`.
+pub trait Encoder {
+    /// Encodes the start of the stream, before any samples.
+    fn start(&mut self, out: &mut Vec<u8>) -> Result<(), String>;
+
+    /// Encodes the next `samples`.
+    fn encode(&mut self, samples: &[i16], out: &mut Vec<u8>) -> Result<(), String>;
+
+    /// Encodes the end of the stream, after all samples.
+    fn finish(&mut self, out: &mut Vec<u8>) -> Result<(), String>;
+}
+
+fn push_samples(samples: &[i16], out: &mut Vec<u8>) {
+    out.extend(samples.iter().flat_map(|sample| sample.to_le_bytes()));
+}
+
+struct RawEncoder;
+
+impl Encoder for RawEncoder {
+    fn start(&mut self, _out: &mut Vec<u8>) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn encode(&mut self, samples: &[i16], out: &mut Vec<u8>) -> Result<(), String> {
+        push_samples(samples, out);
+        Ok(())
+    }
+
+    fn finish(&mut self, _out: &mut Vec<u8>) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+struct WavEncoder;
+
+impl Encoder for WavEncoder {
+    fn start(&mut self, out: &mut Vec<u8>) -> Result<(), String> {
+        // (The length of the speech is not known in advance)
+        out.extend_from_slice(&ttspico::wav_header(None));
+        Ok(())
+    }
+
+    fn encode(&mut self, samples: &[i16], out: &mut Vec<u8>) -> Result<(), String> {
+        push_samples(samples, out);
+        Ok(())
+    }
+
+    fn finish(&mut self, _out: &mut Vec<u8>) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "opus")]
+mod opus {
+    use super::Encoder;
+    use audiopus::coder::Encoder as OpusEncoder;
+    use audiopus::{Application, Channels, SampleRate};
+    use ogg::writing::{PacketWriteEndInfo, PacketWriter};
+    use ttspico::SAMPLE_RATE;
+
+    /// Number of samples per Opus frame (20 ms).
+    const FRAME_SIZE: usize = SAMPLE_RATE as usize / 50;
+
+    /// Ratio between the 48kHz granule positions of Ogg Opus and Pico's sample rate.
+    const GRANULE_RATIO: u64 = 48000 / SAMPLE_RATE as u64;
+
+    /// Serial number of the (only) logical Ogg stream.
+    const SERIAL: u32 = 0x7474_7063;
+
+    /// Maximum size of an encoded Opus packet, as recommended by libopus.
+    const MAX_PACKET_SIZE: usize = 4000;
+
+    /// Encodes Opus in Ogg, as specified by RFC 7845.
+    pub struct OggOpusEncoder {
+        encoder: OpusEncoder,
+        writer: PacketWriter<Vec<u8>>,
+        /// Samples not yet encoded, as they do not fill a frame.
+        pending: Vec<i16>,
+        /// Number of samples (at 48kHz) to skip at the start of the decoded stream.
+        pre_skip: u64,
+        /// Number of samples given to the encoder.
+        n_samples: u64,
+        /// Number of frames encoded.
+        n_frames: u64,
+    }
+
+    impl OggOpusEncoder {
+        pub fn new() -> Result<Self, String> {
+            let encoder = OpusEncoder::new(SampleRate::Hz16000, Channels::Mono, Application::Voip)
+                .map_err(|err| err.to_string())?;
+            let pre_skip =
+                encoder.lookahead().map_err(|err| err.to_string())? as u64 * GRANULE_RATIO;
+            Ok(OggOpusEncoder {
+                encoder,
+                writer: PacketWriter::new(Vec::new()),
+                pending: Vec::with_capacity(FRAME_SIZE),
+                pre_skip,
+                n_samples: 0,
+                n_frames: 0,
+            })
+        }
+
+        fn write_packet(
+            &mut self,
+            packet: Vec<u8>,
+            end: PacketWriteEndInfo,
+            granule: u64,
+        ) -> Result<(), String> {
+            self.writer
+                .write_packet(packet.into_boxed_slice(), SERIAL, end, granule)
+                .map_err(|err| err.to_string())
+        }
+
+        /// Encodes the pending samples (padded with silence) as a frame.
+        fn encode_frame(&mut self, end: PacketWriteEndInfo) -> Result<(), String> {
+            self.pending.resize(FRAME_SIZE, 0);
+            let mut packet = vec![0u8; MAX_PACKET_SIZE];
+            let len = self
+                .encoder
+                .encode(&self.pending, &mut packet)
+                .map_err(|err| err.to_string())?;
+            packet.truncate(len);
+            self.pending.clear();
+            self.n_frames += 1;
+
+            // (The granule position of the last page is where the decoded stream ends, trimming the padding)
+            let granule = if end == PacketWriteEndInfo::EndStream {
+                self.pre_skip + self.n_samples * GRANULE_RATIO
+            } else {
+                self.n_frames * FRAME_SIZE as u64 * GRANULE_RATIO
+            };
+            self.write_packet(packet, end, granule)
+        }
+
+        fn drain(&mut self, out: &mut Vec<u8>) {
+            out.append(self.writer.inner_mut());
+        }
+    }
+
+    impl Encoder for OggOpusEncoder {
+        fn start(&mut self, out: &mut Vec<u8>) -> Result<(), String> {
+            let mut head = Vec::with_capacity(19);
+            head.extend_from_slice(b"OpusHead");
+            head.push(1); // (version)
+            head.push(1); // (channels)
+            head.extend_from_slice(&(self.pre_skip as u16).to_le_bytes());
+            head.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+            head.extend_from_slice(&0i16.to_le_bytes()); // (output gain)
+            head.push(0); // (channel mapping family)
+            self.write_packet(head, PacketWriteEndInfo::EndPage, 0)?;
+
+            let vendor = concat!("ttspico-server ", env!("CARGO_PKG_VERSION"));
+            let mut tags = Vec::new();
+            tags.extend_from_slice(b"OpusTags");
+            tags.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+            tags.extend_from_slice(vendor.as_bytes());
+            tags.extend_from_slice(&0u32.to_le_bytes()); // (user comments)
+            self.write_packet(tags, PacketWriteEndInfo::EndPage, 0)?;
+
+            self.drain(out);
+            Ok(())
+        }
+
+        fn encode(&mut self, mut samples: &[i16], out: &mut Vec<u8>) -> Result<(), String> {
+            self.n_samples += samples.len() as u64;
+            while !samples.is_empty() {
+                let n_taken = std::cmp::min(FRAME_SIZE - self.pending.len(), samples.len());
+                self.pending.extend_from_slice(&samples[..n_taken]);
+                samples = &samples[n_taken..];
+                if self.pending.len() == FRAME_SIZE {
+                    self.encode_frame(PacketWriteEndInfo::NormalPacket)?;
+                }
+            }
+            self.drain(out);
+            Ok(())
+        }
+
+        fn finish(&mut self, out: &mut Vec<u8>) -> Result<(), String> {
+            // (Encode enough frames for the pre-skip and all samples to be decoded)
+            let end = self.pre_skip + self.n_samples * GRANULE_RATIO;
+            while (self.n_frames + 1) * (FRAME_SIZE as u64 * GRANULE_RATIO) < end {
+                self.encode_frame(PacketWriteEndInfo::NormalPacket)?;
+            }
+            self.encode_frame(PacketWriteEndInfo::EndStream)?;
+            self.drain(out);
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,331 @@
+//! A local HTTP speech synthesis server for the Pico TTS engine.
+//!
+//! Endpoints:
+//! - `POST /synthesize?voice=<language>&format=<wav|raw|opus>`: speaks the request's body, text with Pico's markup
+//!   (or SSML, if sent as `application/ssml+xml`), streaming the speech back with chunked transfer encoding;
+//! - `GET /voices`: lists the available voices, as JSON;
+//! - `GET /health`: responds `ok` once the server is ready;
+//! - `GET /metrics`: exposes counters in Prometheus' text format.
+//!
+//! Requests are served by a pool of worker threads, each with its own Pico system and engine; health checks are
+//! answered by the thread accepting the requests, so that they do not wait for a worker while all are busy.
+
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod encode;
+mod metrics;
+mod stream;
+
+use clap::Parser;
+use encode::Format;
+use metrics::Metrics;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use stream::SpeechStream;
+use tiny_http::{Header, Method, Request, Response, Server, StatusCode};
+use ttspico::{MultiVoiceSynthesizer, SynthesizerPool, VoiceCatalog};
+
+/// Serves speech synthesized with Pico TTS over HTTP.
+#[derive(Debug, Parser)]
+#[command(name = "ttspico-server", version)]
+struct Args {
+    /// Address to listen on
+    #[arg(
+        short = 'b',
+        long,
+        value_name = "ADDR",
+        default_value = "127.0.0.1:8080"
+    )]
+    bind: String,
+
+    /// Number of worker threads (by default the number of CPUs)
+    #[arg(short = 'w', long, value_name = "N")]
+    workers: Option<usize>,
+
+    /// Voice of the requests that do not specify one
+    #[arg(
+        short = 'l',
+        long = "lang",
+        value_name = "LANG",
+        default_value = "en-US"
+    )]
+    lang: String,
+
+    /// Directory of the language packs, instead of the default ones
+    #[arg(long, value_name = "DIR")]
+    lang_dir: Option<PathBuf>,
+
+    /// Maximum size in bytes of the text of a request
+    #[arg(long, value_name = "BYTES", default_value_t = 64 * 1024)]
+    max_text_len: usize,
+}
+
+/// What all workers share.
+struct Context {
+    /// The available voices.
+    voices: VoiceCatalog,
+    default_voice: String,
+    max_text_len: usize,
+    metrics: Metrics,
+}
+
+fn header(field: &str, value: &str) -> Header {
+    Header::from_bytes(field.as_bytes(), value.as_bytes()).unwrap()
+}
+
+/// Returns a plain text response.
+fn text_response(status: u16, text: &str) -> Response<io::Cursor<Vec<u8>>> {
+    Response::from_string(format!("{}\n", text))
+        .with_status_code(status)
+        .with_header(header("Content-Type", "text/plain; charset=utf-8"))
+}
+
+/// Decodes a `application/x-www-form-urlencoded` component.
+fn url_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' => match s
+                .get(i + 1..i + 3)
+                .filter(|hex| hex.bytes().all(|c| c.is_ascii_hexdigit()))
+            {
+                Some(hex) => {
+                    decoded.push(u8::from_str_radix(hex, 16).unwrap());
+                    i += 2;
+                }
+                None => decoded.push(b'%'),
+            },
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Splits `url` into its path and query parameters.
+fn parse_url(url: &str) -> (&str, Vec<(String, String)>) {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let params = query
+        .split('&')
+        .filter(|param| !param.is_empty())
+        .map(|param| {
+            let (name, value) = param.split_once('=').unwrap_or((param, ""));
+            (url_decode(name), url_decode(value))
+        })
+        .collect();
+    (path, params)
+}
+
+/// Handles a `POST /synthesize` request.
+fn synthesize(
+    mut request: Request,
+    params: &[(String, String)],
+    worker: &mut MultiVoiceSynthesizer,
+    ctx: &Context,
+) -> io::Result<()> {
+    ctx.metrics
+        .synthesis_requests
+        .fetch_add(1, Ordering::Relaxed);
+    let fail = |request: Request, status: u16, message: &str| {
+        ctx.metrics.synthesis_errors.fetch_add(1, Ordering::Relaxed);
+        request.respond(text_response(status, message))
+    };
+    let param = |name: &str| {
+        params
+            .iter()
+            .find(|(param, _)| param == name)
+            .map(|(_, value)| value.as_str())
+    };
+
+    let voice = param("voice").unwrap_or(&ctx.default_voice).to_string();
+    if ctx.voices.get(&voice).is_none() {
+        return fail(
+            request,
+            404,
+            &format!("unknown voice {} (see /voices)", voice),
+        );
+    }
+    let format_name = param("format").unwrap_or("wav");
+    let format = match Format::from_name(format_name) {
+        Some(format) => format,
+        None => return fail(request, 400, &format!("unsupported format {}", format_name)),
+    };
+    let is_ssml = request.headers().iter().any(|header| {
+        header.field.equiv("Content-Type")
+            && header.value.as_str().starts_with("application/ssml+xml")
+    });
+
+    let mut body = Vec::new();
+    let max_len = ctx.max_text_len as u64;
+    request
+        .as_reader()
+        .take(max_len + 1)
+        .read_to_end(&mut body)?;
+    if body.len() as u64 > max_len {
+        return fail(
+            request,
+            413,
+            &format!("text longer than {} bytes", ctx.max_text_len),
+        );
+    }
+    let text = match String::from_utf8(body) {
+        Ok(text) => text,
+        Err(_) => return fail(request, 400, "text is not valid UTF-8"),
+    };
+    let text = if is_ssml {
+        match ttspico::ssml_to_markup(&text) {
+            Ok(markup) => markup,
+            Err(err) => return fail(request, 400, &err.descr),
+        }
+    } else {
+        text
+    };
+
+    let encoder = match format.encoder() {
+        Ok(encoder) => encoder,
+        Err(err) => return fail(request, 500, &err),
+    };
+    let engine = match worker.engine(&voice) {
+        Ok(engine) => engine,
+        Err(err) => return fail(request, 500, &err.to_string()),
+    };
+    let stream = SpeechStream::new(engine, &text, encoder, &ctx.metrics);
+    // (No length, so that the speech is streamed with chunked transfer encoding as it is synthesized)
+    let response = Response::new(
+        StatusCode(200),
+        vec![header("Content-Type", format.content_type())],
+        stream,
+        None,
+        None,
+    );
+    request.respond(response)
+}
+
+/// Handles a request.
+fn handle(request: Request, worker: &mut MultiVoiceSynthesizer, ctx: &Context) -> io::Result<()> {
+    ctx.metrics.requests.fetch_add(1, Ordering::Relaxed);
+    let url = request.url().to_string();
+    let (path, params) = parse_url(&url);
+    match (request.method(), path) {
+        (Method::Post, "/synthesize") => synthesize(request, &params, worker, ctx),
+        (Method::Get, "/voices") => {
+            let voices: Vec<_> = ctx
+                .voices
+                .voices()
+                .iter()
+                .map(|voice| serde_json::json!({ "language": voice.language, "speaker": voice.speaker }))
+                .collect();
+            let json = serde_json::Value::Array(voices).to_string();
+            request.respond(
+                Response::from_string(json).with_header(header("Content-Type", "application/json")),
+            )
+        }
+        (Method::Get, "/metrics") => request.respond(
+            Response::from_string(ctx.metrics.render())
+                .with_header(header("Content-Type", "text/plain; version=0.0.4")),
+        ),
+        (_, "/synthesize") | (_, "/voices") | (_, "/metrics") => {
+            request.respond(text_response(405, "method not allowed"))
+        }
+        _ => request.respond(text_response(404, "not found")),
+    }
+}
+
+/// Handles a request to `/health`.
+fn health(request: Request, ctx: &Context) -> io::Result<()> {
+    ctx.metrics.requests.fetch_add(1, Ordering::Relaxed);
+    match request.method() {
+        Method::Get => request.respond(text_response(200, "ok")),
+        _ => request.respond(text_response(405, "method not allowed")),
+    }
+}
+
+/// Serves a request on a worker.
+fn work(request: Request, worker: &mut MultiVoiceSynthesizer, ctx: &Context) {
+    ctx.metrics.busy_workers.fetch_add(1, Ordering::Relaxed);
+    if let Err(err) = handle(request, worker, ctx) {
+        eprintln!("ttspico-server: failed to respond: {}", err);
+    }
+    ctx.metrics.busy_workers.fetch_sub(1, Ordering::Relaxed);
+}
+
+fn run(args: Args) -> Result<(), String> {
+    let voices = VoiceCatalog::new(ttspico::lang_search_dirs(args.lang_dir.as_deref()));
+    if voices.get(&args.lang).is_none() {
+        return Err(format!("no language pack for {}", args.lang));
+    }
+    let memsz = voices
+        .system_builder()
+        .measure()
+        .map_err(|err| err.to_string())?;
+
+    let server = Server::http(&args.bind)
+        .map_err(|err| format!("failed to listen on {}: {}", args.bind, err))?;
+    let n_workers = args
+        .workers
+        .unwrap_or_else(|| {
+            std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1)
+        })
+        .max(1);
+    let ctx = Arc::new(Context {
+        voices,
+        default_voice: args.lang.clone(),
+        max_text_len: args.max_text_len,
+        metrics: Metrics::default(),
+    });
+    let workers = SynthesizerPool::start(n_workers, memsz, &ctx.voices, {
+        let ctx = Arc::clone(&ctx);
+        move |request, worker| work(request, worker, &ctx)
+    })
+    .map_err(|err| format!("failed to start a worker: {}", err))?;
+    ctx.metrics
+        .workers
+        .store(n_workers as u64, Ordering::Relaxed);
+
+    match server.server_addr().to_ip() {
+        Some(addr) => println!("ttspico-server: listening on http://{}", addr),
+        None => println!("ttspico-server: listening on {}", args.bind),
+    }
+    let _ = io::stdout().flush();
+
+    for request in server.incoming_requests() {
+        if parse_url(request.url()).0 == "/health" {
+            if let Err(err) = health(request, &ctx) {
+                eprintln!("ttspico-server: failed to respond: {}", err);
+            }
+        } else if workers.send(request).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    match run(Args::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("ttspico-server: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
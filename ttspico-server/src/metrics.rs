@@ -0,0 +1,88 @@
+//! Counters exposed by the `/metrics` endpoint, in Prometheus' text format.
+
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use ttspico::SAMPLE_RATE;
+
+/// The server's metrics, shared by all workers.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    /// Number of workers.
+    pub workers: AtomicU64,
+    /// Number of workers handling a request.
+    pub busy_workers: AtomicU64,
+    /// Number of HTTP requests received.
+    pub requests: AtomicU64,
+    /// Number of `/synthesize` requests received.
+    pub synthesis_requests: AtomicU64,
+    /// Number of `/synthesize` requests that failed.
+    pub synthesis_errors: AtomicU64,
+    /// Number of samples synthesized.
+    pub samples: AtomicU64,
+}
+
+impl Metrics {
+    /// Renders the metrics in Prometheus' text exposition format.
+    pub fn render(&self) -> String {
+        let mut text = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, value: String| {
+            let _ = write!(
+                text,
+                "# HELP {0} {1}\n# TYPE {0} {2}\n{0} {3}\n",
+                name, help, kind, value
+            );
+        };
+        let get = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        metric(
+            "ttspico_workers",
+            "gauge",
+            "Number of synthesis workers.",
+            get(&self.workers).to_string(),
+        );
+        metric(
+            "ttspico_busy_workers",
+            "gauge",
+            "Number of synthesis workers handling a request.",
+            get(&self.busy_workers).to_string(),
+        );
+        metric(
+            "ttspico_http_requests_total",
+            "counter",
+            "Number of HTTP requests received.",
+            get(&self.requests).to_string(),
+        );
+        metric(
+            "ttspico_synthesis_requests_total",
+            "counter",
+            "Number of synthesis requests received.",
+            get(&self.synthesis_requests).to_string(),
+        );
+        metric(
+            "ttspico_synthesis_errors_total",
+            "counter",
+            "Number of synthesis requests that failed.",
+            get(&self.synthesis_errors).to_string(),
+        );
+        metric(
+            "ttspico_synthesized_seconds_total",
+            "counter",
+            "Duration of the speech synthesized, in seconds.",
+            format!("{:.3}", get(&self.samples) as f64 / SAMPLE_RATE as f64),
+        );
+        text
+    }
+}
@@ -0,0 +1,101 @@
+//! The streaming of synthesized speech, as it is synthesized.
+
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::encode::Encoder;
+use crate::metrics::Metrics;
+use std::io::{self, Read};
+use std::sync::atomic::Ordering;
+use ttspico::Engine;
+
+/// Streams the speech synthesized from some text, encoded, as a [`Read`].
+pub struct SpeechStream<'a> {
+    engine: &'a mut Engine,
+    /// Text not yet put into the engine.
+    input: &'a [u8],
+    encoder: Box<dyn Encoder>,
+    metrics: &'a Metrics,
+    pcm_buf: Vec<i16>,
+    /// Encoded bytes not yet read.
+    out: Vec<u8>,
+    out_pos: usize,
+    started: bool,
+    done: bool,
+}
+
+impl<'a> SpeechStream<'a> {
+    pub fn new(
+        engine: &'a mut Engine,
+        text: &'a str,
+        encoder: Box<dyn Encoder>,
+        metrics: &'a Metrics,
+    ) -> Self {
+        SpeechStream {
+            engine,
+            input: text.as_bytes(),
+            encoder,
+            metrics,
+            pcm_buf: vec![0; 1024],
+            out: Vec::new(),
+            out_pos: 0,
+            started: false,
+            done: false,
+        }
+    }
+
+    /// Steps synthesis, appending encoded bytes (if any) to `out`.
+    fn step(&mut self) -> Result<(), String> {
+        if !self.started {
+            self.encoder.start(&mut self.out)?;
+            self.started = true;
+        }
+        let (n_samples, done) = self
+            .engine
+            .step(&mut self.input, &mut self.pcm_buf[..])
+            .map_err(|err| err.to_string())?;
+        self.encoder
+            .encode(&self.pcm_buf[..n_samples], &mut self.out)?;
+        self.metrics
+            .samples
+            .fetch_add(n_samples as u64, Ordering::Relaxed);
+        if done {
+            self.encoder.finish(&mut self.out)?;
+            self.done = true;
+        }
+        Ok(())
+    }
+}
+
+impl Read for SpeechStream<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.out_pos == self.out.len() && !self.done {
+            self.out.clear();
+            self.out_pos = 0;
+            if let Err(err) = self.step() {
+                // (The response has started already, so it can only be cut short)
+                eprintln!("ttspico-server: synthesis failed mid-stream: {}", err);
+                self.metrics
+                    .synthesis_errors
+                    .fetch_add(1, Ordering::Relaxed);
+                self.done = true;
+                return Err(io::Error::other(err));
+            }
+        }
+        let n_read = std::cmp::min(buf.len(), self.out.len() - self.out_pos);
+        buf[..n_read].copy_from_slice(&self.out[self.out_pos..self.out_pos + n_read]);
+        self.out_pos += n_read;
+        Ok(n_read)
+    }
+}
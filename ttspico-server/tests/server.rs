@@ -0,0 +1,281 @@
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Runs `ttspico-server` on localhost, with the language packs bundled with Pico's sources.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+use ttspico_sys as native;
+
+/// Size of the header of the WAV files streamed by `ttspico-server`.
+const WAV_HEADER_LEN: usize = 44;
+
+/// A running `ttspico-server`, killed on drop.
+struct Server {
+    child: Child,
+    addr: String,
+}
+
+impl Server {
+    fn start(workers: usize) -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_ttspico-server"))
+            .args([
+                "--bind",
+                "127.0.0.1:0",
+                "--lang-dir",
+                native::BUNDLED_LANG_DIR,
+            ])
+            .args(["--workers", &workers.to_string()])
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("Failed to run ttspico-server");
+        let mut line = String::new();
+        BufReader::new(child.stdout.take().unwrap())
+            .read_line(&mut line)
+            .unwrap();
+        let addr = line
+            .trim()
+            .rsplit("http://")
+            .next()
+            .expect("ttspico-server did not start")
+            .to_string();
+        Server { child, addr }
+    }
+
+    /// Sends a HTTP/1.1 request, returning the response's status, headers and (de-chunked) body.
+    fn request(
+        &self,
+        method: &str,
+        path: &str,
+        content_type: &str,
+        body: &[u8],
+    ) -> (u16, String, Vec<u8>) {
+        let mut stream = TcpStream::connect(&self.addr).unwrap();
+        // (So that a request left unanswered fails the test instead of hanging it)
+        stream
+            .set_read_timeout(Some(Duration::from_secs(30)))
+            .unwrap();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\nContent-Type: {}\r\nContent-Length: {}\r\n\r\n",
+            method,
+            path,
+            self.addr,
+            content_type,
+            body.len()
+        )
+        .unwrap();
+        stream.write_all(body).unwrap();
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).unwrap();
+
+        let head_len = response
+            .windows(4)
+            .position(|window| window == b"\r\n\r\n")
+            .unwrap();
+        let head = String::from_utf8(response[..head_len].to_vec()).unwrap();
+        let status = head[9..12].parse().unwrap();
+        let mut body = response[head_len + 4..].to_vec();
+        if head
+            .to_ascii_lowercase()
+            .contains("transfer-encoding: chunked")
+        {
+            body = dechunk(&body);
+        }
+        (status, head, body)
+    }
+
+    fn get(&self, path: &str) -> (u16, String, Vec<u8>) {
+        self.request("GET", path, "text/plain", b"")
+    }
+
+    fn synthesize(&self, query: &str, content_type: &str, text: &str) -> Vec<u8> {
+        let (status, head, body) = self.request(
+            "POST",
+            &format!("/synthesize?{}", query),
+            content_type,
+            text.as_bytes(),
+        );
+        assert_eq!(status, 200, "{}", String::from_utf8_lossy(&body));
+        assert!(head.contains("Transfer-Encoding: chunked"), "{}", head);
+        body
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Decodes a body sent with chunked transfer encoding, checking that it is terminated.
+fn dechunk(mut chunked: &[u8]) -> Vec<u8> {
+    let mut body = Vec::new();
+    loop {
+        let line_len = chunked.windows(2).position(|w| w == b"\r\n").unwrap();
+        let size_line = std::str::from_utf8(&chunked[..line_len]).unwrap();
+        let size = usize::from_str_radix(size_line.split(';').next().unwrap(), 16).unwrap();
+        chunked = &chunked[line_len + 2..];
+        if size == 0 {
+            return body;
+        }
+        body.extend_from_slice(&chunked[..size]);
+        chunked = &chunked[size + 2..];
+    }
+}
+
+#[test]
+fn endpoints() {
+    let server = Server::start(1);
+
+    let (status, _, body) = server.get("/health");
+    assert_eq!((status, body.as_slice()), (200, &b"ok\n"[..]));
+
+    let (status, head, body) = server.get("/voices");
+    assert_eq!(status, 200);
+    assert!(head.contains("Content-Type: application/json"));
+    let voices = String::from_utf8(body).unwrap();
+    assert!(
+        voices.contains(r#"{"language":"en-US","speaker":"lh0"}"#),
+        "{}",
+        voices
+    );
+    assert!(
+        voices.contains(r#"{"language":"it-IT","speaker":"cm0"}"#),
+        "{}",
+        voices
+    );
+
+    assert_eq!(server.get("/nowhere").0, 404);
+    assert_eq!(server.get("/synthesize").0, 405);
+    let post = |query: &str, content_type: &str, text: &str| {
+        server
+            .request(
+                "POST",
+                &format!("/synthesize?{}", query),
+                content_type,
+                text.as_bytes(),
+            )
+            .0
+    };
+    assert_eq!(post("voice=xx-XX", "text/plain", "Hello"), 404);
+    assert_eq!(post("format=mp3", "text/plain", "Hello"), 400);
+    assert_eq!(
+        post("", "application/ssml+xml", "<speak><p>Hi</s></speak>"),
+        400
+    );
+
+    let wav = server.synthesize("", "text/plain", "Hello world");
+    let (_, _, metrics) = server.get("/metrics");
+    let metrics = String::from_utf8(metrics).unwrap();
+    assert!(
+        metrics.contains("ttspico_synthesis_requests_total 4\n"),
+        "{}",
+        metrics
+    );
+    assert!(
+        metrics.contains("ttspico_synthesis_errors_total 3\n"),
+        "{}",
+        metrics
+    );
+    let seconds = (wav.len() - WAV_HEADER_LEN) as f64 / 2.0 / 16000.0;
+    assert!(
+        metrics.contains(&format!(
+            "ttspico_synthesized_seconds_total {:.3}\n",
+            seconds
+        )),
+        "{}",
+        metrics
+    );
+}
+
+#[test]
+fn synthesis() {
+    let server = Server::start(2);
+
+    let wav = server.synthesize("voice=en-US&format=wav", "text/plain", "Hello world");
+    assert_eq!(&wav[0..4], b"RIFF");
+    assert_eq!(&wav[36..40], b"data");
+    let raw = server.synthesize("format=raw", "text/plain; charset=utf-8", "Hello world");
+    assert!(raw.len() > 16000);
+    assert_eq!(&wav[WAV_HEADER_LEN..], raw.as_slice());
+
+    // (SSML is converted to Pico's markup)
+    assert_eq!(
+        server.synthesize(
+            "format=raw",
+            "application/ssml+xml",
+            r#"<speak><prosody rate="fast">Hello</prosody> world</speak>"#
+        ),
+        server.synthesize(
+            "format=raw",
+            "text/plain",
+            r#"<speed level="150">Hello</speed> world"#
+        )
+    );
+
+    // (Requests served concurrently, with several voices, give the same speech as one at a time)
+    let texts = [
+        ("en-US", "Hello world"),
+        ("it-IT", "Ciao mondo"),
+        ("en-US", "Hello world"),
+        ("de-DE", "Hallo Welt"),
+    ];
+    let sequential: Vec<Vec<u8>> = texts
+        .iter()
+        .map(|(voice, text)| {
+            server.synthesize(&format!("voice={}&format=raw", voice), "text/plain", text)
+        })
+        .collect();
+    let concurrent: Vec<Vec<u8>> = std::thread::scope(|scope| {
+        let handles: Vec<_> = texts
+            .iter()
+            .map(|(voice, text)| {
+                let server = &server;
+                scope.spawn(move || {
+                    server.synthesize(&format!("voice={}&format=raw", voice), "text/plain", text)
+                })
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+    assert_eq!(sequential[0], raw);
+    assert_eq!(sequential, concurrent);
+}
+
+/// Health checks are answered while all the workers are busy.
+#[test]
+fn health_while_busy() {
+    let server = Server::start(1);
+
+    // (The only worker blocks streaming the speech of a long text to a client that stops reading it)
+    let text = "Hello world. ".repeat(2000);
+    let mut stalled = TcpStream::connect(&server.addr).unwrap();
+    write!(
+        stalled,
+        "POST /synthesize?format=raw HTTP/1.1\r\nHost: {}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\n\r\n{}",
+        server.addr,
+        text.len(),
+        text
+    )
+    .unwrap();
+    stalled.read_exact(&mut [0; 1]).unwrap();
+
+    let (status, _, body) = server.get("/health");
+    assert_eq!((status, body.as_slice()), (200, &b"ok\n"[..]));
+    assert_eq!(server.request("POST", "/health", "text/plain", b"").0, 405);
+}
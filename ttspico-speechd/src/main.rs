@@ -0,0 +1,283 @@
+//! A speech-dispatcher output module for the Pico TTS engine.
+//!
+//! speech-dispatcher runs `sd_ttspico` and talks to it over its standard input and output, with the line-based
+//! protocol of its output modules:
+//! - `INIT` loads the language packs;
+//! - `AUDIO`, `SET` and `LOGLEVEL` are followed by a block of `key=value` lines ending with a `.` line; `SET`'s rate,
+//!   pitch, volume, language, voice and spelling mode apply to the next messages, via Pico's markup;
+//! - `SPEAK` (SSML), `CHAR`, `KEY` and `SOUND_ICON` are followed by a block with the message, which is spoken
+//!   asynchronously: `701 BEGIN`, then the audio in `705` blocks (speech-dispatcher's server-side audio output), then
+//!   `702 END`, or `703 STOP` if stopped by `STOP` (or `704 PAUSE` by `PAUSE`, as Pico cannot resume);
+//! - `LIST VOICES` lists the language packs; `QUIT` quits.
+//!
+//! The `<mark>`s of `SPEAK` messages are sent as `700 INDEX MARK` events, after the audio that precedes them (except
+//! with the `system` feature, whose Pico does not report them).
+
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod protocol;
+mod settings;
+mod synth;
+
+use clap::Parser;
+use protocol::{read_block, read_line, send};
+use settings::Settings;
+use std::io::{self, BufRead};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::Arc;
+use synth::{Cancellation, Job, Synth};
+use ttspico::VoiceCatalog;
+
+/// The voice spoken with when the language of a message has no language pack.
+const DEFAULT_VOICE: &str = "en-US";
+
+/// speech-dispatcher output module for Pico TTS.
+#[derive(Debug, Parser)]
+#[command(name = "sd_ttspico", version)]
+struct Args {
+    /// The module's configuration file, as passed by speech-dispatcher; `TtspicoLangDir <DIR>` sets the directory
+    /// of the language packs
+    config: Option<PathBuf>,
+
+    /// Directory of the language packs, instead of the configuration file's or the default ones
+    #[arg(long, value_name = "DIR")]
+    lang_dir: Option<PathBuf>,
+}
+
+/// Reads the language pack directory set by the configuration file at `path`, if any.
+fn config_lang_dir(path: &PathBuf) -> Result<Option<PathBuf>, String> {
+    let config = std::fs::read_to_string(path)
+        .map_err(|err| format!("failed to read {}: {}", path.display(), err))?;
+    let lang_dir = config
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| line.split_once(char::is_whitespace))
+        .rfind(|(key, _)| *key == "TtspicoLangDir")
+        .map(|(_, dir)| PathBuf::from(dir.trim().trim_matches('"')));
+    Ok(lang_dir)
+}
+
+/// The state of the module.
+struct Module {
+    /// The available voices.
+    voices: VoiceCatalog,
+    default_voice: String,
+    settings: Settings,
+    /// The synthesis thread, once initialized.
+    synth: Option<Synth>,
+    /// Number of messages sent to the synthesis thread.
+    n_jobs: u64,
+    /// Which messages to stop.
+    cancellation: Arc<Cancellation>,
+}
+
+impl Module {
+    fn init(&mut self) -> Result<(), String> {
+        if self.voices.voices().is_empty() {
+            return Err("no language packs found".to_string());
+        }
+        if self.synth.is_none() {
+            let memsz = self
+                .voices
+                .system_builder()
+                .measure()
+                .map_err(|err| err.to_string())?;
+            let synth = Synth::start(memsz, &self.voices, Arc::clone(&self.cancellation))?;
+            self.synth = Some(synth);
+        }
+        Ok(())
+    }
+
+    /// Queues the message of a `SPEAK`, `CHAR`, `KEY` or `SOUND_ICON` command, replying whether it can be spoken.
+    fn speak(&mut self, command: &str, message: &str) -> Result<(), String> {
+        let synth = self
+            .synth
+            .as_ref()
+            .ok_or_else(|| "not initialized".to_string())?;
+        let markup = match command {
+            "SPEAK" => ttspico::ssml_to_markup(message).map_err(|err| err.descr)?,
+            "CHAR" => match message {
+                "space" => " ".to_string(),
+                // (`<` would start a tag)
+                _ if message.contains('<') => String::new(),
+                _ => format!("<spell>{}</spell>", message),
+            },
+            // (Key names are i.e. `a`, `shift_a`, `control_alt_delete`)
+            "KEY" => match message.chars().count() {
+                1 if message != "<" => format!("<spell>{}</spell>", message),
+                _ => message.replace('_', " ").replace('<', ""),
+            },
+            // (Pico has no sound icons)
+            _ => String::new(),
+        };
+        let markup = match markup.trim() {
+            "" => String::new(),
+            _ => self.settings.apply_prosody(&markup),
+        };
+        let voice = self
+            .settings
+            .voice(&self.voices, &self.default_voice)
+            .to_string();
+        // (Before the message is queued, so that its events follow the reply)
+        send("200 OK SPEAKING");
+        synth.speak(Job {
+            seq: self.n_jobs,
+            voice,
+            markup,
+        });
+        self.n_jobs += 1;
+        Ok(())
+    }
+
+    /// Stops the messages queued so far.
+    fn stop(&self, pause: bool) {
+        self.cancellation.cancel(self.n_jobs, pause);
+    }
+
+    /// Handles `command`, reading its block of data (if any) from `input`.
+    /// Returns whether to keep running.
+    fn handle(&mut self, command: &str, input: &mut impl BufRead) -> io::Result<bool> {
+        match command {
+            "INIT" => match self.init() {
+                Ok(()) => send(&format!(
+                    "299-Pico TTS: {} voices\n299 OK LOADED SUCCESSFULLY",
+                    self.voices.voices().len()
+                )),
+                Err(err) => send(&format!("399-{}\n399 ERR CANT INIT MODULE", err)),
+            },
+            "AUDIO" => {
+                send("207 OK RECEIVING AUDIO SETTINGS");
+                // (Audio is always returned to speech-dispatcher, whatever its audio output method)
+                if read_block(input)?.is_none() {
+                    return Ok(false);
+                }
+                send("203 OK AUDIO INITIALIZED");
+            }
+            "LOGLEVEL" => {
+                send("207 OK RECEIVING LOGLEVEL SETTINGS");
+                if read_block(input)?.is_none() {
+                    return Ok(false);
+                }
+                send("203 OK LOG LEVEL SET");
+            }
+            "SET" => {
+                send("203 OK RECEIVING SETTINGS");
+                let lines = match read_block(input)? {
+                    Some(lines) => lines,
+                    None => return Ok(false),
+                };
+                // (Settings are applied all or none)
+                let mut settings = self.settings.clone();
+                match settings.apply(&lines) {
+                    Ok(()) => {
+                        self.settings = settings;
+                        send("203 OK SETTINGS RECEIVED");
+                    }
+                    Err(err) => {
+                        eprintln!("sd_ttspico: {}", err);
+                        send("302 ERROR BAD SYNTAX");
+                    }
+                }
+            }
+            "SPEAK" | "CHAR" | "KEY" | "SOUND_ICON" => {
+                send("202 OK RECEIVING MESSAGE");
+                let message = match read_block(input)? {
+                    Some(lines) => lines.join("\n"),
+                    None => return Ok(false),
+                };
+                if let Err(err) = self.speak(command, &message) {
+                    eprintln!("sd_ttspico: cannot speak: {}", err);
+                    send("301 ERROR CANT SPEAK");
+                }
+            }
+            // (Replied to asynchronously, by the end of the message being spoken)
+            "STOP" => self.stop(false),
+            "PAUSE" => self.stop(true),
+            "LIST VOICES" => {
+                if self.voices.voices().is_empty() {
+                    send("304 CANT LIST VOICES");
+                } else {
+                    let list: String = self
+                        .voices
+                        .voices()
+                        .iter()
+                        .map(|voice| format!("200-{}\t{}\tnone\n", voice.language, voice.language))
+                        .collect();
+                    send(&format!("{}200 OK VOICE LIST SENT", list));
+                }
+            }
+            "QUIT" => {
+                self.stop(false);
+                if let Some(synth) = self.synth.take() {
+                    synth.quit();
+                }
+                send("210 OK QUIT");
+                return Ok(false);
+            }
+            _ => send("300 ERR UNKNOWN COMMAND"),
+        }
+        Ok(true)
+    }
+}
+
+fn run(args: Args) -> Result<(), String> {
+    let lang_dir = match (&args.lang_dir, &args.config) {
+        (Some(lang_dir), _) => Some(lang_dir.clone()),
+        (None, Some(config)) => config_lang_dir(config)?,
+        (None, None) => None,
+    };
+    let voices = VoiceCatalog::new(ttspico::lang_search_dirs(lang_dir.as_deref()));
+    let default_voice = if voices.get(DEFAULT_VOICE).is_some() {
+        DEFAULT_VOICE.to_string()
+    } else {
+        voices
+            .voices()
+            .first()
+            .map(|voice| voice.language.clone())
+            .unwrap_or_default()
+    };
+
+    let mut module = Module {
+        voices,
+        default_voice,
+        settings: Settings::default(),
+        synth: None,
+        n_jobs: 0,
+        cancellation: Arc::default(),
+    };
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    while let Some(command) = read_line(&mut input).map_err(|err| err.to_string())? {
+        if !module
+            .handle(command.trim(), &mut input)
+            .map_err(|err| err.to_string())?
+        {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    match run(Args::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("sd_ttspico: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,88 @@
+//! The I/O of speech-dispatcher's output module protocol: replies and events on the standard output, blocks of
+//! data on the standard input.
+
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::{self, BufRead, Write};
+use ttspico::SAMPLE_RATE;
+
+/// Sends `message` (one or more lines) to speech-dispatcher.
+/// The standard output is locked while writing, so that the replies and the events of the synthesis thread do not
+/// interleave.
+pub fn send(message: &str) {
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    // (If speech-dispatcher is gone, the next read from the standard input fails and the module quits)
+    let _ = writeln!(stdout, "{}", message).and_then(|_| stdout.flush());
+}
+
+/// Sends `samples` to speech-dispatcher, to be played by its server-side audio output.
+pub fn send_audio(samples: &[i16]) {
+    let mut block = format!(
+        "705-bits=16\n705-num_channels=1\n705-sample_rate={}\n705-num_samples={}\n705-big_endian=0\n705-AUDIO\0",
+        SAMPLE_RATE,
+        samples.len()
+    )
+    .into_bytes();
+    // (Newlines would end the block early, so they are escaped as `}` followed by the byte XOR 0x20, as is `}` itself)
+    for byte in samples.iter().flat_map(|sample| sample.to_le_bytes()) {
+        match byte {
+            b'\n' | b'}' => block.extend_from_slice(&[b'}', byte ^ 0x20]),
+            _ => block.push(byte),
+        }
+    }
+    block.extend_from_slice(b"\n705 AUDIO\n");
+
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    let _ = stdout.write_all(&block).and_then(|_| stdout.flush());
+}
+
+/// Sends the index mark `name`, reached once the audio sent before it is played.
+pub fn send_index_mark(name: &str) {
+    // (A newline would end the event early)
+    send(&format!("700-{}\n700 INDEX MARK", name.replace('\n', " ")));
+}
+
+/// Reads a line, without its terminator; `None` at the end of the input.
+pub fn read_line(input: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut line = String::new();
+    if input.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    if line.ends_with('\n') {
+        line.pop();
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    }
+    Ok(Some(line))
+}
+
+/// Reads a block of lines terminated by a line with a single `.`, unescaping lines that start with `..`.
+/// Returns `None` if the input ends before the block does.
+pub fn read_block(input: &mut impl BufRead) -> io::Result<Option<Vec<String>>> {
+    let mut lines = Vec::new();
+    while let Some(line) = read_line(input)? {
+        if line == "." {
+            return Ok(Some(lines));
+        }
+        match line.strip_prefix("..") {
+            Some(rest) => lines.push(format!(".{}", rest)),
+            None => lines.push(line),
+        }
+    }
+    Ok(None)
+}
@@ -0,0 +1,122 @@
+//! The speech settings sent by speech-dispatcher, and how they map to Pico's voices and markup.
+
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use ttspico::VoiceCatalog;
+
+/// The settings of the next messages, as set by `SET` blocks.
+#[derive(Debug, Clone)]
+pub struct Settings {
+    /// Speech rate, pitch and volume, from -100 to 100.
+    pub rate: i32,
+    pub pitch: i32,
+    pub volume: i32,
+    /// Language of the messages (i.e. `en` or `en-US`), if set.
+    pub language: Option<String>,
+    /// Name of the voice to use (as listed by `LIST VOICES`), if set; takes precedence over `language`.
+    pub synthesis_voice: Option<String>,
+    /// Whether messages are spelled out.
+    pub spelling: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        // (As in speech-dispatcher's default configuration)
+        Settings {
+            rate: 0,
+            pitch: 0,
+            volume: 100,
+            language: None,
+            synthesis_voice: None,
+            spelling: false,
+        }
+    }
+}
+
+impl Settings {
+    /// Applies the `key=value` lines of a `SET` block; settings Pico has no equivalent for (i.e. punctuation and
+    /// capital letters) are ignored.
+    pub fn apply(&mut self, lines: &[String]) -> Result<(), String> {
+        for line in lines {
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("invalid setting {:?}", line))?;
+            let level = || {
+                value
+                    .parse::<i32>()
+                    .map(|level| level.clamp(-100, 100))
+                    .map_err(|_| format!("invalid {} {:?}", key, value))
+            };
+            // (speech-dispatcher sends `NULL` for unset strings)
+            let string =
+                || Some(value.to_string()).filter(|value| value != "NULL" && !value.is_empty());
+            match key {
+                "rate" => self.rate = level()?,
+                "pitch" => self.pitch = level()?,
+                "volume" => self.volume = level()?,
+                "language" => self.language = string(),
+                "synthesis_voice" => self.synthesis_voice = string(),
+                "spelling_mode" => self.spelling = value == "on",
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Picks the voice (i.e. language pack) of `voices` to speak with: the synthesis voice if there is one, else
+    /// the one for the language (or a dialect of it), else `default_voice`.
+    pub fn voice<'a>(&self, voices: &'a VoiceCatalog, default_voice: &'a str) -> &'a str {
+        self.synthesis_voice
+            .as_ref()
+            .and_then(|name| voices.get(name))
+            .or_else(|| {
+                self.language
+                    .as_ref()
+                    .and_then(|language| voices.find_voice(language))
+            })
+            .map_or(default_voice, |voice| voice.language.as_str())
+    }
+
+    /// Returns `markup` with the rate, pitch and volume applied via Pico's markup.
+    pub fn apply_prosody(&self, markup: &str) -> String {
+        let markup = if self.spelling {
+            format!("<spell>{}</spell>", markup)
+        } else {
+            markup.to_string()
+        };
+        // (Pico's levels are in percent of the default; see `PICO_*_MIN`, `PICO_*_MAX` in `picotok.c`)
+        let levels = [
+            ("speed", scale(self.rate, 20, 100, 500)),
+            ("pitch", scale(self.pitch, 50, 100, 200)),
+            // (speech-dispatcher's default volume is its maximum, which Pico's default should match)
+            ("volume", scale(self.volume, 0, 50, 100)),
+        ];
+        levels
+            .iter()
+            .fold(markup, |markup, (tag, level)| match level {
+                100 => markup,
+                _ => format!("<{} level=\"{}\">{}</{}>", tag, level, markup, tag),
+            })
+    }
+}
+
+/// Maps `value` from -100..=100 to `min..=max`, linearly on each side of `mid` (its level at 0).
+fn scale(value: i32, min: i32, mid: i32, max: i32) -> i32 {
+    if value < 0 {
+        mid + (mid - min) * value / 100
+    } else {
+        mid + (max - mid) * value / 100
+    }
+}
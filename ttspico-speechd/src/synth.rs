@@ -0,0 +1,139 @@
+//! The synthesis thread, which owns the module's Pico system and speaks the messages queued by the protocol loop.
+
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::protocol;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use ttspico::{MultiVoiceSynthesizer, PicoError, SynthesizerPool, VoiceCatalog};
+
+/// Number of samples sent to speech-dispatcher at a time (a quarter of a second).
+const CHUNK_LEN: usize = ttspico::SAMPLE_RATE as usize / 4;
+
+/// A message to speak.
+#[derive(Debug)]
+pub struct Job {
+    /// Sequence number of the message (see [`Cancellation`]).
+    pub seq: u64,
+    /// The voice (i.e. language) to speak with.
+    pub voice: String,
+    /// The text to speak, with Pico's markup.
+    pub markup: String,
+}
+
+/// Which of the jobs queued so far to stop.
+#[derive(Debug, Default)]
+pub struct Cancellation {
+    /// Number of jobs (the first ones) to stop.
+    n_jobs: AtomicU64,
+    /// Whether they are stopped by `PAUSE` rather than by `STOP`.
+    pause: AtomicBool,
+}
+
+impl Cancellation {
+    /// Stops the first `n_jobs` jobs.
+    pub fn cancel(&self, n_jobs: u64, pause: bool) {
+        self.pause.store(pause, Ordering::SeqCst);
+        self.n_jobs.store(n_jobs, Ordering::SeqCst);
+    }
+
+    fn is_cancelled(&self, job: &Job) -> bool {
+        job.seq < self.n_jobs.load(Ordering::SeqCst)
+    }
+}
+
+/// Speaks `job`, sending its audio and index marks to speech-dispatcher as it is synthesized.
+/// Returns whether the job was stopped (by being cancelled) before its end.
+fn speak(
+    synth: &mut MultiVoiceSynthesizer,
+    job: &Job,
+    cancellation: &Cancellation,
+) -> Result<bool, PicoError> {
+    if job.markup.is_empty() {
+        return Ok(false);
+    }
+    // (Whatever the last message left in the engine, i.e. if it was stopped, is discarded by a full reset)
+    let engine = synth.engine(&job.voice)?;
+    let mut input = job.markup.as_bytes();
+    let mut samples = Vec::with_capacity(CHUNK_LEN);
+    // (The number of samples sent before the ones in `samples`)
+    let mut n_sent = 0;
+    let mut pcm_buf = [0i16; 1024];
+    loop {
+        if cancellation.is_cancelled(job) {
+            return Ok(true);
+        }
+        let (n_samples, done) = engine.step(&mut input, &mut pcm_buf[..])?;
+        samples.extend_from_slice(&pcm_buf[..n_samples]);
+        // (The engine was reset for the job, so its marks are positioned from the job's start)
+        for mark in engine.take_marks() {
+            let n_before = mark.sample.saturating_sub(n_sent).min(samples.len());
+            if n_before > 0 {
+                protocol::send_audio(&samples[..n_before]);
+                samples.drain(..n_before);
+                n_sent += n_before;
+            }
+            protocol::send_index_mark(&mark.name);
+        }
+        if samples.len() >= CHUNK_LEN || (done && !samples.is_empty()) {
+            protocol::send_audio(&samples);
+            n_sent += samples.len();
+            samples.clear();
+        }
+        if done {
+            return Ok(false);
+        }
+    }
+}
+
+/// The synthesis thread.
+pub struct Synth(SynthesizerPool<Job>);
+
+impl Synth {
+    /// Starts the synthesis thread, with a system of `memsz` bytes loading the voices of `catalog`.
+    /// Jobs are stopped as set by `cancellation`.
+    pub fn start(
+        memsz: usize,
+        catalog: &VoiceCatalog,
+        cancellation: Arc<Cancellation>,
+    ) -> Result<Self, String> {
+        let pool = SynthesizerPool::start(1, memsz, catalog, move |job: Job, synth| {
+            protocol::send("701 BEGIN");
+            match speak(synth, &job, &cancellation) {
+                Ok(false) => protocol::send("702 END"),
+                Ok(true) if cancellation.pause.load(Ordering::SeqCst) => {
+                    protocol::send("704 PAUSE")
+                }
+                Ok(true) => protocol::send("703 STOP"),
+                Err(err) => {
+                    eprintln!("sd_ttspico: synthesis failed: {}", err);
+                    protocol::send("702 END");
+                }
+            }
+        })
+        .map_err(|err| err.to_string())?;
+        Ok(Synth(pool))
+    }
+
+    /// Queues `job` to be spoken.
+    pub fn speak(&self, job: Job) {
+        let _ = self.0.send(job);
+    }
+
+    /// Waits for the queued jobs to be spoken (or stopped), then stops the thread.
+    pub fn quit(self) {
+        drop(self.0);
+    }
+}
@@ -0,0 +1,353 @@
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Scripts speech-dispatcher's output module protocol with `sd_ttspico` over pipes, with the language packs bundled
+//! with Pico's sources.
+
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::rc::Rc;
+use ttspico::{EngineStatus, System, Voice};
+use ttspico_sys as native;
+
+/// A running `sd_ttspico`, killed on drop.
+struct Module {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    /// The index marks received, with the number of samples of their message sent before them.
+    marks: Vec<(String, usize)>,
+}
+
+impl Module {
+    fn start() -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_sd_ttspico"))
+            .args(["--lang-dir", native::BUNDLED_LANG_DIR])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("Failed to run sd_ttspico");
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        Module {
+            child,
+            stdin,
+            stdout,
+            marks: Vec::new(),
+        }
+    }
+
+    /// Sends `lines` to the module.
+    fn send(&mut self, lines: &[&str]) {
+        for line in lines {
+            writeln!(self.stdin, "{}", line).unwrap();
+        }
+        self.stdin.flush().unwrap();
+    }
+
+    /// Reads a line from the module, without its terminator.
+    fn line(&mut self) -> Vec<u8> {
+        let mut line = Vec::new();
+        self.stdout.read_until(b'\n', &mut line).unwrap();
+        assert_eq!(line.pop(), Some(b'\n'), "sd_ttspico quit");
+        line
+    }
+
+    /// Checks that the module replies with `lines`.
+    fn expect(&mut self, lines: &[&str]) {
+        for expected in lines {
+            assert_eq!(String::from_utf8(self.line()).unwrap(), *expected);
+        }
+    }
+
+    /// Sends a command followed by a block of data, checking the replies to both.
+    fn command(&mut self, command: &str, block: &[&str], replies: &[&str]) {
+        self.send(&[command]);
+        self.expect(&replies[..1]);
+        self.send(block);
+        self.send(&["."]);
+        self.expect(&replies[1..]);
+    }
+
+    fn init(&mut self) {
+        self.send(&["INIT"]);
+        self.expect(&["299-Pico TTS: 6 voices", "299 OK LOADED SUCCESSFULLY"]);
+    }
+
+    fn set(&mut self, settings: &[&str]) {
+        self.command(
+            "SET",
+            settings,
+            &["203 OK RECEIVING SETTINGS", "203 OK SETTINGS RECEIVED"],
+        );
+    }
+
+    fn speak(&mut self, command: &str, message: &[&str]) {
+        self.command(
+            command,
+            message,
+            &["202 OK RECEIVING MESSAGE", "200 OK SPEAKING"],
+        );
+    }
+
+    /// Sends a `SPEAK` with invalid SSML, checking that it cannot be spoken.
+    fn speak_fails(&mut self) {
+        self.command(
+            "SPEAK",
+            &["<speak><p>Hello</s></speak>"],
+            &["202 OK RECEIVING MESSAGE", "301 ERROR CANT SPEAK"],
+        );
+    }
+
+    /// Reads the events of a message being spoken, returning its audio and the event it ended with.
+    /// Its index marks are added to `self.marks`.
+    fn speech(&mut self) -> (Vec<i16>, String) {
+        self.expect(&["701 BEGIN"]);
+        let mut samples = Vec::new();
+        loop {
+            let line = String::from_utf8_lossy(&self.line()).into_owned();
+            if let Some(name) = line.strip_prefix("700-") {
+                self.marks.push((name.to_string(), samples.len()));
+                self.expect(&["700 INDEX MARK"]);
+                continue;
+            }
+            if line != "705-bits=16" {
+                return (samples, line);
+            }
+            self.expect(&["705-num_channels=1", "705-sample_rate=16000"]);
+            let num_samples: usize = String::from_utf8(self.line()).unwrap()
+                ["705-num_samples=".len()..]
+                .parse()
+                .unwrap();
+            self.expect(&["705-big_endian=0"]);
+
+            let data = self.line();
+            let escaped = data.strip_prefix(b"705-AUDIO\0").unwrap();
+            let mut bytes = Vec::new();
+            let mut escaped = escaped.iter();
+            while let Some(&byte) = escaped.next() {
+                match byte {
+                    b'}' => bytes.push(escaped.next().unwrap() ^ 0x20),
+                    _ => bytes.push(byte),
+                }
+            }
+            assert_eq!(bytes.len(), num_samples * 2);
+            samples.extend(
+                bytes
+                    .chunks(2)
+                    .map(|sample| i16::from_le_bytes([sample[0], sample[1]])),
+            );
+            self.expect(&["705 AUDIO"]);
+        }
+    }
+}
+
+impl Drop for Module {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Synthesizes `markup` with a bundled language pack in this process.
+fn synthesize(ta: &str, sg: &str, markup: &str) -> Vec<i16> {
+    let sys = System::new(8 * 1024 * 1024).unwrap();
+    let lang_file = |name: &str| format!("{}/{}", native::BUNDLED_LANG_DIR, name);
+    let ta_res = System::load_resource(Rc::clone(&sys), lang_file(ta)).unwrap();
+    let sg_res = System::load_resource(Rc::clone(&sys), lang_file(sg)).unwrap();
+    let voice = System::create_voice(sys, "voice").unwrap();
+    voice.borrow_mut().add_resource(ta_res).unwrap();
+    voice.borrow_mut().add_resource(sg_res).unwrap();
+    let mut engine = unsafe { Voice::create_engine(voice).unwrap() };
+
+    engine.put_text(markup).unwrap();
+    engine.flush().unwrap();
+    let mut samples = Vec::new();
+    let mut pcm_buf = [0i16; 1024];
+    loop {
+        let (n_samples, status) = engine.get_data(&mut pcm_buf[..]).unwrap();
+        samples.extend_from_slice(&pcm_buf[..n_samples]);
+        if status == EngineStatus::Idle {
+            return samples;
+        }
+    }
+}
+
+#[test]
+fn session() {
+    let mut module = Module::start();
+    module.init();
+
+    module.send(&["LIST VOICES"]);
+    let mut voices = Vec::new();
+    loop {
+        let line = String::from_utf8(module.line()).unwrap();
+        if line == "200 OK VOICE LIST SENT" {
+            break;
+        }
+        voices.push(line);
+    }
+    assert!(voices.contains(&"200-en-US\ten-US\tnone".to_string()));
+    assert!(voices.contains(&"200-it-IT\tit-IT\tnone".to_string()));
+
+    module.command(
+        "AUDIO",
+        &["audio_output_method=server"],
+        &[
+            "207 OK RECEIVING AUDIO SETTINGS",
+            "203 OK AUDIO INITIALIZED",
+        ],
+    );
+    module.command(
+        "LOGLEVEL",
+        &["log_level=3"],
+        &["207 OK RECEIVING LOGLEVEL SETTINGS", "203 OK LOG LEVEL SET"],
+    );
+    module.set(&[
+        "rate=0",
+        "pitch=0",
+        "volume=100",
+        "punctuation_mode=some",
+        "language=en-US",
+        "synthesis_voice=NULL",
+    ]);
+
+    // (The audio is the same as Pico's, for all the escaped bytes)
+    module.speak("SPEAK", &["<speak>Hello world</speak>"]);
+    let (hello, end) = module.speech();
+    assert_eq!(end, "702 END");
+    assert_eq!(
+        hello,
+        synthesize("en-US_ta.bin", "en-US_lh0_sg.bin", "Hello world")
+    );
+
+    // (Marks are sent after the audio before them)
+    module.speak(
+        "SPEAK",
+        &["<speak>Hello <mark name=\"__spd_1\"/>world<mark name=\"__spd_2\"/></speak>"],
+    );
+    let (marked, end) = module.speech();
+    assert_eq!(end, "702 END");
+    assert_eq!(
+        marked,
+        synthesize(
+            "en-US_ta.bin",
+            "en-US_lh0_sg.bin",
+            "Hello <mark name=\"__spd_1\"/>world<mark name=\"__spd_2\"/>"
+        )
+    );
+    let marks = std::mem::take(&mut module.marks);
+    #[cfg(not(feature = "system"))]
+    {
+        assert_eq!(marks.len(), 2, "{:?}", marks);
+        assert_eq!(marks[0].0, "__spd_1");
+        assert!(marks[0].1 > 0 && marks[0].1 < marked.len(), "{:?}", marks);
+        // (The last mark comes before the silence that ends the speech)
+        assert_eq!(marks[1].0, "__spd_2");
+        assert!(
+            marks[1].1 > marks[0].1 && marks[1].1 <= marked.len(),
+            "{:?}",
+            marks
+        );
+    }
+    #[cfg(feature = "system")]
+    assert_eq!(marks, []);
+
+    // (The language picks the voice, a dialect if needed)
+    module.set(&["language=it"]);
+    module.speak("SPEAK", &["<speak>Ciao mondo</speak>"]);
+    let (ciao, end) = module.speech();
+    assert_eq!(end, "702 END");
+    assert_eq!(
+        ciao,
+        synthesize("it-IT_ta.bin", "it-IT_cm0_sg.bin", "Ciao mondo")
+    );
+
+    // (Settings map to Pico's markup)
+    module.set(&["language=en", "synthesis_voice=en-US", "rate=50"]);
+    module.speak("SPEAK", &["<speak>Hello world</speak>"]);
+    let (fast, _) = module.speech();
+    assert_eq!(
+        fast,
+        synthesize(
+            "en-US_ta.bin",
+            "en-US_lh0_sg.bin",
+            "<speed level=\"300\">Hello world</speed>"
+        )
+    );
+
+    module.set(&["rate=0"]);
+    module.speak("CHAR", &["a"]);
+    let (char_a, end) = module.speech();
+    assert_eq!(end, "702 END");
+    assert!(!char_a.is_empty());
+    module.speak("KEY", &["shift_a"]);
+    assert_eq!(module.speech().1, "702 END");
+    module.speak("SOUND_ICON", &["message"]);
+    assert_eq!(module.speech(), (Vec::new(), "702 END".to_string()));
+
+    module.send(&["QUIT"]);
+    module.expect(&["210 OK QUIT"]);
+    assert!(module.child.wait().unwrap().success());
+}
+
+#[test]
+fn stop() {
+    let mut module = Module::start();
+    module.init();
+
+    let text = "<speak>".to_string()
+        + &"The quick brown fox jumps over the lazy dog. ".repeat(20)
+        + "</speak>";
+    module.speak("SPEAK", &[&text]);
+    module.send(&["STOP"]);
+    let (samples, end) = module.speech();
+    assert_eq!(end, "703 STOP");
+    assert!(samples.len() < 16000 * 10, "{} samples", samples.len());
+
+    // (The next message is spoken from its start)
+    module.speak("SPEAK", &["<speak>Hello world</speak>"]);
+    assert_eq!(
+        module.speech(),
+        (
+            synthesize("en-US_ta.bin", "en-US_lh0_sg.bin", "Hello world"),
+            "702 END".to_string()
+        )
+    );
+
+    module.speak("SPEAK", &[&text]);
+    module.send(&["PAUSE"]);
+    assert_eq!(module.speech().1, "704 PAUSE");
+}
+
+#[test]
+fn errors() {
+    let mut module = Module::start();
+    // (Not initialized yet)
+    module.speak_fails();
+    module.init();
+
+    module.send(&["FROBNICATE"]);
+    module.expect(&["300 ERR UNKNOWN COMMAND"]);
+    module.command(
+        "SET",
+        &["rate=fast"],
+        &["203 OK RECEIVING SETTINGS", "302 ERROR BAD SYNTAX"],
+    );
+    module.speak_fails();
+
+    // (Lines starting with `.` are escaped in blocks)
+    module.speak("SPEAK", &["<speak>", "..", "</speak>"]);
+    assert_eq!(module.speech().1, "702 END");
+}
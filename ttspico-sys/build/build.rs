@@ -12,24 +12,245 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use cc;
 use glob::glob;
+use std::env;
 
 const PICO_SRC_DIR: &str = "build/pico/lib";
 const PICO_LIB_NAME: &str = "svoxpico";
 
+/// Headers and sources of the few C library functions needed by Pico, for targets without a C library
+/// (i.e. `wasm32-unknown-unknown`).
+const FREESTANDING_SRC_DIR: &str = "build/wasm";
+
+/// Name of the shared Pico library shipped by distributions (i.e. `libttspico.so` in Debian's `libttspico0`).
+#[cfg(feature = "system")]
+const SYSTEM_LIB_NAME: &str = "ttspico";
+
+/// Environment variable that can be set to the directory containing the system Pico library.
+const LIB_DIR_ENV: &str = "TTSPICO_LIB_DIR";
+
+// -- Environment variables configuring the vendored build --
+
+/// Optimization level of the C code (`0`..`3`, `s` or `z`), instead of the one of the Cargo profile.
+const OPT_LEVEL_ENV: &str = "TTSPICO_C_OPT_LEVEL";
+/// If set to `1`, the C code is compiled for link-time optimization (i.e. for cross-language LTO).
+const LTO_ENV: &str = "TTSPICO_C_LTO";
+/// Comma-separated sanitizers to compile the C code with (i.e. `address,undefined`).
+const SANITIZE_ENV: &str = "TTSPICO_SANITIZE";
+/// Comma-separated extra defines, as `NAME` or `NAME=VALUE` (i.e. `PICO_DEBUG`).
+const DEFINES_ENV: &str = "TTSPICO_C_DEFINES";
+/// If set to `1`, C compiler warnings are shown.
+const WARNINGS_ENV: &str = "TTSPICO_C_WARNINGS";
+/// Name of the last PU (processing unit) of the TTS processing chain (see [`PICO_PUS`]).
+const LAST_PU_ENV: &str = "TTSPICO_LAST_PU";
+
+/// The PUs of the TTS processing chain in order, with the sources that only they need.
+/// Their index + 1 is their `picodata_putype_t`, as expected by `PICO_LAST_PU` in `picoctrl.c`.
+const PICO_PUS: &[(&str, &[&str])] = &[
+    ("tok", &["picotok.c"]),
+    ("pr", &["picopr.c"]),
+    ("wa", &["picowa.c"]),
+    ("sa", &["picosa.c"]),
+    ("acph", &["picoacph.c"]),
+    ("spho", &["picospho.c"]),
+    ("pam", &["picopam.c"]),
+    ("cep", &["picocep.c"]),
+    ("sig", &["picosig.c", "picosig2.c", "picofftsg.c"]),
+];
+
 fn main() {
+    println!("cargo:rerun-if-changed=build/build.rs");
+    println!("cargo:rerun-if-changed={}", PICO_SRC_DIR);
+    println!("cargo:rerun-if-changed={}", FREESTANDING_SRC_DIR);
+    for var in &[
+        LIB_DIR_ENV,
+        OPT_LEVEL_ENV,
+        LTO_ENV,
+        SANITIZE_ENV,
+        DEFINES_ENV,
+        WARNINGS_ENV,
+        LAST_PU_ENV,
+    ] {
+        println!("cargo:rerun-if-env-changed={}", var);
+    }
+
+    #[cfg(feature = "system")]
+    let linked_system_lib = link_system_lib();
+    #[cfg(not(feature = "system"))]
+    let linked_system_lib = false;
+    if !linked_system_lib {
+        build_vendored_lib();
+    }
+
+    #[cfg(feature = "bindgen")]
+    generate_bindings();
+}
+
+/// Links to the system-installed shared Pico library, found in `$TTSPICO_LIB_DIR` or via pkg-config.
+/// Returns `false` if it could not be found or cannot be used, in which case the vendored Pico should be built.
+#[cfg(feature = "system")]
+fn link_system_lib() -> bool {
+    if env::var_os("CARGO_FEATURE_DEBUG_PICO").is_some() {
+        println!("cargo:warning=`debug-pico` requires the vendored Pico, not linking to the system library");
+        return false;
+    }
+    if let Some(lib_dir) = env::var_os(LIB_DIR_ENV) {
+        println!(
+            "cargo:rustc-link-search=native={}",
+            lib_dir.to_string_lossy()
+        );
+        println!("cargo:rustc-link-lib=dylib={}", SYSTEM_LIB_NAME);
+        return true;
+    }
+    match pkg_config::Config::new().probe(SYSTEM_LIB_NAME) {
+        Ok(_) => true,
+        Err(_) => {
+            println!(
+                "cargo:warning=System Pico library not found, building the vendored one instead"
+            );
+            false
+        }
+    }
+}
+
+/// Returns the value of the environment variable `var`, if set and not empty.
+fn env_var(var: &str) -> Option<String> {
+    env::var(var).ok().filter(|value| !value.is_empty())
+}
+
+/// Returns `true` if the target has no C library, in which case Pico is built with `PICO_FREESTANDING`:
+/// without file access (so that resources can only be loaded from memory), and with the C library functions it
+/// needs from [`FREESTANDING_SRC_DIR`] and Rust's `compiler_builtins`.
+///
+/// (WASI targets have a C library, given a WASI sysroot; see `$WASI_SYSROOT` in the `cc` crate.)
+fn is_freestanding_target() -> bool {
+    env_var("CARGO_CFG_TARGET_ARCH").as_deref() == Some("wasm32")
+        && env_var("CARGO_CFG_TARGET_OS").as_deref() == Some("unknown")
+}
+
+/// Compiles the vendored Pico sources to a static library and links to it.
+fn build_vendored_lib() {
+    let last_pu = match env_var(LAST_PU_ENV) {
+        Some(name) => PICO_PUS
+            .iter()
+            .position(|(pu_name, _)| name.eq_ignore_ascii_case(pu_name))
+            .unwrap_or_else(|| panic!("Invalid {}: unknown PU \"{}\"", LAST_PU_ENV, name)),
+        None => PICO_PUS.len() - 1,
+    };
+    let excluded_srcs: Vec<&str> = PICO_PUS[last_pu + 1..]
+        .iter()
+        .flat_map(|(_, srcs)| srcs.iter().copied())
+        .collect();
+
     let glob_pat = format!("{}/*.c", PICO_SRC_DIR);
     let src_files = glob(glob_pat.as_str())
         .expect("Failed to list *.c files")
-        .map(|p| p.unwrap());
+        .map(|p| p.unwrap())
+        .filter(|p| {
+            let file_name = p.file_name().unwrap().to_string_lossy();
+            !excluded_srcs.contains(&file_name.as_ref())
+        });
 
-    cc::Build::new()
+    let warnings = env_var(WARNINGS_ENV).as_deref() == Some("1");
+    let mut build = cc::Build::new();
+    build
         .include(PICO_SRC_DIR)
         .files(src_files)
-        .warnings(false)
-        .extra_warnings(false)
-        .compile(PICO_LIB_NAME); // (static library)
+        .warnings(warnings)
+        .extra_warnings(warnings)
+        .define("PICO_LAST_PU", (last_pu + 1).to_string().as_str());
+    if env::var_os("CARGO_FEATURE_DEBUG_PICO").is_some() {
+        build.define("PICO_DEBUG", None);
+    }
+    if is_freestanding_target() {
+        if env::var_os("CARGO_FEATURE_DEBUG_PICO").is_some() {
+            panic!("`debug-pico` requires a C library, which the target does not have");
+        }
+        build
+            .include(format!("{}/include", FREESTANDING_SRC_DIR))
+            .file(format!("{}/libc.c", FREESTANDING_SRC_DIR))
+            .define("PICO_FREESTANDING", None)
+            .flag("-ffreestanding");
+    }
+    for define in env_var(DEFINES_ENV)
+        .iter()
+        .flat_map(|defines| defines.split(','))
+    {
+        match define.trim().split_once('=') {
+            Some((name, value)) => build.define(name, value),
+            None => build.define(define.trim(), None),
+        };
+    }
+    if let Some(opt_level) = env_var(OPT_LEVEL_ENV) {
+        build.opt_level_str(&opt_level);
+    }
+
+    let compiler = build.get_compiler();
+    if env_var(LTO_ENV).as_deref() == Some("1") {
+        build.flag(if compiler.is_like_clang() {
+            "-flto=thin"
+        } else {
+            "-flto"
+        });
+    }
+    let sanitizers: Vec<String> = env_var(SANITIZE_ENV)
+        .iter()
+        .flat_map(|sanitizers| sanitizers.split(','))
+        .map(|sanitizer| sanitizer.trim().to_string())
+        .collect();
+    if !sanitizers.is_empty() {
+        build
+            .flag(format!("-fsanitize={}", sanitizers.join(",")))
+            .flag("-fno-omit-frame-pointer");
+        if compiler.is_like_gnu() {
+            // (GCC's sanitizer runtimes; with other compilers they need to be linked manually)
+            for sanitizer in &sanitizers {
+                match sanitizer.as_str() {
+                    "address" => println!("cargo:rustc-link-lib=dylib=asan"),
+                    "undefined" => println!("cargo:rustc-link-lib=dylib=ubsan"),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    build.compile(PICO_LIB_NAME); // (static library)
 
     println!("cargo:rustc-link-lib=static={}", PICO_LIB_NAME);
 }
+
+/// Regenerates `src/ffi.rs` and `src/extapi.rs` from Pico's headers into `$OUT_DIR`, with the same options
+/// that are listed at the top of those files.
+/// Constants are not generated, as bindgen cannot evaluate the casts in `picodefs.h`; see `src/defs.rs`.
+#[cfg(feature = "bindgen")]
+fn generate_bindings() {
+    use std::path::PathBuf;
+
+    let out_dir = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+    let builder = || {
+        bindgen::Builder::default()
+            .clang_arg(format!("-I{}", PICO_SRC_DIR))
+            .use_core()
+            .ctypes_prefix("::core::ffi")
+            .layout_tests(false)
+            .parse_callbacks(Box::new(bindgen::CargoCallbacks::new()))
+    };
+
+    builder()
+        .header(format!("{}/picoapi.h", PICO_SRC_DIR))
+        .allowlist_type("pico.*")
+        .allowlist_function("pico_.*")
+        .generate()
+        .expect("Failed to generate bindings for picoapi.h")
+        .write_to_file(out_dir.join("ffi.rs"))
+        .expect("Failed to write bindings for picoapi.h");
+
+    builder()
+        .header(format!("{}/picoextapi.h", PICO_SRC_DIR))
+        .allowlist_function("picoext_.*")
+        .blocklist_type("pico_.*")
+        .generate()
+        .expect("Failed to generate bindings for picoextapi.h")
+        .write_to_file(out_dir.join("extapi.rs"))
+        .expect("Failed to write bindings for picoextapi.h");
+}
@@ -23,6 +23,7 @@
  * History:
  * - 2009-04-20 -- initial version
  * - 2019-08-28 -- x86_64 compatibility fixes
+ * - 2026-10-18 -- engines use the file system hook of their system
  */
 #include "picodefs.h"
 #include "picoos.h"
@@ -531,6 +532,8 @@ PICO_FUNC pico_newEngine(
             *outEngine = (pico_Engine) picoctrl_newEngine(system->common->mm, system->rm, voiceName);
             if (*outEngine != NULL) {
                 system->engine = (picoctrl_Engine) *outEngine;
+                /* (see picoext_setFileSystem) */
+                picoctrl_engGetCommon(system->engine)->fs = system->common->fs;
             } else {
                 status = picoos_emRaiseException(system->common->em, PICO_EXC_OUT_OF_MEM,
                             (picoos_char *) "out of memory creating new engine", NULL);
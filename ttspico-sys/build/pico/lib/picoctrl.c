@@ -25,6 +25,8 @@
  * History:
  * - 2009-04-20 -- initial version
  * - 2019-08-28 -- x86_64 compatibility fixes
+ * - 2026-10-18 -- PICO_LAST_PU to truncate the TTS processing chain
+ * - 2026-10-18 -- markers kept for picoctrl_engGetMarker
  *
  */
 
@@ -50,6 +52,14 @@
 
 #include "picoctrl.h"
 
+/* Last PU of the TTS processing chain, as a picodata_putype_t value (from
+ * PICODATA_PUTYPE_TOK = 1 to PICODATA_PUTYPE_SIG = 9). The PUs following it
+ * are left out, so that their sources need not be compiled; the engine then
+ * outputs the items produced by this PU instead of speech samples. */
+#if !defined(PICO_LAST_PU)
+#define PICO_LAST_PU 9
+#endif
+
 #ifdef __cplusplus
 extern "C" {
 #endif
@@ -341,41 +351,55 @@ static pico_status_t ctrlAddPU(register picodata_ProcessingUnit this,
             ctrl->procUnit[newPU] = picotok_newTokenizeUnit(this->common->mm,
                     this->common, cbIn, ctrl->procCbOut[newPU], this->voice);
         break;
+#if PICO_LAST_PU >= 2
     case PICODATA_PUTYPE_PR:
             PICODBG_DEBUG(("creating PreprocUnit for pu %i", newPU));
             ctrl->procUnit[newPU] = picopr_newPreprocUnit(this->common->mm,
                     this->common, cbIn, ctrl->procCbOut[newPU], this->voice);
         break;
+#endif
+#if PICO_LAST_PU >= 3
     case PICODATA_PUTYPE_WA:
             PICODBG_DEBUG(("creating WordAnaUnit for pu %i", newPU));
             ctrl->procUnit[newPU] = picowa_newWordAnaUnit(this->common->mm,
                     this->common, cbIn, ctrl->procCbOut[newPU], this->voice);
         break;
+#endif
+#if PICO_LAST_PU >= 4
     case PICODATA_PUTYPE_SA:
             PICODBG_DEBUG(("creating SentAnaUnit for pu %i", newPU));
             ctrl->procUnit[newPU] = picosa_newSentAnaUnit(this->common->mm,
                     this->common, cbIn, ctrl->procCbOut[newPU], this->voice);
         break;
+#endif
+#if PICO_LAST_PU >= 5
     case PICODATA_PUTYPE_ACPH:
             PICODBG_DEBUG(("creating AccPhrUnit for pu %i", newPU));
             ctrl->procUnit[newPU] = picoacph_newAccPhrUnit(this->common->mm,
                     this->common, cbIn, ctrl->procCbOut[newPU], this->voice);
         break;
+#endif
+#if PICO_LAST_PU >= 6
     case PICODATA_PUTYPE_SPHO:
             PICODBG_DEBUG(("creating SentPhoUnit for pu %i", newPU));
             ctrl->procUnit[newPU] = picospho_newSentPhoUnit(this->common->mm,
                     this->common, cbIn, ctrl->procCbOut[newPU], this->voice);
             break;
+#endif
+#if PICO_LAST_PU >= 7
     case PICODATA_PUTYPE_PAM:
             PICODBG_DEBUG(("creating PAMUnit for pu %i", newPU));
             ctrl->procUnit[newPU] = picopam_newPamUnit(this->common->mm,
                     this->common, cbIn, ctrl->procCbOut[newPU], this->voice);
         break;
+#endif
+#if PICO_LAST_PU >= 8
     case PICODATA_PUTYPE_CEP:
             PICODBG_DEBUG(("creating CepUnit for pu %i", newPU));
             ctrl->procUnit[newPU] = picocep_newCepUnit(this->common->mm,
                     this->common, cbIn, ctrl->procCbOut[newPU], this->voice);
         break;
+#endif
 #if defined(PICO_DEVEL_MODE)
         case PICODATA_PUTYPE_SINK:
             PICODBG_DEBUG(("creating SigUnit for pu %i", newPU));
@@ -383,11 +407,13 @@ static pico_status_t ctrlAddPU(register picodata_ProcessingUnit this,
                     this->common, cbIn, ctrl->procCbOut[newPU], this->voice);
         break;
 #endif
+#if PICO_LAST_PU >= 9
         case PICODATA_PUTYPE_SIG:
             PICODBG_DEBUG(("creating SigUnit for pu %i", newPU));
             ctrl->procUnit[newPU] = picosig_newSigUnit(this->common->mm,
                     this->common, cbIn, ctrl->procCbOut[newPU], this->voice);
         break;
+#endif
     default:
             ctrl->procUnit[newPU] = picodata_newProcessingUnit(
                     this->common->mm, this->common, cbIn,
@@ -451,15 +477,31 @@ picodata_ProcessingUnit picoctrl_newControl(picoos_MemoryManager mm,
     ctrl->numProcUnits = 0;
 
     if (
-            (PICO_OK == ctrlAddPU(this,PICODATA_PUTYPE_TOK, FALSE, /*last*/FALSE)) &&
-            (PICO_OK == ctrlAddPU(this,PICODATA_PUTYPE_PR, FALSE, FALSE)) &&
-            (PICO_OK == ctrlAddPU(this,PICODATA_PUTYPE_WA, FALSE, FALSE)) &&
-            (PICO_OK == ctrlAddPU(this,PICODATA_PUTYPE_SA, FALSE, FALSE)) &&
-            (PICO_OK == ctrlAddPU(this,PICODATA_PUTYPE_ACPH, FALSE, FALSE)) &&
-            (PICO_OK == ctrlAddPU(this,PICODATA_PUTYPE_SPHO, FALSE, FALSE)) &&
-            (PICO_OK == ctrlAddPU(this,PICODATA_PUTYPE_PAM, FALSE, FALSE)) &&
-            (PICO_OK == ctrlAddPU(this,PICODATA_PUTYPE_CEP, FALSE, FALSE)) &&
-            (PICO_OK == ctrlAddPU(this,PICODATA_PUTYPE_SIG, FALSE, TRUE))
+            (PICO_OK == ctrlAddPU(this,PICODATA_PUTYPE_TOK, FALSE, /*last*/PICO_LAST_PU == 1))
+#if PICO_LAST_PU >= 2
+            && (PICO_OK == ctrlAddPU(this,PICODATA_PUTYPE_PR, FALSE, PICO_LAST_PU == 2))
+#endif
+#if PICO_LAST_PU >= 3
+            && (PICO_OK == ctrlAddPU(this,PICODATA_PUTYPE_WA, FALSE, PICO_LAST_PU == 3))
+#endif
+#if PICO_LAST_PU >= 4
+            && (PICO_OK == ctrlAddPU(this,PICODATA_PUTYPE_SA, FALSE, PICO_LAST_PU == 4))
+#endif
+#if PICO_LAST_PU >= 5
+            && (PICO_OK == ctrlAddPU(this,PICODATA_PUTYPE_ACPH, FALSE, PICO_LAST_PU == 5))
+#endif
+#if PICO_LAST_PU >= 6
+            && (PICO_OK == ctrlAddPU(this,PICODATA_PUTYPE_SPHO, FALSE, PICO_LAST_PU == 6))
+#endif
+#if PICO_LAST_PU >= 7
+            && (PICO_OK == ctrlAddPU(this,PICODATA_PUTYPE_PAM, FALSE, PICO_LAST_PU == 7))
+#endif
+#if PICO_LAST_PU >= 8
+            && (PICO_OK == ctrlAddPU(this,PICODATA_PUTYPE_CEP, FALSE, PICO_LAST_PU == 8))
+#endif
+#if PICO_LAST_PU >= 9
+            && (PICO_OK == ctrlAddPU(this,PICODATA_PUTYPE_SIG, FALSE, TRUE))
+#endif
          ) {
 
         /* we don't call ctrlInitialize here because ctrlAddPU does initialize the PUs allready and the only thing
@@ -504,6 +546,9 @@ typedef struct picoctrl_engine {
     picorsrc_Voice voice;
     picodata_ProcessingUnit control;
     picodata_CharBuffer cbIn, cbOut;
+    /* last marker item output (if markerLen > 0), i.e. for <mark> */
+    picoos_uint8 marker[PICODATA_MAX_ITEMSIZE];
+    picoos_uint16 markerLen;
 } picoctrl_engine_t;
 
 
@@ -542,6 +587,7 @@ pico_status_t picoctrl_engReset(picoctrl_Engine this, picoos_int32 resetMode)
     if (PICO_OK == status) {
         status = picodata_cbReset(this->cbOut);
     }
+    this->markerLen = 0;
     if (PICO_OK != status) {
         picoos_emRaiseException(this->common->em,status,NULL,(picoos_char*) "problem resetting engine");
     }
@@ -595,6 +641,7 @@ picoctrl_Engine picoctrl_newEngine(picoos_MemoryManager mm,
         this->control = NULL;
         this->cbIn = NULL;
         this->cbOut = NULL;
+        this->markerLen = 0;
 
         this->raw_mem = picoos_allocate(mm, PICOCTRL_DEFAULT_ENGINE_SIZE);
         if (NULL == this->raw_mem) {
@@ -763,9 +810,20 @@ picodata_step_result_t picoctrl_engFetchOutputItemBytes(
     PICODBG_DEBUG(("doing one step"));
     stepResult = this->control->step(this->control,/* mode */0,&ui);
     if (PICODATA_PU_ERROR != stepResult) {
-        PICODBG_TRACE(("filling output buffer"));
-        rv = picodata_cbGetSpeechData(this->cbOut, (picoos_uint8 *)buffer,
-                                      bufferSize, &ui);
+        /* a marker item is kept for picoctrl_engGetMarker (instead of being
+           skipped like the other items that are not speech data), outputting
+           no speech data in this step */
+        rv = picodata_cbGetMarker(this->cbOut, this->marker,
+                                  PICODATA_MAX_ITEMSIZE, &ui);
+        if (PICO_OK == rv) {
+            PICODBG_DEBUG(("got marker"));
+            this->markerLen = ui;
+            ui = 0;
+        } else {
+            PICODBG_TRACE(("filling output buffer"));
+            rv = picodata_cbGetSpeechData(this->cbOut, (picoos_uint8 *)buffer,
+                                          bufferSize, &ui);
+        }
 
         if (ui > 255) {   /* because picoapi uses signed int16 */
             return (picodata_step_result_t)PICO_STEP_ERROR;
@@ -793,6 +851,45 @@ picodata_step_result_t picoctrl_engFetchOutputItemBytes(
     }
 }/*picoctrl_engFetchOutputItemBytes*/
 
+/**
+ * gets the name of the last marker output by the engine, if any
+ * @param    this : handle of the engine
+ * @param    name : the destination buffer, receiving the zero-terminated name
+ * @param    nameMaxLen : max size of the destination buffer
+ * @param    *found : whether a marker was output since the last call (or reset)
+ * @return    PICO_OK : marker name (if any) gotten
+ * @return    PICO_ERR_NULLPTR_ACCESS : if error
+ * @remarks    only the last marker is kept, so this is to be called after each
+ *            call to picoctrl_engFetchOutputItemBytes
+ * @callgraph
+ * @callergraph
+ */
+pico_status_t picoctrl_engGetMarker(
+        picoctrl_Engine this,
+        picoos_char *name,
+        picoos_int16 nameMaxLen,
+        picoos_int16 *found) {
+    picoos_uint16 i, len;
+
+    if ((NULL == this) || (NULL == name) || (NULL == found) || (nameMaxLen < 1)) {
+        return PICO_ERR_NULLPTR_ACCESS;
+    }
+    *found = (this->markerLen > 0);
+    len = 0;
+    if (*found) {
+        len = this->markerLen - PICODATA_ITEM_HEADSIZE;
+        if (len > nameMaxLen - 1) {
+            len = nameMaxLen - 1;
+        }
+        for (i = 0; i < len; i++) {
+            name[i] = (picoos_char) this->marker[PICODATA_ITEM_HEADSIZE + i];
+        }
+        this->markerLen = 0;
+    }
+    name[len] = '\0';
+    return PICO_OK;
+}/*picoctrl_engGetMarker*/
+
 /**
  * returns the last scheduled PU
  * @param    this : handle of the engine
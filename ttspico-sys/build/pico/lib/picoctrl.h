@@ -21,6 +21,7 @@
  *
  * History:
  * - 2009-04-20 -- initial version
+ * - 2026-10-18 -- added picoctrl_engGetMarker
  *
  */
 
@@ -81,6 +82,13 @@ picodata_step_result_t picoctrl_engFetchOutputItemBytes(
         picoos_int16  * bytesReceived
 );
 
+pico_status_t picoctrl_engGetMarker(
+        picoctrl_Engine engine,
+        picoos_char * name,
+        picoos_int16 nameMaxLen,
+        picoos_int16 * found
+);
+
 void picoctrl_engResetExceptionManager(
         picoctrl_Engine this
         );
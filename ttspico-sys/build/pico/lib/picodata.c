@@ -21,6 +21,7 @@
  *
  * History:
  * - 2009-04-20 -- initial version
+ * - 2026-10-18 -- added picodata_cbGetMarker
  *
  */
 
@@ -333,6 +334,20 @@ pico_status_t picodata_cbGetSpeechData(register picodata_CharBuffer this,
     return this->getItem(this, buf, blenmax, blen, TRUE);
 }
 
+pico_status_t picodata_cbGetMarker(register picodata_CharBuffer this,
+        picoos_uint8 *buf, const picoos_uint16 blenmax,
+        picoos_uint16 *blen)
+{
+    if ((this->len < PICODATA_ITEM_HEADSIZE)
+            || (this->buf[this->front] != PICODATA_ITEM_CMD)
+            || (this->buf[(this->front + PICODATA_ITEMIND_INFO1) % this->size]
+                    != PICODATA_ITEMINFO1_CMD_MARKER)) {
+        *blen = 0;
+        return PICO_EOF;
+    }
+    return this->getItem(this, buf, blenmax, blen, FALSE);
+}
+
 
 pico_status_t picodata_cbPutItem(register picodata_CharBuffer this,
         const picoos_uint8 *buf, const picoos_uint16 blenmax,
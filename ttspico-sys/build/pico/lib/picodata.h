@@ -21,6 +21,7 @@
  *
  * History:
  * - 2009-04-20 -- initial version
+ * - 2026-10-18 -- added picodata_cbGetMarker
  *
  */
 #ifndef PICODATA_H_
@@ -314,6 +315,18 @@ pico_status_t picodata_cbGetSpeechData(register picodata_CharBuffer this,
         picoos_uint8 *buf, const picoos_uint16 blenmax,
         picoos_uint16 *blen);
 
+/* gets a single item (head and content) from a CharBuffer in buf, like
+   picodata_cbGetItem, if it is a marker command (as for <mark>); return
+   values:
+     PICO_OK                 <- marker item gotten
+     PICO_EOF                <- no item available, or not a marker item
+     PICO_EXC_BUF_UNDERFLOW  <- cb not empty, but no valid item
+     PICO_EXC_BUF_OVERFLOW   <- buf not large enough
+*/
+pico_status_t picodata_cbGetMarker(register picodata_CharBuffer this,
+        picoos_uint8 *buf, const picoos_uint16 blenmax,
+        picoos_uint16 *blen);
+
 /* puts a single item (head and content) to a CharBuffer; clenmax is
    the max length (in number of bytes) accessible in content; clen is
    set to the number of bytes put from content; return values:
@@ -24,6 +24,8 @@
  *
  * History:
  * - 2009-04-20 -- initial version
+ * - 2026-10-18 -- added log callback
+ * - 2026-10-19 -- process-wide settings that outlive systems, thread-local and bounded message buffers
  */
 
 #ifdef __cplusplus
@@ -72,6 +74,13 @@ extern "C" {
 /* Standard output file for debug messages */
 #define STDDBG                  stdout /* or stderr */
 
+/* Thread-local storage, as messages are formatted by each thread using Pico */
+#if defined(_MSC_VER) && !defined(__clang__)
+#define PICODBG_THREAD_LOCAL __declspec(thread)
+#else
+#define PICODBG_THREAD_LOCAL _Thread_local
+#endif
+
 /* Default setup */
 #define PICODBG_DEFAULT_LEVEL   PICODBG_LOG_LEVEL_WARN
 #define PICODBG_DEFAULT_FILTERFN   ""
@@ -80,6 +89,12 @@ extern "C" {
 #define PICODBG_DEFAULT_COLOR   1
 
 
+/* The settings below are global to the process, not to a system: they are set to their defaults by the
+   first call to picodbg_initialize or picodbg_setLogCallback, and kept by later ones (and by
+   picodbg_terminate), so that a system being initialized or terminated does not change the output of others.
+   (Setting the log callback before initializing any system keeps threads from initializing them at once.) */
+static int initialized = 0;
+
 /* Current log level */
 static int logLevel = PICODBG_DEFAULT_LEVEL;
 
@@ -89,6 +104,9 @@ static char logFilterFN[MAX_FILTERFN_LEN + 1];
 /* Current log file or NULL if no log file is set */
 static FILE *logFile = NULL;
 
+/* Current log callback or NULL if no log callback is set */
+static picodbg_log_callback_t logCallback = NULL;
+
 /* Current output format */
 static int logFormat = PICODBG_DEFAULT_FORMAT;
 
@@ -96,10 +114,10 @@ static int logFormat = PICODBG_DEFAULT_FORMAT;
 static int optColor = 0;
 
 /* Buffer for context information */
-static char ctxbuf[MAX_CONTEXT_LEN + 1];
+static PICODBG_THREAD_LOCAL char ctxbuf[MAX_CONTEXT_LEN + 1];
 
 /* Buffer to format tracing messages */
-static char msgbuf[MAX_MESSAGE_LEN + 1];
+static PICODBG_THREAD_LOCAL char msgbuf[MAX_MESSAGE_LEN + 1];
 
 
 /* *** Support for colored text output to console *****/
@@ -260,25 +278,33 @@ static void picodbg_logToStream(int level, int donewline,
 /* *** Exported routines *****/
 
 
-void picodbg_initialize(int level)
+static void picodbg_setDefaults(int level)
 {
+    initialized = 1;
     logLevel  = level;
     strcpy(logFilterFN, PICODBG_DEFAULT_FILTERFN);
     logFile   = NULL;
     logFormat = PICODBG_DEFAULT_FORMAT;
     optColor  = PICODBG_DEFAULT_COLOR;
+}
+
+
+void picodbg_initialize(int level)
+{
+    /* (the log callback is kept, so that it can be set before initializing) */
     PICODBG_ASSERT_RANGE(level, 0, PICODBG_LOG_LEVEL_TRACE);
+    if (!initialized) {
+        picodbg_setDefaults(level);
+    }
 }
 
 
 void picodbg_terminate()
 {
+    /* (other systems may still be tracing, see initialized) */
     if (logFile != NULL) {
-        fclose(logFile);
+        fflush(logFile);
     }
-
-    logLevel = 0;
-    logFile  = NULL;
 }
 
 
@@ -309,6 +335,15 @@ void picodbg_setLogFile(const char *name)
 }
 
 
+void picodbg_setLogCallback(picodbg_log_callback_t callback)
+{
+    if (!initialized) {
+        picodbg_setDefaults(PICODBG_LOG_LEVEL_INFO);
+    }
+    logCallback = callback;
+}
+
+
 void picodbg_enableColors(int flag)
 {
     optColor = (flag != 0);
@@ -328,8 +363,12 @@ const char *picodbg_varargs(const char *format, ...)
     va_list argptr;
     va_start(argptr, format);
 
-    len = vsprintf(msgbuf, format, argptr);
-    PICODBG_ASSERT_RANGE(len, 0, MAX_MESSAGE_LEN);
+    /* longer messages are truncated */
+    len = vsnprintf(msgbuf, sizeof msgbuf, format, argptr);
+    va_end(argptr);
+    if (len < 0) {
+        msgbuf[0] = '\0';
+    }
 
     return msgbuf;
 }
@@ -344,6 +383,11 @@ void picodbg_log(int level, int donewline, const char *file, int line,
 
     if ((level <= logLevel) &&
         ((strlen(logFilterFN) == 0) || !strcmp(logFilterFN, picodbg_fileTitle(file)))) {
+        if (logCallback != NULL) {
+            logCallback(level, donewline, picodbg_fileTitle(file), line, func, msg);
+            return;
+        }
+
         /* compose output format string */
         strcpy(ctxbuf, "*** ");
         if (logFormat & PICODBG_SHOW_LEVEL) {
@@ -374,17 +418,17 @@ void picodbg_log(int level, int donewline, const char *file, int line,
             /* nyi */
         }
         if (logFormat & PICODBG_SHOW_SRCNAME) {
-            sprintf(cb, "%-10s", picodbg_fileTitle(file));
+            snprintf(cb, sizeof cb, "%-10s", picodbg_fileTitle(file));
             strcat(ctxbuf, cb);
             if (logFormat & PICODBG_SHOW_SRCLINE) {
-                sprintf(cb, "(%d)", line);
+                snprintf(cb, sizeof cb, "(%d)", line);
                 strcat(ctxbuf, cb);
             }
             strcat(ctxbuf, MSG_DELIM);
         }
         if (logFormat & PICODBG_SHOW_FUNCTION) {
             if (strlen(func) > 0) {
-                sprintf(cb, "%-18s", func);
+                snprintf(cb, sizeof cb, "%-18s", func);
                 strcat(ctxbuf, cb);
                 strcat(ctxbuf, MSG_DELIM);
             }
@@ -401,14 +445,21 @@ void picodbg_log_msg(int level, const char *file, const char *msg)
 
     if ((level <= logLevel) &&
         ((strlen(logFilterFN) == 0) || !strcmp(logFilterFN, picodbg_fileTitle(file)))) {
-        picodbg_logToStream(level, 0, "", msg);
+        if (logCallback != NULL) {
+            logCallback(level, 0, picodbg_fileTitle(file), 0, "", msg);
+        } else {
+            picodbg_logToStream(level, 0, "", msg);
+        }
     }
 }
 
 
 void picodbg_assert(const char *file, int line, const char *func, const char *expr)
 {
-    if (strlen(func) > 0) {
+    if (logCallback != NULL) {
+        snprintf(msgbuf, sizeof msgbuf, "assertion failed: %s", expr);
+        logCallback(PICODBG_LOG_LEVEL_ERROR, 1, picodbg_fileTitle(file), line, func, msgbuf);
+    } else if (strlen(func) > 0) {
         fprintf(STDDBG, "assertion failed: %s, file %s, function %s, line %d",
             expr, picodbg_fileTitle(file), func, line);
     } else {
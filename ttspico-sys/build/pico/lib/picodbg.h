@@ -25,6 +25,8 @@
  *
  * History:
  * - 2009-04-20 -- initial version
+ * - 2026-10-18 -- added log callback
+ * - 2026-10-19 -- process-wide settings that outlive systems
  */
 
 /**
@@ -66,7 +68,13 @@
  * Before using any debug macros, this module has to be initialized
  * by calling PICODBG_INITIALIZE(). If the routines are not needed
  * anymore, PICODBG_TERMINATE() has to be called to terminate the
- * module (e.g., to close the log file).
+ * module (e.g., to flush the log file).
+ *
+ * The log level, filter, file and callback are global to the process:
+ * only the first PICODBG_INITIALIZE() (or PICODBG_SET_LOG_CALLBACK())
+ * sets them to their defaults, and PICODBG_TERMINATE() keeps them, so
+ * that initializing or terminating a system does not affect others.
+ * Messages are formatted in thread-local buffers.
  *
  *
  * TRACING\n
@@ -142,6 +150,13 @@
  * on embedded systems, more output targets may be required (e.g.,
  * sending output to a serial port or over the network).
  *
+ * Alternatively, PICODBG_SET_LOG_CALLBACK() installs a callback that
+ * receives every tracing message (with its level, source file name
+ * without path, line and function) instead of the console and log file.
+ * Messages composed piecewise (PICODBG_INFO_CTX/PICODBG_INFO_MSG) are
+ * passed as fragments with 'donewline' set to 0; the line is complete
+ * once a fragment ends with a newline.
+ *
  *
  * ASSERTIONS\n
  * ----------\n
@@ -193,6 +208,10 @@ extern "C" {
 #define PICODBG_SHOW_FUNCTION       0x0020
 #define PICODBG_SHOW_POS            (PICODBG_SHOW_SRCALL | PICODBG_SHOW_FUNCTION)
 
+/* Log callback type (see PICODBG_SET_LOG_CALLBACK) */
+typedef void (* picodbg_log_callback_t)(int level, int donewline,
+        const char *file, int line, const char *func, const char *msg);
+
 /* definition of PICO_DEBUG enables debugging code */
 #if defined(PICO_DEBUG)
 
@@ -211,6 +230,9 @@ extern "C" {
 #define PICODBG_SET_LOG_FILE(name) \
     picodbg_setLogFile(name)
 
+#define PICODBG_SET_LOG_CALLBACK(callback) \
+    picodbg_setLogCallback(callback)
+
 #define PICODBG_ENABLE_COLORS(flag) \
     picodbg_enableColors(flag)
 
@@ -263,6 +285,7 @@ void picodbg_terminate();
 void picodbg_setLogLevel(int level);
 void picodbg_setLogFilterFN(const char *name);
 void picodbg_setLogFile(const char *name);
+void picodbg_setLogCallback(picodbg_log_callback_t callback);
 void picodbg_enableColors(int flag);
 void picodbg_setOutputFormat(unsigned int format);
 
@@ -283,6 +306,7 @@ void picodbg_log_msg(int level, const char *file, const char *msg);
 #define PICODBG_SET_LOG_LEVEL(level)
 #define PICODBG_SET_LOG_FILTERFN(name)
 #define PICODBG_SET_LOG_FILE(name)
+#define PICODBG_SET_LOG_CALLBACK(callback)
 #define PICODBG_ENABLE_COLORS(flag)
 #define PICODBG_SET_OUTPUT_FORMAT(format)
 
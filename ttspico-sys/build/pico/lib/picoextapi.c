@@ -23,6 +23,10 @@
  *
  * History:
  * - 2009-04-20 -- initial version
+ * - 2026-10-18 -- added picoext_loadResourceFromMemory
+ * - 2026-10-18 -- added picoext_getMarker
+ * - 2026-10-18 -- added picoext_setFileSystem
+ * - 2026-10-18 -- added picoext_setEngineFileFilter
  *
  */
 #include "picodefs.h"
@@ -66,6 +70,39 @@ PICO_FUNC picoext_initialize(
 }
 
 
+/* Lingware loading functions **************************************************/
+
+
+PICO_FUNC picoext_loadResourceFromMemory(
+        pico_System system,
+        const pico_Char *resourceFileName,
+        const void *data,
+        const pico_Uint32 size,
+        pico_Resource *outResource
+        )
+{
+    pico_Status status = PICO_OK;
+
+    if (!is_valid_system_handle(system)) {
+        status = PICO_ERR_INVALID_HANDLE;
+    } else if ((resourceFileName == NULL) || (data == NULL) || (outResource == NULL)) {
+        status = PICO_ERR_NULLPTR_ACCESS;
+    } else {
+        picoos_Common common = pico_sysGetCommon(system);
+        picoos_emReset(common->em);
+        common->memFileName = (const picoos_char *) resourceFileName;
+        common->memFileData = (const picoos_uint8 *) data;
+        common->memFileSize = size;
+        status = picorsrc_loadResource(system->rm, (picoos_char *) resourceFileName, (picorsrc_Resource *) outResource);
+        common->memFileName = NULL;
+        common->memFileData = NULL;
+        common->memFileSize = 0;
+    }
+
+    return status;
+}
+
+
 /* System and lingware inspection functions ***********************************/
 
 /* @todo : not supported yet */
@@ -221,6 +258,78 @@ PICO_FUNC picoext_getLastProducedItemType(
     return status;
 }
 
+
+/* Markers ********************************************************************/
+
+PICO_FUNC picoext_getMarker(
+        pico_Engine engine,
+        pico_Char *outName,
+        const pico_Int16 outNameMaxLen,
+        pico_Int16 *outFound
+        )
+{
+    pico_Status status = PICO_OK;
+
+    if (!picoctrl_isValidEngineHandle((picoctrl_Engine) engine)) {
+        status = PICO_ERR_INVALID_HANDLE;
+    } else if ((outName == NULL) || (outFound == NULL)) {
+        status = PICO_ERR_NULLPTR_ACCESS;
+    } else {
+        status = picoctrl_engGetMarker((picoctrl_Engine) engine, (picoos_char *) outName, outNameMaxLen, outFound);
+    }
+
+    return status;
+}
+
+
+/* File system ****************************************************************/
+
+
+PICO_FUNC picoext_setFileSystem(
+        pico_System system,
+        const picoext_FileSystem *fileSystem
+        )
+{
+    pico_Status status = PICO_OK;
+
+    if (!is_valid_system_handle(system)) {
+        status = PICO_ERR_INVALID_HANDLE;
+    } else if ((fileSystem != NULL) && ((fileSystem->open == NULL) || (fileSystem->read == NULL)
+            || (fileSystem->write == NULL) || (fileSystem->close == NULL))) {
+        status = PICO_ERR_NULLPTR_ACCESS;
+    } else {
+        /* (the engine has a common object of its own; see pico_newEngine) */
+        system->common->fs = fileSystem;
+        if (system->engine != NULL) {
+            picoctrl_engGetCommon(system->engine)->fs = fileSystem;
+        }
+    }
+
+    return status;
+}
+
+
+/* File filter ****************************************************************/
+
+
+PICO_FUNC picoext_setEngineFileFilter(
+        pico_Engine engine,
+        const picoext_FileFilter *filter
+        )
+{
+    pico_Status status = PICO_OK;
+
+    if (!picoctrl_isValidEngineHandle((picoctrl_Engine) engine)) {
+        status = PICO_ERR_INVALID_HANDLE;
+    } else if ((filter != NULL) && (filter->check == NULL)) {
+        status = PICO_ERR_NULLPTR_ACCESS;
+    } else {
+        picoctrl_engGetCommon((picoctrl_Engine) engine)->filter = filter;
+    }
+
+    return status;
+}
+
 #ifdef __cplusplus
 }
 #endif
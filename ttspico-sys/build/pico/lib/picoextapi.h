@@ -23,6 +23,11 @@
  *
  * History:
  * - 2009-04-20 -- initial version
+ * - 2026-10-18 -- added picoext_loadResourceFromMemory
+ * - 2026-10-18 -- added picoext_getMarker
+ * - 2026-10-18 -- added picoext_setFileSystem
+ * - 2026-10-18 -- added picoext_setEngineFileFilter
+ * - 2026-10-18 -- picoext_FileFilter notified of the closing of memory files
  *
  */
 
@@ -84,6 +89,22 @@ PICO_FUNC picoext_initialize(
         );
 
 
+/* Lingware loading functions **************************************************/
+
+/* Same as pico_loadResource, but reads the resource file contents from the
+   'size' bytes at 'data' instead of the file system. 'resourceFileName' is
+   used in place of the file name (and must end in ".bin"). The contents are
+   copied into Pico's memory; 'data' does not need to outlive the call. */
+
+PICO_FUNC picoext_loadResourceFromMemory(
+        pico_System system,
+        const pico_Char *resourceFileName,
+        const void *data,
+        const pico_Uint32 size,
+        pico_Resource *outResource
+        );
+
+
 /* System and lingware inspection functions ***********************************/
 
 /* Returns version information of the current Pico engine. */
@@ -155,6 +176,99 @@ PICO_FUNC picoext_getLastProducedItemType(
         pico_Engine engine
         );
 
+
+/* Markers ********************************************************************/
+
+/* Gets the name of the last <mark> reached by the speech data output by
+   pico_getData into 'outName' (zero-terminated, truncated to
+   'outNameMaxLen'), setting 'outFound' to whether a marker was reached since
+   the last call (or engine reset). Only the last marker is kept, and a marker
+   is reached by a call to pico_getData that outputs no speech data; so the
+   position of a marker in the speech data is exact if this is called after
+   each call to pico_getData. */
+
+PICO_FUNC picoext_getMarker(
+        pico_Engine engine,
+        pico_Char *outName,
+        const pico_Int16 outNameMaxLen,
+        pico_Int16 *outFound
+        );
+
+
+/* File system ****************************************************************/
+
+/* Callbacks replacing the file system for the files opened by a system and
+   its engine: resources, audio files inserted by <play> and the outputs of
+   <genfile>. Files are opened by name, then read or written at the given
+   byte positions; 'userData' is passed to each callback. */
+typedef struct picoext_file_system {
+    void *userData;
+    /* Opens file 'fileName' for reading, setting 'outLength' to its length in
+       bytes, or creates (truncating) it for writing if 'write' is non-zero.
+       Returns a non-NULL handle to the file, or NULL if it cannot be opened. */
+    void *(*open)(void *userData, const pico_Char *fileName, pico_Int16 write, pico_Uint32 *outLength);
+    /* Reads up to 'len' bytes at 'pos' into 'bytes'. Returns the number of
+       bytes read (less than 'len' at the end of the file), or -1 on errors. */
+    pico_Int32 (*read)(void *userData, void *file, pico_Uint32 pos, pico_Char *bytes, pico_Uint32 len);
+    /* Writes 'len' bytes from 'bytes' at 'pos'. Returns the number of bytes
+       written, or -1 on errors. */
+    pico_Int32 (*write)(void *userData, void *file, pico_Uint32 pos, const pico_Char *bytes, pico_Uint32 len);
+    /* Closes the file, invalidating its handle. */
+    void (*close)(void *userData, void *file);
+} picoext_FileSystem;
+
+/* Sets the file system used to open files by 'system' and its engine (also
+   one created later), or restores the real one if 'fileSystem' is NULL.
+   Resources loaded with picoext_loadResourceFromMemory are not affected.
+   Files that are open keep using the file system they were opened with, so
+   'fileSystem' must outlive them (i.e. the resources loaded through it). */
+
+PICO_FUNC picoext_setFileSystem(
+        pico_System system,
+        const picoext_FileSystem *fileSystem
+        );
+
+
+/* File filter ****************************************************************/
+
+/* Decisions of a picoext_FileFilter */
+#define PICOEXT_FILE_ALLOW  0   /* opens the file as usual */
+#define PICOEXT_FILE_DENY   1   /* fails to open the file */
+#define PICOEXT_FILE_MEMORY 2   /* reads the file from memory instead */
+
+/* Filter deciding whether the files opened by an engine (the audio files
+   inserted by <play> and <usesig>, and the outputs of <genfile>) can be
+   opened; 'userData' is passed to its callback. */
+typedef struct picoext_file_filter {
+    void *userData;
+    /* Decides whether file 'fileName' can be opened for reading, or for
+       writing if 'write' is non-zero. Returns one of PICOEXT_FILE_*; for
+       PICOEXT_FILE_MEMORY (only valid for reading), sets 'outData' and
+       'outSize' to the file contents, which must stay valid while the file
+       is open. */
+    pico_Int16 (*check)(void *userData, const pico_Char *fileName, pico_Int16 write,
+            const void **outData, pico_Uint32 *outSize);
+    /* Called (if not NULL) when a file that 'check' served from memory is
+       closed, with the 'outData' it was opened with. */
+    void (*close)(void *userData, const void *data);
+} picoext_FileFilter;
+
+/* Sets the filter of the files opened by 'engine', or removes it if 'filter'
+   is NULL (allowing all files, like Pico does by default). Resources are
+   opened by the engine's system, so they are not filtered. 'filter' must
+   outlive the engine, as the files it served are closed by later calls (up
+   to the engine's disposal). */
+
+PICO_FUNC picoext_setEngineFileFilter(
+        pico_Engine engine,
+        const picoext_FileFilter *filter
+        );
+
+/* Prefix of the file names under which <play id="..."> plays clips: the tag
+   plays file PICOEXT_CLIP_PREFIX "<id>.wav", which a picoext_FileFilter can
+   serve from memory (and is otherwise opened as usual). */
+#define PICOEXT_CLIP_PREFIX "clip:"
+
 #ifdef __cplusplus
 }
 #endif
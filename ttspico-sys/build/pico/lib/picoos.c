@@ -24,6 +24,11 @@
  * History:
  * - 2009-04-20 -- initial version
  * - 2020-04-14 -- 64-bit compatibility fixes
+ * - 2026-10-18 -- in-memory files
+ * - 2026-10-18 -- fixed out-of-bounds read in picoos_readPicoHeader
+ * - 2026-10-18 -- files opened via the file system hook of picoos_common
+ * - 2026-10-18 -- files checked by the file filter of picoos_common
+ * - 2026-10-18 -- closing of the files served by the file filter reported to it
  *
  */
 
@@ -32,6 +37,8 @@
 #include "picopal.h"
 #include "picoos.h"
 #include "picodbg.h"
+#include "picoapi.h"
+#include "picoextapi.h"
 
 #ifdef __cplusplus
 extern "C" {
@@ -223,6 +230,11 @@ picoos_Common picoos_newCommon(picoos_MemoryManager mm)
         this->em = NULL;
         this->mm = NULL;
         this->fileList = NULL;
+        this->memFileName = NULL;
+        this->memFileData = NULL;
+        this->memFileSize = 0;
+        this->fs = NULL;
+        this->filter = NULL;
     }
     return this;
 }
@@ -939,6 +951,10 @@ typedef struct picoos_file
     picoos_uint8 write;
 
     picopal_File nf;
+    const picoos_uint8 * mem; /* file contents if in-memory, else NULL */
+    const picoext_FileSystem * fs; /* file system hook the file was opened with, else NULL */
+    void * fsFile; /* the file's handle in 'fs' */
+    const picoext_FileFilter * filter; /* file filter that served 'mem', else NULL */
 
     picoos_uint32 lFileLen;
     picoos_uint32 lPos;
@@ -992,6 +1008,9 @@ static picoos_bool LOpen(picoos_Common g, picoos_File * f,
         picoos_char fileName[], picopal_access_mode mode)
 {
     picoos_bool done = TRUE;
+    pico_Int16 decision = PICOEXT_FILE_ALLOW;
+    const void * filterData = NULL;
+    pico_Uint32 filterSize = 0;
 
     *f = picoos_newFile(g->mm);
     picopal_strcpy((*f)->name, fileName);
@@ -1002,15 +1021,42 @@ static picoos_bool LOpen(picoos_Common g, picoos_File * f,
     (*f)->next = NULL;
     (*f)->prev = NULL;
     (*f)->nf = picopal_get_fnil();
+    (*f)->mem = NULL;
+    (*f)->fs = NULL;
+    (*f)->fsFile = NULL;
+    (*f)->filter = NULL;
     (*f)->lFileLen = 0;
     (*f)->lPos = 0;
-    if (picopal_strlen((*f)->name)) {
+    if (NULL != g->filter) {
+        decision = g->filter->check(g->filter->userData, (*f)->name, (*f)->write, &filterData, &filterSize);
+    }
+    if (PICOEXT_FILE_DENY == decision) {
+        done = FALSE;
+    } else if (PICOEXT_FILE_MEMORY == decision) {
+        /* (memory can only be read) */
+        done = !(*f)->write && (NULL != filterData);
+        (*f)->mem = (const picoos_uint8 *) filterData;
+        (*f)->filter = g->filter;
+        (*f)->lFileLen = filterSize;
+    } else if (!(*f)->write && (NULL != g->memFileName)
+            && (0 == picopal_strcmp((*f)->name, g->memFileName))) {
+        (*f)->mem = g->memFileData;
+        (*f)->lFileLen = g->memFileSize;
+    } else if (NULL != g->fs) {
+        (*f)->fs = g->fs;
+        (*f)->fsFile = g->fs->open(g->fs->userData, (*f)->name, (*f)->write, &(*f)->lFileLen);
+        done = (NULL != (*f)->fsFile);
+    } else if (picopal_strlen((*f)->name)) {
        (*f)->nf = picopal_fopen((*f)->name, mode);
         done = !(picopal_is_fnil((*f)->nf));
         if (done) {
             (*f)->lFileLen = picopal_flength((*f)->nf);
         }
     }
+    /* (a memory file that could not be opened is closed right away) */
+    if (!done && (NULL != (*f)->filter) && (NULL != (*f)->filter->close)) {
+        (*f)->filter->close((*f)->filter->userData, (*f)->mem);
+    }
     if (done) {
         (*f)->next = g->fileList;
         if (g->fileList != NULL) {
@@ -1030,7 +1076,15 @@ static picoos_bool LClose(picoos_Common g, picoos_File * f)
     picoos_bool done;
 
     if (((*f) != NULL)) {
-        done = (PICO_OK == picopal_fclose((*f)->nf));
+        if (NULL != (*f)->fs) {
+            (*f)->fs->close((*f)->fs->userData, (*f)->fsFile);
+        } else if (NULL != (*f)->filter) {
+            if (NULL != (*f)->filter->close) {
+                (*f)->filter->close((*f)->filter->userData, (*f)->mem);
+            }
+        } else if (NULL == (*f)->mem) {
+            done = (PICO_OK == picopal_fclose((*f)->nf));
+        }
         if (((*f)->next != NULL)) {
             (*f)->next->prev = (*f)->prev;
         }
@@ -1058,7 +1112,14 @@ static picoos_bool LReadBytes(picoos_File f, picoos_uint8 bytes[],
 
     PICODBG_TRACE(("trying to read %i bytes",*len));
     if ((f != NULL)) {
-        res = picopal_fread_bytes(f->nf, (void *) &bytes[(0)], 1, (*len));
+        if (NULL != f->mem) {
+            res = (picoos_int32) (((*len) < f->lFileLen - f->lPos) ? (*len) : (f->lFileLen - f->lPos));
+            picopal_mem_copy(f->mem + f->lPos, (void *) &bytes[(0)], res);
+        } else if (NULL != f->fs) {
+            res = f->fs->read(f->fs->userData, f->fsFile, f->lPos, &bytes[(0)], (*len));
+        } else {
+            res = picopal_fread_bytes(f->nf, (void *) &bytes[(0)], 1, (*len));
+        }
         PICODBG_TRACE(("res = %i",res));
         if (res < 0) { /* non-ansi */
             (*len) = 0;
@@ -1084,7 +1145,11 @@ static picoos_bool LReadBytes(picoos_File f, picoos_uint8 bytes[],
     void * bptr; */
 
     if (f != NULL) {
-        res = picopal_fwrite_bytes(f->nf, (void *) bytes, 1, *len);
+        if (NULL != f->fs) {
+            res = f->fs->write(f->fs->userData, f->fsFile, f->lPos, (const picoos_uint8 *) bytes, *len);
+        } else {
+            res = picopal_fwrite_bytes(f->nf, (void *) bytes, 1, *len);
+        }
         if ((res < 0)) {
             (*len) = 0;
             done = FALSE;
@@ -1114,6 +1179,11 @@ static picoos_bool LSetPos(picoos_File f, unsigned int pos)
     if ((f != NULL)) {
         if ((pos == f->lPos)) {
             done = TRUE;
+        } else if ((NULL != f->mem) || (NULL != f->fs)) {
+            done = (pos <= f->lFileLen);
+            if (done) {
+                f->lPos = pos;
+            }
         } else {
             done = (PICO_OK == picopal_fseek(f->nf, pos, PICOPAL_SEEK_SET));
             if (done) {
@@ -1144,7 +1214,7 @@ static picoos_bool LEof(picoos_File f)
     picoos_bool isEof;
 
     if ((f != NULL)) {
-        isEof = picopal_feof(f->nf);
+        isEof = ((NULL != f->mem) || (NULL != f->fs)) ? (f->lPos >= f->lFileLen) : picopal_feof(f->nf);
     } else {
         isEof = TRUE;
     }
@@ -1987,7 +2057,7 @@ pico_status_t picoos_readPicoHeader(picoos_File f, picoos_uint32 * headerlen)
             bufpos = (bufpos + 1) % strlen;
             done = picoos_ReadBytes(f,(picoos_uint8 *)buf+bufpos,&n) && 1 == n;
             done = done && os_matched(str,strlen,buf,bufpos);
-            headerlen++;
+            (*headerlen)++;
         }
     }
     if (done) {
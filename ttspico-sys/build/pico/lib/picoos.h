@@ -23,6 +23,9 @@
  * History:
  * - 2009-04-20 -- initial version
  * - 2019-08-28 -- added picoos_uintptr_t
+ * - 2026-10-18 -- added in-memory files to picoos_common
+ * - 2026-10-18 -- added a file system hook to picoos_common
+ * - 2026-10-18 -- added a file filter to picoos_common
  *
  */
 /**
@@ -123,11 +126,27 @@ typedef struct picoos_file * picoos_File;
  */
 typedef struct picoos_common * picoos_Common;
 
+/* file system replacing the real one (see picoext_FileSystem in picoextapi.h) */
+struct picoext_file_system;
+/* filter deciding which files can be opened (see picoext_FileFilter in picoextapi.h) */
+struct picoext_file_filter;
+
 /* the picoos_common structure itself is exported so no access functions are needed. Handle with care! (might be changed later) */
 typedef struct picoos_common {
     picoos_ExceptionManager em;
     picoos_MemoryManager mm;
     picoos_File fileList;
+    /* if not NULL, opening 'memFileName' for reading reads 'memFileSize'
+       bytes from 'memFileData' instead of the file system */
+    const picoos_char * memFileName;
+    const picoos_uint8 * memFileData;
+    picoos_uint32 memFileSize;
+    /* if not NULL, files other than 'memFileName' are opened via 'fs'
+       instead of the file system */
+    const struct picoext_file_system * fs;
+    /* if not NULL, decides whether each file (including 'memFileName') can be
+       opened, or is to be read from memory instead */
+    const struct picoext_file_filter * filter;
 } picoos_common_t;
 
 picoos_Common picoos_newCommon(picoos_MemoryManager mm);
@@ -23,6 +23,9 @@
  *
  * History:
  * - 2009-04-20 -- initial version
+ * - 2026-10-18 -- memory protection on Linux and MacOSX
+ * - 2026-10-18 -- zero-terminate the output of picopal_vslprintf
+ * - 2026-10-18 -- PICO_FREESTANDING builds, without a hosted C library
  *
  */
 
@@ -64,12 +67,19 @@
 
 #include <stdlib.h>
 #include <string.h>
+#if !defined(PICO_FREESTANDING)
 #include <stdio.h>
+#endif
 #include <stdarg.h>
 
 #include "picodefs.h"
 #include "picopal.h"
 
+#if (PICO_PLATFORM == PICO_Linux) || (PICO_PLATFORM == PICO_MacOSX)
+#include <sys/mman.h>
+#include <unistd.h>
+#endif
+
 #ifdef __cplusplus
 extern "C" {
 #endif
@@ -86,7 +96,9 @@ extern "C" {
 #define USE_CLOCK 1
 #endif
 
+#if !defined(PICO_FREESTANDING)
 #include <time.h>
+#endif
 #if PICO_PLATFORM == PICO_Windows
 #include <windows.h>
 #endif
@@ -161,6 +173,7 @@ picopal_objsize_t picopal_strlcpy(picopal_char *dst, const picopal_char *src, pi
 }
 
 
+#if !defined(PICO_FREESTANDING)
 picopal_int16 picopal_sprintf(picopal_char * dst, const picopal_char *fmt, ...)
 {
     picopal_int16 i;
@@ -171,6 +184,28 @@ picopal_int16 picopal_sprintf(picopal_char * dst, const picopal_char *fmt, ...)
     va_end(args);
     return i;
 }
+#endif /* !defined(PICO_FREESTANDING) */
+
+
+/* writes the decimal representation of 'val' to 'buf' (of at least 12 bytes), zero-terminated */
+static void picopal_format_int(picopal_char * buf, picopal_int32 val)
+{
+    picopal_char digits[10];
+    picopal_uint32 uval = (val < 0) ? (picopal_uint32) 0 - (picopal_uint32) val : (picopal_uint32) val;
+    picopal_int16 n = 0;
+
+    do {
+        digits[n++] = (picopal_char) ('0' + uval % 10);
+        uval /= 10;
+    } while (uval > 0);
+    if (val < 0) {
+        *(buf++) = '-';
+    }
+    while (n > 0) {
+        *(buf++) = digits[--n];
+    }
+    *buf = NULLC;
+}
 
 
 picopal_objsize_t picopal_vslprintf(picopal_char * dst, picopal_objsize_t siz, const picopal_char *fmt, va_list args) {
@@ -192,13 +227,14 @@ picopal_objsize_t picopal_vslprintf(picopal_char * dst, picopal_objsize_t siz, c
                 case 'i':
                     f++;
                     ival = va_arg(args,int);
-                    picopal_sprintf(buf,(picopal_char *)"%i",ival);
+                    picopal_format_int(buf,ival);
                     b = buf;
                     break;
                 case 'c':
                     f++;
                     cval = va_arg(args,int);
-                    picopal_sprintf(buf,(picopal_char *)"%c",cval);
+                    buf[0] = cval;
+                    buf[1] = NULLC;
                     b = buf;
                     break;
                 case 's':
@@ -230,6 +266,12 @@ picopal_objsize_t picopal_vslprintf(picopal_char * dst, picopal_objsize_t siz, c
             f++;
         }
     }
+    /* zero-terminate (truncating if needed) */
+    if (n > 0) {
+        *d = 0;
+    } else if (siz > 0) {
+        dst[siz-1] = 0;
+    }
 
     return i;
 }
@@ -306,6 +348,87 @@ picopal_char picopal_eol(void)
     If the opening of the file is successful a file pointer is given
     back. Otherwise a NIL-File is given back.
 */
+#if defined(PICO_FREESTANDING)
+/* without a hosted C library there are no files: opening one always fails (returning a NIL-File), so resources
+   can only be loaded from memory */
+picopal_File picopal_fopen (picopal_char filename[], picopal_access_mode mode)
+{
+    filename = filename;    /* avoid warning "var not used in this function"*/
+    mode = mode;            /* avoid warning "var not used in this function"*/
+    return (picopal_File) NULL;
+}
+
+
+picopal_File picopal_get_fnil (void)
+{
+    return (picopal_File) NULL;
+}
+
+
+picopal_int8 picopal_is_fnil (picopal_File f)
+{
+    return (NULL == f);
+}
+
+pico_status_t picopal_fflush (picopal_File f)
+{
+    f = f;
+    return PICO_EOF;
+}
+
+
+pico_status_t picopal_fclose (picopal_File f)
+{
+    f = f;
+    return PICO_EOF;
+}
+
+
+picopal_uint32 picopal_flength (picopal_File stream)
+{
+    stream = stream;
+    return 0;
+}
+
+picopal_uint8 picopal_feof (picopal_File stream)
+{
+    stream = stream;
+    return 1;
+}
+
+pico_status_t picopal_fseek (picopal_File f, picopal_uint32 offset, picopal_int8 seekmode)
+{
+    f = f;
+    offset = offset;
+    seekmode = seekmode;
+    return PICO_EOF;
+}
+
+pico_status_t picopal_fget_char (picopal_File f, picopal_char * ch)
+{
+    f = f;
+    *ch = '\0';
+    return PICO_EOF;
+}
+
+picopal_objsize_t picopal_fread_bytes (picopal_File f, void * ptr, picopal_objsize_t objsize, picopal_uint32 nobj)
+{
+    f = f;
+    ptr = ptr;
+    objsize = objsize;
+    nobj = nobj;
+    return 0;
+}
+
+picopal_objsize_t picopal_fwrite_bytes (picopal_File f, void * ptr, picopal_objsize_t objsize, picopal_uint32 nobj)
+{
+    f = f;
+    ptr = ptr;
+    objsize = objsize;
+    nobj = nobj;
+    return 0;
+}
+#else
 picopal_File picopal_fopen (picopal_char filename[], picopal_access_mode mode)
 {
     picopal_File res;
@@ -400,6 +523,7 @@ picopal_objsize_t picopal_fread_bytes (picopal_File f, void * ptr, picopal_objsi
 }
 
 picopal_objsize_t picopal_fwrite_bytes (picopal_File f, void * ptr, picopal_objsize_t objsize, picopal_uint32 nobj){    return (picopal_objsize_t) fwrite(ptr, objsize, nobj, (FILE *)f);}
+#endif /* defined(PICO_FREESTANDING) */
 /* *************************************************/
 /* functions for debugging/testing purposes only   */
 /* *************************************************/
@@ -408,6 +532,17 @@ void *picopal_mpr_alloc(picopal_objsize_t size)
 {
 #if PICO_PLATFORM == PICO_Windows
     return VirtualAlloc(NULL, size, MEM_COMMIT, PAGE_READWRITE);
+#elif (PICO_PLATFORM == PICO_Linux) || (PICO_PLATFORM == PICO_MacOSX)
+    /* the mapping's total size is stored in a page preceding the returned
+       memory, so that the returned memory is page-aligned */
+    size_t pageSize = (size_t) sysconf(_SC_PAGESIZE);
+    size_t totalSize = pageSize + ((size + pageSize - 1) / pageSize) * pageSize;
+    void *base = mmap(NULL, totalSize, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANON, -1, 0);
+    if (base == MAP_FAILED) {
+        return NULL;
+    }
+    *((size_t *) base) = totalSize;
+    return (char *) base + pageSize;
 #else
     /* not yet implemented for other platforms;  corresponding
        function on UNIX systems is pvalloc */
@@ -421,6 +556,11 @@ void picopal_mpr_free(void **p)
 {
 #if PICO_PLATFORM == PICO_Windows
     VirtualFree(*p, 0, MEM_RELEASE);
+#elif (PICO_PLATFORM == PICO_Linux) || (PICO_PLATFORM == PICO_MacOSX)
+    if (*p != NULL) {
+        void *base = (char *) *p - (size_t) sysconf(_SC_PAGESIZE);
+        munmap(base, *((size_t *) base));
+    }
 #else
     /* not yet implemented for other platforms */
 #endif
@@ -447,6 +587,19 @@ pico_status_t picopal_mpr_protect(void *addr, picopal_objsize_t len, picopal_int
     if (!VirtualProtect(addr, len, dwNewProtect, &dwOldProtect)) {
         status = PICO_ERR_OTHER;
     }
+#elif (PICO_PLATFORM == PICO_Linux) || (PICO_PLATFORM == PICO_MacOSX)
+    int newProt = PROT_NONE;
+    size_t pageSize = (size_t) sysconf(_SC_PAGESIZE);
+    char *pageAddr = (char *) ((picopal_uintptr_t) addr & ~(picopal_uintptr_t) (pageSize - 1));
+    if (prot & PICOPAL_PROT_READ) {
+        newProt |= PROT_READ;
+    }
+    if (prot & PICOPAL_PROT_WRITE) {
+        newProt |= PROT_WRITE;
+    }
+    if (0 != mprotect(pageAddr, len + ((char *) addr - pageAddr), newProt)) {
+        status = PICO_ERR_OTHER;
+    }
 #else
     /* not yet implemented for other platforms */
     addr = addr;        /* avoid warning "var not used in this function"*/
@@ -25,6 +25,7 @@
  * History:
  * - 2009-04-20 -- initial version
  * - 2019-08-28 -- added picopal_uintptr_t
+ * - 2026-10-18 -- PICO_FREESTANDING builds, without a hosted C library
  *
  */
 /**
@@ -36,7 +37,11 @@
 #ifndef PICOPAL_H_
 #define PICOPAL_H_
 
+/* with PICO_FREESTANDING there is no hosted C library (i.e. on wasm32-unknown-unknown): file access
+   always fails, so resources can only be loaded from memory */
+#if !defined(PICO_FREESTANDING)
 #include <stdio.h>
+#endif
 #include <stdlib.h>
 #include <stdarg.h>
 #include <math.h>
@@ -135,7 +140,9 @@ picopal_char * picopal_strchr(const picopal_char *, picopal_char);
 picopal_char * picopal_strcpy(picopal_char *d, const picopal_char *s);
 picopal_char *picopal_strstr(const picopal_char *s, const picopal_char *substr);
 picopal_char *picopal_strcat(picopal_char *dest, const picopal_char *src);
+#if !defined(PICO_FREESTANDING)
 picopal_int16 picopal_sprintf(picopal_char * dst, const picopal_char *fmt, ...);
+#endif
 
 /* copies 'length' bytes from 'src' to 'dest'. (regions may be overlapping) no error checks! */
 void * picopal_mem_copy(const void * src, void * dst,  picopal_objsize_t length);
@@ -174,7 +181,11 @@ picopal_double picopal_fabs (const picopal_double fabs_arg);
 
 extern picopal_char picopal_eol(void);
 
+#if defined(PICO_FREESTANDING)
+#define picopal_FILE      void
+#else
 #define picopal_FILE      FILE
+#endif
 
 
 /* seek modes to be used with the 'FSeek' procedure */
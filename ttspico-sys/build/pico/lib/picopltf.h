@@ -26,6 +26,7 @@
  *
  * History:
  * - 2009-04-20 -- initial version
+ * - 2026-10-18 -- WebAssembly platform
  */
 
 #if !defined(__PICOPLTF_H__)
@@ -38,6 +39,7 @@
 #define PICO_Windows    1   /* Windows */
 #define PICO_MacOSX     5   /* Macintosh OS X */
 #define PICO_Linux      7   /* Linux */
+#define PICO_WebAssembly 9  /* WebAssembly (wasm32-unknown-unknown or WASI) */
 
 /* * definition of current platform ***/
 #if !defined(PICO_PLATFORM)
@@ -47,6 +49,8 @@
 #define PICO_PLATFORM    PICO_MacOSX
 #elif defined(linux) || defined(__linux__) || defined(__linux)
 #define PICO_PLATFORM    PICO_Linux
+#elif defined(__wasm__)
+#define PICO_PLATFORM    PICO_WebAssembly
 #else
 #error PICO_PLATFORM not defined
 #endif
@@ -60,6 +64,8 @@
 #define PICO_PLATFORM_STRING "MacOSX"
 #elif (PICO_PLATFORM == PICO_Linux)
 #define PICO_PLATFORM_STRING "Linux"
+#elif (PICO_PLATFORM == PICO_WebAssembly)
+#define PICO_PLATFORM_STRING "WebAssembly"
 #elif (PICO_PLATFORM == PICO_GENERIC)
 #define PICO_PLATFORM_STRING "UnknownPlatform"
 #endif
@@ -25,6 +25,7 @@
  * History:
  * - 2009-04-20 -- initial version
  * - 2019-08-28 -- x86_64 compatibility fixes
+ * - 2026-10-18 -- allocate the case-folded strings of spelled tokens
  *
  */
 
@@ -1219,7 +1220,7 @@ static void pr_appendItemToOutItemList (picodata_ProcessingUnit this, pr_subobj_
                 ltype = PICODATA_ITEMINFO1_TOKTYPE_UNDEFINED;
                 lsubtype =  -(1);
             }
-            pr_newItem(this, pr_DynMem,& litem, PICODATA_ITEM_TOKEN, ln2, /*inItem*/FALSE);
+            pr_newItem(this, pr_DynMem,& litem, PICODATA_ITEM_TOKEN, ln2, /*inItem*/TRUE);
             if (pr->outOfMemory) return;
             litem->head.type = PICODATA_ITEM_TOKEN;
             litem->head.info1 = item->head.info1;
@@ -1240,7 +1241,7 @@ static void pr_appendItemToOutItemList (picodata_ProcessingUnit this, pr_subobj_
 
             pr_appendItem(this, firstItem, lastItem, litem);
             if (pr->spellMode == PR_SPELL_WITH_SENTENCE_BREAK) {
-                pr_newItem(this, pr_DynMem,& litem, PICODATA_ITEM_TOKEN, 2, /*inItem*/FALSE);
+                pr_newItem(this, pr_DynMem,& litem, PICODATA_ITEM_TOKEN, 2, /*inItem*/TRUE);
                 if (pr->outOfMemory) return;
                 litem->head.type = PICODATA_ITEM_TOKEN;
                 litem->head.info1 = PICODATA_ITEMINFO1_TOKTYPE_CHAR;
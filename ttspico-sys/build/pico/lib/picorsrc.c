@@ -23,6 +23,7 @@
  * History:
  * - 2009-04-20 -- initial version
  * - 2019-08-28 -- x86_64 compatibility fixes
+ * - 2026-10-18 -- reject corrupt resource lengths and knowledge base names
  */
 
 #include "picodefs.h"
@@ -491,7 +492,7 @@ static pico_status_t picorsrc_getKbList(picorsrc_ResourceManager this,
     PICODBG_DEBUG(("number of kbs = %i",numKbs));
     i = 0;
     while ((PICO_OK == status) && (i++ < numKbs)) {
-        status = (picoos_get_str((picoos_char *)data,&curpos,str,PICOOS_MAX_FIELD_STRING_LEN)) ? PICO_OK :  PICO_EXC_FILE_CORRUPT;
+        status = (picoos_get_str((picoos_char *)data,&curpos,str,PICOKNOW_MAX_KB_NAME_SIZ)) ? PICO_OK :  PICO_EXC_FILE_CORRUPT;
         PICODBG_DEBUG(("contains knowledge base %s (status: %i)",str, status));
     }
     /* consume termination of last str */
@@ -595,6 +596,10 @@ pico_status_t picorsrc_loadResource(picorsrc_ResourceManager this,
             /* get data length */
         status = picoos_read_pi_uint32(res->file, &len);
         PICODBG_DEBUG(("found net resource len of %i",len));
+        /* (a corrupt length would overflow maxlen below) */
+        if ((PICO_OK == status) && (len + PICOOS_ALIGN_SIZE < len)) {
+            status = picoos_emRaiseException(this->common->em,PICO_EXC_FILE_CORRUPT,NULL,(picoos_char *)"resource length out of range");
+        }
         /* allocate memory */
         if (PICO_OK == status) {
             PICODBG_TRACE((">>> 2"));
@@ -23,6 +23,7 @@
  *
  * History:
  * - 2009-04-20 -- initial version
+ * - 2026-10-18 -- reset closes the files being played and generated
  *
  */
 
@@ -136,6 +137,12 @@ static pico_status_t sigInitialize(register picodata_ProcessingUnit this, picoos
     /*-----------------------------------------------------------------
      * MANAGE Item I/O control management
      ------------------------------------------------------------------*/
+    if (sig_subObj->sInSDFile != NULL) {
+        picoos_sdfCloseIn(this->common, &(sig_subObj->sInSDFile));
+    }
+    if (sig_subObj->sOutSDFile != NULL) {
+        picoos_sdfCloseOut(this->common, &(sig_subObj->sOutSDFile));
+    }
     sig_subObj->sInSDFile = NULL;
     sig_subObj->sInSDFilePos = 0;
     sig_subObj->sInSDFileName[0] = '\0';
@@ -281,6 +288,8 @@ picodata_ProcessingUnit picosig_newSigUnit(picoos_MemoryManager mm,
         return NULL;
     }
     sig_subObj = (sig_subobj_t *) this->subObj;
+    sig_subObj->sInSDFile = NULL;
+    sig_subObj->sOutSDFile = NULL;
 
     /*-----------------------------------------------------------------
      * Allocate memory for DSP inner algorithms
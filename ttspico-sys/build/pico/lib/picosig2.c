@@ -23,6 +23,7 @@
  *
  * History:
  * - 2009-04-20 -- initial version
+ * - 2026-10-18 -- full reset clears the phase angles, so that a reset engine speaks like a new one
  *
  */
 #include "picoos.h"
@@ -463,6 +464,7 @@ void sigDspInitialize(sig_innerobj_t *sig_inObj, picoos_int32 resetMode)
 
     for (i = 0; i < PICODSP_HFFTSIZE_P1; i++) {
         sig_inObj->idx_vect2[i] = (picoos_int16) 0;
+        sig_inObj->ang_p[i] = 0; /*phase angles carry over between frames*/
     }
 
     for (i = 0; i < CEPST_BUFF_SIZE; i++) {
@@ -23,6 +23,7 @@
  *
  * History:
  * - 2009-04-20 -- initial version
+ * - 2026-10-18 -- <play id="..."> plays the clip registered under that id
  *
  */
 
@@ -67,6 +68,8 @@
 #include "picodata.h"
 #include "picotok.h"
 #include "picoktab.h"
+#include "picoapi.h"
+#include "picoextapi.h"
 
 #ifdef __cplusplus
 extern "C" {
@@ -145,6 +148,7 @@ typedef enum {MTNone, MTStart, MTEnd, MTEmpty} MarkupTagType;
 #define KWSB (picoos_uchar *)"sb"
 #define KWPB (picoos_uchar *)"pb"
 #define KWFile (picoos_uchar *)"file"
+#define KWId (picoos_uchar *)"id"
 #define KWType (picoos_uchar *)"type"
 #define KWF0Beg (picoos_uchar *)"f0beg"
 #define KWF0End (picoos_uchar *)"f0end"
@@ -670,6 +674,23 @@ static void tok_checkRealLimits (picodata_ProcessingUnit this, picoos_single * v
 
 #define VAL_STR_LEN 21
 
+/* Sets 'fileName' (of 'size' bytes) to the name of the file played by
+   <play id="'id'">; returns FALSE if the name does not fit. */
+static picoos_bool tok_clipFileName (picoos_uchar id[], picoos_uchar fileName[], picoos_int32 size)
+{
+    picoos_int32 prefixLen, idLen;
+
+    prefixLen = picoos_strlen((picoos_char*)PICOEXT_CLIP_PREFIX);
+    idLen = picoos_strlen((picoos_char*)id);
+    if ((idLen == 0) || (prefixLen + idLen + 4 >= size)) {
+        return FALSE;
+    }
+    picoos_strlcpy((picoos_char*)fileName, (picoos_char*)PICOEXT_CLIP_PREFIX, size);
+    picoos_strlcpy((picoos_char*)fileName + prefixLen, (picoos_char*)id, size - prefixLen);
+    picoos_strlcpy((picoos_char*)fileName + prefixLen + idLen, (picoos_char*)".wav", size - prefixLen - idLen);
+    return TRUE;
+}
+
 static void tok_interpretMarkup (picodata_ProcessingUnit this, tok_subobj_t * tok, picoos_bool isStartTag, MarkupId mId)
 {
     picoos_bool done;
@@ -687,6 +708,8 @@ static void tok_interpretMarkup (picodata_ProcessingUnit this, tok_subobj_t * to
     picoos_uint8 data[256];
     picoos_int32 pos, n, len;
     picoos_uchar part[10];
+    picoos_uchar clipFile[IN_BUF_SIZE];
+    picoos_uchar * playFile;
 
     done = FALSE;
     switch (mId) {
@@ -876,21 +899,31 @@ static void tok_interpretMarkup (picodata_ProcessingUnit this, tok_subobj_t * to
             }
             break;
         case MIPlay:
+            playFile = NULL;
             if (isStartTag && tok_strEqual(tok->markupParams[0].paramId, KWFile)) {
-                if (picoos_FileExists(this->common, (picoos_char*)tok->markupParams[0].paramVal)) {
+                playFile = tok->markupParams[0].paramVal;
+            } else if (isStartTag && tok_strEqual(tok->markupParams[0].paramId, KWId)) {
+                /* (ids too long for a file name are never found) */
+                if (!tok_clipFileName(tok->markupParams[0].paramVal, clipFile, IN_BUF_SIZE)) {
+                    clipFile[0] = 0;
+                }
+                playFile = clipFile;
+            }
+            if (playFile != NULL) {
+                if ((playFile[0] != 0) && picoos_FileExists(this->common, (picoos_char*)playFile)) {
                     tok_getParamIntVal(tok->markupParams,KWF0Beg,& ival,& paramFound);
                     tok_getParamIntVal(tok->markupParams,KWF0End,& ival2,& paramFound);
                     tok_getParamStrVal(tok->markupParams,KWAlphabet,valStr3,& paramFound);
                     tok_getParamPhonesStr(tok->markupParams,KWXFadeBeg,valStr3,valStr,VAL_STR_LEN,& paramFound);
                     tok_getParamPhonesStr(tok->markupParams,KWXFadeEnd,valStr3,valStr2,VAL_STR_LEN,& paramFound);
                     tok_putItem(this, tok, PICODATA_ITEM_CMD, PICODATA_ITEMINFO1_CMD_PLAY,
-                                picodata_getPuTypeFromExtension(tok->markupParams[0].paramVal, /*input*/TRUE), 0, tok->markupParams[0].paramVal);
+                                picodata_getPuTypeFromExtension(playFile, /*input*/TRUE), 0, playFile);
                     tok_startIgnore(tok);
                 } else {
                     if (tok->ignLevel > 0) {
                         tok_startIgnore(tok);
                     } else {
-                       picoos_emRaiseWarning(this->common->em, PICO_EXC_CANT_OPEN_FILE, (picoos_char*)"", (picoos_char*)"file '%s' not found; synthesizing enclosed text instead\n", tok->markupParams[0].paramVal);
+                       picoos_emRaiseWarning(this->common->em, PICO_EXC_CANT_OPEN_FILE, (picoos_char*)"", (picoos_char*)"file '%s' not found; synthesizing enclosed text instead\n", playFile);
                     }
                 }
                 done = TRUE;
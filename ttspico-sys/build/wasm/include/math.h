@@ -0,0 +1,18 @@
+/*
+ * The part of <math.h> needed by Pico, for PICO_FREESTANDING builds on wasm32-unknown-unknown.
+ * The functions are provided by Rust's `compiler_builtins` (or are WebAssembly instructions).
+ *
+ * Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+ * Licensed under the Apache License, Version 2.0.
+ */
+
+#ifndef TTSPICO_WASM_MATH_H
+#define TTSPICO_WASM_MATH_H
+
+double sin(double x);
+double cos(double x);
+double exp(double x);
+double sqrt(double x);
+double fabs(double x);
+
+#endif /* TTSPICO_WASM_MATH_H */
@@ -0,0 +1,16 @@
+/*
+ * The part of <stdlib.h> needed by Pico, for PICO_FREESTANDING builds on wasm32-unknown-unknown.
+ * See libc.c.
+ *
+ * Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+ * Licensed under the Apache License, Version 2.0.
+ */
+
+#ifndef TTSPICO_WASM_STDLIB_H
+#define TTSPICO_WASM_STDLIB_H
+
+#include <stddef.h>
+
+int atoi(const char *s);
+
+#endif /* TTSPICO_WASM_STDLIB_H */
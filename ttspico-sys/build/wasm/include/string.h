@@ -0,0 +1,27 @@
+/*
+ * The part of <string.h> needed by Pico, for PICO_FREESTANDING builds on wasm32-unknown-unknown.
+ * The memory functions are provided by Rust's `compiler_builtins`, the string functions by libc.c.
+ *
+ * Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+ * Licensed under the Apache License, Version 2.0.
+ */
+
+#ifndef TTSPICO_WASM_STRING_H
+#define TTSPICO_WASM_STRING_H
+
+#include <stddef.h>
+
+void *memcpy(void *dst, const void *src, size_t n);
+void *memmove(void *dst, const void *src, size_t n);
+void *memset(void *dst, int c, size_t n);
+int memcmp(const void *a, const void *b, size_t n);
+
+size_t strlen(const char *s);
+int strcmp(const char *a, const char *b);
+int strncmp(const char *a, const char *b, size_t n);
+char *strchr(const char *s, int c);
+char *strstr(const char *s, const char *substr);
+char *strcpy(char *dst, const char *src);
+char *strcat(char *dst, const char *src);
+
+#endif /* TTSPICO_WASM_STRING_H */
@@ -0,0 +1,84 @@
+/*
+ * The string functions of the C library needed by Pico, for PICO_FREESTANDING builds on wasm32-unknown-unknown
+ * (which has no C library). Built by build/build.rs.
+ *
+ * Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+ * Licensed under the Apache License, Version 2.0.
+ */
+
+#include <stdlib.h>
+#include <string.h>
+
+int atoi(const char *s) {
+    int sign = 1, val = 0;
+    while (*s == ' ' || (*s >= '\t' && *s <= '\r')) {
+        s++;
+    }
+    if (*s == '-' || *s == '+') {
+        sign = (*s == '-') ? -1 : 1;
+        s++;
+    }
+    while (*s >= '0' && *s <= '9') {
+        val = val * 10 + (*s - '0');
+        s++;
+    }
+    return sign * val;
+}
+
+size_t strlen(const char *s) {
+    const char *end = s;
+    while (*end) {
+        end++;
+    }
+    return (size_t)(end - s);
+}
+
+int strcmp(const char *a, const char *b) {
+    while (*a && *a == *b) {
+        a++;
+        b++;
+    }
+    return (int)*(const unsigned char *)a - (int)*(const unsigned char *)b;
+}
+
+int strncmp(const char *a, const char *b, size_t n) {
+    for (; n > 0; n--, a++, b++) {
+        if (*a != *b || !*a) {
+            return (int)*(const unsigned char *)a - (int)*(const unsigned char *)b;
+        }
+    }
+    return 0;
+}
+
+char *strchr(const char *s, int c) {
+    for (;; s++) {
+        if (*s == (char)c) {
+            return (char *)s;
+        }
+        if (!*s) {
+            return NULL;
+        }
+    }
+}
+
+char *strstr(const char *s, const char *substr) {
+    size_t len = strlen(substr);
+    for (; *s; s++) {
+        if (strncmp(s, substr, len) == 0) {
+            return (char *)s;
+        }
+    }
+    return len == 0 ? (char *)s : NULL;
+}
+
+char *strcpy(char *dst, const char *src) {
+    char *d = dst;
+    while ((*d++ = *src++)) {
+    }
+    return dst;
+}
+
+char *strcat(char *dst, const char *src) {
+    strcpy(dst + strlen(dst), src);
+    return dst;
+}
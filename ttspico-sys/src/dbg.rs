@@ -0,0 +1,33 @@
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// automatically generated by rust-bindgen
+// bindgen --use-core --ctypes-prefix '::core::ffi' -DPICO_DEBUG --whitelist-type 'picodbg_log_callback_t' --whitelist-function 'picodbg_setLogCallback' picodbg.h
+// (only available if Pico is compiled with `PICO_DEBUG`, i.e. with the `debug-pico` feature)
+
+#![allow(non_camel_case_types)]
+
+pub type picodbg_log_callback_t = ::core::option::Option<
+    unsafe extern "C" fn(
+        level: ::core::ffi::c_int,
+        donewline: ::core::ffi::c_int,
+        file: *const ::core::ffi::c_char,
+        line: ::core::ffi::c_int,
+        func: *const ::core::ffi::c_char,
+        msg: *const ::core::ffi::c_char,
+    ),
+>;
+extern "C" {
+    pub fn picodbg_setLogCallback(callback: picodbg_log_callback_t);
+}
@@ -12,7 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::ffi::{pico_Int16, pico_Status};
+use crate::ffi::{pico_Int16, pico_Int32, pico_Status};
 
 pub const PICO_MAX_NUM_RESOURCES: u32 = 64;
 pub const PICO_MAX_NUM_VOICE_DEFINITIONS: u32 = 64;
@@ -30,11 +30,35 @@ pub const PICO_MAX_VOICE_NAME_SIZE: usize = 32;
 pub const PICO_MAX_RESOURCE_NAME_SIZE: usize = 32;
 pub const PICO_MAX_DATAPATH_NAME_SIZE: usize = 128;
 
+// Size of the memory block an engine allocates from its system (from `picoctrl.h`)
+pub const PICOCTRL_DEFAULT_ENGINE_SIZE: usize = 1000000;
+
 pub const PICO_RESET_FULL: u32 = 0;
 pub const PICO_RESET_SOFT: u32 = 16;
 
 pub const PICO_DATA_PCM_16BIT: pico_Int16 = 1;
 
+pub const PICO_STRENC_UTF8: u32 = 0;
+pub const PICO_STRENC_UTF16: u32 = 1;
+
+// -- Trace levels (see `picoext_setTraceLevel`) --
+
+pub const PICODBG_LOG_LEVEL_ERROR: pico_Int32 = 1;
+pub const PICODBG_LOG_LEVEL_WARN: pico_Int32 = 2;
+pub const PICODBG_LOG_LEVEL_INFO: pico_Int32 = 3;
+pub const PICODBG_LOG_LEVEL_DEBUG: pico_Int32 = 4;
+pub const PICODBG_LOG_LEVEL_TRACE: pico_Int32 = 5;
+
+// -- File filter decisions (see `picoext_setEngineFileFilter`) --
+
+pub const PICOEXT_FILE_ALLOW: pico_Int16 = 0;
+pub const PICOEXT_FILE_DENY: pico_Int16 = 1;
+pub const PICOEXT_FILE_MEMORY: pico_Int16 = 2;
+
+// -- Clip file names (`PICOEXT_CLIP_PREFIX` + id + `.wav`, played by `<play id="...">`) --
+
+pub const PICOEXT_CLIP_PREFIX: &str = "clip:";
+
 // -- Status codes --
 
 pub const PICO_OK: pico_Status = 0;
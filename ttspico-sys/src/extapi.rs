@@ -0,0 +1,202 @@
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// automatically generated by rust-bindgen
+// bindgen --use-core --ctypes-prefix '::core::ffi' --no-layout-tests --allowlist-function 'picoext_.*' --blocklist-type 'pico_.*' picoextapi.h
+
+#![allow(non_camel_case_types, non_snake_case)]
+
+use crate::ffi::*;
+
+extern "C" {
+    #[doc = "Same as pico_initialize, but allows to enable memory protection"]
+    #[doc = "functionality for testing purposes (enableMemProt != 0)."]
+    pub fn picoext_initialize(
+        memory: *mut ::core::ffi::c_void,
+        size: pico_Uint32,
+        enableMemProt: pico_Int16,
+        outSystem: *mut pico_System,
+    ) -> pico_Status;
+}
+extern "C" {
+    #[doc = "Same as pico_loadResource, but reads the resource file contents from the"]
+    #[doc = "\'size\' bytes at \'data\' instead of the file system. \'resourceFileName\' is"]
+    #[doc = "used in place of the file name (and must end in \".bin\"). The contents are"]
+    #[doc = "copied into Pico\'s memory; \'data\' does not need to outlive the call."]
+    pub fn picoext_loadResourceFromMemory(
+        system: pico_System,
+        resourceFileName: *const pico_Char,
+        data: *const ::core::ffi::c_void,
+        size: pico_Uint32,
+        outResource: *mut pico_Resource,
+    ) -> pico_Status;
+}
+extern "C" {
+    #[doc = "Returns version information of the current Pico engine."]
+    pub fn picoext_getVersionInfo(
+        outInfo: *mut ::core::ffi::c_char,
+        outInfoMaxLen: pico_Int16,
+    ) -> pico_Status;
+}
+extern "C" {
+    #[doc = "Sets tracing level. Increasing amounts of information is displayed"]
+    #[doc = "at each level."]
+    pub fn picoext_setTraceLevel(system: pico_System, level: pico_Int32) -> pico_Status;
+}
+extern "C" {
+    #[doc = "Sets trace filtering. Limits tracing output to tracing information"]
+    #[doc = "resulting from the source file name being filtered."]
+    pub fn picoext_setTraceFilterFN(system: pico_System, name: *const pico_Char) -> pico_Status;
+}
+extern "C" {
+    #[doc = "Enables logging of debug output to log file \'name\'. If \'name\' is NULL"]
+    #[doc = "or an empty string, logging is disabled."]
+    pub fn picoext_setLogFile(system: pico_System, name: *const pico_Char) -> pico_Status;
+}
+extern "C" {
+    pub fn picoext_getSystemMemUsage(
+        system: pico_System,
+        resetIncremental: pico_Int16,
+        outUsedBytes: *mut pico_Int32,
+        outIncrUsedBytes: *mut pico_Int32,
+        outMaxUsedBytes: *mut pico_Int32,
+    ) -> pico_Status;
+}
+extern "C" {
+    pub fn picoext_getEngineMemUsage(
+        engine: pico_Engine,
+        resetIncremental: pico_Int16,
+        outUsedBytes: *mut pico_Int32,
+        outIncrUsedBytes: *mut pico_Int32,
+        outMaxUsedBytes: *mut pico_Int32,
+    ) -> pico_Status;
+}
+extern "C" {
+    pub fn picoext_getLastScheduledPU(engine: pico_Engine) -> pico_Status;
+}
+extern "C" {
+    pub fn picoext_getLastProducedItemType(engine: pico_Engine) -> pico_Status;
+}
+extern "C" {
+    #[doc = "Gets the name of the last <mark> reached by the speech data output by"]
+    #[doc = "pico_getData into \'outName\' (zero-terminated, truncated to"]
+    #[doc = "\'outNameMaxLen\'), setting \'outFound\' to whether a marker was reached since"]
+    #[doc = "the last call (or engine reset). Only the last marker is kept, and a marker"]
+    #[doc = "is reached by a call to pico_getData that outputs no speech data; so the"]
+    #[doc = "position of a marker in the speech data is exact if this is called after"]
+    #[doc = "each call to pico_getData."]
+    pub fn picoext_getMarker(
+        engine: pico_Engine,
+        outName: *mut pico_Char,
+        outNameMaxLen: pico_Int16,
+        outFound: *mut pico_Int16,
+    ) -> pico_Status;
+}
+#[doc = "Callbacks replacing the file system for the files opened by a system and"]
+#[doc = "its engine: resources, audio files inserted by <play> and the outputs of"]
+#[doc = "<genfile>. Files are opened by name, then read or written at the given"]
+#[doc = "byte positions; \'userData\' is passed to each callback."]
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct picoext_file_system {
+    pub userData: *mut ::core::ffi::c_void,
+    #[doc = "Opens file \'fileName\' for reading, setting \'outLength\' to its length in"]
+    #[doc = "bytes, or creates (truncating) it for writing if \'write\' is non-zero."]
+    #[doc = "Returns a non-NULL handle to the file, or NULL if it cannot be opened."]
+    pub open: ::core::option::Option<
+        unsafe extern "C" fn(
+            userData: *mut ::core::ffi::c_void,
+            fileName: *const pico_Char,
+            write: pico_Int16,
+            outLength: *mut pico_Uint32,
+        ) -> *mut ::core::ffi::c_void,
+    >,
+    #[doc = "Reads up to \'len\' bytes at \'pos\' into \'bytes\'. Returns the number of"]
+    #[doc = "bytes read (less than \'len\' at the end of the file), or -1 on errors."]
+    pub read: ::core::option::Option<
+        unsafe extern "C" fn(
+            userData: *mut ::core::ffi::c_void,
+            file: *mut ::core::ffi::c_void,
+            pos: pico_Uint32,
+            bytes: *mut pico_Char,
+            len: pico_Uint32,
+        ) -> pico_Int32,
+    >,
+    #[doc = "Writes \'len\' bytes from \'bytes\' at \'pos\'. Returns the number of bytes"]
+    #[doc = "written, or -1 on errors."]
+    pub write: ::core::option::Option<
+        unsafe extern "C" fn(
+            userData: *mut ::core::ffi::c_void,
+            file: *mut ::core::ffi::c_void,
+            pos: pico_Uint32,
+            bytes: *const pico_Char,
+            len: pico_Uint32,
+        ) -> pico_Int32,
+    >,
+    #[doc = "Closes the file, invalidating its handle."]
+    pub close: ::core::option::Option<
+        unsafe extern "C" fn(userData: *mut ::core::ffi::c_void, file: *mut ::core::ffi::c_void),
+    >,
+}
+pub type picoext_FileSystem = picoext_file_system;
+extern "C" {
+    #[doc = "Sets the file system used to open files by \'system\' and its engine (also"]
+    #[doc = "one created later), or restores the real one if \'fileSystem\' is NULL."]
+    #[doc = "Resources loaded with picoext_loadResourceFromMemory are not affected."]
+    #[doc = "Files that are open keep using the file system they were opened with, so"]
+    #[doc = "\'fileSystem\' must outlive them (i.e. the resources loaded through it)."]
+    pub fn picoext_setFileSystem(
+        system: pico_System,
+        fileSystem: *const picoext_FileSystem,
+    ) -> pico_Status;
+}
+#[doc = "Filter deciding whether the files opened by an engine (the audio files"]
+#[doc = "inserted by <play> and <usesig>, and the outputs of <genfile>) can be"]
+#[doc = "opened; 'userData' is passed to its callback."]
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct picoext_file_filter {
+    pub userData: *mut ::core::ffi::c_void,
+    #[doc = "Decides whether file 'fileName' can be opened for reading, or for"]
+    #[doc = "writing if 'write' is non-zero. Returns one of PICOEXT_FILE_*; for"]
+    #[doc = "PICOEXT_FILE_MEMORY (only valid for reading), sets 'outData' and"]
+    #[doc = "'outSize' to the file contents, which must stay valid while the file"]
+    #[doc = "is open."]
+    pub check: ::core::option::Option<
+        unsafe extern "C" fn(
+            userData: *mut ::core::ffi::c_void,
+            fileName: *const pico_Char,
+            write: pico_Int16,
+            outData: *mut *const ::core::ffi::c_void,
+            outSize: *mut pico_Uint32,
+        ) -> pico_Int16,
+    >,
+    #[doc = "Called (if not NULL) when a file that 'check' served from memory is"]
+    #[doc = "closed, with the 'outData' it was opened with."]
+    pub close: ::core::option::Option<
+        unsafe extern "C" fn(userData: *mut ::core::ffi::c_void, data: *const ::core::ffi::c_void),
+    >,
+}
+pub type picoext_FileFilter = picoext_file_filter;
+extern "C" {
+    #[doc = "Sets the filter of the files opened by 'engine', or removes it if 'filter'"]
+    #[doc = "is NULL (allowing all files, like Pico does by default). Resources are"]
+    #[doc = "opened by the engine's system, so they are not filtered. 'filter' must"]
+    #[doc = "outlive the engine, as the files it served are closed by later calls (up"]
+    #[doc = "to the engine's disposal)."]
+    pub fn picoext_setEngineFileFilter(
+        engine: pico_Engine,
+        filter: *const picoext_FileFilter,
+    ) -> pico_Status;
+}
@@ -13,7 +13,7 @@
 // limitations under the License.
 
 // automatically generated by rust-bindgen
-// bindgen --whitelist-type 'pico.*' --whitelist-var '(pico|PICO).*' --whitelist-function 'pico.*' picoapi.h
+// bindgen --use-core --ctypes-prefix '::core::ffi' --no-layout-tests --allowlist-type 'pico.*' --allowlist-function 'pico_.*' picoapi.h
 
 #![allow(non_camel_case_types)]
 
@@ -35,13 +35,13 @@ pub struct pico_engine {
     _unused: [u8; 0],
 }
 pub type pico_Engine = *mut pico_engine;
-pub type pico_Int16 = ::std::os::raw::c_short;
-pub type pico_Uint16 = ::std::os::raw::c_ushort;
-pub type pico_Int32 = ::std::os::raw::c_int;
-pub type pico_Uint32 = ::std::os::raw::c_uint;
-pub type pico_Char = ::std::os::raw::c_uchar;
-pub type pico_Retstring = [::std::os::raw::c_char; 200usize];
-pub type pico_Status = ::std::os::raw::c_int;
+pub type pico_Int16 = ::core::ffi::c_short;
+pub type pico_Uint16 = ::core::ffi::c_ushort;
+pub type pico_Int32 = ::core::ffi::c_int;
+pub type pico_Uint32 = ::core::ffi::c_uint;
+pub type pico_Char = ::core::ffi::c_uchar;
+pub type pico_Retstring = [::core::ffi::c_char; 200usize];
+pub type pico_Status = ::core::ffi::c_int;
 extern "C" {
     #[doc = "Initializes the Pico system and returns its handle in \'outSystem\'."]
     #[doc = "\'memory\' and \'size\' define the location and maximum size of memory"]
@@ -52,7 +52,7 @@ extern "C" {
     #[doc = "function is called. It may only be called once (e.g. at application"]
     #[doc = "startup), unless a call to \'pico_terminate\'."]
     pub fn pico_initialize(
-        memory: *mut ::std::os::raw::c_void,
+        memory: *mut ::core::ffi::c_void,
         size: pico_Uint32,
         outSystem: *mut pico_System,
     ) -> pico_Status;
@@ -74,7 +74,7 @@ extern "C" {
     pub fn pico_getSystemStatusMessage(
         system: pico_System,
         errCode: pico_Status,
-        outMessage: *mut ::std::os::raw::c_char,
+        outMessage: *mut ::core::ffi::c_char,
     ) -> pico_Status;
 }
 extern "C" {
@@ -95,7 +95,7 @@ extern "C" {
         system: pico_System,
         warningIndex: pico_Int32,
         outCode: *mut pico_Status,
-        outMessage: *mut ::std::os::raw::c_char,
+        outMessage: *mut ::core::ffi::c_char,
     ) -> pico_Status;
 }
 extern "C" {
@@ -129,7 +129,7 @@ extern "C" {
     pub fn pico_getResourceName(
         system: pico_System,
         resource: pico_Resource,
-        outName: *mut ::std::os::raw::c_char,
+        outName: *mut ::core::ffi::c_char,
     ) -> pico_Status;
 }
 extern "C" {
@@ -215,7 +215,7 @@ extern "C" {
     #[doc = "indicate a system error."]
     pub fn pico_getData(
         engine: pico_Engine,
-        outBuffer: *mut ::std::os::raw::c_void,
+        outBuffer: *mut ::core::ffi::c_void,
         bufferSize: pico_Int16,
         outBytesReceived: *mut pico_Int16,
         outDataType: *mut pico_Int16,
@@ -235,7 +235,7 @@ extern "C" {
     pub fn pico_getEngineStatusMessage(
         engine: pico_Engine,
         errCode: pico_Status,
-        outMessage: *mut ::std::os::raw::c_char,
+        outMessage: *mut ::core::ffi::c_char,
     ) -> pico_Status;
 }
 extern "C" {
@@ -256,6 +256,6 @@ extern "C" {
         engine: pico_Engine,
         warningIndex: pico_Int32,
         outCode: *mut pico_Status,
-        outMessage: *mut ::std::os::raw::c_char,
+        outMessage: *mut ::core::ffi::c_char,
     ) -> pico_Status;
 }
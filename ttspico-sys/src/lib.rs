@@ -15,8 +15,34 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+#![no_std]
+
+#[cfg(not(feature = "bindgen"))]
 mod ffi;
+#[cfg(feature = "bindgen")]
+#[allow(non_camel_case_types)]
+mod ffi {
+    include!(concat!(env!("OUT_DIR"), "/ffi.rs"));
+}
 pub use ffi::*;
 
 mod defs;
 pub use defs::*;
+
+#[cfg(not(feature = "bindgen"))]
+mod extapi;
+#[cfg(feature = "bindgen")]
+#[allow(non_camel_case_types, non_snake_case)]
+mod extapi {
+    use crate::ffi::*;
+    include!(concat!(env!("OUT_DIR"), "/extapi.rs"));
+}
+pub use extapi::*;
+
+#[cfg(feature = "debug-pico")]
+mod dbg;
+#[cfg(feature = "debug-pico")]
+pub use dbg::*;
+
+/// The directory of the language packs bundled with Pico's sources (`build/pico/lang` in this crate).
+pub const BUNDLED_LANG_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/build/pico/lang");
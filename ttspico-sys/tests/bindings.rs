@@ -0,0 +1,167 @@
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Checks that the checked-in bindings in `src/` match Pico's headers.
+
+use std::collections::HashMap;
+
+/// Pico's headers, with the prefixes of their macros that are bound in `defs.rs`.
+const PICO_HEADERS: &[(&str, &[&str])] = &[
+    (include_str!("../build/pico/lib/picoapi.h"), &["PICO_"]),
+    (include_str!("../build/pico/lib/picodefs.h"), &["PICO_"]),
+    (
+        include_str!("../build/pico/lib/picoextapi.h"),
+        &["PICO_", "PICOEXT_FILE_"],
+    ),
+    (
+        include_str!("../build/pico/lib/picodbg.h"),
+        &["PICODBG_LOG_LEVEL_"],
+    ),
+    (
+        include_str!("../build/pico/lib/picoctrl.h"),
+        &["PICOCTRL_DEFAULT_ENGINE_SIZE"],
+    ),
+];
+
+const DEFS_RS: &str = include_str!("../src/defs.rs");
+
+/// Parses an integer literal as written in C or Rust (optionally negative and/or hexadecimal).
+fn parse_int(literal: &str) -> Option<i64> {
+    let (negative, literal) = match literal.strip_prefix('-') {
+        Some(literal) => (true, literal),
+        None => (false, literal),
+    };
+    let value = match literal
+        .strip_prefix("0x")
+        .or_else(|| literal.strip_prefix("0X"))
+    {
+        Some(hex) => i64::from_str_radix(hex, 16).ok()?,
+        None => literal.parse().ok()?,
+    };
+    Some(if negative { -value } else { value })
+}
+
+/// Returns `code` without its `/* ... */` comments (i.e. commented-out macros).
+fn strip_comments(code: &str) -> String {
+    let mut stripped = String::with_capacity(code.len());
+    let mut rest = code;
+    while let Some(start) = rest.find("/*") {
+        stripped.push_str(&rest[..start]);
+        rest = match rest[start..].find("*/") {
+            Some(end) => &rest[start + end + 2..],
+            None => "",
+        };
+    }
+    stripped.push_str(rest);
+    stripped
+}
+
+/// Returns all integer macros defined in `header` whose names start with one of `prefixes`, i.e.
+/// `#define PICO_OK (pico_Status) 0`.
+fn header_constants(header: &str, prefixes: &[&str]) -> HashMap<String, i64> {
+    strip_comments(header)
+        .lines()
+        .filter_map(|line| {
+            let mut tokens = line.split_whitespace();
+            if tokens.next() != Some("#define") {
+                return None;
+            }
+            let name = tokens.next()?;
+            let mut value = tokens.next()?;
+            if value.starts_with('(') && value.ends_with(')') {
+                value = tokens.next()?; // (skip the cast)
+            }
+            if !prefixes.iter().any(|prefix| name.starts_with(prefix)) {
+                return None;
+            }
+            Some((name.to_string(), parse_int(value)?))
+        })
+        .collect()
+}
+
+/// Returns all constants defined in `defs.rs`, i.e. `pub const PICO_OK: pico_Status = 0;`.
+fn defs_constants() -> HashMap<String, i64> {
+    DEFS_RS
+        .lines()
+        .filter_map(|line| {
+            let decl = line.strip_prefix("pub const ")?.strip_suffix(';')?;
+            let (name, rest) = decl.split_once(':')?;
+            let (_, value) = rest.split_once('=')?;
+            Some((name.trim().to_string(), parse_int(value.trim())?))
+        })
+        .collect()
+}
+
+#[test]
+fn defs_match_headers() {
+    let defs = defs_constants();
+    let mut mismatches = Vec::new();
+    for (header, prefixes) in PICO_HEADERS {
+        for (name, value) in header_constants(header, prefixes) {
+            match defs.get(&name) {
+                Some(&def_value) if def_value == value => {}
+                Some(&def_value) => mismatches.push(format!(
+                    "{} is {} in defs.rs, {} in the headers",
+                    name, def_value, value
+                )),
+                None => mismatches.push(format!("{} is missing from defs.rs", name)),
+            }
+        }
+    }
+    mismatches.sort();
+    assert!(
+        mismatches.is_empty(),
+        "defs.rs does not match the headers:\n{}",
+        mismatches.join("\n")
+    );
+}
+
+/// Strips comments, attributes that do not affect the ABI and whitespace from Rust `code`,
+/// so that bindings generated by different bindgen versions (and formatted differently) can be compared.
+#[cfg(feature = "bindgen")]
+fn normalize_bindings(code: &str) -> String {
+    code.lines()
+        .map(str::trim)
+        .filter(|line| {
+            !line.starts_with("//") && !line.starts_with("#[doc") && !line.starts_with("#![")
+        })
+        .filter(|line| !line.starts_with("use "))
+        .flat_map(str::split_whitespace)
+        .collect()
+}
+
+#[cfg(feature = "bindgen")]
+#[test]
+fn checked_in_bindings_match_headers() {
+    let pairs = [
+        (
+            "src/ffi.rs",
+            include_str!("../src/ffi.rs"),
+            include_str!(concat!(env!("OUT_DIR"), "/ffi.rs")),
+        ),
+        (
+            "src/extapi.rs",
+            include_str!("../src/extapi.rs"),
+            include_str!(concat!(env!("OUT_DIR"), "/extapi.rs")),
+        ),
+    ];
+    for (path, checked_in, generated) in pairs.iter() {
+        assert_eq!(
+            normalize_bindings(checked_in),
+            normalize_bindings(generated),
+            "{} is out of date; regenerate it with the `bindgen` feature (see `$OUT_DIR`)",
+            path
+        );
+    }
+}
@@ -0,0 +1,163 @@
+//! WebAssembly bindings to [`ttspico`], for speaking in the browser (or Node.js) without a server:
+//!
+//! ```js
+//! import init, { Synthesizer, sampleRate } from "ttspico-wasm";
+//!
+//! await init();
+//! const synth = new Synthesizer(taBytes, sgBytes); // i.e. en-US_ta.bin and en-US_lh0_sg.bin, as Uint8Arrays
+//! const audio = synth.synthesize("Hello world"); // Float32Array, at sampleRate()
+//! synth.speak("A longer text, played as it is synthesized.");
+//! for (let chunk; (chunk = synth.nextChunk()) !== undefined; ) {
+//!     ...
+//! }
+//! ```
+//!
+//! WebAssembly has no file system, so language resources are loaded from their bytes (i.e. fetched by the page).
+//! Audio is returned as samples in `[-1, 1)`, as expected by the Web Audio API.
+
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use js_sys::Float32Array;
+use std::rc::Rc;
+use ttspico::{Engine, EngineResetMode, PicoError, System, Voice, SAMPLE_RATE};
+use ttspico_sys as native;
+use wasm_bindgen::prelude::*;
+
+/// Minimum number of samples of the chunks returned by `nextChunk()`, except the last one of a speech (a tenth of
+/// a second).
+const CHUNK_SIZE: usize = SAMPLE_RATE as usize / 10;
+
+/// Memory needed by a system besides its resources: the memory block of its engine, plus headroom.
+const ENGINE_MEMORY_SIZE: usize = native::PICOCTRL_DEFAULT_ENGINE_SIZE + 128 * 1024;
+
+fn pico_err(err: PicoError) -> JsError {
+    JsError::new(&err.to_string())
+}
+
+/// Returns the sample rate of the audio returned by synthesizers.
+#[wasm_bindgen(js_name = sampleRate)]
+pub fn sample_rate() -> u32 {
+    SAMPLE_RATE
+}
+
+/// A synthesizer, speaking with the voice of one language pack.
+///
+/// Pico allows one system per thread, so only one synthesizer can exist at a time on a page (or worker):
+/// `free()` it before creating another one.
+#[wasm_bindgen]
+pub struct Synthesizer {
+    engine: Engine,
+    /// The text of the speech in progress (with Pico's markup), if speaking.
+    input: Option<Vec<u8>>,
+    /// The number of bytes of `input` already put into the engine.
+    input_pos: usize,
+}
+
+#[wasm_bindgen]
+impl Synthesizer {
+    /// Creates a synthesizer given the contents of the TA (text analysis) and SG (signal generation) resources of a
+    /// language pack, i.e. of `en-US_ta.bin` and `en-US_lh0_sg.bin`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(ta: &[u8], sg: &[u8]) -> Result<Synthesizer, JsError> {
+        // (Resources are copied into the system's memory, so they take about as much memory as their size)
+        let sys = System::new(ta.len() + sg.len() + ENGINE_MEMORY_SIZE).map_err(pico_err)?;
+        let ta_res =
+            System::load_resource_from_bytes(Rc::clone(&sys), "ta.bin", ta).map_err(pico_err)?;
+        let sg_res =
+            System::load_resource_from_bytes(Rc::clone(&sys), "sg.bin", sg).map_err(pico_err)?;
+        let voice = System::create_voice(sys, "voice").map_err(pico_err)?;
+        voice.borrow_mut().add_resource(ta_res).map_err(pico_err)?;
+        voice.borrow_mut().add_resource(sg_res).map_err(pico_err)?;
+        // SAFETY: both a TA and a SG resource were added to the voice above.
+        let engine = unsafe { Voice::create_engine(voice) }.map_err(pico_err)?;
+        Ok(Synthesizer {
+            engine,
+            input: None,
+            input_pos: 0,
+        })
+    }
+
+    /// Starts speaking `text` (SSML if `ssml` is set, else text with Pico's markup), dropping the speech in progress
+    /// if any. Its audio is then returned by `nextChunk()`.
+    pub fn speak(&mut self, text: &str, ssml: Option<bool>) -> Result<(), JsError> {
+        let markup = if ssml.unwrap_or(false) {
+            ttspico::ssml_to_markup(text).map_err(pico_err)?
+        } else {
+            text.to_string()
+        };
+        self.input = None;
+        // (So that the speech does not depend on the texts spoken before)
+        self.engine.reset(EngineResetMode::Full).map_err(pico_err)?;
+        self.input = Some(markup.into_bytes());
+        self.input_pos = 0;
+        Ok(())
+    }
+
+    /// Returns the next chunk of audio of the speech in progress, or `undefined` once it is over.
+    #[wasm_bindgen(js_name = nextChunk)]
+    pub fn next_chunk(&mut self) -> Result<Option<Float32Array>, JsError> {
+        let samples = self.next_samples().map_err(pico_err)?;
+        Ok(samples.map(|samples| Float32Array::from(&samples[..])))
+    }
+
+    /// Speaks `text` (SSML if `ssml` is set, else text with Pico's markup) all at once, returning its audio.
+    pub fn synthesize(&mut self, text: &str, ssml: Option<bool>) -> Result<Float32Array, JsError> {
+        self.speak(text, ssml)?;
+        let mut audio = Vec::new();
+        while let Some(samples) = self.next_samples().map_err(pico_err)? {
+            audio.extend(samples);
+        }
+        Ok(Float32Array::from(&audio[..]))
+    }
+}
+
+impl Synthesizer {
+    /// Returns the next samples of the speech in progress, or `None` once it is over (or failed).
+    fn next_samples(&mut self) -> Result<Option<Vec<f32>>, PicoError> {
+        let result = self.step();
+        if !matches!(result, Ok(Some(_))) {
+            self.input = None;
+        }
+        result
+    }
+
+    /// Steps the engine until it outputs a chunk of audio, returning it, or until the speech in progress is over.
+    fn step(&mut self) -> Result<Option<Vec<f32>>, PicoError> {
+        let text = match self.input.as_ref() {
+            Some(text) => text,
+            None => return Ok(None),
+        };
+        let mut input = &text[self.input_pos..];
+        let mut samples = Vec::with_capacity(CHUNK_SIZE);
+        let mut pcm_buf = [0i16; 1024];
+        while samples.len() < CHUNK_SIZE {
+            let (n_samples, done) = self.engine.step(&mut input, &mut pcm_buf)?;
+            samples.extend(
+                pcm_buf[..n_samples]
+                    .iter()
+                    .map(|&sample| f32::from(sample) / 32768.0),
+            );
+            if done {
+                break;
+            }
+        }
+        self.input_pos = text.len() - input.len();
+        Ok(if samples.is_empty() {
+            None
+        } else {
+            Some(samples)
+        })
+    }
+}
@@ -0,0 +1,85 @@
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tests the bindings in Node.js (`wasm-pack test --node`), with the en-US language pack bundled with Pico's sources.
+
+#![cfg(target_arch = "wasm32")]
+
+use ttspico_wasm::{sample_rate, Synthesizer};
+use wasm_bindgen_test::wasm_bindgen_test;
+
+const TA: &[u8] = include_bytes!("../../ttspico-sys/build/pico/lang/en-US_ta.bin");
+const SG: &[u8] = include_bytes!("../../ttspico-sys/build/pico/lang/en-US_lh0_sg.bin");
+
+// (Each test drops its synthesizer before the next one creates its own, as Pico allows one system per thread)
+
+#[wasm_bindgen_test]
+fn synthesize() {
+    let mut synth = Synthesizer::new(TA, SG).unwrap();
+    let audio = synth.synthesize("Hello world", None).unwrap().to_vec();
+    let rate = sample_rate() as usize;
+    assert!(audio.len() > rate / 2 && audio.len() < rate * 3);
+    assert!(audio.iter().all(|sample| (-1.0..1.0).contains(sample)));
+    assert!(audio.iter().any(|&sample| sample != 0.0));
+    // (Each text is spoken from a fully reset engine)
+    assert_eq!(
+        synth.synthesize("Hello world", None).unwrap().to_vec(),
+        audio
+    );
+
+    let pause = synth
+        .synthesize(
+            r#"<speak>Hello <break time="500ms"/> world</speak>"#,
+            Some(true),
+        )
+        .unwrap();
+    assert!(pause.length() as usize >= audio.len() + rate / 2);
+    assert!(synth.synthesize("<speak>Hello</s>", Some(true)).is_err());
+}
+
+#[wasm_bindgen_test]
+fn chunks() {
+    let mut synth = Synthesizer::new(TA, SG).unwrap();
+    let audio = synth
+        .synthesize("Hello world. How are you?", None)
+        .unwrap()
+        .to_vec();
+
+    synth.speak("Hello world. How are you?", None).unwrap();
+    let mut chunks = Vec::new();
+    while let Some(chunk) = synth.next_chunk().unwrap() {
+        chunks.push(chunk.to_vec());
+    }
+    assert!(chunks.len() > 1);
+    assert_eq!(chunks.concat(), audio);
+    assert!(synth.next_chunk().unwrap().is_none());
+
+    // (Speaking drops the speech in progress)
+    synth.speak("The quick brown fox.", None).unwrap();
+    synth.next_chunk().unwrap().unwrap();
+    synth.speak("Hello world. How are you?", None).unwrap();
+    let mut speech = Vec::new();
+    while let Some(chunk) = synth.next_chunk().unwrap() {
+        speech.extend(chunk.to_vec());
+    }
+    assert_eq!(speech, audio);
+}
+
+#[wasm_bindgen_test]
+fn invalid_resources() {
+    assert!(Synthesizer::new(&[], &[]).is_err());
+    assert!(Synthesizer::new(&TA[..1000], SG).is_err());
+    // (The failed synthesizers freed their systems)
+    assert!(Synthesizer::new(TA, SG).is_ok());
+}
@@ -0,0 +1,118 @@
+//! Reading and writing the events of the Wyoming protocol.
+//!
+//! An event is a line of JSON with its `type`, optionally followed by `data_length` bytes of JSON data (merged with
+//! the line's own `data`) and `payload_length` bytes of binary payload (i.e. audio).
+
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use serde_json::{json, Map, Value};
+use std::io::{self, BufRead, Read, Write};
+
+/// Version of the Wyoming protocol spoken.
+pub const VERSION: &str = "1.5.4";
+
+/// Maximum length of an event's header line, data or payload, against misbehaving clients.
+const MAX_LEN: u64 = 16 * 1024 * 1024;
+
+/// An event.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    pub kind: String,
+    pub data: Map<String, Value>,
+    pub payload: Vec<u8>,
+}
+
+impl Event {
+    pub fn new(kind: &str, data: Value) -> Self {
+        Event {
+            kind: kind.to_string(),
+            data: match data {
+                Value::Object(data) => data,
+                _ => Map::new(),
+            },
+            payload: Vec::new(),
+        }
+    }
+
+    pub fn with_payload(mut self, payload: Vec<u8>) -> Self {
+        self.payload = payload;
+        self
+    }
+
+    /// Reads an event; `None` at the end of the input.
+    pub fn read(input: &mut impl BufRead) -> io::Result<Option<Self>> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+
+        let mut line = Vec::new();
+        input.by_ref().take(MAX_LEN).read_until(b'\n', &mut line)?;
+        if line.is_empty() {
+            return Ok(None);
+        }
+        let header: Map<String, Value> = serde_json::from_slice(&line)
+            .map_err(|err| invalid(format!("invalid event header: {}", err)))?;
+        let kind = header
+            .get("type")
+            .and_then(Value::as_str)
+            .ok_or_else(|| invalid("event without a type".to_string()))?
+            .to_string();
+        let length = |field: &str| -> io::Result<u64> {
+            match header.get(field) {
+                None | Some(Value::Null) => Ok(0),
+                Some(value) => value
+                    .as_u64()
+                    .filter(|&length| length <= MAX_LEN)
+                    .ok_or_else(|| invalid(format!("invalid {}", field))),
+            }
+        };
+        let (data_length, payload_length) = (length("data_length")?, length("payload_length")?);
+
+        let mut data = match header.get("data") {
+            Some(Value::Object(data)) => data.clone(),
+            _ => Map::new(),
+        };
+        if data_length > 0 {
+            let mut data_bytes = vec![0; data_length as usize];
+            input.read_exact(&mut data_bytes)?;
+            let more_data: Map<String, Value> = serde_json::from_slice(&data_bytes)
+                .map_err(|err| invalid(format!("invalid event data: {}", err)))?;
+            data.extend(more_data);
+        }
+        let mut payload = vec![0; payload_length as usize];
+        input.read_exact(&mut payload)?;
+        Ok(Some(Event {
+            kind,
+            data,
+            payload,
+        }))
+    }
+
+    /// Writes the event.
+    pub fn write(&self, output: &mut impl Write) -> io::Result<()> {
+        let data = serde_json::to_vec(&self.data)?;
+        let mut header = json!({ "type": self.kind, "version": VERSION });
+        if !self.data.is_empty() {
+            header["data_length"] = data.len().into();
+        }
+        if !self.payload.is_empty() {
+            header["payload_length"] = self.payload.len().into();
+        }
+        serde_json::to_writer(&mut *output, &header)?;
+        output.write_all(b"\n")?;
+        if !self.data.is_empty() {
+            output.write_all(&data)?;
+        }
+        output.write_all(&self.payload)
+    }
+}
@@ -0,0 +1,325 @@
+//! A Wyoming protocol speech synthesis server for the Pico TTS engine, i.e. for Home Assistant.
+//!
+//! Clients connect over TCP and send events:
+//! - `describe`: answered with `info`, listing the available voices (one per language pack);
+//! - `synthesize`: the `text` is spoken with the requested `voice` (by `name`, else by `language`), and streamed
+//!   back as `audio-start`, `audio-chunk`s (16-bit mono PCM at 16kHz) and `audio-stop`, or `error` if it cannot be.
+//!   The text is plain: `<` and `>` are read as spaces, so that it cannot contain Pico's markup.
+//!
+//! Other events are ignored. Each connection is served by a thread of its own, and its texts are spoken by a pool of
+//! worker threads, each with its own Pico system.
+
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod event;
+
+use clap::Parser;
+use event::Event;
+use serde_json::{json, Value};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::{mpsc, Arc};
+use ttspico::{MultiVoiceSynthesizer, SynthesizerPool, VoiceCatalog, SAMPLE_RATE};
+
+/// Serves speech synthesized with Pico TTS over the Wyoming protocol.
+#[derive(Debug, Parser)]
+#[command(name = "ttspico-wyoming", version)]
+struct Args {
+    /// Address to listen on
+    #[arg(
+        short = 'u',
+        long,
+        value_name = "URI",
+        default_value = "tcp://127.0.0.1:10200"
+    )]
+    uri: String,
+
+    /// Number of worker threads, i.e. of texts spoken at once (by default the number of CPUs)
+    #[arg(short = 'w', long, value_name = "N")]
+    workers: Option<usize>,
+
+    /// Voice of the requests that do not specify one
+    #[arg(
+        short = 'l',
+        long = "lang",
+        value_name = "LANG",
+        default_value = "en-US"
+    )]
+    lang: String,
+
+    /// Directory of the language packs, instead of the default ones
+    #[arg(long, value_name = "DIR")]
+    lang_dir: Option<PathBuf>,
+
+    /// Number of samples in each audio chunk
+    #[arg(long, value_name = "N", default_value_t = 1024)]
+    samples_per_chunk: usize,
+}
+
+/// What all connections and workers share.
+struct Context {
+    /// The available voices.
+    voices: VoiceCatalog,
+    default_voice: String,
+    samples_per_chunk: usize,
+}
+
+/// Returns the `info` event answering `describe`.
+fn info(ctx: &Context) -> Event {
+    let attribution = json!({ "name": "SVOX", "url": "https://android.googlesource.com/platform/external/svox/" });
+    let voices: Vec<Value> = ctx
+        .voices
+        .voices()
+        .iter()
+        .map(|voice| {
+            let language = &voice.language;
+            json!({
+                "name": language,
+                "description": format!("Pico {} ({})", language, voice.speaker),
+                "attribution": attribution,
+                "installed": true,
+                "version": null,
+                "languages": [language],
+                "speakers": null,
+            })
+        })
+        .collect();
+    Event::new(
+        "info",
+        json!({
+            "asr": [],
+            "tts": [{
+                "name": "ttspico",
+                "description": "Pico TTS",
+                "attribution": attribution,
+                "installed": true,
+                "version": env!("CARGO_PKG_VERSION"),
+                "voices": voices,
+            }],
+            "handle": [],
+            "intent": [],
+            "wake": [],
+        }),
+    )
+}
+
+/// Picks the voice requested by a `synthesize` event: by name, else by language (or a dialect of it), else the
+/// default one.
+fn pick_voice<'a>(ctx: &'a Context, voice: Option<&Value>) -> Result<&'a str, String> {
+    let field = |name: &str| {
+        voice
+            .and_then(|voice| voice.get(name))
+            .and_then(Value::as_str)
+    };
+    if let Some(name) = field("name") {
+        return ctx
+            .voices
+            .get(name)
+            .map(|voice| voice.language.as_str())
+            .ok_or_else(|| format!("unknown voice {}", name));
+    }
+    if let Some(language) = field("language") {
+        return ctx
+            .voices
+            .find_voice(language)
+            .map(|voice| voice.language.as_str())
+            .ok_or_else(|| format!("no voice for language {}", language.replace('_', "-")));
+    }
+    Ok(&ctx.default_voice)
+}
+
+/// Returns an `error` event.
+fn error(message: &str) -> Event {
+    Event::new(
+        "error",
+        json!({ "text": message, "code": "synthesis-failed" }),
+    )
+}
+
+/// Returns the data of an audio event at `n_samples` into the audio.
+fn audio_data(n_samples: usize) -> Value {
+    json!({
+        "rate": SAMPLE_RATE,
+        "width": 2,
+        "channels": 1,
+        "timestamp": n_samples as u64 * 1000 / SAMPLE_RATE as u64,
+    })
+}
+
+/// Speaks the text of a `synthesize` event, streaming the audio to `output`.
+fn synthesize(
+    event: &Event,
+    output: &mut impl Write,
+    worker: &mut MultiVoiceSynthesizer,
+    ctx: &Context,
+) -> io::Result<()> {
+    // (The text is plain, so that clients cannot inject Pico's markup, i.e. play files or change the voice)
+    let text = event
+        .data
+        .get("text")
+        .and_then(Value::as_str)
+        .unwrap_or("")
+        .replace(['<', '>'], " ");
+    let engine = match pick_voice(ctx, event.data.get("voice"))
+        .and_then(|voice| worker.engine(voice).map_err(|err| err.to_string()))
+    {
+        Ok(engine) => engine,
+        Err(err) => return error(&err).write(output),
+    };
+
+    Event::new("audio-start", audio_data(0)).write(output)?;
+    let mut input = text.as_bytes();
+    let mut samples = Vec::with_capacity(ctx.samples_per_chunk);
+    // (Pico fails to output into buffers smaller than its own items, so chunks are cut from a buffer of samples)
+    let mut pcm_buf = [0i16; 1024];
+    let mut n_sent = 0;
+    loop {
+        let (n_samples, done) = match engine.step(&mut input, &mut pcm_buf[..]) {
+            Ok(step) => step,
+            // (The audio has started already, so it can only be cut short)
+            Err(err) => return error(&err.to_string()).write(output),
+        };
+        samples.extend_from_slice(&pcm_buf[..n_samples]);
+        while samples.len() >= ctx.samples_per_chunk || (done && !samples.is_empty()) {
+            let chunk_len = samples.len().min(ctx.samples_per_chunk);
+            let payload = samples
+                .drain(..chunk_len)
+                .flat_map(|sample| sample.to_le_bytes())
+                .collect();
+            Event::new("audio-chunk", audio_data(n_sent))
+                .with_payload(payload)
+                .write(output)?;
+            n_sent += chunk_len;
+        }
+        if done {
+            break;
+        }
+    }
+    Event::new(
+        "audio-stop",
+        json!({ "timestamp": audio_data(n_sent)["timestamp"] }),
+    )
+    .write(output)
+}
+
+/// A `synthesize` event, for a worker to answer.
+struct Job {
+    event: Event,
+    /// The connection to answer on.
+    output: TcpStream,
+    /// Where the outcome of answering goes.
+    done: mpsc::Sender<io::Result<()>>,
+}
+
+/// Serves a connection, until the client closes it; its `synthesize` events are answered by `workers`, so that idle
+/// connections do not hold a worker.
+fn serve(stream: TcpStream, workers: &SynthesizerPool<Job>, ctx: &Context) -> io::Result<()> {
+    let mut input = BufReader::new(stream.try_clone()?);
+    let mut output = BufWriter::new(stream.try_clone()?);
+    while let Some(event) = Event::read(&mut input)? {
+        match event.kind.as_str() {
+            "describe" => info(ctx).write(&mut output)?,
+            "synthesize" => {
+                let (done_tx, done_rx) = mpsc::channel();
+                let job = Job {
+                    event,
+                    output: stream.try_clone()?,
+                    done: done_tx,
+                };
+                let stopped = || io::Error::other("the workers stopped");
+                workers.send(job).map_err(|_| stopped())?;
+                done_rx.recv().map_err(|_| stopped())??;
+            }
+            _ => {}
+        }
+        output.flush()?;
+    }
+    Ok(())
+}
+
+fn run(args: Args) -> Result<(), String> {
+    let addr = args
+        .uri
+        .strip_prefix("tcp://")
+        .ok_or_else(|| format!("unsupported URI {} (expected tcp://HOST:PORT)", args.uri))?;
+    let voices = VoiceCatalog::new(ttspico::lang_search_dirs(args.lang_dir.as_deref()));
+    if voices.get(&args.lang).is_none() {
+        return Err(format!("no language pack for {}", args.lang));
+    }
+    let memsz = voices
+        .system_builder()
+        .measure()
+        .map_err(|err| err.to_string())?;
+
+    let listener =
+        TcpListener::bind(addr).map_err(|err| format!("failed to listen on {}: {}", addr, err))?;
+    let n_workers = args
+        .workers
+        .unwrap_or_else(|| {
+            std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1)
+        })
+        .max(1);
+    let ctx = Arc::new(Context {
+        voices,
+        default_voice: args.lang.clone(),
+        samples_per_chunk: args.samples_per_chunk.max(1),
+    });
+    let workers = SynthesizerPool::start(n_workers, memsz, &ctx.voices, {
+        let ctx = Arc::clone(&ctx);
+        move |job: Job, worker| {
+            let mut output = BufWriter::new(job.output);
+            let written =
+                synthesize(&job.event, &mut output, worker, &ctx).and_then(|()| output.flush());
+            let _ = job.done.send(written);
+        }
+    })
+    .map_err(|err| format!("failed to start a worker: {}", err))?;
+
+    match listener.local_addr() {
+        Ok(addr) => println!("ttspico-wyoming: listening on tcp://{}", addr),
+        Err(_) => println!("ttspico-wyoming: listening on {}", args.uri),
+    }
+    let _ = io::stdout().flush();
+    std::thread::scope(|scope| {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let (workers, ctx) = (&workers, &ctx);
+                    scope.spawn(move || {
+                        if let Err(err) = serve(stream, workers, ctx) {
+                            eprintln!("ttspico-wyoming: connection failed: {}", err);
+                        }
+                    });
+                }
+                Err(err) => eprintln!("ttspico-wyoming: failed to accept a connection: {}", err),
+            }
+        }
+    });
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    match run(Args::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("ttspico-wyoming: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,284 @@
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Runs `ttspico-wyoming` on localhost, with the language packs bundled with Pico's sources.
+
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::process::{Child, Command, Stdio};
+use std::rc::Rc;
+use std::time::Duration;
+use ttspico::{EngineStatus, System, Voice};
+use ttspico_sys as native;
+
+/// A running `ttspico-wyoming`, killed on drop.
+struct Server {
+    child: Child,
+    addr: String,
+}
+
+impl Server {
+    fn start(samples_per_chunk: usize, workers: usize) -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_ttspico-wyoming"))
+            .args([
+                "--uri",
+                "tcp://127.0.0.1:0",
+                "--lang-dir",
+                native::BUNDLED_LANG_DIR,
+            ])
+            .args(["--workers", &workers.to_string()])
+            .args(["--samples-per-chunk", &samples_per_chunk.to_string()])
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("Failed to run ttspico-wyoming");
+        let mut line = String::new();
+        BufReader::new(child.stdout.take().unwrap())
+            .read_line(&mut line)
+            .unwrap();
+        let addr = line
+            .trim()
+            .rsplit("tcp://")
+            .next()
+            .expect("ttspico-wyoming did not start")
+            .to_string();
+        Server { child, addr }
+    }
+
+    fn connect(&self) -> Client {
+        let stream = TcpStream::connect(&self.addr).unwrap();
+        // (So that a server that does not answer fails the test instead of hanging it)
+        stream
+            .set_read_timeout(Some(Duration::from_secs(60)))
+            .unwrap();
+        Client {
+            input: BufReader::new(stream.try_clone().unwrap()),
+            output: stream,
+        }
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// A connection to the server.
+struct Client {
+    input: BufReader<TcpStream>,
+    output: TcpStream,
+}
+
+impl Client {
+    /// Sends an event, with its data in the header line (as Wyoming 1.0) or after it (as later versions).
+    fn send(&mut self, kind: &str, data: Value, inline: bool) {
+        let mut message = Vec::new();
+        if inline {
+            serde_json::to_writer(&mut message, &json!({ "type": kind, "data": data })).unwrap();
+            message.push(b'\n');
+        } else {
+            let data = serde_json::to_vec(&data).unwrap();
+            serde_json::to_writer(
+                &mut message,
+                &json!({ "type": kind, "version": "1.5.4", "data_length": data.len() }),
+            )
+            .unwrap();
+            message.push(b'\n');
+            message.extend_from_slice(&data);
+        }
+        self.output.write_all(&message).unwrap();
+    }
+
+    /// Receives an event, as its type, data and payload.
+    fn receive(&mut self) -> (String, Value, Vec<u8>) {
+        let mut line = String::new();
+        self.input.read_line(&mut line).unwrap();
+        let header: Value = serde_json::from_str(&line).unwrap();
+        let length = |field: &str| header.get(field).and_then(Value::as_u64).unwrap_or(0) as usize;
+        let mut data = vec![0; length("data_length")];
+        self.input.read_exact(&mut data).unwrap();
+        let data = if data.is_empty() {
+            json!({})
+        } else {
+            serde_json::from_slice(&data).unwrap()
+        };
+        let mut payload = vec![0; length("payload_length")];
+        self.input.read_exact(&mut payload).unwrap();
+        (header["type"].as_str().unwrap().to_string(), data, payload)
+    }
+
+    /// Synthesizes `text` with `voice`, returning the audio chunks received.
+    fn synthesize(&mut self, text: &str, voice: Value) -> Vec<Vec<i16>> {
+        self.send("synthesize", json!({ "text": text, "voice": voice }), false);
+        let (kind, start, _) = self.receive();
+        assert_eq!(kind, "audio-start", "{}", start);
+        assert_eq!(
+            start,
+            json!({ "rate": 16000, "width": 2, "channels": 1, "timestamp": 0 })
+        );
+        let mut chunks = Vec::new();
+        let mut n_samples = 0;
+        loop {
+            let (kind, data, payload) = self.receive();
+            let timestamp = (n_samples * 1000 / 16000) as u64;
+            if kind == "audio-stop" {
+                assert_eq!(data, json!({ "timestamp": timestamp }));
+                return chunks;
+            }
+            assert_eq!(kind, "audio-chunk", "{}", data);
+            assert_eq!(
+                data,
+                json!({ "rate": 16000, "width": 2, "channels": 1, "timestamp": timestamp })
+            );
+            let chunk: Vec<i16> = payload
+                .chunks(2)
+                .map(|sample| i16::from_le_bytes([sample[0], sample[1]]))
+                .collect();
+            n_samples += chunk.len();
+            chunks.push(chunk);
+        }
+    }
+}
+
+/// Synthesizes `text` with a bundled language pack in this process.
+fn synthesize(ta: &str, sg: &str, text: &str) -> Vec<i16> {
+    let sys = System::new(8 * 1024 * 1024).unwrap();
+    let lang_file = |name: &str| format!("{}/{}", native::BUNDLED_LANG_DIR, name);
+    let ta_res = System::load_resource(Rc::clone(&sys), lang_file(ta)).unwrap();
+    let sg_res = System::load_resource(Rc::clone(&sys), lang_file(sg)).unwrap();
+    let voice = System::create_voice(sys, "voice").unwrap();
+    voice.borrow_mut().add_resource(ta_res).unwrap();
+    voice.borrow_mut().add_resource(sg_res).unwrap();
+    let mut engine = unsafe { Voice::create_engine(voice).unwrap() };
+
+    engine.put_text(text).unwrap();
+    engine.flush().unwrap();
+    let mut samples = Vec::new();
+    let mut pcm_buf = [0i16; 1024];
+    loop {
+        let (n_samples, status) = engine.get_data(&mut pcm_buf[..]).unwrap();
+        samples.extend_from_slice(&pcm_buf[..n_samples]);
+        if status == EngineStatus::Idle {
+            return samples;
+        }
+    }
+}
+
+#[test]
+fn describe() {
+    let server = Server::start(1024, 2);
+    let mut client = server.connect();
+    // (Unknown events are ignored)
+    client.send("ping", json!({}), true);
+    client.send("describe", json!({}), true);
+    let (kind, info, _) = client.receive();
+    assert_eq!(kind, "info");
+    let tts = &info["tts"][0];
+    assert_eq!(tts["name"], "ttspico");
+    let voices = tts["voices"].as_array().unwrap();
+    assert_eq!(voices.len(), 6);
+    let en_us = voices
+        .iter()
+        .find(|voice| voice["name"] == "en-US")
+        .unwrap();
+    assert_eq!(en_us["languages"], json!(["en-US"]));
+    assert_eq!(en_us["description"], "Pico en-US (lh0)");
+    assert_eq!(en_us["installed"], true);
+}
+
+#[test]
+fn synthesis() {
+    let server = Server::start(1000, 2);
+    let mut client = server.connect();
+
+    let hello = synthesize("en-US_ta.bin", "en-US_lh0_sg.bin", "Hello world");
+    let chunks = client.synthesize("Hello world", json!({ "name": "en-US" }));
+    assert!(chunks.len() > 1);
+    assert!(chunks[..chunks.len() - 1]
+        .iter()
+        .all(|chunk| chunk.len() == 1000));
+    assert_eq!(chunks.concat(), hello);
+
+    // (Without a name, the voice is picked by language, a dialect if needed, else the default one)
+    let ciao = synthesize("it-IT_ta.bin", "it-IT_cm0_sg.bin", "Ciao mondo");
+    assert_eq!(
+        client
+            .synthesize("Ciao mondo", json!({ "language": "it" }))
+            .concat(),
+        ciao
+    );
+    assert_eq!(client.synthesize("Hello world", json!({})).concat(), hello);
+    assert_eq!(
+        client.synthesize("Hello world", Value::Null).concat(),
+        hello
+    );
+
+    // (Several connections are served at once)
+    let mut other_client = server.connect();
+    assert_eq!(
+        other_client
+            .synthesize("Ciao mondo", json!({ "name": "it-IT" }))
+            .concat(),
+        ciao
+    );
+
+    client.send(
+        "synthesize",
+        json!({ "text": "Hello", "voice": { "name": "xx-XX" } }),
+        true,
+    );
+    let (kind, data, _) = client.receive();
+    assert_eq!(kind, "error");
+    assert_eq!(data["text"], "unknown voice xx-XX");
+    assert_eq!(client.synthesize("Hello world", json!({})).concat(), hello);
+}
+
+#[test]
+fn plain_text() {
+    let server = Server::start(1024, 2);
+    let mut client = server.connect();
+
+    // (Markup in the text is spoken, not followed)
+    let text = "Hello <speed level=\"500\">world</speed>";
+    assert_eq!(
+        client.synthesize(text, json!({})).concat(),
+        synthesize(
+            "en-US_ta.bin",
+            "en-US_lh0_sg.bin",
+            "Hello  speed level=\"500\" world /speed "
+        )
+    );
+    assert!(!client
+        .synthesize("<play file=\"/etc/passwd\"/>", json!({}))
+        .concat()
+        .is_empty());
+}
+
+#[test]
+fn idle_connections() {
+    let server = Server::start(1024, 1);
+    let hello = synthesize("en-US_ta.bin", "en-US_lh0_sg.bin", "Hello world");
+
+    // (Connections only hold the worker while it speaks their texts)
+    let mut clients: Vec<_> = (0..3).map(|_| server.connect()).collect();
+    for client in clients.iter_mut().rev() {
+        assert_eq!(client.synthesize("Hello world", json!({})).concat(), hello);
+    }
+    assert_eq!(
+        clients[1].synthesize("Hello world", json!({})).concat(),
+        hello
+    );
+}
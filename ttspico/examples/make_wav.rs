@@ -22,7 +22,6 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
 // THE SOFTWARE.
 
-use hound;
 use std::rc::Rc;
 use ttspico as pico;
 
@@ -64,7 +63,7 @@ fn main() {
     // 5. Put (UTF-8) text to be spoken into the engine
     // See `Engine::put_text()` for more details.
     let mut text_bytes: &[u8] = b"1, 2, 3, Hello Rust!\0"; //< The null terminator tells Pico to start synthesizing!
-    while text_bytes.len() > 0 {
+    while !text_bytes.is_empty() {
         let n_put = engine
             .put_text(text_bytes)
             .expect("pico_putTextUtf8 failed");
@@ -87,7 +86,7 @@ fn main() {
 
     let spec = hound::WavSpec {
         channels: 1,
-        sample_rate: 16000,
+        sample_rate: ttspico::SAMPLE_RATE,
         bits_per_sample: 16,
         sample_format: hound::SampleFormat::Int,
     };
@@ -64,7 +64,7 @@ fn main() {
     // 5. Put (UTF-8) text to be spoken into the engine
     // See `Engine::put_text()` for more details.
     let mut text_bytes: &[u8] = b"1, 2, 3, Hello Rust!\0"; //< The null terminator tells Pico to start synthesizing!
-    while text_bytes.len() > 0 {
+    while !text_bytes.is_empty() {
         let n_put = engine
             .put_text(text_bytes)
             .expect("pico_putTextUtf8 failed");
@@ -85,7 +85,7 @@ fn main() {
         }
     }
 
-    audio_out(&*pcm_data);
+    audio_out(&pcm_data);
 }
 
 /// Plays an audio buffer (16-bit signed PCM @ 16kHz) to the system default output device.
@@ -99,7 +99,7 @@ fn audio_out(pcm_data: &[i16]) -> ! {
 
     let format = cpal::Format {
         channels: 1,
-        sample_rate: cpal::SampleRate(16_000),
+        sample_rate: cpal::SampleRate(ttspico::SAMPLE_RATE),
         data_type: cpal::SampleFormat::I16,
     };
 
@@ -0,0 +1,79 @@
+//! The code run by the fuzz targets, which is also used by `ttspico/tests/fuzz_regressions.rs` to replay the
+//! inputs that made them crash.
+
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use ttspico::{Engine, EngineResetMode, System, Voice};
+
+const TA_NAME: &str = "en-US_ta.bin";
+const TA_BYTES: &[u8] = include_bytes!("../../../ttspico-sys/build/pico/lang/en-US_ta.bin");
+const SG_NAME: &str = "en-US_lh0_sg.bin";
+const SG_BYTES: &[u8] = include_bytes!("../../../ttspico-sys/build/pico/lang/en-US_lh0_sg.bin");
+
+/// Memory size of the systems, on top of the size of the resources loaded in them.
+const MEMORY_SIZE: usize = 4 * 1024 * 1024;
+
+thread_local! {
+    /// The engine used by [`synthesize`], created on first use (only one system can exist per thread).
+    static ENGINE: RefCell<Option<Engine>> = const { RefCell::new(None) };
+}
+
+fn create_engine() -> Engine {
+    let sys = System::new(MEMORY_SIZE).expect("Could not init system");
+    let ta_res = System::load_resource_from_bytes(Rc::clone(&sys), TA_NAME, TA_BYTES)
+        .expect("Failed to load TA");
+    let sg_res = System::load_resource_from_bytes(Rc::clone(&sys), SG_NAME, SG_BYTES)
+        .expect("Failed to load SG");
+    let voice = System::create_voice(sys, "TestVoice").expect("Failed to create voice");
+    voice
+        .borrow_mut()
+        .add_resource(ta_res)
+        .expect("Failed to add TA to voice");
+    voice
+        .borrow_mut()
+        .add_resource(sg_res)
+        .expect("Failed to add SG to voice");
+    // SAFETY: both a TA and a SG resource were added to the voice above.
+    unsafe { Voice::create_engine(voice).expect("Failed to create engine") }
+}
+
+/// Synthesizes `text` (which may contain markup) to completion, discarding the generated audio.
+/// Pico errors are ignored; only crashes and hangs are of interest.
+pub fn synthesize(text: &str) {
+    ENGINE.with(|engine| {
+        let mut engine = engine.borrow_mut();
+        let engine = engine.get_or_insert_with(create_engine);
+        if engine.reset(EngineResetMode::Full).is_err() {
+            return;
+        }
+
+        let mut pcm_buf = [0i16; 1024];
+        let mut input = text.as_bytes();
+        // (Until the speech is over, or the first error)
+        while let Ok((_, false)) = engine.step(&mut input, &mut pcm_buf) {}
+    });
+}
+
+/// Loads `bytes` as the contents of a resource file in a new system.
+/// Pico errors are ignored; only crashes and hangs are of interest.
+pub fn load_resource(bytes: &[u8]) {
+    let sys = match System::new(MEMORY_SIZE + bytes.len()) {
+        Ok(sys) => sys,
+        Err(_) => return,
+    };
+    let _ = System::load_resource_from_bytes(sys, "fuzz.bin", bytes);
+}
@@ -0,0 +1,134 @@
+//! A builder for [`System`]s that can size their memory automatically.
+
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{PicoError, System, Voice};
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+/// Memory size used by [`System`]s when neither a size nor any language packs are given to the builder.
+const DEFAULT_MEMORY_SIZE: usize = 4 * 1024 * 1024;
+
+/// Memory size of the temporary [`System`] used to measure the memory needed by language packs.
+const DEFAULT_PROBE_MEMORY_SIZE: usize = 32 * 1024 * 1024;
+
+/// Memory used by Pico's own bookkeeping, which is not accounted for by [`System::memory_usage`].
+const SYSTEM_OVERHEAD: usize = 4 * 1024;
+
+/// Extra memory added by default on top of the measured peak usage.
+const DEFAULT_HEADROOM: usize = 64 * 1024;
+
+/// Builds a [`System`], optionally measuring the memory it needs for a set of language packs.
+///
+/// ```no_run
+/// # use ttspico::System;
+/// let sys = System::builder()
+///     .language_pack("lang/en-US_ta.bin", "lang/en-US_lh0_sg.bin")
+///     .language_pack("lang/it-IT_ta.bin", "lang/it-IT_cm0_sg.bin")
+///     .build()
+///     .expect("Could not init system");
+/// ```
+#[derive(Debug, Clone)]
+pub struct SystemBuilder {
+    memsz: Option<usize>,
+    probe_memsz: usize,
+    headroom: usize,
+    language_packs: Vec<(String, String)>,
+}
+
+impl SystemBuilder {
+    /// Creates a new builder with default settings.
+    pub fn new() -> Self {
+        SystemBuilder {
+            memsz: None,
+            probe_memsz: DEFAULT_PROBE_MEMORY_SIZE,
+            headroom: DEFAULT_HEADROOM,
+            language_packs: Vec::new(),
+        }
+    }
+
+    /// Sets the size in bytes of the memory to allocate for the system explicitly, disabling automatic sizing.
+    pub fn memory_size(mut self, memsz: usize) -> Self {
+        self.memsz = Some(memsz);
+        self
+    }
+
+    /// Adds a language pack, given the filepaths of its TA and SG resources.
+    /// Unless an explicit [memory size](`SystemBuilder::memory_size`) is set, the system's memory will be sized
+    /// to fit all language packs loaded at once plus an engine for any one of them.
+    pub fn language_pack(mut self, ta_path: impl Into<String>, sg_path: impl Into<String>) -> Self {
+        self.language_packs.push((ta_path.into(), sg_path.into()));
+        self
+    }
+
+    /// Sets the number of bytes added on top of the measured peak memory usage (64 KiB by default).
+    pub fn headroom(mut self, headroom: usize) -> Self {
+        self.headroom = headroom;
+        self
+    }
+
+    /// Sets the memory size of the temporary system used to measure language packs
+    /// (32 MiB by default).
+    pub fn probe_memory_size(mut self, probe_memsz: usize) -> Self {
+        self.probe_memsz = probe_memsz;
+        self
+    }
+
+    /// Measures the memory size needed to load all language packs and create an engine for each of them
+    /// (one at a time), including headroom.
+    ///
+    /// This loads the language packs in a temporary [`System`], so it must not be called on a thread that
+    /// already has one.
+    pub fn measure(&self) -> Result<usize, PicoError> {
+        let sys = System::new(self.probe_memsz)?;
+        let mut resources = Vec::with_capacity(self.language_packs.len());
+        for (ta_path, sg_path) in &self.language_packs {
+            let ta_res = System::load_resource(Rc::clone(&sys), ta_path)?;
+            let sg_res = System::load_resource(Rc::clone(&sys), sg_path)?;
+            resources.push((ta_res, sg_res));
+        }
+        for (i, (ta_res, sg_res)) in resources.into_iter().enumerate() {
+            let voice = System::create_voice(Rc::clone(&sys), format!("__ttspico_probe{}", i))?;
+            voice.borrow_mut().add_resource(ta_res)?;
+            voice.borrow_mut().add_resource(sg_res)?;
+            // SAFETY: both a TA and a SG resource were added to the voice above.
+            let engine = unsafe { Voice::create_engine(voice)? };
+            drop(engine);
+        }
+        let max_used = sys.borrow().memory_usage(false)?.max_used;
+        Ok(max_used + SYSTEM_OVERHEAD + self.headroom)
+    }
+
+    /// Builds the [`System`], [measuring](`SystemBuilder::measure`) its memory size first if needed.
+    /// # See
+    /// [`System::new`].
+    pub fn build(self) -> Result<Rc<RefCell<System>>, PicoError> {
+        let memsz = match self.memsz {
+            Some(memsz) => memsz,
+            None if self.language_packs.is_empty() => DEFAULT_MEMORY_SIZE,
+            None => self.measure()?,
+        };
+        System::new(memsz)
+    }
+}
+
+impl Default for SystemBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
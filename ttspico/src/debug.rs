@@ -0,0 +1,233 @@
+//! Forwards Pico's diagnostic output (see `picodbg.h`) to the [`log`] crate (with the `debug-pico` feature)
+//! and/or as `tracing` events (with the `tracing` feature).
+
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::TraceLevel;
+use std::cell::RefCell;
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_int};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Once;
+use ttspico_sys as native;
+
+/// The parent target of all messages coming from Pico.
+/// Each message's target is `ttspico::pico::<module>`, where `<module>` is the name of the Pico source file
+/// that produced it without extension (for example `ttspico::pico::picotok` for `picotok.c`).
+pub const LOG_TARGET: &str = "ttspico::pico";
+
+/// Calls `$callback!` with the names of the Pico source modules that can produce diagnostic output.
+macro_rules! with_pico_modules {
+    ($callback:ident!($($args:tt)*)) => {
+        $callback!(
+            ($($args)*)
+            picoacph picoapi picobase picocep picoctrl picodata picodbg picoextapi picofftsg picokdbg picokdt
+            picokfst picoklex picoknow picokpdf picokpr picoktab picoos picopal picopam picopr picorsrc picosa
+            picosig picosig2 picospho picotok picotrns picowa
+        )
+    };
+}
+
+/// Returns the target of messages produced by the Pico source file `file`.
+fn target_for_file(file: &str) -> &'static str {
+    macro_rules! match_module {
+        (($file:expr) $($module:ident)*) => {
+            match $file.trim_end_matches(".c").trim_end_matches(".h") {
+                $(stringify!($module) => concat!("ttspico::pico::", stringify!($module)),)*
+                _ => LOG_TARGET,
+            }
+        };
+    }
+    with_pico_modules!(match_module!(file))
+}
+
+/// Returns the name of the Pico source file whose messages have the given `ttspico::pico::<module>` target,
+/// or `None` if `target` is not such a target.
+pub(crate) fn file_for_target(target: &str) -> Option<String> {
+    target
+        .strip_prefix(LOG_TARGET)
+        .and_then(|module| module.strip_prefix("::"))
+        .filter(|module| !module.is_empty() && !module.contains(':'))
+        .map(|module| format!("{}.c", module))
+}
+
+/// A message composed piecewise by Pico, which is emitted once its line is complete.
+#[derive(Default)]
+struct PendingLine {
+    level: c_int,
+    file: String,
+    line: u32,
+    func: String,
+    msg: String,
+}
+
+thread_local! {
+    static PENDING_LINE: RefCell<Option<PendingLine>> = const { RefCell::new(None) };
+}
+
+/// Installs [`log_callback`] as Pico's log callback, once per process.
+/// Called before a system is initialized, so that Pico's global tracing settings are set up before threads use them.
+pub fn install() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| unsafe {
+        native::picodbg_setLogCallback(Some(log_callback));
+    });
+}
+
+/// Returns `true` the first time it is called in the process, i.e. for the first system to be initialized.
+pub fn is_first_system() -> bool {
+    static INITIALIZED: AtomicBool = AtomicBool::new(false);
+    !INITIALIZED.swap(true, Ordering::Relaxed)
+}
+
+/// Returns the [`TraceLevel`] that makes Pico trace all messages that `log` and/or `tracing` would currently
+/// output.
+pub fn max_trace_level() -> TraceLevel {
+    #[allow(unused_mut)]
+    let mut level = TraceLevel::Off;
+    #[cfg(feature = "debug-pico")]
+    {
+        level = level.max(match log::max_level() {
+            log::LevelFilter::Off => TraceLevel::Off,
+            log::LevelFilter::Error => TraceLevel::Error,
+            log::LevelFilter::Warn => TraceLevel::Warn,
+            log::LevelFilter::Info => TraceLevel::Info,
+            log::LevelFilter::Debug => TraceLevel::Debug,
+            log::LevelFilter::Trace => TraceLevel::Trace,
+        });
+    }
+    #[cfg(feature = "tracing")]
+    {
+        use tracing::level_filters::LevelFilter;
+        level = level.max(match LevelFilter::current() {
+            LevelFilter::OFF => TraceLevel::Off,
+            LevelFilter::ERROR => TraceLevel::Error,
+            LevelFilter::WARN => TraceLevel::Warn,
+            LevelFilter::INFO => TraceLevel::Info,
+            LevelFilter::DEBUG => TraceLevel::Debug,
+            _ => TraceLevel::Trace,
+        });
+    }
+    level
+}
+
+#[cfg(feature = "debug-pico")]
+fn emit_log(level: c_int, target: &str, file: &str, line: u32, func: &str, msg: &str) {
+    let level = match level {
+        native::PICODBG_LOG_LEVEL_ERROR => log::Level::Error,
+        native::PICODBG_LOG_LEVEL_WARN => log::Level::Warn,
+        native::PICODBG_LOG_LEVEL_INFO => log::Level::Info,
+        native::PICODBG_LOG_LEVEL_DEBUG => log::Level::Debug,
+        _ => log::Level::Trace,
+    };
+    if level > log::max_level() {
+        return;
+    }
+    let func_sep = if func.is_empty() { "" } else { ": " };
+    log::logger().log(
+        &log::Record::builder()
+            .args(format_args!("{}{}{}", func, func_sep, msg))
+            .level(level)
+            .target(target)
+            .file(Some(file))
+            .line(Some(line))
+            .build(),
+    );
+}
+
+#[cfg(feature = "tracing")]
+fn emit_tracing(level: c_int, target: &str, file: &str, line: u32, func: &str, msg: &str) {
+    // `tracing` needs the target and level of each event callsite to be constants
+    macro_rules! event_at_level {
+        ($target:expr) => {
+            match level {
+                native::PICODBG_LOG_LEVEL_ERROR => {
+                    tracing::event!(target: $target, tracing::Level::ERROR, file, line, function = func, "{}", msg)
+                }
+                native::PICODBG_LOG_LEVEL_WARN => {
+                    tracing::event!(target: $target, tracing::Level::WARN, file, line, function = func, "{}", msg)
+                }
+                native::PICODBG_LOG_LEVEL_INFO => {
+                    tracing::event!(target: $target, tracing::Level::INFO, file, line, function = func, "{}", msg)
+                }
+                native::PICODBG_LOG_LEVEL_DEBUG => {
+                    tracing::event!(target: $target, tracing::Level::DEBUG, file, line, function = func, "{}", msg)
+                }
+                _ => tracing::event!(target: $target, tracing::Level::TRACE, file, line, function = func, "{}", msg),
+            }
+        };
+    }
+    macro_rules! event_for_target {
+        (($target:expr) $($module:ident)*) => {
+            match $target {
+                $(concat!("ttspico::pico::", stringify!($module)) => {
+                    event_at_level!(concat!("ttspico::pico::", stringify!($module)))
+                })*
+                _ => event_at_level!("ttspico::pico"),
+            }
+        };
+    }
+    with_pico_modules!(event_for_target!(target))
+}
+
+fn emit(level: c_int, file: &str, line: u32, func: &str, msg: &str) {
+    let target = target_for_file(file);
+    let msg = msg.trim_end();
+    #[cfg(feature = "debug-pico")]
+    emit_log(level, target, file, line, func, msg);
+    #[cfg(feature = "tracing")]
+    emit_tracing(level, target, file, line, func, msg);
+}
+
+unsafe fn to_str<'a>(c_str: *const c_char) -> std::borrow::Cow<'a, str> {
+    if c_str.is_null() {
+        "".into()
+    } else {
+        CStr::from_ptr(c_str).to_string_lossy()
+    }
+}
+
+unsafe extern "C" fn log_callback(
+    level: c_int,
+    donewline: c_int,
+    file: *const c_char,
+    line: c_int,
+    func: *const c_char,
+    msg: *const c_char,
+) {
+    let (file, func, msg) = (to_str(file), to_str(func), to_str(msg));
+    let line = line.max(0) as u32;
+    if donewline != 0 {
+        emit(level, &file, line, &func, &msg);
+        return;
+    }
+
+    // Message fragment: the first one carries the context, the last one ends with a newline
+    PENDING_LINE.with(|pending| {
+        let mut pending = pending.borrow_mut();
+        let pending_line = pending.get_or_insert_with(|| PendingLine {
+            level,
+            file: file.into_owned(),
+            line,
+            func: func.into_owned(),
+            ..Default::default()
+        });
+        pending_line.msg.push_str(&msg);
+        if pending_line.msg.ends_with('\n') {
+            let done = pending.take().unwrap();
+            emit(done.level, &done.file, done.line, &done.func, &done.msg);
+        }
+    });
+}
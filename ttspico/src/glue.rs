@@ -15,56 +15,55 @@
 // limitations under the License.
 
 use crate::PicoError;
-use std::{ffi, mem};
+use alloc::ffi::CString;
+use alloc::format;
+use core::ffi::{c_char, CStr};
+use ttspico_sys as native;
 
-/// A C string of fixed size that can be used to hold strings returned by Pico.
-#[derive(Debug)]
+/// A C string of fixed size that can be used to hold strings returned by Pico.  
+/// Its buffer is stored inline, so no heap allocation is needed.
 pub struct PicoString {
-    mem_layout: std::alloc::Layout,
-    buf: *mut u8,
+    buf: [c_char; native::PICO_RETSTRINGSIZE],
 }
 
 impl PicoString {
-    /// Allocates memory for a [`PicoString`] of a certain size and returns it.
-    pub fn new(size: usize) -> PicoString {
-        let mem_size: usize = mem::size_of::<std::os::raw::c_char>() * size;
-        let mem_layout = std::alloc::Layout::from_size_align(mem_size, 16).unwrap();
-        let buf = unsafe { std::alloc::alloc(mem_layout) };
-        PicoString { mem_layout, buf }
+    /// Returns a new, empty [`PicoString`] able to hold any string returned by Pico.
+    pub fn new() -> PicoString {
+        PicoString {
+            buf: [0; native::PICO_RETSTRINGSIZE],
+        }
     }
 }
 
 impl PicoString {
     /// Returns a readonly pointer to the underlying C string.  
-    /// **Unsafe**: the pointer becomes dangling when `self` is dropped!
-    pub unsafe fn as_ptr(&self) -> *const std::os::raw::c_char {
-        self.buf as *const std::os::raw::c_char
+    /// **Unsafe**: the pointer becomes dangling when `self` is dropped or moved!
+    pub unsafe fn as_ptr(&self) -> *const c_char {
+        self.buf.as_ptr()
     }
 
     /// Returns a read/write pointer to the underlying C string.  
-    /// **Unsafe**: the pointer becomes dangling when `self` is dropped!
-    pub unsafe fn as_mut_ptr(&mut self) -> *mut std::os::raw::c_char {
-        self.buf as *mut std::os::raw::c_char
+    /// **Unsafe**: the pointer becomes dangling when `self` is dropped or moved!
+    pub unsafe fn as_mut_ptr(&mut self) -> *mut c_char {
+        self.buf.as_mut_ptr()
     }
 
     /// Tries to convert the underlying C string to a Rust [`str`].
-    pub fn to_str(&self) -> Result<&str, std::str::Utf8Error> {
-        let c_str = unsafe { ffi::CStr::from_ptr(self.buf as *const std::os::raw::c_char) };
+    pub fn to_str(&self) -> Result<&str, core::str::Utf8Error> {
+        let c_str = unsafe { CStr::from_ptr(self.buf.as_ptr()) };
         c_str.to_str()
     }
 }
 
-impl Drop for PicoString {
-    fn drop(&mut self) {
-        unsafe {
-            std::alloc::dealloc(self.buf, self.mem_layout);
-        }
+impl core::fmt::Debug for PicoString {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("PicoString").field(&self.to_str()).finish()
     }
 }
 
-/// Convert `string` to a [`ffi::CString`]; on failure, returns a `PicoError` with the given description (and code -1).
-pub fn make_cstring(string: impl AsRef<str>, err_descr: &str) -> Result<ffi::CString, PicoError> {
-    ffi::CString::new(string.as_ref()).map_err(|err| PicoError {
+/// Convert `string` to a [`CString`]; on failure, returns a `PicoError` with the given description (and code -1).
+pub fn make_cstring(string: impl AsRef<str>, err_descr: &str) -> Result<CString, PicoError> {
+    CString::new(string.as_ref()).map_err(|err| PicoError {
         code: -1,
         descr: format!("{}: {}", err_descr, err),
     })
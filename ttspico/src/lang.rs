@@ -0,0 +1,201 @@
+//! Discovery of installed language packs.
+
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::multi::find_language;
+use crate::{System, SystemBuilder};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The directory where distributions install Pico's language packs (i.e. Debian's `libttspico-data`).
+pub const SYSTEM_LANG_DIR: &str = "/usr/share/pico/lang";
+
+/// An environment variable that can be set to a directory to search for language packs before
+/// [`SYSTEM_LANG_DIR`].
+pub const LANG_DIR_ENV: &str = "TTSPICO_LANG_DIR";
+
+/// Returns the directories searched for language packs, in order: `$TTSPICO_LANG_DIR` (if set), then
+/// [`SYSTEM_LANG_DIR`].
+pub fn lang_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::with_capacity(2);
+    if let Some(dir) = env::var_os(LANG_DIR_ENV) {
+        dirs.push(PathBuf::from(dir));
+    }
+    dirs.push(PathBuf::from(SYSTEM_LANG_DIR));
+    dirs
+}
+
+/// Returns the directories to search for language packs: `lang_dir` if given, or else the [`lang_dirs`] followed by
+/// the ones bundled with Pico's sources ([`ttspico_sys::BUNDLED_LANG_DIR`]).
+pub fn lang_search_dirs(lang_dir: Option<&Path>) -> Vec<PathBuf> {
+    match lang_dir {
+        Some(lang_dir) => vec![lang_dir.to_path_buf()],
+        None => {
+            let mut dirs = lang_dirs();
+            dirs.push(PathBuf::from(ttspico_sys::BUNDLED_LANG_DIR));
+            dirs
+        }
+    }
+}
+
+/// Finds the language pack for `language` (i.e. `"en-US"`) in the [`lang_dirs`], returning the filepaths
+/// of its TA and SG resources (see [`SystemBuilder::language_pack`](`crate::SystemBuilder::language_pack`)).
+pub fn find_language_pack(language: &str) -> Option<(String, String)> {
+    lang_dirs()
+        .iter()
+        .find_map(|dir| find_language_pack_in(dir, language))
+}
+
+/// Finds the language pack for `language` in `dir`: `<language>_ta.bin` plus the first `<language>_*_sg.bin`.
+pub fn find_language_pack_in(dir: &Path, language: &str) -> Option<(String, String)> {
+    let ta_path = dir.join(format!("{}_ta.bin", language));
+    if !ta_path.is_file() {
+        return None;
+    }
+    let sg_prefix = format!("{}_", language);
+    let mut sg_paths: Vec<PathBuf> = fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(&sg_prefix) && name.ends_with("_sg.bin"))
+        })
+        .collect();
+    sg_paths.sort();
+    let sg_path = sg_paths.into_iter().next()?;
+    Some((
+        ta_path.to_string_lossy().into_owned(),
+        sg_path.to_string_lossy().into_owned(),
+    ))
+}
+
+/// Lists the language packs in `dir` as (language, TA filepath, SG filepath) triples, sorted by language.
+/// A language with several SG resources (i.e. speakers) is listed once for each of them.
+pub fn list_language_packs(dir: &Path) -> Vec<(String, String, String)> {
+    let mut sg_names: Vec<String> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .filter(|name| name.ends_with("_sg.bin"))
+            .collect(),
+        Err(_) => return Vec::new(),
+    };
+    sg_names.sort();
+    sg_names
+        .into_iter()
+        .filter_map(|sg_name| {
+            let language = sg_name.split('_').next()?.to_string();
+            let ta_path = dir.join(format!("{}_ta.bin", language));
+            if !ta_path.is_file() {
+                return None;
+            }
+            Some((
+                language,
+                ta_path.to_string_lossy().into_owned(),
+                dir.join(&sg_name).to_string_lossy().into_owned(),
+            ))
+        })
+        .collect()
+}
+
+/// A voice of a [`VoiceCatalog`], i.e. an installed language pack.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CatalogVoice {
+    /// The language of the voice, i.e. `"en-US"`.
+    pub language: String,
+    /// The speaker of the voice, i.e. `"lh0"` (from the name of its SG resource, `<language>_<speaker>_sg.bin`).
+    pub speaker: String,
+    /// The filepath of the voice's TA resource.
+    pub ta_path: String,
+    /// The filepath of the voice's SG resource.
+    pub sg_path: String,
+}
+
+/// The voices installed in some directories, one per language.
+///
+/// ```
+/// use ttspico::VoiceCatalog;
+///
+/// let catalog = VoiceCatalog::new([ttspico_sys::BUNDLED_LANG_DIR]);
+/// assert_eq!(catalog.find_voice("en_us").unwrap().speaker, "lh0");
+/// assert_eq!(catalog.find_voice("it").unwrap().language, "it-IT");
+/// assert_eq!(catalog.find_voice("xx"), None);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VoiceCatalog {
+    /// Sorted by language.
+    voices: Vec<CatalogVoice>,
+}
+
+impl VoiceCatalog {
+    /// Lists the voices in `dirs` (i.e. the [`lang_dirs`]); the first directory with a language pack for a language
+    /// wins, and so does the first of its speakers (as listed by [`list_language_packs`]).
+    pub fn new(dirs: impl IntoIterator<Item = impl AsRef<Path>>) -> Self {
+        let mut voices: Vec<CatalogVoice> = Vec::new();
+        for dir in dirs {
+            for (language, ta_path, sg_path) in list_language_packs(dir.as_ref()) {
+                if voices.iter().any(|voice| voice.language == language) {
+                    continue;
+                }
+                let speaker = Path::new(&sg_path)
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .and_then(|name| name.strip_suffix("_sg.bin"))
+                    .and_then(|name| name.strip_prefix(language.as_str()))
+                    .map(|speaker| speaker.trim_start_matches('_'))
+                    .unwrap_or("")
+                    .to_string();
+                voices.push(CatalogVoice {
+                    language,
+                    speaker,
+                    ta_path,
+                    sg_path,
+                });
+            }
+        }
+        voices.sort_by(|a, b| a.language.cmp(&b.language));
+        VoiceCatalog { voices }
+    }
+
+    /// Returns the voices, sorted by language.
+    pub fn voices(&self) -> &[CatalogVoice] {
+        &self.voices
+    }
+
+    /// Returns the voice of exactly `language`, as listed (i.e. `"en-US"`).
+    pub fn get(&self, language: &str) -> Option<&CatalogVoice> {
+        self.voices.iter().find(|voice| voice.language == language)
+    }
+
+    /// Returns the voice that speaks `language`: the voice of that language (i.e. `en-GB`, or `en_GB`), else the
+    /// first with a dialect of it (i.e. `en-GB` for `en`), case-insensitive.
+    pub fn find_voice(&self, language: &str) -> Option<&CatalogVoice> {
+        let languages = self.voices.iter().map(|voice| voice.language.as_str());
+        find_language(languages, language).map(|index| &self.voices[index])
+    }
+
+    /// Returns a builder of a [`System`] with enough memory for all the voices (see
+    /// [`SystemBuilder::language_pack`]).
+    pub fn system_builder(&self) -> SystemBuilder {
+        self.voices
+            .iter()
+            .fold(System::builder(), |builder, voice| {
+                builder.language_pack(voice.ta_path.as_str(), voice.sg_path.as_str())
+            })
+    }
+}
@@ -0,0 +1,421 @@
+//! Identification of the language of untagged text, to pick the voices that speak it.
+
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{MultiVoiceDocument, PicoError};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// The corpora bundled with the crate, by language: the same everyday sentences (i.e. notifications) in each of the
+/// languages of Pico's language packs.
+const BUNDLED_CORPORA: &[(&str, &str)] = &[
+    ("de", include_str!("../langid/de.txt")),
+    ("en", include_str!("../langid/en.txt")),
+    ("es", include_str!("../langid/es.txt")),
+    ("fr", include_str!("../langid/fr.txt")),
+    ("it", include_str!("../langid/it.txt")),
+];
+
+/// Length of the longest n-grams (of characters) in a [`LanguageModel`].
+const MAX_N: usize = 3;
+
+/// Default [confidence threshold](`LanguageDetector::threshold`) of a [`LanguageDetector`].
+const DEFAULT_THRESHOLD: f64 = 0.9;
+
+/// A model of the character n-grams (of 1 to 3 characters) of some languages, identifying the language of text with
+/// a naive Bayes classifier.
+///
+/// The model is built from text in each language, on the spot: [`LanguageModel::bundled`] builds it from the corpora
+/// bundled with the crate (for the languages of Pico's language packs), without any data files or network access.
+#[derive(Debug, Clone, Default)]
+pub struct LanguageModel {
+    profiles: Vec<LanguageProfile>,
+    /// The distinct n-grams of each length in all languages.
+    vocabulary: [HashSet<String>; MAX_N],
+}
+
+/// The n-grams of a language in a [`LanguageModel`].
+#[derive(Debug, Clone)]
+struct LanguageProfile {
+    language: String,
+    counts: HashMap<String, u32>,
+    /// The number of n-grams of each length.
+    totals: [u32; MAX_N],
+}
+
+/// A language [identified](`LanguageModel::identify`) for some text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectedLanguage {
+    /// The language, as trained (i.e. `"de"`).
+    pub language: String,
+    /// The probability of the language among the candidates, from 0 to 1.
+    pub confidence: f64,
+}
+
+impl LanguageModel {
+    /// Returns a model without any languages.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a model of the languages of the corpora bundled with the crate: `de`, `en`, `es`, `fr` and `it`.
+    pub fn bundled() -> Self {
+        let mut model = Self::new();
+        for (language, corpus) in BUNDLED_CORPORA {
+            model.train(*language, corpus);
+        }
+        model
+    }
+
+    /// Adds the n-grams of `text` to the profile of `language`, adding the language if the model lacks it.
+    /// A few kilobytes of text per language are enough to tell sentences apart.
+    pub fn train(&mut self, language: impl Into<String>, text: &str) {
+        let language = language.into();
+        let index = match self.profiles.iter().position(|p| p.language == language) {
+            Some(index) => index,
+            None => {
+                self.profiles.push(LanguageProfile {
+                    language,
+                    counts: HashMap::new(),
+                    totals: [0; MAX_N],
+                });
+                self.profiles.len() - 1
+            }
+        };
+        let (profile, vocabulary) = (&mut self.profiles[index], &mut self.vocabulary);
+        for_each_ngram(text, |ngram, n| {
+            let count = profile.counts.entry(ngram.to_string()).or_insert(0);
+            // (Only n-grams new to the language can be new to the model)
+            if *count == 0 && !vocabulary[n - 1].contains(ngram) {
+                vocabulary[n - 1].insert(ngram.to_string());
+            }
+            *count += 1;
+            profile.totals[n - 1] += 1;
+        });
+    }
+
+    /// Returns the languages of the model, in the order they were added.
+    pub fn languages(&self) -> impl Iterator<Item = &str> {
+        self.profiles.iter().map(|p| p.language.as_str())
+    }
+
+    /// Returns the probability of each language of the model (among the ones for which `candidate` returns `true`)
+    /// being the language of `text`, most probable first; empty if `text` has no letters.
+    ///
+    /// Markup tags in the text (i.e. `<break time="1s"/>`) are ignored.
+    pub fn scores(&self, text: &str, candidate: impl Fn(&str) -> bool) -> Vec<DetectedLanguage> {
+        let profiles: Vec<&LanguageProfile> = self
+            .profiles
+            .iter()
+            .filter(|p| candidate(&p.language))
+            .collect();
+        let mut log_probs = vec![0f64; profiles.len()];
+        let mut any = false;
+        for_each_ngram(&strip_tags(text), |ngram, n| {
+            any = true;
+            // (Add-one smoothing, so that n-grams unseen in a language do not rule it out)
+            let vocabulary = self.vocabulary[n - 1].len() as f64 + 1.0;
+            for (log_prob, profile) in log_probs.iter_mut().zip(&profiles) {
+                let count = profile.counts.get(ngram).copied().unwrap_or(0) as f64;
+                *log_prob += ((count + 1.0) / (profile.totals[n - 1] as f64 + vocabulary)).ln();
+            }
+        });
+        if !any || profiles.is_empty() {
+            return Vec::new();
+        }
+        // (Softmax of the log-likelihoods, with equal priors)
+        let max = log_probs.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let sum: f64 = log_probs
+            .iter()
+            .map(|log_prob| (log_prob - max).exp())
+            .sum();
+        let mut scores: Vec<DetectedLanguage> = profiles
+            .iter()
+            .zip(&log_probs)
+            .map(|(profile, log_prob)| DetectedLanguage {
+                language: profile.language.clone(),
+                confidence: (log_prob - max).exp() / sum,
+            })
+            .collect();
+        scores.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+        scores
+    }
+
+    /// Returns the most probable language of `text` among all the languages of the model, if it has letters; see
+    /// [`LanguageModel::scores`].
+    pub fn identify(&self, text: &str) -> Option<DetectedLanguage> {
+        self.scores(text, |_| true).into_iter().next()
+    }
+}
+
+/// Calls `f` with each n-gram (of 1 to [`MAX_N`] characters) of the lowercase words of `text` and its length.
+/// Words are padded with a space on each side, so that n-grams mark their beginnings and ends.
+fn for_each_ngram(text: &str, mut f: impl FnMut(&str, usize)) {
+    let mut word = String::from(" ");
+    let mut chars: Vec<usize> = Vec::new();
+    for c in text.chars().chain(core::iter::once(' ')) {
+        if c.is_alphabetic() {
+            word.extend(c.to_lowercase());
+            continue;
+        }
+        if word.len() == 1 {
+            continue;
+        }
+        word.push(' ');
+        chars.clear();
+        chars.extend(word.char_indices().map(|(i, _)| i));
+        chars.push(word.len());
+        for n in 1..=MAX_N {
+            for start in 0..chars.len().saturating_sub(n) {
+                let ngram = &word[chars[start]..chars[start + n]];
+                if ngram != " " {
+                    f(ngram, n);
+                }
+            }
+        }
+        word.truncate(1);
+    }
+}
+
+/// Returns `text` without its markup tags.
+fn strip_tags(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut in_tag = false;
+    for c in text.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                stripped.push(' ');
+            }
+            c if !in_tag => stripped.push(c),
+            _ => {}
+        }
+    }
+    stripped
+}
+
+/// How a [`LanguageDetector`] splits text, identifying the language of each part.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Granularity {
+    /// Sentences, ending with `.`, `!`, `?` or `…` (followed by whitespace).
+    Sentence,
+    /// Paragraphs, separated by blank lines.
+    Paragraph,
+}
+
+/// Splits untagged text into a [`MultiVoiceDocument`] whose segments are tagged with the language of each sentence
+/// (or paragraph), for a [`MultiVoiceSynthesizer`](`crate::MultiVoiceSynthesizer`) to speak with the voices of
+/// those languages.
+///
+/// Parts whose language is identified with less than the [threshold](`LanguageDetector::threshold`) confidence are
+/// spoken with the [fallback](`LanguageDetector::fallback`) voice. Detected languages can be
+/// [mapped](`LanguageDetector::map_language`) to given voices, and the parts of the text in
+/// `<voice name="...">` elements keep their voice.
+///
+/// ```
+/// # use ttspico::{LanguageDetector, LanguageModel};
+/// let detector = LanguageDetector::new(LanguageModel::bundled()).map_language("en", "en-GB");
+/// let doc = detector.split("Your train is running late. Il treno per Milano è in ritardo.").unwrap();
+/// let voices: Vec<&str> = doc.segments().iter().map(|segment| segment.voice.as_str()).collect();
+/// assert_eq!(voices, ["en-GB", "it"]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct LanguageDetector {
+    model: LanguageModel,
+    /// The languages that can be detected (all of the model's if `None`).
+    candidates: Option<Vec<String>>,
+    /// The voices of detected languages, by language.
+    voices: BTreeMap<String, String>,
+    threshold: f64,
+    fallback: String,
+    granularity: Granularity,
+}
+
+impl LanguageDetector {
+    /// Returns a detector of the languages of `model`, splitting text into sentences, with a confidence threshold of
+    /// 0.9 and the default voice as the fallback.
+    pub fn new(model: LanguageModel) -> Self {
+        LanguageDetector {
+            model,
+            candidates: None,
+            voices: BTreeMap::new(),
+            threshold: DEFAULT_THRESHOLD,
+            fallback: String::new(),
+            granularity: Granularity::Sentence,
+        }
+    }
+
+    /// Only detects the given languages, i.e. the ones of the voices of a
+    /// [`MultiVoiceSynthesizer`](`crate::MultiVoiceSynthesizer::languages`). Languages are matched by their base
+    /// language (`en-GB` matches `en`).
+    pub fn candidates(mut self, languages: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        self.candidates = Some(
+            languages
+                .into_iter()
+                .map(|language| base_language(language.as_ref()).to_ascii_lowercase())
+                .collect(),
+        );
+        self
+    }
+
+    /// Sets the confidence (from 0 to 1) below which the language of a part of the text is not trusted, and the
+    /// fallback voice speaks it instead.
+    pub fn threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Sets the voice (name or language) speaking the parts whose language is not detected; the default voice
+    /// (`""`) by default.
+    pub fn fallback(mut self, voice: impl Into<String>) -> Self {
+        self.fallback = voice.into();
+        self
+    }
+
+    /// Sets how the text is split.
+    pub fn granularity(mut self, granularity: Granularity) -> Self {
+        self.granularity = granularity;
+        self
+    }
+
+    /// Speaks the parts detected as `language` with `voice` (name or language, i.e. `en-GB` for `en`) instead of
+    /// the voice of the detected language itself.
+    pub fn map_language(mut self, language: impl Into<String>, voice: impl Into<String>) -> Self {
+        self.voices.insert(language.into(), voice.into());
+        self
+    }
+
+    /// Returns the most probable language of `text` among the candidates, whatever its confidence; see
+    /// [`LanguageModel::scores`].
+    pub fn detect(&self, text: &str) -> Option<DetectedLanguage> {
+        let candidates = self.candidates.as_ref();
+        self.model
+            .scores(text, |language| match candidates {
+                Some(candidates) => candidates
+                    .iter()
+                    .any(|c| c.eq_ignore_ascii_case(base_language(language))),
+                None => true,
+            })
+            .into_iter()
+            .next()
+    }
+
+    /// Returns the voice speaking `text`: the one [mapped](`LanguageDetector::map_language`) to its language, else
+    /// the language itself, or the fallback voice if its language is not detected confidently.
+    /// Returns `None` if `text` has no letters.
+    pub fn voice_for(&self, text: &str) -> Option<String> {
+        let detected = self.detect(text)?;
+        if detected.confidence < self.threshold {
+            return Some(self.fallback.clone());
+        }
+        Some(match self.voices.get(&detected.language) {
+            Some(voice) => voice.clone(),
+            None => detected.language,
+        })
+    }
+
+    /// Splits `text` into parts (see [`Granularity`]) tagged with the voice of their language (see
+    /// [`LanguageDetector::voice_for`]), merging consecutive parts with the same voice. Parts without letters are
+    /// spoken with the voice of the previous part.
+    ///
+    /// The `<voice name="...">` elements of the text are kept as they are (see [`MultiVoiceDocument::parse`]).
+    pub fn split(&self, text: &str) -> Result<MultiVoiceDocument, PicoError> {
+        let mut doc = MultiVoiceDocument::new();
+        let mut last: Option<(String, String)> = None;
+        for segment in MultiVoiceDocument::parse(text)?.segments() {
+            if !segment.voice.is_empty() {
+                if let Some((voice, text)) = last.take() {
+                    doc.push(voice, text);
+                }
+                doc.push(segment.voice.clone(), segment.text.clone());
+                continue;
+            }
+            for part in split_parts(&segment.text, self.granularity) {
+                let voice = self.voice_for(part);
+                match (&mut last, voice) {
+                    (Some((last_voice, last_text)), voice)
+                        if voice.is_none() || voice.as_ref() == Some(last_voice) =>
+                    {
+                        last_text.push_str(part);
+                    }
+                    (last, voice) => {
+                        if let Some((voice, text)) = last.take() {
+                            doc.push(voice, text);
+                        }
+                        let voice = voice.unwrap_or_else(|| self.fallback.clone());
+                        *last = Some((voice, part.to_string()));
+                    }
+                }
+            }
+        }
+        if let Some((voice, text)) = last {
+            doc.push(voice, text);
+        }
+        Ok(doc)
+    }
+}
+
+/// Returns the base language of `language` (i.e. `en` for `en-GB` or `en_GB`).
+fn base_language(language: &str) -> &str {
+    language.split(['-', '_']).next().unwrap_or("")
+}
+
+/// Splits `text` into sentences or paragraphs, each with the whitespace that follows it.
+fn split_parts(text: &str, granularity: Granularity) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut in_tag = false;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let ends_part = match c {
+            '<' => {
+                in_tag = true;
+                false
+            }
+            '>' => {
+                in_tag = false;
+                false
+            }
+            '.' | '!' | '?' | '…' => granularity == Granularity::Sentence && !in_tag,
+            '\n' => {
+                granularity == Granularity::Paragraph && {
+                    // (A blank line: only whitespace up to the next newline)
+                    let rest = &text[i + 1..];
+                    rest.find('\n')
+                        .is_some_and(|end| rest[..end].trim().is_empty())
+                }
+            }
+            _ => false,
+        };
+        if !ends_part || chars.peek().is_some_and(|&(_, next)| !next.is_whitespace()) {
+            continue;
+        }
+        // (The part ends after the whitespace that follows it)
+        let mut end = text.len();
+        while let Some(&(j, next)) = chars.peek() {
+            if !next.is_whitespace() {
+                end = j;
+                break;
+            }
+            chars.next();
+        }
+        parts.push(&text[start..end]);
+        start = end;
+    }
+    if start < text.len() {
+        parts.push(&text[start..]);
+    }
+    parts
+}
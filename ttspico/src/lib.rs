@@ -16,13 +16,82 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+mod builder;
+pub use builder::SystemBuilder;
+
+#[cfg(any(feature = "debug-pico", feature = "tracing"))]
+mod debug;
+#[cfg(any(feature = "debug-pico", feature = "tracing"))]
+pub use debug::LOG_TARGET;
+
 mod glue;
+
+#[cfg(feature = "std")]
+mod lang;
+
+mod mock;
+pub use mock::{MockEngine, MockOperation};
+
+#[cfg(feature = "langid")]
+mod langid;
+#[cfg(feature = "langid")]
+pub use langid::{DetectedLanguage, Granularity, LanguageDetector, LanguageModel};
+
+mod multi;
+pub use multi::{MultiVoiceDocument, MultiVoiceSynthesizer, VoiceSegment};
+
+#[cfg(feature = "std")]
+mod pool;
+#[cfg(feature = "std")]
+pub use pool::{spawn_synthesis_thread, SynthesizerPool};
+
+#[cfg(feature = "std")]
+mod ssml;
+#[cfg(feature = "std")]
+pub use ssml::ssml_to_markup;
+
+#[cfg(not(feature = "system"))]
+mod policy;
+#[cfg(not(feature = "system"))]
+pub use policy::FilePolicy;
+
+mod wav;
+pub use wav::{wav_header, WAV_HEADER_LEN};
+
+mod words;
+pub use words::{mark_words, word_index, Word};
+
+#[cfg(not(feature = "system"))]
+mod vfs;
+#[cfg(all(feature = "std", not(feature = "system")))]
+pub use vfs::MemoryFileSystem;
+#[cfg(not(feature = "system"))]
+pub use vfs::{PicoFile, PicoFileSystem};
+
+#[cfg(not(feature = "system"))]
+use alloc::boxed::Box;
+use alloc::ffi::CString;
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::fmt;
 use glue::{make_cstring, PicoString};
-use std::cell::RefCell;
-use std::rc::Rc;
-use std::{ffi, fmt};
+#[cfg(feature = "std")]
+pub use lang::{
+    find_language_pack, find_language_pack_in, lang_dirs, lang_search_dirs, list_language_packs,
+    CatalogVoice, VoiceCatalog, LANG_DIR_ENV, SYSTEM_LANG_DIR,
+};
 use ttspico_sys as native;
 
+/// Sample rate of Pico's output (16-bit signed PCM, mono), in Hz.
+pub const SAMPLE_RATE: u32 = 16000;
+
 /// An error caused by Pico TTS.
 #[derive(Debug, PartialEq, Eq)]
 pub struct PicoError {
@@ -40,12 +109,63 @@ impl fmt::Display for PicoError {
     }
 }
 
+/// Memory usage statistics of a [`System`] or [`Engine`], in bytes.
+/// # See
+/// [`ttspico_sys::picoext_getSystemMemUsage`], [`ttspico_sys::picoext_getEngineMemUsage`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryUsage {
+    /// Number of bytes currently in use.
+    pub used: usize,
+
+    /// Change in the number of bytes in use since the incremental counter was last reset.
+    pub incremental: isize,
+
+    /// Peak number of bytes that were in use at once.
+    pub max_used: usize,
+}
+
+impl MemoryUsage {
+    fn from_raw(
+        used: native::pico_Int32,
+        incremental: native::pico_Int32,
+        max_used: native::pico_Int32,
+    ) -> Self {
+        MemoryUsage {
+            used: used as usize,
+            incremental: incremental as isize,
+            max_used: max_used as usize,
+        }
+    }
+}
+
+/// The amount of diagnostic output Pico should trace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(i32)]
+pub enum TraceLevel {
+    /// No tracing at all.
+    Off = 0,
+    Error = native::PICODBG_LOG_LEVEL_ERROR,
+    Warn = native::PICODBG_LOG_LEVEL_WARN,
+    Info = native::PICODBG_LOG_LEVEL_INFO,
+    Debug = native::PICODBG_LOG_LEVEL_DEBUG,
+    Trace = native::PICODBG_LOG_LEVEL_TRACE,
+}
+
+/// Maximum length in bytes of a trace filter (see `MAX_FILTERFN_LEN` in `picodbg.c`).
+const TRACE_FILTER_MAX_LEN: usize = 16;
+
 /// A Pico TTS system, i.e. the context from which to load [`Resource`]s and create [`Voice`]s.
 #[derive(Debug)]
 pub struct System {
     c_sys: native::pico_System,
     mem: *mut u8,
-    mem_layout: std::alloc::Layout,
+    mem_size: usize,
+    /// Set if `mem` was allocated by [`System::new`] (and is to be freed on drop), unset if caller-provided.
+    mem_layout: Option<alloc::alloc::Layout>,
+    /// The file systems [set](`System::set_file_system`) on the system, the last of which is in use.
+    /// (Files opened through the others may still be open, so they are only dropped with the system)
+    #[cfg(not(feature = "system"))]
+    fs_hooks: Vec<vfs::FileSystemHook>,
 }
 
 impl System {
@@ -55,7 +175,7 @@ impl System {
         if code == native::PICO_OK {
             Ok(())
         } else {
-            let mut c_str = PicoString::new(native::PICO_RETSTRINGSIZE);
+            let mut c_str = PicoString::new();
             native::pico_getSystemStatusMessage(self.c_sys, code, c_str.as_mut_ptr());
             Err(PicoError {
                 code,
@@ -67,62 +187,316 @@ impl System {
         }
     }
 
+    /// Turns a [`PICO_EXC_OUT_OF_MEM`](`ttspico_sys::PICO_EXC_OUT_OF_MEM`) `err` raised while allocating about
+    /// `needed` bytes for `what` into one naming how many bytes the memory arena is short of.
+    /// Other errors (or any error, if `needed` is unknown) are returned unchanged.
+    fn out_of_mem_error(&self, err: PicoError, what: &str, needed: Option<usize>) -> PicoError {
+        let needed = match needed {
+            Some(needed) => needed,
+            None => return err,
+        };
+        if err.code != native::PICO_EXC_OUT_OF_MEM {
+            return err;
+        }
+        let free = match self.memory_usage(false) {
+            Ok(usage) => self.mem_size.saturating_sub(usage.used),
+            Err(_) => 0,
+        };
+        PicoError {
+            code: err.code,
+            descr: format!(
+                "{}: {} needs about {} bytes, but only about {} of the {} bytes of memory are free \
+                 (about {} bytes short; see `System::builder()` to size memory automatically)",
+                err.descr,
+                what,
+                needed,
+                free,
+                self.mem_size,
+                needed.saturating_sub(free),
+            ),
+        }
+    }
+
+    /// Returns a builder for a [`System`], which can size its memory automatically.
+    pub fn builder() -> SystemBuilder {
+        SystemBuilder::new()
+    }
+
     /// Instantiates a Pico [`System`], given the size in bytes of the memory to allocate for it.
     /// # Remarks
     /// Only one [`System`] should be instantiated per thread!
+    ///
+    /// With the `debug-pico` feature, Pico's memory protection checks are enabled and its diagnostic output is
+    /// forwarded to the [`log`](https://docs.rs/log) crate (with targets under [`LOG_TARGET`]).
+    /// With the `tracing` feature, the same output is emitted as [`tracing`](https://docs.rs/tracing) events.
     /// # See
-    /// [`ttspico_sys::pico_initialize`].
+    /// [`ttspico_sys::picoext_initialize`], [`System::builder`].
     pub fn new(memsz: usize) -> Result<Rc<RefCell<System>>, PicoError> {
+        let mem_layout = alloc::alloc::Layout::from_size_align(memsz, 16).unwrap();
         unsafe {
-            let mem_layout = std::alloc::Layout::from_size_align(memsz, 16).unwrap();
-            let mut ret = System {
-                c_sys: std::ptr::null_mut(),
-                mem: std::alloc::alloc(mem_layout),
-                mem_layout,
-            };
-            let c_code = native::pico_initialize(
-                ret.mem as *mut std::os::raw::c_void,
-                ret.mem_layout.size() as native::pico_Uint32,
-                &mut ret.c_sys,
-            );
-            match ret.get_error(c_code) {
-                Ok(_) => Ok(Rc::new(RefCell::new(ret))),
-                Err(err) => Err(err),
-            }
+            System::init(System {
+                c_sys: core::ptr::null_mut(),
+                mem: alloc::alloc::alloc(mem_layout),
+                mem_size: memsz,
+                mem_layout: Some(mem_layout),
+                #[cfg(not(feature = "system"))]
+                fs_hooks: Vec::new(),
+            })
+        }
+    }
+
+    /// Instantiates a Pico [`System`] that uses the caller-provided `mem` as its memory, without allocating
+    /// any memory of its own.
+    /// # Remarks
+    /// Only one [`System`] should be instantiated per thread!
+    /// # See
+    /// [`ttspico_sys::pico_initialize`].
+    pub fn with_memory(mem: &'static mut [u8]) -> Result<Rc<RefCell<System>>, PicoError> {
+        // (Align the start of the memory to 16 bytes, like `System::new` does)
+        let align_offset = core::cmp::min(mem.as_ptr().align_offset(16), mem.len());
+        let mem = &mut mem[align_offset..];
+        unsafe {
+            System::init(System {
+                c_sys: core::ptr::null_mut(),
+                mem: mem.as_mut_ptr(),
+                mem_size: mem.len(),
+                mem_layout: None,
+                #[cfg(not(feature = "system"))]
+                fs_hooks: Vec::new(),
+            })
+        }
+    }
+
+    /// Initializes Pico for `sys`, whose `mem` and `mem_size` are set but `c_sys` is not.
+    unsafe fn init(mut sys: System) -> Result<Rc<RefCell<System>>, PicoError> {
+        #[cfg(any(feature = "debug-pico", feature = "tracing"))]
+        debug::install();
+        let c_code = native::picoext_initialize(
+            sys.mem as *mut core::ffi::c_void,
+            sys.mem_size as native::pico_Uint32,
+            cfg!(feature = "debug-pico") as native::pico_Int16, // (memory protection)
+            &mut sys.c_sys,
+        );
+        sys.get_error(c_code)?;
+
+        // (Pico's trace level and filter are global, so that later systems must not reset them)
+        #[cfg(any(feature = "debug-pico", feature = "tracing"))]
+        if debug::is_first_system() {
+            sys.set_trace(debug::max_trace_level(), None)?;
+        }
+
+        Ok(Rc::new(RefCell::new(sys)))
+    }
+
+    /// Returns the version information string of the Pico library.  
+    /// (The vendored Pico sources do not track their version and always report `"invalid"`.)
+    /// # See
+    /// [`ttspico_sys::picoext_getVersionInfo`].
+    pub fn version() -> Result<String, PicoError> {
+        let mut c_str = PicoString::new();
+        let c_code = unsafe {
+            native::picoext_getVersionInfo(
+                c_str.as_mut_ptr(),
+                native::PICO_RETSTRINGSIZE as native::pico_Int16,
+            )
+        };
+        if c_code != native::PICO_OK {
+            return Err(PicoError {
+                code: c_code,
+                descr: "Failed to get Pico version info".to_string(),
+            });
+        }
+        c_str.to_str().map(str::to_string).map_err(|err| PicoError {
+            code: -1,
+            descr: format!("Invalid Pico version info: {}", err),
+        })
+    }
+
+    /// Sets the amount of diagnostic output traced by Pico, optionally limiting it to the output of a single
+    /// source file (i.e. `Some("picotok.c")`; at most 16 bytes long).
+    /// With the `debug-pico` or `tracing` features, the filter can also be given as the target of the messages
+    /// to keep (i.e. `Some("ttspico::pico::picotok")`).
+    ///
+    /// Pico's trace level and filter are global to the process: they apply to the output of every system, on every
+    /// thread, and are kept when systems are created or dropped. (The first system sets the level to that of the
+    /// messages that `log` and/or `tracing` would output, and no filter.)
+    ///
+    /// Tracing output is only generated if Pico was compiled with `PICO_DEBUG`; otherwise this is a no-op.
+    /// # See
+    /// [`ttspico_sys::picoext_setTraceLevel`], [`ttspico_sys::picoext_setTraceFilterFN`].
+    pub fn set_trace(&self, level: TraceLevel, filter: Option<&str>) -> Result<(), PicoError> {
+        #[cfg(any(feature = "debug-pico", feature = "tracing"))]
+        let filter_file = filter.and_then(debug::file_for_target);
+        #[cfg(any(feature = "debug-pico", feature = "tracing"))]
+        let filter = filter_file.as_deref().or(filter);
+        let filter = filter.unwrap_or("");
+        if filter.len() > TRACE_FILTER_MAX_LEN {
+            return Err(PicoError {
+                code: -1,
+                descr: format!(
+                    "Invalid trace filter: longer than {} bytes",
+                    TRACE_FILTER_MAX_LEN
+                ),
+            });
+        }
+        let c_filter = make_cstring(filter, "Invalid trace filter")?;
+        unsafe {
+            self.get_error(native::picoext_setTraceLevel(
+                self.c_sys,
+                level as native::pico_Int32,
+            ))?;
+            self.get_error(native::picoext_setTraceFilterFN(
+                self.c_sys,
+                c_filter.as_ptr() as *const native::pico_Char,
+            ))
+        }
+    }
+
+    /// Returns the memory usage statistics of the system (including any resources and engines it holds).
+    /// If `reset_incremental` is set, the incremental counter is reset after reading it.
+    /// # See
+    /// [`ttspico_sys::picoext_getSystemMemUsage`].
+    pub fn memory_usage(&self, reset_incremental: bool) -> Result<MemoryUsage, PicoError> {
+        let (mut used, mut incremental, mut max_used) = (0, 0, 0);
+        unsafe {
+            self.get_error(native::picoext_getSystemMemUsage(
+                self.c_sys,
+                reset_incremental as native::pico_Int16,
+                &mut used,
+                &mut incremental,
+                &mut max_used,
+            ))?;
         }
+        Ok(MemoryUsage::from_raw(used, incremental, max_used))
+    }
+
+    /// Returns the size in bytes of the memory allocated for the system.
+    pub fn memory_size(&self) -> usize {
+        self.mem_size
     }
 
     /// Creates a Pico [`Resource`] given its filepath.
+    /// Fails with [`PICO_WARN_RESOURCE_DOUBLE_LOAD`](`ttspico_sys::PICO_WARN_RESOURCE_DOUBLE_LOAD`) if a resource
+    /// with the same name is already loaded.
     /// # See
     /// [`ttspico_sys::pico_loadResource`], [`ttspico_sys::pico_getResourceName`].
     pub fn load_resource(
         sys: Rc<RefCell<Self>>,
         path: impl AsRef<str>,
     ) -> Result<Rc<RefCell<Resource>>, PicoError> {
-        let c_path = make_cstring(path, "Invalid resource name")?;
+        let c_path = make_cstring(path.as_ref(), "Invalid resource name")?;
         unsafe {
-            let mut c_res = std::ptr::null_mut::<native::pico_resource>();
-            sys.borrow().get_error(native::pico_loadResource(
-                sys.borrow().c_sys,
-                c_path.as_ptr() as *const native::pico_Char,
-                &mut c_res,
-            ))?;
+            let mut c_res = core::ptr::null_mut::<native::pico_resource>();
+            sys.borrow()
+                .get_error(native::pico_loadResource(
+                    sys.borrow().c_sys,
+                    c_path.as_ptr() as *const native::pico_Char,
+                    &mut c_res,
+                ))
+                .map_err(|err| {
+                    #[cfg(feature = "std")]
+                    let needed = std::fs::metadata(path.as_ref())
+                        .ok()
+                        .map(|md| md.len() as usize);
+                    #[cfg(not(feature = "std"))]
+                    let needed = None;
+                    let what = format!("resource \"{}\"", path.as_ref());
+                    sys.borrow().out_of_mem_error(err, &what, needed)
+                })?;
+            System::loaded_resource(sys, c_res)
+        }
+    }
 
-            let mut c_name = PicoString::new(native::PICO_MAX_RESOURCE_NAME_SIZE);
-            sys.borrow().get_error(native::pico_getResourceName(
-                sys.borrow().c_sys,
-                c_res,
-                c_name.as_mut_ptr(),
-            ))?;
+    /// Creates a Pico [`Resource`] from the contents of a resource file.
+    /// `name` is used in place of the resource's filepath and must end in `.bin`.
+    ///
+    /// The contents are copied into the system's memory, so `bytes` does not need to outlive this call; this
+    /// allows loading resources embedded in the binary (e.g. via `include_bytes!`) without any file system access.
+    /// # See
+    /// [`ttspico_sys::picoext_loadResourceFromMemory`], [`System::load_resource`].
+    #[cfg(not(feature = "system"))]
+    pub fn load_resource_from_bytes(
+        sys: Rc<RefCell<Self>>,
+        name: impl AsRef<str>,
+        bytes: &[u8],
+    ) -> Result<Rc<RefCell<Resource>>, PicoError> {
+        let c_name = make_cstring(name.as_ref(), "Invalid resource name")?;
+        unsafe {
+            let mut c_res = core::ptr::null_mut::<native::pico_resource>();
+            sys.borrow()
+                .get_error(native::picoext_loadResourceFromMemory(
+                    sys.borrow().c_sys,
+                    c_name.as_ptr() as *const native::pico_Char,
+                    bytes.as_ptr() as *const core::ffi::c_void,
+                    bytes.len() as native::pico_Uint32,
+                    &mut c_res,
+                ))
+                .map_err(|err| {
+                    let what = format!("resource \"{}\"", name.as_ref());
+                    sys.borrow().out_of_mem_error(err, &what, Some(bytes.len()))
+                })?;
+            System::loaded_resource(sys, c_res)
+        }
+    }
+
+    /// Sets the [`PicoFileSystem`] through which the system and its engine open files from now on: resources loaded
+    /// with [`System::load_resource`], audio files inserted with `<play>` and files written by `<genfile>`.
+    /// `None` restores the real file system.
+    ///
+    /// Files that are already open (i.e. the resources loaded before) keep using the file system they were opened
+    /// with, which is only dropped with the system. Resources loaded with [`System::load_resource_from_bytes`] do not
+    /// go through any file system.
+    /// # See
+    /// [`ttspico_sys::picoext_setFileSystem`].
+    #[cfg(not(feature = "system"))]
+    pub fn set_file_system(
+        &mut self,
+        fs: Option<Box<dyn PicoFileSystem>>,
+    ) -> Result<(), PicoError> {
+        let hook = fs.map(vfs::FileSystemHook::new);
+        let c_fs = hook
+            .as_ref()
+            .map_or(core::ptr::null(), |hook| hook.as_ptr());
+        unsafe {
+            self.get_error(native::picoext_setFileSystem(self.c_sys, c_fs))?;
+        }
+        self.fs_hooks.extend(hook);
+        Ok(())
+    }
 
-            Ok(Rc::new(RefCell::new(Resource { sys, c_res, c_name })))
+    /// Wraps `c_res`, freshly loaded by `sys`, in a [`Resource`].
+    unsafe fn loaded_resource(
+        sys: Rc<RefCell<Self>>,
+        c_res: native::pico_Resource,
+    ) -> Result<Rc<RefCell<Resource>>, PicoError> {
+        if c_res.is_null() {
+            // (Pico only raises a warning, loading nothing, if a resource with the same name is already loaded)
+            let mut code = native::PICO_WARN_RESOURCE_DOUBLE_LOAD;
+            let mut c_msg = PicoString::new();
+            native::pico_getSystemWarning(sys.borrow().c_sys, 0, &mut code, c_msg.as_mut_ptr());
+            return Err(PicoError {
+                code,
+                descr: match c_msg.to_str() {
+                    Ok(pico_msg) => pico_msg.to_string(),
+                    Err(utf8_err) => format!("[invalid Pico message: {}]", utf8_err),
+                },
+            });
         }
+
+        let mut c_name = PicoString::new();
+        sys.borrow().get_error(native::pico_getResourceName(
+            sys.borrow().c_sys,
+            c_res,
+            c_name.as_mut_ptr(),
+        ))?;
+
+        Ok(Rc::new(RefCell::new(Resource { sys, c_res, c_name })))
     }
 
     /// Creates a Pico [`Voice`] given its name.
     /// # See
     /// [`ttspico_sys::pico_createVoiceDefinition`].
-    pub fn create_voice<'a>(
+    pub fn create_voice(
         sys: Rc<RefCell<Self>>,
         name: impl AsRef<str>,
     ) -> Result<Rc<RefCell<Voice>>, PicoError> {
@@ -147,10 +521,10 @@ impl Drop for System {
             if !self.c_sys.is_null() {
                 native::pico_terminate(&mut self.c_sys);
             }
-            if !self.mem.is_null() {
-                std::alloc::dealloc(self.mem, self.mem_layout);
-                self.mem = std::ptr::null_mut();
+            if let (false, Some(mem_layout)) = (self.mem.is_null(), self.mem_layout) {
+                alloc::alloc::dealloc(self.mem, mem_layout);
             }
+            self.mem = core::ptr::null_mut();
         }
     }
 }
@@ -181,7 +555,7 @@ impl Resource {
     }
 
     /// Returns the resource's internal name (if it can be converted to UTF-8).
-    pub fn name(&self) -> Result<&str, std::str::Utf8Error> {
+    pub fn name(&self) -> Result<&str, core::str::Utf8Error> {
         self.c_name.to_str()
     }
 }
@@ -210,7 +584,7 @@ unsafe impl Send for Resource {}
 #[derive(Debug)]
 pub struct Voice {
     sys: Rc<RefCell<System>>,
-    c_name: ffi::CString,
+    c_name: CString,
     resources: Vec<Rc<RefCell<Resource>>>,
 }
 
@@ -221,7 +595,7 @@ impl Voice {
     }
 
     /// Returns the voice's name (if it can be converted to UTF-8).
-    pub fn name(&self) -> Result<&str, std::str::Utf8Error> {
+    pub fn name(&self) -> Result<&str, core::str::Utf8Error> {
         self.c_name.to_str()
     }
 
@@ -249,23 +623,43 @@ impl Voice {
     }
 
     /// Creates a Pico [`Engine`] for this voice.
-    /// # Unsafe
+    /// # Safety
     /// Both a TA and a SG [`Resource`] need to be loaded and [added](`Voice::add_resource`) to a voice before
     /// creating an engine. Failing to do so could result in a segmentation fault!
     /// # See
     /// [`ttspico_sys::pico_newEngine`].
     pub unsafe fn create_engine(voice: Rc<RefCell<Voice>>) -> Result<Engine, PicoError> {
-        let mut c_engine = std::ptr::null_mut::<native::pico_engine>();
-        voice
-            .borrow()
-            .sys
-            .borrow()
-            .get_error(native::pico_newEngine(
-                voice.borrow().sys.borrow().c_sys,
-                voice.borrow().c_name.as_ptr() as *const native::pico_Char,
+        let mut c_engine = core::ptr::null_mut::<native::pico_engine>();
+        {
+            let voice = voice.borrow();
+            let sys = voice.sys.borrow();
+            sys.get_error(native::pico_newEngine(
+                sys.c_sys,
+                voice.c_name.as_ptr() as *const native::pico_Char,
                 &mut c_engine,
-            ))?;
-        Ok(Engine { voice, c_engine })
+            ))
+            .map_err(|err| {
+                sys.out_of_mem_error(
+                    err,
+                    "the engine",
+                    Some(native::PICOCTRL_DEFAULT_ENGINE_SIZE),
+                )
+            })?;
+        }
+        let engine = Engine {
+            voice,
+            c_engine,
+            n_samples: 0,
+            marks: Vec::new(),
+            #[cfg(not(feature = "system"))]
+            file_filter: policy::FileFilter::new(FilePolicy::deny_all()),
+        };
+        #[cfg(not(feature = "system"))]
+        engine.get_error(native::picoext_setEngineFileFilter(
+            engine.c_engine,
+            engine.file_filter.as_ptr(),
+        ))?;
+        Ok(engine)
     }
 }
 
@@ -295,10 +689,28 @@ unsafe impl Send for Voice {}
 pub struct Engine {
     voice: Rc<RefCell<Voice>>,
     c_engine: native::pico_Engine,
+    /// Number of samples output since the engine was created or reset.
+    n_samples: usize,
+    /// The marks reached since the last call to [`Engine::take_marks`].
+    marks: Vec<Mark>,
+    /// The file policy [set](`Engine::set_file_policy`) on the engine, and the clips
+    /// [registered](`Engine::register_clip`) on it.
+    #[cfg(not(feature = "system"))]
+    file_filter: policy::FileFilter,
+}
+
+/// A `<mark>` in the text, reached by an [`Engine`]'s speech.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mark {
+    /// The mark's `name`.
+    pub name: String,
+    /// Position of the mark in the speech, as the number of samples output before it (since the engine was created
+    /// or [reset](`Engine::reset`)).
+    pub sample: usize,
 }
 
 /// An [`Engine`]'s status after [stepping](`Engine::get_data`) it.
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(i32)]
 pub enum EngineStatus {
     /// Idle: no more speech audio to be generated.
@@ -310,7 +722,7 @@ pub enum EngineStatus {
 }
 
 /// The ways an [`Engine`]' can be reset.
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u32)]
 pub enum EngineResetMode {
     /// Full reset: to be used after an [engine error](`PicoError`) is raised.
@@ -320,6 +732,66 @@ pub enum EngineResetMode {
     Soft = native::PICO_RESET_SOFT,
 }
 
+/// The operations of a text-to-speech engine, implemented by [`Engine`] and by [`MockEngine`].
+///
+/// Code written against this trait (instead of [`Engine`]) can be tested with a [`MockEngine`], without Pico or
+/// any language files.
+pub trait SpeechEngine {
+    /// Puts UTF-8 text to be spoken into the engine; returns the number of bytes of `utf8_text` that were put.
+    /// # See
+    /// [`Engine::put_text`].
+    fn put_text(&mut self, utf8_text: &[u8]) -> Result<usize, PicoError>;
+
+    /// Flushes the engine, forcing speech generation.
+    /// # See
+    /// [`Engine::flush`].
+    fn flush(&mut self) -> Result<usize, PicoError> {
+        self.put_text(b"\0")
+    }
+
+    /// Generates speech audio (16-bit signed PCM at 16kHz) into `buf`; returns the number of samples generated
+    /// and the engine's status afterwards.
+    /// # See
+    /// [`Engine::get_data`].
+    fn get_data(&mut self, buf: &mut [i16]) -> Result<(usize, EngineStatus), PicoError>;
+
+    /// Resets the engine according to `mode`.
+    /// # See
+    /// [`Engine::reset`].
+    fn reset(&mut self, mode: EngineResetMode) -> Result<(), PicoError>;
+
+    /// Steps the speaking of `input`: puts as much of it into the engine as it takes (advancing `input` past that,
+    /// and flushing the engine once all of it is in), then generates speech audio into `buf`. Returns the number of
+    /// samples generated and whether all of `input` has been spoken.
+    ///
+    /// ```
+    /// use ttspico::{MockEngine, SpeechEngine};
+    ///
+    /// let mut engine = MockEngine::new().samples_per_byte(10);
+    /// let (mut input, mut buf) = (&b"Hello"[..], [0i16; 32]);
+    /// let mut samples = Vec::new();
+    /// loop {
+    ///     let (n_samples, done) = engine.step(&mut input, &mut buf).unwrap();
+    ///     samples.extend_from_slice(&buf[..n_samples]);
+    ///     if done {
+    ///         break;
+    ///     }
+    /// }
+    /// assert_eq!(samples.len(), 50);
+    /// ```
+    fn step(&mut self, input: &mut &[u8], buf: &mut [i16]) -> Result<(usize, bool), PicoError> {
+        if !input.is_empty() {
+            let n_put = self.put_text(input)?;
+            *input = &input[n_put..];
+            if input.is_empty() {
+                self.flush()?;
+            }
+        }
+        let (n_samples, status) = self.get_data(buf)?;
+        Ok((n_samples, input.is_empty() && status == EngineStatus::Idle))
+    }
+}
+
 impl Engine {
     /// Converts a Pico engine-level error `code` to a `Err(PicoError)` if code is not
     /// [`PICO_OK`](`ttspico_sys::PICO_OK`), else returns `Ok(())`.
@@ -327,7 +799,7 @@ impl Engine {
         if code == native::PICO_OK {
             Ok(())
         } else {
-            let mut c_str = PicoString::new(native::PICO_RETSTRINGSIZE);
+            let mut c_str = PicoString::new();
             native::pico_getEngineStatusMessage(self.c_engine, code, c_str.as_mut_ptr());
             Err(PicoError {
                 code,
@@ -346,7 +818,7 @@ impl Engine {
     /// # See
     /// [`ttspico_sys::pico_putTextUtf8`].
     pub fn put_text(&mut self, utf8_text: impl AsRef<[u8]>) -> Result<usize, PicoError> {
-        let buf_size = std::cmp::min(utf8_text.as_ref().len(), native::PICO_INT16_MAX as usize);
+        let buf_size = core::cmp::min(utf8_text.as_ref().len(), native::PICO_INT16_MAX as usize);
         let mut bytes_put: i16 = 0;
         unsafe {
             self.get_error(native::pico_putTextUtf8(
@@ -365,10 +837,22 @@ impl Engine {
         self.put_text(b"\0")
     }
 
+    /// Steps the speaking of `input`, returning the number of samples generated into `buf` and whether all of
+    /// `input` has been spoken.
+    /// # See
+    /// [`SpeechEngine::step`].
+    pub fn step(&mut self, input: &mut &[u8], buf: &mut [i16]) -> Result<(usize, bool), PicoError> {
+        SpeechEngine::step(self, input, buf)
+    }
+
     /// Resets the TTS engine according to [`mode`](`EngineResetMode`).
     /// # See
     /// [`ttspico_sys::pico_resetEngine`].
     pub fn reset(&mut self, mode: EngineResetMode) -> Result<(), PicoError> {
+        self.n_samples = 0;
+        self.marks.clear();
+        #[cfg(not(feature = "system"))]
+        self.file_filter.take_violation();
         unsafe {
             self.get_error(native::pico_resetEngine(
                 self.c_engine,
@@ -377,22 +861,134 @@ impl Engine {
         }
     }
 
+    /// Returns the `<mark>`s (i.e. `<mark name="here"/>` in the text) that the speech output by
+    /// [`get_data`](`Engine::get_data`) reached since the last call, in order.
+    ///
+    /// Always empty with the `system` feature, as the system-installed Pico does not report marks.
+    /// # See
+    /// [`ttspico_sys::picoext_getMarker`].
+    pub fn take_marks(&mut self) -> Vec<Mark> {
+        core::mem::take(&mut self.marks)
+    }
+
+    /// Sets the [`FilePolicy`] deciding which files the `<play>`, `<usesig>` and `<genfile>` tags in the text can
+    /// read and write. New engines deny all files.
+    /// # See
+    /// [`ttspico_sys::picoext_setEngineFileFilter`].
+    #[cfg(not(feature = "system"))]
+    pub fn set_file_policy(&mut self, policy: FilePolicy) {
+        self.file_filter.set_policy(policy);
+    }
+
+    /// Registers `samples` (16-bit signed PCM at `sample_rate`) as the clip `id`, played by `<play id="...">` tags
+    /// in the text (i.e. `<play id="chime"/>`) in place of their content, whatever the engine's [`FilePolicy`].
+    /// Registering a clip again replaces it.
+    ///
+    /// Clips are resampled to 16kHz, and inserted into the speech sample by sample (at the default volume), so
+    /// that [marks](`Engine::take_marks`) after them account for their length. Tags with an id that is not registered
+    /// are skipped, and their content spoken instead.
+    ///
+    /// Fails if `id` is empty or longer than 245 bytes, has characters that cannot appear in a markup attribute
+    /// (`"`, `\` or NUL), or if `sample_rate` is zero.
+    #[cfg(not(feature = "system"))]
+    pub fn register_clip(
+        &mut self,
+        id: impl Into<String>,
+        samples: &[i16],
+        sample_rate: u32,
+    ) -> Result<(), PicoError> {
+        let id = id.into();
+        if id.is_empty() || id.len() > policy::CLIP_ID_MAX_LEN || id.contains(['"', '\\', '\0']) {
+            return Err(PicoError {
+                code: -1,
+                descr: format!("Invalid clip id: \"{}\"", id),
+            });
+        }
+        if sample_rate == 0 {
+            return Err(PicoError {
+                code: -1,
+                descr: format!("Invalid sample rate for clip \"{}\": 0", id),
+            });
+        }
+        let samples = policy::resample(samples, sample_rate);
+        self.file_filter.add_clip(id, &samples);
+        Ok(())
+    }
+
+    /// Returns the memory usage statistics of the engine.
+    /// If `reset_incremental` is set, the incremental counter is reset after reading it.
+    /// # See
+    /// [`ttspico_sys::picoext_getEngineMemUsage`].
+    pub fn memory_usage(&self, reset_incremental: bool) -> Result<MemoryUsage, PicoError> {
+        let (mut used, mut incremental, mut max_used) = (0, 0, 0);
+        unsafe {
+            self.get_error(native::picoext_getEngineMemUsage(
+                self.c_engine,
+                reset_incremental as native::pico_Int16,
+                &mut used,
+                &mut incremental,
+                &mut max_used,
+            ))?;
+        }
+        Ok(MemoryUsage::from_raw(used, incremental, max_used))
+    }
+
+    /// Returns the warnings raised during the last call to [`put_text`](`Engine::put_text`),
+    /// [`get_data`](`Engine::get_data`) or [`reset`](`Engine::reset`), which Pico clears at the start of each.
+    /// Each warning is returned as a [`PicoError`], i.e. `PICO_EXC_CANT_OPEN_FILE` for a missing `<play>` file.
+    /// # See
+    /// [`ttspico_sys::pico_getNrEngineWarnings`], [`ttspico_sys::pico_getEngineWarning`].
+    pub fn warnings(&self) -> Result<Vec<PicoError>, PicoError> {
+        let mut n_warnings: native::pico_Int32 = 0;
+        unsafe {
+            self.get_error(native::pico_getNrEngineWarnings(
+                self.c_engine,
+                &mut n_warnings,
+            ))?;
+        }
+        let mut warnings = Vec::with_capacity(n_warnings as usize);
+        for i in 0..n_warnings {
+            let mut code: native::pico_Status = native::PICO_OK;
+            let mut c_str = PicoString::new();
+            unsafe {
+                self.get_error(native::pico_getEngineWarning(
+                    self.c_engine,
+                    i,
+                    &mut code,
+                    c_str.as_mut_ptr(),
+                ))?;
+            }
+            warnings.push(PicoError {
+                code,
+                descr: match c_str.to_str() {
+                    Ok(pico_msg) => pico_msg.to_string(),
+                    Err(utf8_err) => format!("[invalid Pico message: {}]", utf8_err),
+                },
+            });
+        }
+        Ok(warnings)
+    }
+
     /// Generates speech audio from the text input via [`put_text`](`Engine::put_text`), outputting to `buf`.  
     /// Returns either a <number of samples generated, [`EngineStatus`] after stepping> pair (on success) or a
     /// `PicoError` (on failure).
     ///
     /// Output data is encoded as 16-bit signed PCM, at a sample rate of 16kHz.
     /// `buf` should have length <= [`PICO_INT16_MAX`](`ttspico_sys::PICO_INT16_MAX`).
+    ///
+    /// Fails with [`PICO_EXC_CANT_OPEN_FILE`](`ttspico_sys::PICO_EXC_CANT_OPEN_FILE`) if the text has a tag whose
+    /// file is denied by the engine's [`FilePolicy`]; the speech goes on without the file. A call that generates
+    /// samples returns them (as [`Busy`](`EngineStatus::Busy`)), and the next call fails.
     /// # See
     /// [`ttspico_sys::pico_getData`].
     pub fn get_data(
         &mut self,
         mut buf: impl AsMut<[i16]>,
     ) -> Result<(usize, EngineStatus), PicoError> {
-        let c_buf = buf.as_mut().as_mut_ptr() as *mut std::os::raw::c_void;
-        let max_size = buf.as_mut().len() * std::mem::size_of::<i16>();
+        let c_buf = buf.as_mut().as_mut_ptr() as *mut core::ffi::c_void;
+        let max_size = core::mem::size_of_val(buf.as_mut());
         let max_size_i16 =
-            std::cmp::min(max_size, native::PICO_INT16_MAX as usize) as native::pico_Int16;
+            core::cmp::min(max_size, native::PICO_INT16_MAX as usize) as native::pico_Int16;
 
         let mut written_size: native::pico_Int16 = 0;
         let mut written_dtype: native::pico_Int16 = 0;
@@ -408,17 +1004,84 @@ impl Engine {
             // (Should never fail, only 16-bit PCM seems to be supported)
             assert_eq!(written_dtype, native::PICO_DATA_PCM_16BIT);
 
-            let n_written = (written_size as usize) / std::mem::size_of::<i16>();
-            match c_code {
-                native::PICO_STEP_BUSY => Ok((n_written, EngineStatus::Busy)),
-                native::PICO_STEP_IDLE => Ok((n_written, EngineStatus::Idle)),
-                err_code => Err(self
-                    .voice
-                    .borrow()
-                    .sys
-                    .borrow()
-                    .get_error(err_code)
-                    .unwrap_err()),
+            let n_written = (written_size as usize) / core::mem::size_of::<i16>();
+            let status = match c_code {
+                native::PICO_STEP_BUSY => EngineStatus::Busy,
+                native::PICO_STEP_IDLE => EngineStatus::Idle,
+                err_code => {
+                    return Err(self
+                        .voice
+                        .borrow()
+                        .sys
+                        .borrow()
+                        .get_error(err_code)
+                        .unwrap_err())
+                }
+            };
+            self.n_samples += n_written;
+            #[cfg(not(feature = "system"))]
+            {
+                self.get_mark()?;
+                if self.file_filter.has_violation() {
+                    if n_written == 0 {
+                        return Err(self.file_filter.take_violation().unwrap());
+                    }
+                    return Ok((n_written, EngineStatus::Busy));
+                }
+            }
+            Ok((n_written, status))
+        }
+    }
+
+    /// Records the mark reached by the last call to [`pico_getData`](`ttspico_sys::pico_getData`), if any.
+    #[cfg(not(feature = "system"))]
+    unsafe fn get_mark(&mut self) -> Result<(), PicoError> {
+        // (Marker names are the contents of Pico items, so at most 255 bytes long)
+        let mut name = [0 as native::pico_Char; 256];
+        let mut found: native::pico_Int16 = 0;
+        self.get_error(native::picoext_getMarker(
+            self.c_engine,
+            name.as_mut_ptr(),
+            name.len() as native::pico_Int16,
+            &mut found,
+        ))?;
+        if found != 0 {
+            let len = name.iter().position(|&c| c == 0).unwrap_or(name.len());
+            self.marks.push(Mark {
+                name: String::from_utf8_lossy(&name[..len]).into_owned(),
+                sample: self.n_samples,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl SpeechEngine for Engine {
+    fn put_text(&mut self, utf8_text: &[u8]) -> Result<usize, PicoError> {
+        Engine::put_text(self, utf8_text)
+    }
+
+    fn flush(&mut self) -> Result<usize, PicoError> {
+        Engine::flush(self)
+    }
+
+    fn get_data(&mut self, buf: &mut [i16]) -> Result<(usize, EngineStatus), PicoError> {
+        Engine::get_data(self, buf)
+    }
+
+    fn reset(&mut self, mode: EngineResetMode) -> Result<(), PicoError> {
+        Engine::reset(self, mode)
+    }
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        unsafe {
+            if !self.c_engine.is_null() {
+                let _ = native::pico_disposeEngine(
+                    self.voice.borrow().sys.borrow().c_sys,
+                    &mut self.c_engine,
+                );
             }
         }
     }
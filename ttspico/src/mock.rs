@@ -0,0 +1,197 @@
+//! A deterministic stand-in for [`Engine`](`crate::Engine`), for testing code built on top of `ttspico`.
+
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{EngineResetMode, EngineStatus, PicoError, SpeechEngine, SAMPLE_RATE};
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+
+/// Default number of samples of tone generated per byte of text (5 ms).
+const DEFAULT_SAMPLES_PER_BYTE: usize = 80;
+
+/// Default frequency of the generated tone, in Hz.
+const DEFAULT_FREQUENCY: u32 = 400;
+
+/// Default peak amplitude of the generated tone.
+const DEFAULT_AMPLITUDE: i16 = 8000;
+
+/// A [`SpeechEngine`] operation, for [scripting errors](`MockEngine::fail_next`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MockOperation {
+    /// [`SpeechEngine::put_text`] (and [`SpeechEngine::flush`]).
+    PutText,
+    /// [`SpeechEngine::get_data`].
+    GetData,
+    /// [`SpeechEngine::reset`].
+    Reset,
+}
+
+/// A [`SpeechEngine`] that "speaks" text as a triangle-wave tone, for testing code built on top of `ttspico`
+/// without Pico or any language files.
+///
+/// Text put into the engine is synthesized once flushed (i.e. when a `\0` is put): each byte of it becomes a fixed
+/// number of samples of tone, handed out by `get_data` like an [`Engine`](`crate::Engine`) would. Errors can be
+/// scripted for any operation via [`fail_next`](`MockEngine::fail_next`).
+///
+/// ```
+/// use ttspico::{MockEngine, MockOperation, PicoError, SpeechEngine};
+///
+/// let mut engine = MockEngine::new().samples_per_byte(10);
+/// engine.put_text(b"Hello\0").unwrap();
+/// let mut buf = [0i16; 1024];
+/// assert_eq!(engine.get_data(&mut buf).unwrap().0, 50);
+///
+/// engine.fail_next(
+///     MockOperation::GetData,
+///     PicoError { code: ttspico_sys::PICO_EXC_BUF_OVERFLOW, descr: "buffer overflow".into() },
+/// );
+/// assert_eq!(engine.get_data(&mut buf).unwrap_err().code, ttspico_sys::PICO_EXC_BUF_OVERFLOW);
+/// ```
+#[derive(Debug)]
+pub struct MockEngine {
+    samples_per_byte: usize,
+    period: u32,
+    amplitude: i16,
+    max_put_len: usize,
+    pending_text: Vec<u8>,
+    utterances: Vec<Vec<u8>>,
+    output: VecDeque<i16>,
+    errors: VecDeque<(MockOperation, PicoError)>,
+}
+
+impl MockEngine {
+    /// Creates a new mock engine with default settings.
+    pub fn new() -> Self {
+        MockEngine {
+            samples_per_byte: DEFAULT_SAMPLES_PER_BYTE,
+            period: SAMPLE_RATE / DEFAULT_FREQUENCY,
+            amplitude: DEFAULT_AMPLITUDE,
+            max_put_len: usize::MAX,
+            pending_text: Vec::new(),
+            utterances: Vec::new(),
+            output: VecDeque::new(),
+            errors: VecDeque::new(),
+        }
+    }
+
+    /// Sets the number of samples generated for each byte of text (80, i.e. 5 ms, by default).
+    pub fn samples_per_byte(mut self, samples_per_byte: usize) -> Self {
+        self.samples_per_byte = samples_per_byte;
+        self
+    }
+
+    /// Sets the frequency in Hz of the generated tone (400 by default).
+    pub fn frequency(mut self, frequency: u32) -> Self {
+        self.period = core::cmp::max(SAMPLE_RATE / core::cmp::max(frequency, 1), 2);
+        self
+    }
+
+    /// Sets the peak amplitude of the generated tone (8000 by default).
+    pub fn amplitude(mut self, amplitude: i16) -> Self {
+        self.amplitude = amplitude;
+        self
+    }
+
+    /// Sets the maximum number of bytes accepted by each `put_text` call (unlimited by default), to exercise
+    /// code that puts text in several calls like it has to with a real [`Engine`](`crate::Engine`).
+    pub fn max_put_len(mut self, max_put_len: usize) -> Self {
+        self.max_put_len = core::cmp::max(max_put_len, 1);
+        self
+    }
+
+    /// Makes the next call to `operation` fail with `error`, without any other effect.
+    /// Errors scripted for the same operation are returned by its subsequent calls, in order.
+    pub fn fail_next(&mut self, operation: MockOperation, error: PicoError) {
+        self.errors.push_back((operation, error));
+    }
+
+    /// Returns the text of the utterances flushed so far (without their `\0` terminators).
+    pub fn utterances(&self) -> &[Vec<u8>] {
+        &self.utterances
+    }
+
+    /// Returns the text put into the engine that was not flushed yet.
+    pub fn pending_text(&self) -> &[u8] {
+        &self.pending_text
+    }
+
+    /// Removes and returns the next error scripted for `operation`, if any.
+    fn take_error(&mut self, operation: MockOperation) -> Result<(), PicoError> {
+        match self.errors.iter().position(|(op, _)| *op == operation) {
+            Some(i) => Err(self.errors.remove(i).unwrap().1),
+            None => Ok(()),
+        }
+    }
+
+    /// Generates the tone for the pending text, which becomes an utterance.
+    fn synthesize_pending(&mut self) {
+        let period = self.period as i32;
+        let amplitude = self.amplitude as i32;
+        let num_samples = self.pending_text.len() * self.samples_per_byte;
+        self.output.extend((0..num_samples).map(|i| {
+            let t = (i % period as usize) as i32;
+            let value = if t < period / 2 {
+                -amplitude + 4 * amplitude * t / period
+            } else {
+                3 * amplitude - 4 * amplitude * t / period
+            };
+            value as i16
+        }));
+        self.utterances
+            .push(core::mem::take(&mut self.pending_text));
+    }
+}
+
+impl Default for MockEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SpeechEngine for MockEngine {
+    fn put_text(&mut self, utf8_text: &[u8]) -> Result<usize, PicoError> {
+        self.take_error(MockOperation::PutText)?;
+        let n_put = core::cmp::min(utf8_text.len(), self.max_put_len);
+        for &byte in &utf8_text[..n_put] {
+            if byte == 0 {
+                self.synthesize_pending();
+            } else {
+                self.pending_text.push(byte);
+            }
+        }
+        Ok(n_put)
+    }
+
+    fn get_data(&mut self, buf: &mut [i16]) -> Result<(usize, EngineStatus), PicoError> {
+        self.take_error(MockOperation::GetData)?;
+        let n_samples = core::cmp::min(buf.len(), self.output.len());
+        for (dst, src) in buf.iter_mut().zip(self.output.drain(..n_samples)) {
+            *dst = src;
+        }
+        let status = if self.output.is_empty() {
+            EngineStatus::Idle
+        } else {
+            EngineStatus::Busy
+        };
+        Ok((n_samples, status))
+    }
+
+    fn reset(&mut self, _mode: EngineResetMode) -> Result<(), PicoError> {
+        self.take_error(MockOperation::Reset)?;
+        self.pending_text.clear();
+        self.output.clear();
+        Ok(())
+    }
+}
@@ -0,0 +1,435 @@
+//! A synthesizer speaking documents whose segments are tagged with different voices or languages.
+
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{Engine, EngineResetMode, Mark, PicoError, Resource, System, Voice, SAMPLE_RATE};
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::time::Duration;
+
+/// A segment of a [`MultiVoiceDocument`]: text (with Pico's markup) spoken with one voice.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VoiceSegment {
+    /// The name or language of the voice to speak the segment with (see [`MultiVoiceSynthesizer::find_voice`]),
+    /// or an empty string for the synthesizer's default voice.
+    pub voice: String,
+    /// The text to speak.
+    pub text: String,
+}
+
+/// A document whose segments are spoken with different voices, i.e. a dialogue or a text with passages in several
+/// languages, to be spoken by a [`MultiVoiceSynthesizer`].
+///
+/// ```
+/// # use ttspico::MultiVoiceDocument;
+/// let doc = MultiVoiceDocument::new()
+///     .segment("en-GB", "Shall we go?")
+///     .segment("en-US", "Sure, let's go.");
+/// let markup = "<voice name=\"en-GB\">Shall we go?</voice> <voice name=\"en-US\">Sure, let's go.</voice>";
+/// assert_eq!(MultiVoiceDocument::parse(markup).unwrap(), doc);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MultiVoiceDocument {
+    segments: Vec<VoiceSegment>,
+}
+
+impl MultiVoiceDocument {
+    /// Returns an empty document.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a segment of `text` spoken with `voice` (a voice name or language, or `""` for the default voice).
+    pub fn segment(mut self, voice: impl Into<String>, text: impl Into<String>) -> Self {
+        self.push(voice, text);
+        self
+    }
+
+    /// Adds a segment of `text` spoken with `voice`; see [`MultiVoiceDocument::segment`].
+    pub fn push(&mut self, voice: impl Into<String>, text: impl Into<String>) {
+        self.segments.push(VoiceSegment {
+            voice: voice.into(),
+            text: text.into(),
+        });
+    }
+
+    /// Returns the segments of the document, in order.
+    pub fn segments(&self) -> &[VoiceSegment] {
+        &self.segments
+    }
+
+    /// Parses a document from markup where `<voice name="...">...</voice>` elements (whose name is a voice name or
+    /// language) delimit the segments; the text outside of them is spoken with the default voice, and the markup
+    /// inside them is left to Pico.
+    ///
+    /// Fails if `<voice>` elements are nested, unclosed or have no `name`.
+    pub fn parse(markup: &str) -> Result<Self, PicoError> {
+        let mut doc = MultiVoiceDocument::new();
+        let mut rest = markup;
+        while !rest.is_empty() {
+            let (before, start) = match find_tag(rest, "<voice") {
+                Some(start) => (&rest[..start], Some(start)),
+                None => (rest, None),
+            };
+            if find_tag(before, "</voice").is_some() {
+                return Err(parse_error("</voice> without <voice>"));
+            }
+            if !before.trim().is_empty() {
+                doc.push("", before);
+            }
+            let start = match start {
+                Some(start) => start,
+                None => break,
+            };
+            let tag_len = rest[start..]
+                .find('>')
+                .ok_or_else(|| parse_error("unclosed <voice> tag"))?;
+            let tag = &rest[start + "<voice".len()..start + tag_len];
+            let name =
+                attr_value(tag, "name").ok_or_else(|| parse_error("<voice> without a name"))?;
+            let body = &rest[start + tag_len + 1..];
+            let end =
+                find_tag(body, "</voice").ok_or_else(|| parse_error("<voice> without </voice>"))?;
+            if find_tag(&body[..end], "<voice").is_some() {
+                return Err(parse_error("nested <voice> elements"));
+            }
+            doc.push(name, &body[..end]);
+            let end_len = body[end..]
+                .find('>')
+                .ok_or_else(|| parse_error("unclosed </voice> tag"))?;
+            rest = &body[end + end_len + 1..];
+        }
+        Ok(doc)
+    }
+}
+
+/// Returns the position of the first tag `tag` (i.e. `"<voice"`, case-insensitive) in `markup`, if any.
+fn find_tag(markup: &str, tag: &str) -> Option<usize> {
+    let bytes = markup.as_bytes();
+    (0..bytes.len()).find(|&i| {
+        bytes[i..].len() > tag.len()
+            && bytes[i..i + tag.len()].eq_ignore_ascii_case(tag.as_bytes())
+            && matches!(
+                bytes[i + tag.len()],
+                b'>' | b'/' | b' ' | b'\t' | b'\r' | b'\n'
+            )
+    })
+}
+
+/// Returns the value of the attribute `name` (quoted with `"` or `'`) in the attributes `attrs` of a tag, if any.
+fn attr_value<'a>(attrs: &'a str, name: &str) -> Option<&'a str> {
+    let mut rest = attrs;
+    loop {
+        rest = rest.trim_start();
+        let eq = rest.find('=')?;
+        let (attr, value) = (rest[..eq].trim(), rest[eq + 1..].trim_start());
+        let quote = value.chars().next().filter(|&c| c == '"' || c == '\'')?;
+        let len = value[1..].find(quote)?;
+        if attr.eq_ignore_ascii_case(name) {
+            return Some(&value[1..1 + len]);
+        }
+        rest = &value[len + 2..];
+    }
+}
+
+fn parse_error(what: &str) -> PicoError {
+    PicoError {
+        code: -1,
+        descr: format!("Invalid multi-voice document: {}", what),
+    }
+}
+
+fn no_voice_error(voice: &str) -> PicoError {
+    PicoError {
+        code: -1,
+        descr: format!("No voice for \"{}\"", voice),
+    }
+}
+
+/// Returns the index of the language of `languages` that speaks `language`: the same language (i.e. `en-GB`, or
+/// `en_GB`), else the first dialect of it (i.e. `en-GB` for `en`), case-insensitive.
+pub(crate) fn find_language<'a>(
+    languages: impl Iterator<Item = &'a str> + Clone,
+    language: &str,
+) -> Option<usize> {
+    let language = language.replace('_', "-");
+    let base = language.split('-').next().unwrap_or("");
+    languages
+        .clone()
+        .position(|candidate| candidate.eq_ignore_ascii_case(&language))
+        .or_else(|| {
+            languages.into_iter().position(|candidate| {
+                candidate
+                    .split('-')
+                    .next()
+                    .is_some_and(|candidate_base| candidate_base.eq_ignore_ascii_case(base))
+            })
+        })
+}
+
+/// A voice of a [`MultiVoiceSynthesizer`].
+#[derive(Debug)]
+struct SynthVoice {
+    name: String,
+    language: String,
+    voice: Rc<RefCell<Voice>>,
+}
+
+/// Speaks [`MultiVoiceDocument`]s, each segment with its own voice, as one continuous stream of speech (16-bit
+/// signed PCM at 16kHz) with a configurable gap of silence between segments.
+///
+/// A [`System`] can only have one [`Engine`] at a time, so the synthesizer loads all of its voices in its system
+/// and creates an engine for the voice of each segment in turn (keeping it for the following segments with the same
+/// voice). Size the system for its language packs with [`System::builder`]:
+///
+/// ```no_run
+/// # use ttspico::{MultiVoiceDocument, MultiVoiceSynthesizer, System};
+/// let sys = System::builder()
+///     .language_pack("lang/en-GB_ta.bin", "lang/en-GB_kh0_sg.bin")
+///     .language_pack("lang/en-US_ta.bin", "lang/en-US_lh0_sg.bin")
+///     .build()
+///     .expect("Could not init system");
+/// let mut synth = MultiVoiceSynthesizer::new(sys);
+/// synth.add_voice("Kate", "en-GB", "lang/en-GB_ta.bin", "lang/en-GB_kh0_sg.bin").unwrap();
+/// synth.add_voice("Lisa", "en-US", "lang/en-US_ta.bin", "lang/en-US_lh0_sg.bin").unwrap();
+/// let doc = MultiVoiceDocument::new()
+///     .segment("Kate", "Shall we go?")
+///     .segment("Lisa", "Sure, let's go.");
+/// let audio = synth.synthesize(&doc).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct MultiVoiceSynthesizer {
+    sys: Rc<RefCell<System>>,
+    voices: Vec<SynthVoice>,
+    /// The resources loaded for the voices, by filepath (shared by voices with the same resources).
+    resources: Vec<(String, Rc<RefCell<Resource>>)>,
+    /// The engine of the voice last spoken with, by index in `voices`.
+    engine: Option<(usize, Engine)>,
+    /// The number of samples of silence between segments.
+    gap: usize,
+    /// The marks reached since the last call to [`MultiVoiceSynthesizer::take_marks`].
+    marks: Vec<Mark>,
+}
+
+impl MultiVoiceSynthesizer {
+    /// Returns a synthesizer for the voices to be [added](`MultiVoiceSynthesizer::add_voice`) to `sys`.
+    pub fn new(sys: Rc<RefCell<System>>) -> Self {
+        MultiVoiceSynthesizer {
+            sys,
+            voices: Vec::new(),
+            resources: Vec::new(),
+            engine: None,
+            gap: 0,
+            marks: Vec::new(),
+        }
+    }
+
+    /// Adds the voice `name` speaking `language` (i.e. `"en-GB"`), loading the TA and SG resources of its language
+    /// pack from the given filepaths. The first voice added is the default one.
+    pub fn add_voice(
+        &mut self,
+        name: impl Into<String>,
+        language: impl Into<String>,
+        ta_path: impl AsRef<str>,
+        sg_path: impl AsRef<str>,
+    ) -> Result<(), PicoError> {
+        let name = name.into();
+        if self.voices.iter().any(|voice| voice.name == name) {
+            return Err(PicoError {
+                code: -1,
+                descr: format!("A voice named \"{}\" was already added", name),
+            });
+        }
+        let ta_res = self.resource(ta_path.as_ref())?;
+        let sg_res = self.resource(sg_path.as_ref())?;
+        let voice = System::create_voice(Rc::clone(&self.sys), &name)?;
+        voice.borrow_mut().add_resource(ta_res)?;
+        voice.borrow_mut().add_resource(sg_res)?;
+        self.voices.push(SynthVoice {
+            name,
+            language: language.into().replace('_', "-"),
+            voice,
+        });
+        Ok(())
+    }
+
+    /// Adds the voices of `catalog`, each named after its language (i.e. `"en-GB"`).
+    #[cfg(feature = "std")]
+    pub fn add_catalog(&mut self, catalog: &crate::VoiceCatalog) -> Result<(), PicoError> {
+        for voice in catalog.voices() {
+            self.add_voice(
+                voice.language.as_str(),
+                voice.language.as_str(),
+                &voice.ta_path,
+                &voice.sg_path,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Returns the resource at `path`, loading it if it was not loaded yet.
+    fn resource(&mut self, path: &str) -> Result<Rc<RefCell<Resource>>, PicoError> {
+        if let Some((_, res)) = self.resources.iter().find(|(res_path, _)| res_path == path) {
+            return Ok(Rc::clone(res));
+        }
+        let res = System::load_resource(Rc::clone(&self.sys), path)?;
+        self.resources.push((path.to_string(), Rc::clone(&res)));
+        Ok(res)
+    }
+
+    /// Sets the silence between the segments of documents (none by default), rounded to whole samples.
+    pub fn set_gap(&mut self, gap: Duration) {
+        self.gap = (gap.as_micros() * SAMPLE_RATE as u128 / 1_000_000) as usize;
+    }
+
+    /// Returns the names of the voices, in the order they were added.
+    pub fn voices(&self) -> impl Iterator<Item = &str> {
+        self.voices.iter().map(|voice| voice.name.as_str())
+    }
+
+    /// Returns the languages of the voices, in the order they were added (i.e. to only
+    /// [detect](`crate::LanguageDetector::candidates`) those).
+    pub fn languages(&self) -> impl Iterator<Item = &str> {
+        self.voices.iter().map(|voice| voice.language.as_str())
+    }
+
+    /// Returns the name of the voice that speaks segments tagged with `voice`: the voice with that name, else the
+    /// one with that language (i.e. `en-GB`, or `en_GB`), else the first with a dialect of that language (i.e.
+    /// `en-GB` for `en`), all case-insensitive. An empty `voice` is the default voice.
+    pub fn find_voice(&self, voice: &str) -> Option<&str> {
+        self.voice_index(voice)
+            .map(|index| self.voices[index].name.as_str())
+    }
+
+    fn voice_index(&self, voice: &str) -> Option<usize> {
+        if voice.is_empty() {
+            return if self.voices.is_empty() {
+                None
+            } else {
+                Some(0)
+            };
+        }
+        self.voices
+            .iter()
+            .position(|v| v.name.eq_ignore_ascii_case(voice))
+            .or_else(|| find_language(self.voices.iter().map(|v| v.language.as_str()), voice))
+    }
+
+    /// Returns a fully reset engine for the voice that speaks segments tagged with `voice` (see
+    /// [`find_voice`](`MultiVoiceSynthesizer::find_voice`)), i.e. to speak with it directly.
+    pub fn engine(&mut self, voice: &str) -> Result<&mut Engine, PicoError> {
+        let index = self
+            .voice_index(voice)
+            .ok_or_else(|| no_voice_error(voice))?;
+        self.engine_at(index)
+    }
+
+    /// Returns a fully reset engine for the `index`-th voice.
+    fn engine_at(&mut self, index: usize) -> Result<&mut Engine, PicoError> {
+        if self.engine.as_ref().map(|(voice, _)| *voice) != Some(index) {
+            // (The system can only have one engine at a time)
+            self.engine = None;
+            let voice = Rc::clone(&self.voices[index].voice);
+            // SAFETY: both a TA and a SG resource were added to the voice in `add_voice`.
+            let engine = unsafe { Voice::create_engine(voice)? };
+            self.engine = Some((index, engine));
+        }
+        let (_, engine) = self.engine.as_mut().unwrap();
+        // (So that each segment is spoken the same whatever was spoken before)
+        engine.reset(EngineResetMode::Full)?;
+        Ok(engine)
+    }
+
+    /// Speaks `doc`, passing its speech to `on_audio` in chunks as it is synthesized.
+    ///
+    /// Fails before speaking anything if a segment has no matching [voice](`MultiVoiceSynthesizer::find_voice`).
+    pub fn speak(
+        &mut self,
+        doc: &MultiVoiceDocument,
+        mut on_audio: impl FnMut(&[i16]),
+    ) -> Result<(), PicoError> {
+        let voices = doc
+            .segments
+            .iter()
+            .map(|segment| {
+                self.voice_index(&segment.voice)
+                    .ok_or_else(|| no_voice_error(&segment.voice))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut pcm_buf = [0i16; 1024];
+        // (The number of samples spoken before the current segment, to position its marks)
+        #[cfg(not(feature = "system"))]
+        let mut n_samples = 0;
+        for (i, (segment, voice)) in doc.segments.iter().zip(voices).enumerate() {
+            if i > 0 {
+                let silence = [0i16; 1024];
+                let mut gap = self.gap;
+                while gap > 0 {
+                    let n_silence = gap.min(silence.len());
+                    on_audio(&silence[..n_silence]);
+                    gap -= n_silence;
+                }
+                #[cfg(not(feature = "system"))]
+                {
+                    n_samples += self.gap;
+                }
+            }
+            let engine = self.engine_at(voice)?;
+            let mut input = segment.text.as_bytes();
+            loop {
+                let (n_written, done) = engine.step(&mut input, &mut pcm_buf)?;
+                if n_written > 0 {
+                    on_audio(&pcm_buf[..n_written]);
+                }
+                if done {
+                    break;
+                }
+            }
+            // (The engine was reset for the segment, so its marks are positioned from the segment's start)
+            #[cfg(not(feature = "system"))]
+            {
+                let (marks, n_segment) = (engine.take_marks(), engine.n_samples);
+                self.marks.extend(marks.into_iter().map(|mark| Mark {
+                    name: mark.name,
+                    sample: n_samples + mark.sample,
+                }));
+                n_samples += n_segment;
+            }
+        }
+        Ok(())
+    }
+
+    /// Speaks `doc` to completion; returns its speech.
+    /// # See
+    /// [`MultiVoiceSynthesizer::speak`].
+    pub fn synthesize(&mut self, doc: &MultiVoiceDocument) -> Result<Vec<i16>, PicoError> {
+        let mut samples = Vec::new();
+        self.speak(doc, |chunk| samples.extend_from_slice(chunk))?;
+        Ok(samples)
+    }
+
+    /// Returns the `<mark>`s in the segments that the speech reached since the last call, in order; they are
+    /// positioned from the start of the document they are in.
+    ///
+    /// Always empty with the `system` feature.
+    /// # See
+    /// [`Engine::take_marks`].
+    pub fn take_marks(&mut self) -> Vec<Mark> {
+        core::mem::take(&mut self.marks)
+    }
+}
@@ -0,0 +1,366 @@
+//! The policy deciding which files the markup spoken by an [`Engine`](crate::Engine) can read and write.
+
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{wav_header, PicoError, SAMPLE_RATE, WAV_HEADER_LEN};
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ffi::{c_void, CStr};
+use ttspico_sys as native;
+
+/// The files that the `<play>`, `<usesig>` and `<genfile>` tags in the text spoken by an [`Engine`](crate::Engine)
+/// can read and write, [set](crate::Engine::set_file_policy) on the engine.
+///
+/// Pico opens the file names in these tags as they are, so text from untrusted sources could read or overwrite any
+/// file the process can access. Engines deny all files by default: a policy can allow the ones in given directories
+/// (or all of them, for trusted text), and redirect `<play>`/`<usesig>` file names to clips held in memory.
+/// A tag whose file is denied makes [`Engine::get_data`](crate::Engine::get_data) fail.
+///
+/// The files are opened through the [file system](crate::System::set_file_system) of the engine's system, if any;
+/// directories can only be allowed in the real file system.
+///
+/// There are no file policies with the `system` feature: the system-installed Pico opens every file named in the text.
+#[derive(Debug, Clone, Default)]
+pub struct FilePolicy {
+    allow_all: bool,
+    #[cfg(feature = "std")]
+    read_dirs: Vec<std::path::PathBuf>,
+    #[cfg(feature = "std")]
+    write_dirs: Vec<std::path::PathBuf>,
+    /// The WAV files of the clips, by name.
+    clips: BTreeMap<String, Vec<u8>>,
+}
+
+impl FilePolicy {
+    /// Returns a policy denying all files (the default).
+    pub fn deny_all() -> Self {
+        Self::default()
+    }
+
+    /// Returns a policy allowing all files, as Pico itself does; only for trusted text!
+    pub fn allow_all() -> Self {
+        FilePolicy {
+            allow_all: true,
+            ..Self::default()
+        }
+    }
+
+    /// Allows reading (with `<play>` and `<usesig>`) the files in directory `dir` and its subdirectories.
+    ///
+    /// File names are resolved (following symlinks) when the tags are spoken, so they cannot escape `dir` through
+    /// `..` or symlinks.
+    #[cfg(feature = "std")]
+    pub fn allow_reading(mut self, dir: impl Into<std::path::PathBuf>) -> Self {
+        self.read_dirs.push(dir.into());
+        self
+    }
+
+    /// Allows writing (with `<genfile>`) the files in directory `dir` and its subdirectories; see
+    /// [`FilePolicy::allow_reading`].
+    #[cfg(feature = "std")]
+    pub fn allow_writing(mut self, dir: impl Into<std::path::PathBuf>) -> Self {
+        self.write_dirs.push(dir.into());
+        self
+    }
+
+    /// Plays `samples` (16-bit signed PCM at 16kHz) in place of the file `name` in `<play>` and `<usesig>` tags,
+    /// whether other files are allowed or not.
+    ///
+    /// Pico only plays files whose names end in `.wav`.
+    pub fn clip(mut self, name: impl Into<String>, samples: &[i16]) -> Self {
+        self.clips.insert(name.into(), wav(samples));
+        self
+    }
+
+    /// Decides whether the file `name` can be opened, for writing if `write` is set.
+    fn check(&self, name: &str, write: bool) -> Decision<'_> {
+        if let (false, Some(clip)) = (write, self.clips.get(name)) {
+            return Decision::Memory(clip);
+        }
+        if self.allow_all {
+            return Decision::Allow;
+        }
+        #[cfg(feature = "std")]
+        {
+            let dirs = if write {
+                &self.write_dirs
+            } else {
+                &self.read_dirs
+            };
+            if is_in_dirs(std::path::Path::new(name), dirs) {
+                return Decision::Allow;
+            }
+        }
+        Decision::Deny
+    }
+}
+
+/// The decision of a [`FilePolicy`] on a file.
+enum Decision<'a> {
+    Allow,
+    Deny,
+    /// Fail to open the file as if it did not exist, without a violation.
+    Missing,
+    /// Read the given contents instead.
+    Memory(&'a [u8]),
+}
+
+/// Returns whether the file at `path` is in one of `dirs` (or their subdirectories), once resolved.
+/// The file does not need to exist, but its directory does.
+#[cfg(feature = "std")]
+fn is_in_dirs(path: &std::path::Path, dirs: &[std::path::PathBuf]) -> bool {
+    let resolved = match std::fs::canonicalize(path) {
+        Ok(resolved) => resolved,
+        // (A file to be created, not i.e. a dangling symlink: its name is a plain name, which cannot escape its
+        // resolved directory)
+        Err(_) if std::fs::symlink_metadata(path).is_err() => {
+            let (dir, name) = match (path.parent(), path.file_name()) {
+                (Some(dir), Some(name)) => (dir, name),
+                _ => return false,
+            };
+            let dir = if dir.as_os_str().is_empty() {
+                std::path::Path::new(".")
+            } else {
+                dir
+            };
+            match std::fs::canonicalize(dir) {
+                Ok(dir) => dir.join(name),
+                Err(_) => return false,
+            }
+        }
+        Err(_) => return false,
+    };
+    dirs.iter().any(|dir| {
+        std::fs::canonicalize(dir)
+            .map(|dir| resolved.starts_with(dir))
+            .unwrap_or(false)
+    })
+}
+
+/// Returns a WAV file of `samples` (at [`SAMPLE_RATE`]), as Pico can play it.
+fn wav(samples: &[i16]) -> Vec<u8> {
+    let mut wav = Vec::with_capacity(WAV_HEADER_LEN + samples.len() * 2);
+    wav.extend_from_slice(&wav_header(Some(samples.len())));
+    for sample in samples {
+        wav.extend_from_slice(&sample.to_le_bytes());
+    }
+    wav
+}
+
+/// Maximum length in bytes of the id of a clip, as Pico can name its file.
+pub(crate) const CLIP_ID_MAX_LEN: usize = 245;
+
+/// Returns `samples`, sampled at `sample_rate`, resampled to the [`SAMPLE_RATE`] of Pico's clips.
+///
+/// Upsampling interpolates linearly between samples; downsampling averages the samples spanned by each output
+/// sample, which filters out most of the frequencies above 8kHz.
+pub(crate) fn resample(samples: &[i16], sample_rate: u32) -> Vec<i16> {
+    let (from, to) = (sample_rate as u64, SAMPLE_RATE as u64);
+    if from == to || samples.is_empty() {
+        return samples.to_vec();
+    }
+    let len = ((samples.len() as u64 * to + from / 2) / from).max(1) as usize;
+    let sample = |i: u64| samples[(i as usize).min(samples.len() - 1)] as i64;
+    (0..len as u64)
+        .map(|i| {
+            if from < to {
+                let (pos, frac) = (i * from / to, (i * from % to) as i64);
+                let to = to as i64;
+                (sample(pos) * (to - frac) + sample(pos + 1) * frac + to / 2).div_euclid(to)
+            } else {
+                let (start, end) = (i * from / to, (i + 1) * from / to);
+                let end = end.min(samples.len() as u64).max(start + 1);
+                let sum: i64 = (start..end).map(sample).sum();
+                let n = (end - start) as i64;
+                (sum + n / 2).div_euclid(n)
+            }
+        })
+        .map(|sample| sample.clamp(i16::MIN as i64, i16::MAX as i64) as i16)
+        .collect()
+}
+
+/// The [`FilePolicy`] and clips set on an engine, with the callback through which Pico checks them.
+pub(crate) struct FileFilter {
+    /// (Boxed so that the pointer Pico is given to it stays valid)
+    c_filter: Box<native::picoext_FileFilter>,
+    /// The policy, clips and violations, as a raw `Box<FilterState>` (also `c_filter.userData`).
+    state: *mut FilterState,
+}
+
+struct FilterState {
+    policy: FilePolicy,
+    /// The WAV files of the clips [registered](crate::Engine::register_clip) on the engine, by id.
+    clips: BTreeMap<String, Vec<u8>>,
+    /// The policies and clips replaced while Pico was reading some of their files, freed once it has closed them.
+    retired_policies: Vec<FilePolicy>,
+    retired_clips: Vec<Vec<u8>>,
+    /// The contents of the files that Pico has open from memory, once per open file.
+    open: Vec<*const u8>,
+    /// The first file denied since the last call to [`FileFilter::take_violation`], if any.
+    violation: Option<PicoError>,
+}
+
+impl FileFilter {
+    /// Wraps `policy`, with no clips.
+    pub fn new(policy: FilePolicy) -> FileFilter {
+        let state = Box::into_raw(Box::new(FilterState {
+            policy,
+            clips: BTreeMap::new(),
+            retired_policies: Vec::new(),
+            retired_clips: Vec::new(),
+            open: Vec::new(),
+            violation: None,
+        }));
+        FileFilter {
+            c_filter: Box::new(native::picoext_FileFilter {
+                userData: state as *mut c_void,
+                check: Some(check),
+                close: Some(close),
+            }),
+            state,
+        }
+    }
+
+    /// Returns the callback to pass to [`ttspico_sys::picoext_setEngineFileFilter`].
+    /// The pointer is valid for as long as `self` is.
+    pub fn as_ptr(&self) -> *const native::picoext_FileFilter {
+        &*self.c_filter
+    }
+
+    /// Replaces the policy with `policy`.
+    pub fn set_policy(&mut self, policy: FilePolicy) {
+        let state = unsafe { &mut *self.state };
+        let old = core::mem::replace(&mut state.policy, policy);
+        state.retired_policies.push(old);
+        state.free_retired();
+    }
+
+    /// Adds (or replaces) the clip `id`, of `samples` at 16kHz.
+    pub fn add_clip(&mut self, id: String, samples: &[i16]) {
+        let state = unsafe { &mut *self.state };
+        if let Some(old) = state.clips.insert(id, wav(samples)) {
+            state.retired_clips.push(old);
+            state.free_retired();
+        }
+    }
+
+    /// Returns whether a file was denied since the last call to [`FileFilter::take_violation`].
+    pub fn has_violation(&self) -> bool {
+        unsafe { (*self.state).violation.is_some() }
+    }
+
+    /// Returns the error for the first file denied since the last call, if any.
+    pub fn take_violation(&mut self) -> Option<PicoError> {
+        unsafe { (*self.state).violation.take() }
+    }
+}
+
+impl Drop for FileFilter {
+    fn drop(&mut self) {
+        unsafe { drop(Box::from_raw(self.state)) }
+    }
+}
+
+impl core::fmt::Debug for FileFilter {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let state = unsafe { &*self.state };
+        f.debug_struct("FileFilter")
+            .field("policy", &state.policy)
+            .field("clips", &state.clips.keys())
+            .finish_non_exhaustive()
+    }
+}
+
+impl FilterState {
+    /// Decides whether the file `name` can be opened, for writing if `write` is set.
+    /// File names starting with [`PICOEXT_CLIP_PREFIX`](ttspico_sys::PICOEXT_CLIP_PREFIX) are the clips played by
+    /// `<play id="...">`, and nothing else.
+    fn check(&self, name: &str, write: bool) -> Decision<'_> {
+        let id = name
+            .strip_prefix(native::PICOEXT_CLIP_PREFIX)
+            .and_then(|name| name.strip_suffix(".wav"));
+        match (name.starts_with(native::PICOEXT_CLIP_PREFIX), id) {
+            (false, _) => self.policy.check(name, write),
+            (true, Some(id)) if !write => match self.clips.get(id) {
+                Some(clip) => Decision::Memory(clip),
+                None => Decision::Missing,
+            },
+            (true, _) => Decision::Deny,
+        }
+    }
+
+    /// Frees the retired policies and clips that have no open files.
+    fn free_retired(&mut self) {
+        let open = &self.open;
+        let is_open = |contents: &Vec<u8>| open.contains(&contents.as_ptr());
+        self.retired_clips.retain(is_open);
+        self.retired_policies
+            .retain(|policy| policy.clips.values().any(is_open));
+    }
+}
+
+unsafe extern "C" fn check(
+    user_data: *mut c_void,
+    file_name: *const native::pico_Char,
+    write: native::pico_Int16,
+    out_data: *mut *const c_void,
+    out_size: *mut native::pico_Uint32,
+) -> native::pico_Int16 {
+    let state = &mut *(user_data as *mut FilterState);
+    let name = CStr::from_ptr(file_name as *const core::ffi::c_char);
+    let decision = match name.to_str() {
+        Ok(name) => state.check(name, write != 0),
+        // (Names that are not UTF-8 are never allowed)
+        Err(_) => Decision::Deny,
+    };
+    match decision {
+        Decision::Allow => native::PICOEXT_FILE_ALLOW,
+        Decision::Memory(contents) => {
+            *out_data = contents.as_ptr() as *const c_void;
+            *out_size = contents.len() as native::pico_Uint32;
+            state.open.push(contents.as_ptr());
+            native::PICOEXT_FILE_MEMORY
+        }
+        Decision::Missing => native::PICOEXT_FILE_DENY,
+        Decision::Deny => {
+            if state.violation.is_none() {
+                state.violation = Some(PicoError {
+                    code: native::PICO_EXC_CANT_OPEN_FILE,
+                    descr: format!(
+                        "{} \"{}\" is denied by the engine's file policy",
+                        if write != 0 { "Writing" } else { "Reading" },
+                        name.to_string_lossy()
+                    ),
+                });
+            }
+            native::PICOEXT_FILE_DENY
+        }
+    }
+}
+
+unsafe extern "C" fn close(user_data: *mut c_void, data: *const c_void) {
+    let state = &mut *(user_data as *mut FilterState);
+    if let Some(i) = state
+        .open
+        .iter()
+        .position(|&open| open == data as *const u8)
+    {
+        state.open.swap_remove(i);
+        state.free_retired();
+    }
+}
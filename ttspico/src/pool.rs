@@ -0,0 +1,155 @@
+//! Synthesis threads, each owning a Pico system (which cannot be sent to other threads).
+
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{MultiVoiceSynthesizer, PicoError, System, VoiceCatalog};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+/// Spawns a thread that calls `init`, then `run` with what it returned; `init`'s outcome is sent to the receiver.
+fn spawn<T, E, I, R>(init: I, run: R) -> (JoinHandle<()>, mpsc::Receiver<Result<(), E>>)
+where
+    I: FnOnce() -> Result<T, E> + Send + 'static,
+    R: FnOnce(T) + Send + 'static,
+    E: Send + 'static,
+{
+    let (ready_tx, ready_rx) = mpsc::channel();
+    let thread = thread::spawn(move || match init() {
+        Ok(state) => {
+            let _ = ready_tx.send(Ok(()));
+            run(state);
+        }
+        Err(err) => {
+            let _ = ready_tx.send(Err(err));
+        }
+    });
+    (thread, ready_rx)
+}
+
+/// Waits for `thread` to send the outcome of its `init` to `ready` (see [`spawn`]), resuming its panic if it panicked
+/// instead.
+fn wait_ready<E>(
+    thread: JoinHandle<()>,
+    ready: mpsc::Receiver<Result<(), E>>,
+) -> Result<JoinHandle<()>, E> {
+    match ready.recv() {
+        Ok(Ok(())) => Ok(thread),
+        Ok(Err(err)) => Err(err),
+        Err(_) => match thread.join() {
+            Err(panic) => std::panic::resume_unwind(panic),
+            Ok(()) => unreachable!("the thread ended without reporting its initialization"),
+        },
+    }
+}
+
+/// Spawns a synthesis thread, which calls `init` (i.e. to set up its [`System`]) and then `run` with what it
+/// returned.
+///
+/// Returns once `init` has: with its error if it failed, or else with the handle of the thread. If `init` panics,
+/// the panic is resumed on the calling thread.
+pub fn spawn_synthesis_thread<T, E, I, R>(init: I, run: R) -> Result<JoinHandle<()>, E>
+where
+    I: FnOnce() -> Result<T, E> + Send + 'static,
+    R: FnOnce(T) + Send + 'static,
+    E: Send + 'static,
+{
+    let (thread, ready) = spawn(init, run);
+    wait_ready(thread, ready)
+}
+
+/// A pool of synthesis threads, each with a [`MultiVoiceSynthesizer`] of the voices of a [`VoiceCatalog`], which
+/// serve the jobs sent to the pool one at a time, in order.
+///
+/// Dropping the pool waits for the jobs sent so far to be served, then stops the threads.
+pub struct SynthesizerPool<J> {
+    jobs: Option<mpsc::Sender<J>>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl<J: Send + 'static> SynthesizerPool<J> {
+    /// Starts `n_threads` synthesis threads (at least one), each loading the voices of `catalog` in a system of
+    /// `memsz` bytes (see [`VoiceCatalog::system_builder`]); they call `serve` with each job and their synthesizer.
+    ///
+    /// Returns once all the threads are set up, or with the error of the first one that failed to.
+    pub fn start<F>(
+        n_threads: usize,
+        memsz: usize,
+        catalog: &VoiceCatalog,
+        serve: F,
+    ) -> Result<Self, PicoError>
+    where
+        F: Fn(J, &mut MultiVoiceSynthesizer) + Send + Sync + 'static,
+    {
+        let (jobs_tx, jobs_rx) = mpsc::channel::<J>();
+        let jobs_rx = Arc::new(Mutex::new(jobs_rx));
+        let serve = Arc::new(serve);
+        // (The threads are all spawned before waiting for any of them, so that they load their voices concurrently)
+        let starting: Vec<_> = (0..n_threads.max(1))
+            .map(|_| {
+                let catalog = catalog.clone();
+                let (jobs_rx, serve) = (Arc::clone(&jobs_rx), Arc::clone(&serve));
+                let init = move || {
+                    let mut synth =
+                        MultiVoiceSynthesizer::new(System::builder().memory_size(memsz).build()?);
+                    synth.add_catalog(&catalog)?;
+                    Ok(synth)
+                };
+                let run = move |mut synth: MultiVoiceSynthesizer| loop {
+                    // (The lock is only held while waiting, by one of the idle threads)
+                    let job = jobs_rx.lock().unwrap().recv();
+                    match job {
+                        Ok(job) => serve(job, &mut synth),
+                        Err(_) => return,
+                    }
+                };
+                spawn(init, run)
+            })
+            .collect();
+
+        let mut pool = SynthesizerPool {
+            jobs: Some(jobs_tx),
+            threads: Vec::with_capacity(starting.len()),
+        };
+        let mut result = Ok(());
+        for (thread, ready) in starting {
+            match wait_ready(thread, ready) {
+                Ok(thread) => pool.threads.push(thread),
+                Err(err) => result = result.and(Err(err)),
+            }
+        }
+        // (On errors, dropping the pool stops the threads that did start)
+        result.map(|()| pool)
+    }
+
+    /// Sends `job` to be served by the next idle thread; returns it back if the threads have stopped (i.e. all of them
+    /// panicked).
+    pub fn send(&self, job: J) -> Result<(), J> {
+        self.jobs
+            .as_ref()
+            .unwrap()
+            .send(job)
+            .map_err(|mpsc::SendError(job)| job)
+    }
+}
+
+impl<J> Drop for SynthesizerPool<J> {
+    fn drop(&mut self) {
+        // (Dropping the sender ends the threads' loops once the queue is empty, which frees their systems)
+        self.jobs = None;
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
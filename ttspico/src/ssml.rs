@@ -0,0 +1,466 @@
+//! Conversion of SSML to Pico's own markup.
+//!
+//! Pico's markup is close to SSML, but differs in tag names and in how prosody is expressed; elements without a
+//! Pico equivalent are dropped, keeping their content.
+
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::PicoError;
+
+/// A prosodic property controlled by Pico's markup.
+#[derive(Debug, Clone, Copy)]
+struct Property {
+    /// The Pico markup tag setting the property.
+    tag: &'static str,
+    /// The SSML `<prosody>` attribute setting the property.
+    attr: &'static str,
+    /// The property's range of levels, in percent of the default (see `PICO_*_MIN`, `PICO_*_MAX` in `picotok.c`).
+    min: u32,
+    max: u32,
+    /// The levels of the SSML keywords for the property.
+    keywords: &'static [(&'static str, u32)],
+}
+
+const PROPERTIES: [Property; 3] = [
+    Property {
+        tag: "speed",
+        attr: "rate",
+        min: 20,
+        max: 500,
+        keywords: &[
+            ("x-slow", 50),
+            ("slow", 75),
+            ("medium", 100),
+            ("fast", 150),
+            ("x-fast", 200),
+            ("default", 100),
+        ],
+    },
+    Property {
+        tag: "pitch",
+        attr: "pitch",
+        min: 50,
+        max: 200,
+        keywords: &[
+            ("x-low", 50),
+            ("low", 75),
+            ("medium", 100),
+            ("high", 150),
+            ("x-high", 200),
+            ("default", 100),
+        ],
+    },
+    Property {
+        tag: "volume",
+        attr: "volume",
+        min: 0,
+        max: 500,
+        keywords: &[
+            ("silent", 0),
+            ("x-soft", 25),
+            ("soft", 50),
+            ("medium", 100),
+            ("loud", 150),
+            ("x-loud", 200),
+            ("default", 100),
+        ],
+    },
+];
+
+/// Pause durations of the SSML `<break>` strengths.
+const BREAK_STRENGTHS: &[(&str, &str)] = &[
+    ("none", ""),
+    ("x-weak", "100ms"),
+    ("weak", "200ms"),
+    ("medium", "400ms"),
+    ("strong", "700ms"),
+    ("x-strong", "1000ms"),
+];
+
+/// What to do when an SSML element is closed.
+#[derive(Debug)]
+enum Close {
+    /// Nothing.
+    Nothing,
+    /// Emit the given Pico markup.
+    Markup(String),
+    /// Restore the properties (indices in [`PROPERTIES`]) set by a `<prosody>`.
+    Prosody(Vec<usize>),
+    /// Stop skipping content.
+    Skip,
+}
+
+/// A parsed SSML tag.
+#[derive(Debug)]
+struct Tag {
+    /// The tag's local name (i.e. without any namespace prefix).
+    name: String,
+    attrs: Vec<(String, String)>,
+    is_end: bool,
+    is_empty: bool,
+}
+
+impl Tag {
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(attr_name, _)| attr_name == name)
+            .map(|(_, value)| value.trim())
+    }
+}
+
+#[derive(Debug, Default)]
+struct Converter {
+    out: String,
+    /// The open elements, with what to do when each is closed.
+    open: Vec<(String, Close)>,
+    /// The stack of levels set for each property in [`PROPERTIES`].
+    levels: [Vec<u32>; 3],
+    /// Number of open elements whose content is not to be spoken.
+    skip_depth: usize,
+}
+
+/// Converts SSML to text with Pico's markup, to be [put](`crate::Engine::put_text`) into an engine.
+///
+/// Elements without a Pico equivalent are dropped, keeping their content; on malformed SSML, returns a
+/// [`PicoError`] with code -1. Escaped `<` and `>` in text (i.e. `&lt;`) become spaces and attribute values are
+/// escaped, so that no Pico markup can be injected through the SSML's content.
+pub fn ssml_to_markup(ssml: &str) -> Result<String, PicoError> {
+    convert(ssml).map_err(|err| PicoError {
+        code: -1,
+        descr: format!("invalid SSML: {}", err),
+    })
+}
+
+fn convert(ssml: &str) -> Result<String, String> {
+    let mut converter = Converter::default();
+    let mut rest = ssml;
+    while let Some(start) = rest.find('<') {
+        converter.text(&rest[..start]);
+        rest = &rest[start..];
+        // (Skip comments, processing instructions and declarations)
+        let skip_until = if rest.starts_with("<!--") {
+            Some("-->")
+        } else if rest.starts_with("<?") {
+            Some("?>")
+        } else if rest.starts_with("<!") {
+            Some(">")
+        } else {
+            None
+        };
+        if let Some(terminator) = skip_until {
+            let end = rest
+                .find(terminator)
+                .ok_or_else(|| format!("unterminated {}", &rest[..2]))?;
+            rest = &rest[end + terminator.len()..];
+            continue;
+        }
+        let end = tag_end(rest).ok_or("unterminated tag")?;
+        converter.tag(parse_tag(&rest[1..end])?)?;
+        rest = &rest[end + 1..];
+    }
+    converter.text(rest);
+    // (Be lenient about unclosed elements)
+    while let Some((_, close)) = converter.open.pop() {
+        converter.close(close);
+    }
+    Ok(converter.out)
+}
+
+impl Converter {
+    fn text(&mut self, text: &str) {
+        if self.skip_depth == 0 {
+            self.out += &plain_text(&decode_entities(text));
+        }
+    }
+
+    fn tag(&mut self, tag: Tag) -> Result<(), String> {
+        if tag.is_end {
+            return match self.open.pop() {
+                Some((name, close)) if name == tag.name => {
+                    self.close(close);
+                    Ok(())
+                }
+                Some((name, _)) => Err(format!("</{}> closes <{}>", tag.name, name)),
+                None => Err(format!("unexpected </{}>", tag.name)),
+            };
+        }
+        let close = if self.skip_depth > 0 {
+            Close::Nothing
+        } else {
+            self.open(&tag)
+        };
+        if let Close::Skip = close {
+            self.skip_depth += 1;
+        }
+        if tag.is_empty {
+            self.close(close);
+        } else {
+            self.open.push((tag.name, close));
+        }
+        Ok(())
+    }
+
+    /// Emits the Pico markup for the start of the element of `tag`, returning what to do when it is closed.
+    fn open(&mut self, tag: &Tag) -> Close {
+        match tag.name.as_str() {
+            "p" | "paragraph" => self.markup("<p>", "</p>"),
+            "s" | "sentence" => self.markup("<s>", "</s>"),
+            "break" => {
+                let time = match (tag.attr("time"), tag.attr("strength")) {
+                    (Some(time), _) => time,
+                    (None, Some(strength)) => BREAK_STRENGTHS
+                        .iter()
+                        .find(|(name, _)| *name == strength)
+                        .map_or("", |(_, time)| *time),
+                    (None, None) => "400ms",
+                };
+                if !time.is_empty() {
+                    self.out += &format!("<break time=\"{}\"/>", attr_value(time));
+                }
+                Close::Nothing
+            }
+            "prosody" => {
+                let mut set = Vec::new();
+                for (i, property) in PROPERTIES.iter().enumerate() {
+                    let current = self.levels[i].last().cloned().unwrap_or(100);
+                    if let Some(level) = tag
+                        .attr(property.attr)
+                        .and_then(|value| parse_level(value, property, current))
+                    {
+                        self.levels[i].push(level);
+                        self.out += &format!("<{} level=\"{}\">", property.tag, level);
+                        set.push(i);
+                    }
+                }
+                Close::Prosody(set)
+            }
+            "say-as" => match tag.attr("interpret-as") {
+                Some("characters") | Some("spell-out") | Some("letters") | Some("verbatim") => {
+                    self.markup("<spell>", "</spell>")
+                }
+                _ => Close::Nothing,
+            },
+            "phoneme" => match tag.attr("ph") {
+                Some(ph) => {
+                    let alphabet = match tag.attr("alphabet") {
+                        Some("x-sampa") | None => "xsampa",
+                        Some(alphabet) => alphabet,
+                    };
+                    self.out += &format!(
+                        "<phoneme alphabet=\"{}\" ph=\"{}\"/>",
+                        attr_value(alphabet),
+                        attr_value(ph)
+                    );
+                    Close::Skip
+                }
+                None => Close::Nothing,
+            },
+            "mark" => {
+                if let Some(name) = tag.attr("name") {
+                    self.out += &format!("<mark name=\"{}\"/>", attr_value(name));
+                }
+                Close::Nothing
+            }
+            "audio" => match tag.attr("src") {
+                // (Pico plays the file in place of the element's content, or speaks the content if it fails to)
+                Some(src) => self.markup(
+                    &format!("<play file=\"{}\">", file_attr_value(src)),
+                    "</play>",
+                ),
+                None => Close::Nothing,
+            },
+            "sub" => {
+                if let Some(alias) = tag.attr("alias") {
+                    self.out += &plain_text(alias);
+                }
+                Close::Skip
+            }
+            "desc" | "meta" | "metadata" => Close::Skip,
+            // (i.e. `<speak>`, `<voice>`, `<emphasis>`, `<lang>`)
+            _ => Close::Nothing,
+        }
+    }
+
+    fn markup(&mut self, start: &str, end: &str) -> Close {
+        self.out += start;
+        Close::Markup(end.to_string())
+    }
+
+    fn close(&mut self, close: Close) {
+        match close {
+            Close::Nothing => {}
+            Close::Markup(end) => self.out += &end,
+            Close::Prosody(set) => {
+                for &i in set.iter().rev() {
+                    self.levels[i].pop();
+                    // (Pico's end tags reset properties to their default, not to the enclosing level)
+                    self.out += &match self.levels[i].last() {
+                        Some(level) => format!("<{} level=\"{}\">", PROPERTIES[i].tag, level),
+                        None => format!("</{}>", PROPERTIES[i].tag),
+                    };
+                }
+            }
+            Close::Skip => self.skip_depth -= 1,
+        }
+    }
+}
+
+/// Parses an SSML `value` of `property`, relative to its `current` level, into an absolute level.
+/// Returns `None` for unsupported values (which are ignored).
+fn parse_level(value: &str, property: &Property, current: u32) -> Option<u32> {
+    let level = if let Some(&(_, level)) = property.keywords.iter().find(|(kw, _)| *kw == value) {
+        level as f64
+    } else if let Some(percent) = value.strip_suffix('%') {
+        let percent: f64 = percent.parse().ok()?;
+        if value.starts_with('+') || value.starts_with('-') {
+            current as f64 * (100.0 + percent) / 100.0
+        } else {
+            percent
+        }
+    } else if let Some(db) = value.strip_suffix("dB") {
+        if property.attr != "volume" {
+            return None;
+        }
+        current as f64 * 10f64.powf(db.parse::<f64>().ok()? / 20.0)
+    } else if property.attr == "rate" {
+        // (A multiplier of the default rate)
+        value.parse::<f64>().ok()? * 100.0
+    } else {
+        return None;
+    };
+    Some((level.round().max(0.0) as u32).clamp(property.min, property.max))
+}
+
+/// Returns the index of the `>` ending the tag at the start of `s`, skipping over quoted attribute values.
+fn tag_end(s: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in s.char_indices() {
+        match (quote, c) {
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, '>') => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Parses the contents of a tag (between `<` and `>`).
+fn parse_tag(s: &str) -> Result<Tag, String> {
+    let (is_end, s) = match s.strip_prefix('/') {
+        Some(s) => (true, s),
+        None => (false, s),
+    };
+    let (is_empty, s) = match s.strip_suffix('/') {
+        Some(s) => (true, s),
+        None => (false, s),
+    };
+    let s = s.trim();
+    let name_end = s.find(char::is_whitespace).unwrap_or(s.len());
+    let name = &s[..name_end];
+    if name.is_empty() {
+        return Err("empty tag".to_string());
+    }
+    let name = name.rsplit(':').next().unwrap().to_string();
+
+    let mut attrs = Vec::new();
+    let mut rest = s[name_end..].trim_start();
+    while !rest.is_empty() {
+        let eq = rest
+            .find('=')
+            .ok_or_else(|| format!("attribute without a value in <{}>", name))?;
+        let attr_name = rest[..eq].trim();
+        let after_eq = rest[eq + 1..].trim_start();
+        let quote = after_eq
+            .chars()
+            .next()
+            .filter(|&c| c == '"' || c == '\'')
+            .ok_or_else(|| format!("unquoted attribute value in <{}>", name))?;
+        let value_end = after_eq[1..]
+            .find(quote)
+            .ok_or_else(|| format!("unterminated attribute value in <{}>", name))?;
+        let attr_name = attr_name.rsplit(':').next().unwrap().to_string();
+        attrs.push((attr_name, decode_entities(&after_eq[1..1 + value_end])));
+        rest = after_eq[value_end + 2..].trim_start();
+    }
+    Ok(Tag {
+        name,
+        attrs,
+        is_end,
+        is_empty,
+    })
+}
+
+/// Decodes XML character and entity references in `s`; unknown references are left as they are.
+fn decode_entities(s: &str) -> String {
+    let mut decoded = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find('&') {
+        decoded += &rest[..start];
+        rest = &rest[start..];
+        let c = rest.find(';').and_then(|end| {
+            let c = match &rest[1..end] {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                entity => entity
+                    .strip_prefix("#x")
+                    .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                    .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                    .and_then(char::from_u32),
+            };
+            c.map(|c| (c, end))
+        });
+        match c {
+            Some((c, end)) => {
+                decoded.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded + rest
+}
+
+/// Makes decoded `text` safe to put as text among Pico's markup, replacing the `<` and `>` that would start or end
+/// a tag with spaces.
+fn plain_text(text: &str) -> String {
+    text.replace(['<', '>'], " ")
+}
+
+/// Makes `value` safe to use as the value of a Pico markup attribute, removing tag delimiters and escaping quotes
+/// and backslashes with a backslash.
+fn attr_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars().filter(|&c| c != '<' && c != '>') {
+        if c == '"' || c == '\'' || c == '\\' {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Makes `value` safe to use as the value of a Pico file attribute (i.e. `<play file="...">`), where backslashes are
+/// path separators and nothing can be escaped: removes quotes and tag delimiters.
+fn file_attr_value(value: &str) -> String {
+    value.replace(['"', '\'', '<', '>'], "")
+}
@@ -0,0 +1,282 @@
+//! A hook through which a [`System`](crate::System) and its engine access files, in place of the real file system.
+
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::boxed::Box;
+use core::ffi::{c_void, CStr};
+use ttspico_sys as native;
+
+/// The files seen by a [`System`](crate::System) and its engine, once [set](crate::System::set_file_system).
+///
+/// Pico opens files by name: the resources passed to [`System::load_resource`](crate::System::load_resource), the
+/// audio files inserted with `<play file="...">` and the ones written by `<genfile file="...">`. A file system can
+/// serve them from anywhere (i.e. an archive, an encrypted blob or memory), as well as sandbox or deny file writes.
+///
+/// Its methods are called from within the Pico calls that open files, so they must not panic (which would abort).
+pub trait PicoFileSystem: Send {
+    /// Opens the file `name` for reading, or returns `None` if there is no such file (or it cannot be read).
+    fn open(&mut self, name: &str) -> Option<Box<dyn PicoFile>>;
+
+    /// Creates (or truncates) the file `name` for writing, or returns `None` if it cannot be written.
+    /// Writes are denied by default.
+    fn create(&mut self, name: &str) -> Option<Box<dyn PicoFile>> {
+        let _ = name;
+        None
+    }
+}
+
+/// A file opened by a [`PicoFileSystem`], closed when dropped.
+pub trait PicoFile: Send {
+    /// Returns the size of the file in bytes, as of when it was opened.
+    fn size(&self) -> u64;
+
+    /// Reads up to `buf.len()` bytes at byte position `pos` into `buf`; returns the number of bytes read (less than
+    /// `buf.len()` only at the end of the file), or `None` on errors.
+    fn read_at(&mut self, pos: u64, buf: &mut [u8]) -> Option<usize>;
+
+    /// Writes `buf` at byte position `pos`; returns the number of bytes written, or `None` on errors.
+    /// Files are read-only by default.
+    fn write_at(&mut self, pos: u64, buf: &[u8]) -> Option<usize> {
+        let _ = (pos, buf);
+        None
+    }
+}
+
+/// A [`PicoFileSystem`] set on a system, with the callbacks through which Pico calls it.
+pub(crate) struct FileSystemHook {
+    /// (Boxed so that the pointer Pico is given to it stays valid)
+    c_fs: Box<native::picoext_FileSystem>,
+    /// The file system, as a raw `Box<Box<dyn PicoFileSystem>>` (also `c_fs.userData`).
+    fs: *mut Box<dyn PicoFileSystem>,
+}
+
+impl FileSystemHook {
+    /// Wraps `fs`.
+    pub fn new(fs: Box<dyn PicoFileSystem>) -> FileSystemHook {
+        let fs = Box::into_raw(Box::new(fs));
+        FileSystemHook {
+            c_fs: Box::new(native::picoext_FileSystem {
+                userData: fs as *mut c_void,
+                open: Some(open),
+                read: Some(read),
+                write: Some(write),
+                close: Some(close),
+            }),
+            fs,
+        }
+    }
+
+    /// Returns the callbacks to pass to [`ttspico_sys::picoext_setFileSystem`].
+    /// The pointer is valid for as long as `self` is.
+    pub fn as_ptr(&self) -> *const native::picoext_FileSystem {
+        &*self.c_fs
+    }
+}
+
+impl Drop for FileSystemHook {
+    fn drop(&mut self) {
+        unsafe { drop(Box::from_raw(self.fs)) }
+    }
+}
+
+impl core::fmt::Debug for FileSystemHook {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("FileSystemHook").finish_non_exhaustive()
+    }
+}
+
+/// Returns the file system behind a callback's `user_data`.
+unsafe fn file_system<'a>(user_data: *mut c_void) -> &'a mut dyn PicoFileSystem {
+    &mut **(user_data as *mut Box<dyn PicoFileSystem>)
+}
+
+/// Returns the file behind a callback's `file` handle.
+unsafe fn file<'a>(file: *mut c_void) -> &'a mut dyn PicoFile {
+    &mut **(file as *mut Box<dyn PicoFile>)
+}
+
+unsafe extern "C" fn open(
+    user_data: *mut c_void,
+    file_name: *const native::pico_Char,
+    write: native::pico_Int16,
+    out_length: *mut native::pico_Uint32,
+) -> *mut c_void {
+    // (Names that are not UTF-8 cannot be opened)
+    let name = match CStr::from_ptr(file_name as *const core::ffi::c_char).to_str() {
+        Ok(name) => name,
+        Err(_) => return core::ptr::null_mut(),
+    };
+    let fs = file_system(user_data);
+    let file = if write != 0 {
+        fs.create(name)
+    } else {
+        fs.open(name)
+    };
+    match file {
+        // (Pico cannot address files of 4GiB or more)
+        Some(file) if file.size() <= native::pico_Uint32::MAX as u64 => {
+            *out_length = file.size() as native::pico_Uint32;
+            Box::into_raw(Box::new(file)) as *mut c_void
+        }
+        _ => core::ptr::null_mut(),
+    }
+}
+
+unsafe extern "C" fn read(
+    _user_data: *mut c_void,
+    file_handle: *mut c_void,
+    pos: native::pico_Uint32,
+    bytes: *mut native::pico_Char,
+    len: native::pico_Uint32,
+) -> native::pico_Int32 {
+    let buf = core::slice::from_raw_parts_mut(bytes, len as usize);
+    match file(file_handle).read_at(pos as u64, buf) {
+        Some(n_read) => n_read.min(buf.len()) as native::pico_Int32,
+        None => -1,
+    }
+}
+
+unsafe extern "C" fn write(
+    _user_data: *mut c_void,
+    file_handle: *mut c_void,
+    pos: native::pico_Uint32,
+    bytes: *const native::pico_Char,
+    len: native::pico_Uint32,
+) -> native::pico_Int32 {
+    let buf = core::slice::from_raw_parts(bytes, len as usize);
+    match file(file_handle).write_at(pos as u64, buf) {
+        Some(n_written) => n_written.min(buf.len()) as native::pico_Int32,
+        None => -1,
+    }
+}
+
+unsafe extern "C" fn close(_user_data: *mut c_void, file_handle: *mut c_void) {
+    drop(Box::from_raw(file_handle as *mut Box<dyn PicoFile>));
+}
+
+/// A [`PicoFileSystem`] holding its files in memory, i.e. to serve resources bundled with an application or to
+/// capture the files written by `<genfile>`.
+///
+/// Clones share the same files, so one can be [set](crate::System::set_file_system) on a system while another
+/// inspects them.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Default)]
+pub struct MemoryFileSystem {
+    files: std::sync::Arc<std::sync::Mutex<std::collections::BTreeMap<String, Vec<u8>>>>,
+    writable: bool,
+}
+
+#[cfg(feature = "std")]
+impl MemoryFileSystem {
+    /// Returns a new, empty file system, whose files can only be read.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether files can be created (and written) in the file system.
+    pub fn writable(mut self, writable: bool) -> Self {
+        self.writable = writable;
+        self
+    }
+
+    /// Adds (or replaces) the file `name` with the given contents.
+    pub fn insert(&self, name: impl Into<String>, contents: impl Into<Vec<u8>>) {
+        self.files().insert(name.into(), contents.into());
+    }
+
+    /// Returns a copy of the contents of the file `name`, if any.
+    pub fn get(&self, name: &str) -> Option<Vec<u8>> {
+        self.files().get(name).cloned()
+    }
+
+    /// Removes the file `name`, returning its contents if there was such a file.
+    /// Open files keep the contents they were opened with.
+    pub fn remove(&self, name: &str) -> Option<Vec<u8>> {
+        self.files().remove(name)
+    }
+
+    /// Returns the names of the files in the file system, in order.
+    pub fn names(&self) -> Vec<String> {
+        self.files().keys().cloned().collect()
+    }
+
+    fn files(&self) -> std::sync::MutexGuard<'_, std::collections::BTreeMap<String, Vec<u8>>> {
+        // (The lock is never held across calls that could panic)
+        self.files.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+#[cfg(feature = "std")]
+impl PicoFileSystem for MemoryFileSystem {
+    fn open(&mut self, name: &str) -> Option<Box<dyn PicoFile>> {
+        let contents = self.get(name)?;
+        Some(Box::new(MemoryFile {
+            contents,
+            target: None,
+        }))
+    }
+
+    fn create(&mut self, name: &str) -> Option<Box<dyn PicoFile>> {
+        if !self.writable {
+            return None;
+        }
+        self.insert(name, Vec::new());
+        Some(Box::new(MemoryFile {
+            contents: Vec::new(),
+            target: Some((self.clone(), name.to_string())),
+        }))
+    }
+}
+
+/// A file opened from a [`MemoryFileSystem`].
+#[cfg(feature = "std")]
+struct MemoryFile {
+    contents: Vec<u8>,
+    /// The file system and name to store the contents to when closed, if the file was created.
+    target: Option<(MemoryFileSystem, String)>,
+}
+
+#[cfg(feature = "std")]
+impl PicoFile for MemoryFile {
+    fn size(&self) -> u64 {
+        self.contents.len() as u64
+    }
+
+    fn read_at(&mut self, pos: u64, buf: &mut [u8]) -> Option<usize> {
+        let rest = self.contents.get(pos as usize..).unwrap_or(&[]);
+        let n_read = rest.len().min(buf.len());
+        buf[..n_read].copy_from_slice(&rest[..n_read]);
+        Some(n_read)
+    }
+
+    fn write_at(&mut self, pos: u64, buf: &[u8]) -> Option<usize> {
+        self.target.as_ref()?;
+        let end = pos as usize + buf.len();
+        if self.contents.len() < end {
+            self.contents.resize(end, 0);
+        }
+        self.contents[pos as usize..end].copy_from_slice(buf);
+        Some(buf.len())
+    }
+}
+
+#[cfg(feature = "std")]
+impl Drop for MemoryFile {
+    fn drop(&mut self) {
+        if let Some((fs, name)) = self.target.take() {
+            fs.insert(name, core::mem::take(&mut self.contents));
+        }
+    }
+}
@@ -0,0 +1,57 @@
+//! WAV headers for Pico's output.
+
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::SAMPLE_RATE;
+
+/// Length in bytes of the header returned by [`wav_header`].
+pub const WAV_HEADER_LEN: usize = 44;
+
+/// Returns the header of a WAV file of `num_samples` samples of Pico's output (16-bit signed PCM, mono, at
+/// [`SAMPLE_RATE`]), to be followed by the samples in little-endian order.
+///
+/// If `num_samples` is `None`, i.e. when streaming speech whose length is not known in advance, the RIFF and data
+/// chunk sizes are set to their maximum, as is customary for streamed WAV files.
+pub fn wav_header(num_samples: Option<usize>) -> [u8; WAV_HEADER_LEN] {
+    let (riff_len, data_len) = match num_samples {
+        Some(num_samples) => {
+            let data_len = (num_samples * 2) as u32;
+            (36 + data_len, data_len)
+        }
+        None => (u32::MAX, u32::MAX),
+    };
+    let mut header = [0u8; WAV_HEADER_LEN];
+    let fields: [&[u8]; 13] = [
+        b"RIFF",
+        &riff_len.to_le_bytes(),
+        b"WAVE",
+        b"fmt ",
+        &16u32.to_le_bytes(), // (chunk size)
+        &1u16.to_le_bytes(),  // (PCM)
+        &1u16.to_le_bytes(),  // (channels)
+        &SAMPLE_RATE.to_le_bytes(),
+        &(SAMPLE_RATE * 2).to_le_bytes(), // (byte rate)
+        &2u16.to_le_bytes(),              // (block align)
+        &16u16.to_le_bytes(),             // (bits per sample)
+        b"data",
+        &data_len.to_le_bytes(),
+    ];
+    let mut pos = 0;
+    for field in fields {
+        header[pos..pos + field.len()].copy_from_slice(field);
+        pos += field.len();
+    }
+    header
+}
@@ -0,0 +1,126 @@
+//! Marks before the words of a text, to report when the speech reaches each of them.
+
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Prefix of the names of the marks put before words, followed by the index of the word.
+const WORD_MARK: &str = "ttspico-word-";
+
+/// A word of a text marked by [`mark_words`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Word {
+    /// The byte offset of the word in the text.
+    pub offset: usize,
+    /// The length of the word, in bytes.
+    pub length: usize,
+}
+
+/// Returns `text` (with Pico's markup or SSML, whose `<mark>` is the same) with a mark before each of its words,
+/// and the words.
+///
+/// Words are runs of characters other than whitespace and tags, trimmed of the punctuation and entities around them
+/// (i.e. `"Hello,"` and `&quot;Hello&quot;` are the word `Hello`). The [`Engine`](`crate::Engine`) reports the marks
+/// as the speech reaches them (see [`Engine::take_marks`](`crate::Engine::take_marks`)), and [`word_index`] tells
+/// which word each is before.
+///
+/// ```
+/// use ttspico::{mark_words, word_index, Word};
+///
+/// let (marked, words) = mark_words("Hello, <break time=\"1s\"/>world!");
+/// assert_eq!(
+///     marked,
+///     "<mark name=\"ttspico-word-0\"/>Hello, <break time=\"1s\"/><mark name=\"ttspico-word-1\"/>world!"
+/// );
+/// assert_eq!(words[1], Word { offset: 25, length: 5 });
+/// assert_eq!(word_index("ttspico-word-1"), Some(1));
+/// assert_eq!(word_index("chapter-1"), None);
+/// ```
+pub fn mark_words(text: &str) -> (String, Vec<Word>) {
+    let mut marked = String::with_capacity(text.len() * 2);
+    let mut words = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(|c: char| !c.is_whitespace()) {
+        marked += &rest[..start];
+        rest = &rest[start..];
+        let offset = text.len() - rest.len();
+        if rest.starts_with('<') {
+            // (Tags are copied as they are, with any `>` in their quoted attributes)
+            let mut quote = None;
+            let end = rest
+                .char_indices()
+                .find(|&(_, c)| {
+                    match quote {
+                        Some(q) if c == q => quote = None,
+                        Some(_) => {}
+                        None if c == '"' || c == '\'' => quote = Some(c),
+                        None => return c == '>',
+                    }
+                    false
+                })
+                .map_or(rest.len(), |(i, _)| i + 1);
+            marked += &rest[..end];
+            rest = &rest[end..];
+            continue;
+        }
+
+        let len = rest
+            .find(|c: char| c.is_whitespace() || c == '<')
+            .unwrap_or(rest.len());
+        let run = &rest[..len];
+        let (lead, word_len) = trim_word(run);
+        if word_len > 0 {
+            marked += &run[..lead];
+            marked += &format!("<mark name=\"{}{}\"/>", WORD_MARK, words.len());
+            marked += &run[lead..];
+            words.push(Word {
+                offset: offset + lead,
+                length: word_len,
+            });
+        } else {
+            marked += run;
+        }
+        rest = &rest[len..];
+    }
+    marked += rest;
+    (marked, words)
+}
+
+/// Trims `run` of the punctuation and entities around its word, returning the word's offset in it and length.
+fn trim_word(run: &str) -> (usize, usize) {
+    let (mut start, mut end) = (0, run.len());
+    while start < end {
+        let word = &run[start..end];
+        if let Some(entity_len) = word.strip_prefix('&').and_then(|rest| rest.find(';')) {
+            start += entity_len + 2;
+        } else if let Some(entity) = word.strip_suffix(';').and_then(|rest| rest.rfind('&')) {
+            end = start + entity;
+        } else if let Some(c) = word.chars().next().filter(|c| !c.is_alphanumeric()) {
+            start += c.len_utf8();
+        } else if let Some(c) = word.chars().next_back().filter(|c| !c.is_alphanumeric()) {
+            end -= c.len_utf8();
+        } else {
+            break;
+        }
+    }
+    (start, end.saturating_sub(start))
+}
+
+/// Returns the index of the word whose mark is named `mark`, if it is one of those put by [`mark_words`].
+pub fn word_index(mark: &str) -> Option<usize> {
+    mark.strip_prefix(WORD_MARK)?.parse().ok()
+}
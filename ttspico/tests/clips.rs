@@ -0,0 +1,137 @@
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tests the clips registered on engines, played by `<play id="...">` tags.
+
+#![cfg(not(feature = "system"))]
+
+use ttspico::{Engine, FilePolicy, Mark};
+
+mod common;
+use common::{create_engine, SAMPLE_RATE, TEXT};
+
+/// The most silence Pico pads clips with (i.e. ending a phrase), in samples.
+const MAX_PADDING: usize = SAMPLE_RATE / 10;
+
+/// Synthesizes `text` (see [`common::synthesize`]) and returns the generated samples and marks.
+fn synthesize(engine: &mut Engine, text: &str) -> (Vec<i16>, Vec<Mark>) {
+    let samples = common::synthesize(engine, text).unwrap();
+    (samples, engine.take_marks())
+}
+
+fn chime(len: usize) -> Vec<i16> {
+    (0..len)
+        .map(|i| ((i as f64 * 0.2).sin() * 12000.0) as i16)
+        .collect()
+}
+
+/// Returns the position of `part` in `samples`, if any.
+fn find(samples: &[i16], part: &[i16]) -> Option<usize> {
+    samples
+        .windows(part.len())
+        .position(|window| window == part)
+}
+
+/// Asserts that `len` samples are a clip of `clip_len` samples, padded with silence.
+fn assert_padded(len: usize, clip_len: usize) {
+    assert!(
+        len >= clip_len && len <= clip_len + MAX_PADDING,
+        "{} samples for a clip of {}",
+        len,
+        clip_len
+    );
+}
+
+#[test]
+fn sample_accurate() {
+    let mut engine = create_engine("en-US", "lh0");
+    let clip = chime(SAMPLE_RATE / 4);
+    engine
+        .register_clip("chime", &clip, SAMPLE_RATE as u32)
+        .unwrap();
+
+    let (played, marks) = synthesize(
+        &mut engine,
+        &format!(
+            "{}<mark name=\"before\"/><play id=\"chime\"/><mark name=\"after\"/>{}",
+            TEXT, TEXT
+        ),
+    );
+    let start = find(&played, &clip).expect("the clip is not in the speech");
+    assert_eq!(
+        marks.iter().map(|m| m.name.as_str()).collect::<Vec<_>>(),
+        ["before", "after"]
+    );
+    assert!(marks[0].sample <= start);
+    assert!(marks[1].sample >= start + clip.len());
+
+    // (The events after the clip are delayed by its length)
+    let (plain, plain_marks) = synthesize(
+        &mut engine,
+        &format!(
+            "{}<mark name=\"before\"/><mark name=\"after\"/>{}",
+            TEXT, TEXT
+        ),
+    );
+    assert_eq!(marks[0].sample, plain_marks[0].sample);
+    assert_padded(marks[1].sample - plain_marks[1].sample, clip.len());
+    assert_padded(played.len() - plain.len(), clip.len());
+}
+
+#[test]
+fn resampled() {
+    let mut engine = create_engine("en-US", "lh0");
+    let (plain, _) = synthesize(&mut engine, TEXT);
+
+    // (Constant clips are resampled exactly)
+    for &(sample_rate, len) in &[(8000, 1000), (22050, 2205), (48000, 3000)] {
+        let clip = vec![1234i16; len];
+        engine.register_clip("tone", &clip, sample_rate).unwrap();
+        let (played, _) = synthesize(&mut engine, &format!("{}<play id=\"tone\"/>", TEXT));
+        let resampled_len = len * SAMPLE_RATE / sample_rate as usize;
+        assert_padded(played.len() - plain.len(), resampled_len);
+        assert!(find(&played, &vec![1234i16; resampled_len]).is_some());
+        assert!(find(&played, &vec![1234i16; resampled_len + 1]).is_none());
+    }
+}
+
+#[test]
+fn ids() {
+    let mut engine = create_engine("en-US", "lh0");
+    let (plain, _) = synthesize(&mut engine, TEXT);
+    let clip = chime(SAMPLE_RATE / 4);
+    engine
+        .register_clip("chime", &clip, SAMPLE_RATE as u32)
+        .unwrap();
+
+    // (Tags with an unknown id speak their content instead)
+    let (unknown, _) = synthesize(&mut engine, &format!("<play id=\"gong\">{}</play>", TEXT));
+    assert_eq!(unknown, plain);
+    let (replaced, _) = synthesize(&mut engine, &format!("<play id=\"chime\">{}</play>", TEXT));
+    assert!(find(&replaced, &clip).is_some() && replaced.len() < plain.len());
+
+    // (Clips are played whatever the file policy, and replaced when registered again)
+    engine.set_file_policy(FilePolicy::allow_all());
+    let other = chime(SAMPLE_RATE / 8);
+    engine
+        .register_clip("chime", &other, SAMPLE_RATE as u32)
+        .unwrap();
+    let (played, _) = synthesize(&mut engine, &format!("{}<play id=\"chime\"/>", TEXT));
+    assert_padded(played.len() - plain.len(), other.len());
+
+    for id in &["", "a\"b", "a\\b", &"x".repeat(246)] {
+        assert!(engine.register_clip(*id, &clip, 16000).is_err(), "{}", id);
+    }
+    assert!(engine.register_clip("chime", &clip, 0).is_err());
+}
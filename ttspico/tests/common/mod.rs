@@ -0,0 +1,82 @@
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Helpers shared by the integration tests, which use the language packs bundled with Pico's sources.
+
+// (Each test crate uses only some of the helpers)
+#![allow(dead_code)]
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use ttspico::{Engine, EngineResetMode, PicoError, System, Voice};
+
+pub const LANG_DIR: &str = ttspico_sys::BUNDLED_LANG_DIR;
+
+/// The text synthesized by tests that do not care what is spoken.
+pub const TEXT: &str = "This is a test.";
+
+pub const MEMORY_SIZE: usize = 4 * 1024 * 1024;
+pub const SAMPLE_RATE: usize = ttspico::SAMPLE_RATE as usize;
+
+/// Returns the path of the bundled language pack file `name`.
+pub fn lang_file(name: &str) -> String {
+    format!("{}/{}", LANG_DIR, name)
+}
+
+/// Returns the paths of the TA and SG resources of the voice of `lang` spoken by `speaker`.
+pub fn language_pack(lang: &str, speaker: &str) -> (String, String) {
+    (
+        lang_file(&format!("{}_ta.bin", lang)),
+        lang_file(&format!("{}_{}_sg.bin", lang, speaker)),
+    )
+}
+
+/// Creates an engine for the voice of `lang` spoken by `speaker` in a new system.
+pub fn create_engine(lang: &str, speaker: &str) -> Engine {
+    let sys = System::new(MEMORY_SIZE).expect("Could not init system");
+    create_engine_in(sys, lang, speaker)
+}
+
+/// Creates an engine for the voice of `lang` spoken by `speaker` in `sys`.
+pub fn create_engine_in(sys: Rc<RefCell<System>>, lang: &str, speaker: &str) -> Engine {
+    let (ta_path, sg_path) = language_pack(lang, speaker);
+    let ta_res = System::load_resource(Rc::clone(&sys), ta_path).expect("Failed to load TA");
+    let sg_res = System::load_resource(Rc::clone(&sys), sg_path).expect("Failed to load SG");
+    let voice = System::create_voice(sys, "TestVoice").expect("Failed to create voice");
+    voice
+        .borrow_mut()
+        .add_resource(ta_res)
+        .expect("Failed to add TA to voice");
+    voice
+        .borrow_mut()
+        .add_resource(sg_res)
+        .expect("Failed to add SG to voice");
+    // SAFETY: both a TA and a SG resource were added to the voice above.
+    unsafe { Voice::create_engine(voice).expect("Failed to create engine") }
+}
+
+/// Fully resets `engine`, then synthesizes `text` to completion and returns the generated samples.
+pub fn synthesize(engine: &mut Engine, text: &str) -> Result<Vec<i16>, PicoError> {
+    engine.reset(EngineResetMode::Full)?;
+    let mut samples = Vec::new();
+    let mut pcm_buf = [0i16; 1024];
+    let mut input = text.as_bytes();
+    loop {
+        let (n_samples, done) = engine.step(&mut input, &mut pcm_buf[..])?;
+        samples.extend_from_slice(&pcm_buf[..n_samples]);
+        if done {
+            return Ok(samples);
+        }
+    }
+}
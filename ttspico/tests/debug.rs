@@ -0,0 +1,190 @@
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tests that Pico's diagnostic output reaches the `log` crate (with the `debug-pico` feature) and `tracing`
+//! subscribers (with the `tracing` feature), with the targets of its source files.
+
+#![cfg(any(feature = "debug-pico", feature = "tracing"))]
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::Mutex;
+use ttspico::{System, TraceLevel, LOG_TARGET};
+
+mod common;
+use common::{create_engine_in, synthesize, MEMORY_SIZE, TEXT};
+
+/// A message output by Pico: its target, level (as a [`TraceLevel`]) and text.
+type Message = (String, TraceLevel, String);
+
+thread_local! {
+    /// The messages output on this thread, as tests run in parallel.
+    static MESSAGES: RefCell<Vec<Message>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Serializes the tests, as Pico's trace level and filter are global.
+static TRACE: Mutex<()> = Mutex::new(());
+
+fn take_messages() -> Vec<Message> {
+    MESSAGES.with(|messages| messages.take())
+}
+
+/// Speaks with a new system, tracing all messages and then only those of `picoctrl.c`; returns the messages of each.
+fn speak_traced() -> (Vec<Message>, Vec<Message>) {
+    let _trace = TRACE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let sys = System::new(MEMORY_SIZE).unwrap();
+    sys.borrow().set_trace(TraceLevel::Trace, None).unwrap();
+    let mut engine = create_engine_in(Rc::clone(&sys), "en-US", "lh0");
+    synthesize(&mut engine, TEXT).unwrap();
+    let all = take_messages();
+
+    sys.borrow()
+        .set_trace(TraceLevel::Trace, Some("ttspico::pico::picoctrl"))
+        .unwrap();
+    // (Creating and dropping another system keeps the filter)
+    drop(System::new(MEMORY_SIZE).unwrap());
+    synthesize(&mut engine, TEXT).unwrap();
+    let filtered = take_messages();
+
+    sys.borrow().set_trace(TraceLevel::Trace, None).unwrap();
+    (all, filtered)
+}
+
+fn check_messages(all: &[Message], filtered: &[Message]) {
+    assert!(!all.is_empty());
+    assert!(all
+        .iter()
+        .all(|(target, _, _)| target.starts_with(LOG_TARGET)));
+    assert!(
+        all.iter()
+            .any(|(target, _, _)| target != "ttspico::pico::picoctrl"),
+        "{:?}",
+        all
+    );
+    assert!(!filtered.is_empty());
+    assert!(
+        filtered
+            .iter()
+            .all(|(target, _, _)| target == "ttspico::pico::picoctrl"),
+        "{:?}",
+        filtered
+    );
+    // (Lines composed piecewise by Pico are output whole)
+    assert!(all.iter().all(|(_, _, text)| !text.contains('\n')));
+}
+
+#[cfg(feature = "debug-pico")]
+#[test]
+fn log() {
+    struct Capture;
+
+    impl log::Log for Capture {
+        fn enabled(&self, _: &log::Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record) {
+            let level = match record.level() {
+                log::Level::Error => TraceLevel::Error,
+                log::Level::Warn => TraceLevel::Warn,
+                log::Level::Info => TraceLevel::Info,
+                log::Level::Debug => TraceLevel::Debug,
+                log::Level::Trace => TraceLevel::Trace,
+            };
+            let message = (
+                record.target().to_string(),
+                level,
+                record.args().to_string(),
+            );
+            MESSAGES.with(|messages| messages.borrow_mut().push(message));
+        }
+
+        fn flush(&self) {}
+    }
+
+    log::set_logger(&Capture).unwrap();
+    log::set_max_level(log::LevelFilter::Trace);
+    let (all, filtered) = speak_traced();
+    check_messages(&all, &filtered);
+
+    // (Messages that `log` would not output are dropped)
+    log::set_max_level(log::LevelFilter::Warn);
+    speak_traced();
+    assert!(take_messages()
+        .iter()
+        .all(|(_, level, _)| *level <= TraceLevel::Warn));
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn tracing() {
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Level, Metadata, Subscriber};
+
+    struct Capture;
+
+    /// Collects the `message` field of an event.
+    struct Text(String);
+
+    impl Visit for Text {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            if field.name() == "message" {
+                self.0 = format!("{:?}", value);
+            }
+        }
+    }
+
+    impl Subscriber for Capture {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn max_level_hint(&self) -> Option<tracing::level_filters::LevelFilter> {
+            Some(tracing::level_filters::LevelFilter::TRACE)
+        }
+
+        fn new_span(&self, _: &Attributes<'_>) -> Id {
+            Id::from_u64(1)
+        }
+
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            let metadata = event.metadata();
+            let level = match *metadata.level() {
+                Level::ERROR => TraceLevel::Error,
+                Level::WARN => TraceLevel::Warn,
+                Level::INFO => TraceLevel::Info,
+                Level::DEBUG => TraceLevel::Debug,
+                Level::TRACE => TraceLevel::Trace,
+            };
+            let mut text = Text(String::new());
+            event.record(&mut text);
+            let message = (metadata.target().to_string(), level, text.0);
+            MESSAGES.with(|messages| messages.borrow_mut().push(message));
+        }
+
+        fn enter(&self, _: &Id) {}
+
+        fn exit(&self, _: &Id) {}
+    }
+
+    let (all, filtered) = tracing::subscriber::with_default(Capture, speak_traced);
+    check_messages(&all, &filtered);
+}
@@ -0,0 +1,266 @@
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tests the [`FilePolicy`] of engines against the files named by `<play>`, `<usesig>` and `<genfile>` tags.
+
+#![cfg(all(feature = "std", not(feature = "system")))]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use ttspico::{Engine, EngineResetMode, FilePolicy, PicoError};
+use ttspico_sys as native;
+
+mod common;
+use common::{create_engine, synthesize, SAMPLE_RATE, TEXT};
+
+fn clip_samples(len: usize) -> Vec<i16> {
+    (0..len)
+        .map(|i| ((i as f64 * 0.1).sin() * 10000.0) as i16)
+        .collect()
+}
+
+/// Writes a WAV file of `len` samples to `path`.
+fn write_clip(path: &Path, len: usize) {
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: SAMPLE_RATE as u32,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut clip = hound::WavWriter::create(path, spec).unwrap();
+    for sample in clip_samples(len) {
+        clip.write_sample(sample).unwrap();
+    }
+    clip.finalize().unwrap();
+}
+
+/// A temporary directory with an `allowed` and an `outside` subdirectory, each with a `clip.wav`.
+struct Dirs {
+    root: PathBuf,
+}
+
+impl Dirs {
+    fn new(test: &str) -> Self {
+        let root =
+            std::env::temp_dir().join(format!("ttspico-policy-{}-{}", test, std::process::id()));
+        for dir in &["allowed", "outside"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+            write_clip(&root.join(dir).join("clip.wav"), SAMPLE_RATE / 2);
+        }
+        Dirs { root }
+    }
+
+    fn allowed(&self) -> PathBuf {
+        self.root.join("allowed")
+    }
+}
+
+impl Drop for Dirs {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+fn assert_denied(result: Result<Vec<i16>, PicoError>, name: &str) {
+    let err = result.unwrap_err();
+    assert_eq!(err.code, native::PICO_EXC_CANT_OPEN_FILE);
+    assert!(err.descr.contains(name), "{}", err.descr);
+}
+
+#[test]
+fn denied_by_default() {
+    let dirs = Dirs::new("default");
+    let mut engine = create_engine("en-US", "lh0");
+    let plain = synthesize(&mut engine, TEXT).unwrap();
+
+    let clip = dirs.allowed().join("clip.wav");
+    let clip = clip.to_str().unwrap();
+    assert_denied(
+        synthesize(&mut engine, &format!("{}<play file=\"{}\"/>", TEXT, clip)),
+        clip,
+    );
+    assert_denied(
+        synthesize(
+            &mut engine,
+            &format!("<usesig file=\"{}\">{}</usesig>", clip, TEXT),
+        ),
+        clip,
+    );
+    let out = dirs.allowed().join("out.wav");
+    let out = out.to_str().unwrap();
+    assert_denied(
+        synthesize(
+            &mut engine,
+            &format!("<genfile file=\"{}\">{}</genfile>", out, TEXT),
+        ),
+        out,
+    );
+    assert!(!Path::new(out).exists());
+
+    // (The engine can go on after a violation)
+    assert_eq!(synthesize(&mut engine, TEXT).unwrap(), plain);
+}
+
+#[test]
+fn allowed_directories() {
+    let dirs = Dirs::new("dirs");
+    let mut engine = create_engine("en-US", "lh0");
+    engine.set_file_policy(
+        FilePolicy::deny_all()
+            .allow_reading(dirs.allowed())
+            .allow_writing(dirs.allowed()),
+    );
+    let plain = synthesize(&mut engine, TEXT).unwrap();
+
+    let play = |file: &Path| format!("{}<play file=\"{}\"/>", TEXT, file.display());
+    let played = synthesize(&mut engine, &play(&dirs.allowed().join("clip.wav"))).unwrap();
+    assert!(played.len() >= plain.len() + SAMPLE_RATE / 2);
+    // (Missing files are allowed, and skipped by Pico)
+    let missing = synthesize(&mut engine, &play(&dirs.allowed().join("missing.wav"))).unwrap();
+    assert!(missing.len() < played.len());
+
+    let escaping = dirs.allowed().join("../outside/clip.wav");
+    assert_denied(
+        synthesize(&mut engine, &play(&escaping)),
+        escaping.to_str().unwrap(),
+    );
+    assert_denied(
+        synthesize(&mut engine, &play(Path::new("clip.wav"))),
+        "clip.wav",
+    );
+    #[cfg(unix)]
+    {
+        let link = dirs.allowed().join("link.wav");
+        std::os::unix::fs::symlink(dirs.root.join("outside/clip.wav"), &link).unwrap();
+        assert_denied(synthesize(&mut engine, &play(&link)), "link.wav");
+    }
+
+    let genfile = |file: &Path| format!("<genfile file=\"{}\">{}</genfile>", file.display(), TEXT);
+    let out = dirs.allowed().join("out.wav");
+    synthesize(&mut engine, &genfile(&out)).unwrap();
+    assert!(hound::WavReader::open(&out).unwrap().len() > 0);
+    let escaping = dirs.allowed().join("../outside/out.wav");
+    assert_denied(
+        synthesize(&mut engine, &genfile(&escaping)),
+        escaping.to_str().unwrap(),
+    );
+    assert!(!dirs.root.join("outside/out.wav").exists());
+}
+
+#[test]
+fn clips() {
+    let mut engine = create_engine("en-US", "lh0");
+    let plain = synthesize(&mut engine, TEXT).unwrap();
+    let clip_len = SAMPLE_RATE / 2;
+    engine.set_file_policy(FilePolicy::deny_all().clip("beep.wav", &clip_samples(clip_len)));
+
+    let played = synthesize(&mut engine, &format!("{}<play file=\"beep.wav\"/>", TEXT)).unwrap();
+    assert!(played.len() >= plain.len() + clip_len);
+    // (The clip is played in place of the text in the tag)
+    let replaced = synthesize(
+        &mut engine,
+        &format!("<play file=\"beep.wav\">{}</play>", TEXT),
+    )
+    .unwrap();
+    assert!(replaced.len() >= clip_len && replaced.len() < plain.len());
+
+    assert_denied(
+        synthesize(&mut engine, &format!("{}<play file=\"other.wav\"/>", TEXT)),
+        "other.wav",
+    );
+    assert_denied(
+        synthesize(
+            &mut engine,
+            &format!("<genfile file=\"beep.wav\">{}</genfile>", TEXT),
+        ),
+        "beep.wav",
+    );
+
+    // (Allowing all files lets the clips take precedence)
+    engine.set_file_policy(FilePolicy::allow_all().clip("beep.wav", &clip_samples(clip_len)));
+    let played_again =
+        synthesize(&mut engine, &format!("{}<play file=\"beep.wav\"/>", TEXT)).unwrap();
+    assert_eq!(played_again, played);
+}
+
+/// Replacing the policy and the clips while Pico plays them keeps them until Pico is done with their files, whether
+/// it finishes playing them, is reset or is dropped.
+#[test]
+fn replaced_while_playing() {
+    let mut engine = create_engine("en-US", "lh0");
+    let clip = clip_samples(SAMPLE_RATE);
+    let set_clips = |engine: &mut Engine| {
+        engine.set_file_policy(FilePolicy::deny_all().clip("beep.wav", &clip));
+        engine
+            .register_clip("beep", &clip, SAMPLE_RATE as u32)
+            .unwrap();
+    };
+    set_clips(&mut engine);
+    let text = format!(
+        "{}<play file=\"beep.wav\"/><play id=\"beep\"/>{}",
+        TEXT, TEXT
+    );
+    let played = synthesize(&mut engine, &text).unwrap();
+    assert!(played.len() >= clip.len() * 2);
+
+    // (Speaks `text`, replacing the clips whenever it outputs samples, up to `max_samples`)
+    let speak = |engine: &mut Engine, max_samples: usize| {
+        engine.reset(EngineResetMode::Full).unwrap();
+        let (mut input, mut buf) = (text.as_bytes(), [0i16; 1024]);
+        let mut samples = Vec::new();
+        while samples.len() < max_samples {
+            let (n_samples, done) = engine.step(&mut input, &mut buf).unwrap();
+            samples.extend_from_slice(&buf[..n_samples]);
+            if n_samples > 0 {
+                set_clips(engine);
+            }
+            if done {
+                break;
+            }
+        }
+        samples
+    };
+    assert_eq!(speak(&mut engine, usize::MAX), played);
+    let halfway = played.len() / 2;
+    assert_eq!(speak(&mut engine, halfway)[..halfway], played[..halfway]);
+    assert_eq!(synthesize(&mut engine, &text).unwrap(), played);
+    speak(&mut engine, halfway);
+}
+
+/// The samples generated by the call that reaches a denied file are returned, and the violation by the next call.
+#[test]
+fn samples_before_violation() {
+    let mut engine = create_engine("en-US", "lh0");
+    // (Pico skips an unregistered clip like a denied file, but without a violation)
+    let skipped = synthesize(&mut engine, &format!("{}<play id=\"none\"/>{}", TEXT, TEXT)).unwrap();
+
+    engine.reset(EngineResetMode::Full).unwrap();
+    let text = format!("{}<play file=\"denied.wav\"/>{}", TEXT, TEXT);
+    let (mut input, mut buf) = (text.as_bytes(), [0i16; 1024]);
+    let (mut samples, mut errors) = (Vec::new(), Vec::new());
+    loop {
+        match engine.step(&mut input, &mut buf) {
+            Ok((n_samples, done)) => {
+                samples.extend_from_slice(&buf[..n_samples]);
+                if done {
+                    break;
+                }
+            }
+            Err(err) => errors.push(err),
+        }
+    }
+    assert_eq!(errors.len(), 1);
+    assert_denied(Err(errors.remove(0)), "denied.wav");
+    assert_eq!(samples, skipped);
+}
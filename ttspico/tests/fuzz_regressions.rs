@@ -0,0 +1,66 @@
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Replays the inputs in `fuzz/regressions/<fuzz target>/` through the code of the fuzz targets.
+//! Inputs that made a fuzz target crash should be added there once the crash is fixed.
+
+#![cfg(not(feature = "system"))]
+
+#[path = "../fuzz/src/lib.rs"]
+mod fuzz;
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// Returns the contents of all regression inputs of `fuzz_target` (if any).
+fn regression_inputs(fuzz_target: &str) -> Vec<(PathBuf, Vec<u8>)> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("fuzz/regressions")
+        .join(fuzz_target);
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Vec::new(),
+        Err(err) => panic!("Failed to list {}: {}", dir.display(), err),
+    };
+    let mut inputs: Vec<_> = entries
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.is_file())
+        .map(|path| {
+            let bytes = fs::read(&path).unwrap();
+            (path, bytes)
+        })
+        .collect();
+    inputs.sort();
+    inputs
+}
+
+#[test]
+fn synthesize_regressions() {
+    for (path, bytes) in regression_inputs("synthesize") {
+        // (Like `fuzz_target!`, skip inputs that are not valid UTF-8)
+        if let Ok(text) = std::str::from_utf8(&bytes) {
+            println!("Replaying {}", path.display());
+            fuzz::synthesize(text);
+        }
+    }
+}
+
+#[test]
+fn load_resource_regressions() {
+    for (path, bytes) in regression_inputs("load_resource") {
+        println!("Replaying {}", path.display());
+        fuzz::load_resource(&bytes);
+    }
+}
@@ -0,0 +1,564 @@
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Golden-audio regression tests.
+//!
+//! A fixed sentence is synthesized with each bundled voice and compared to the reference fingerprint in
+//! `tests/golden/<language>_<speaker>.txt`: the number of samples plus the RMS energy of [`NUM_SEGMENTS`] equal
+//! segments of the audio, which are matched within a tolerance (exact hashes would break on any change in
+//! floating-point rounding).
+//!
+//! The references are the output of the original engine: the unpatched sources built for i686, like Pico on
+//! 32-bit Android, by `tests/golden/reference/generate.sh` (which reruns [`golden_audio`] with `TTSPICO_REFERENCE`
+//! set to the program it built). This crate's changes to the synthesis must stay within the tolerance: the fix of
+//! `pam_initialize`'s uninitialized silence weights changes the timing of de-DE, en-GB, en-US and es-ES
+//! slightly, and the 64-bit fixes only widen integers that are 32-bit on i686 anyway.
+//!
+//! The effect of each markup tag supported by `picotok.c` is checked as well, together with every `PICO_EXC_*`
+//! error that can be triggered through the safe API. The remaining ones cannot: `NUMBER_FORMAT` and
+//! `FILE_NOT_FOUND` are never raised, `BUF_*` only flow between processing units, `NAME_UNDEFINED`,
+//! `RESOURCE_BUSY` and `RESOURCE_MISSING` are prevented by voices owning their resources, and `KB_MISSING` by the
+//! safety contract of [`Voice::create_engine`].
+
+#![cfg(not(feature = "system"))]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::rc::Rc;
+#[cfg(feature = "std")]
+use ttspico::FilePolicy;
+use ttspico::{EngineResetMode, EngineStatus, System};
+use ttspico_sys as native;
+
+mod common;
+use common::{
+    create_engine, create_engine_in, lang_file, language_pack, synthesize, MEMORY_SIZE,
+    SAMPLE_RATE, TEXT,
+};
+
+const GOLDEN_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden");
+
+/// The bundled voices, as (language, speaker) pairs, and the text synthesized with each of them.
+const CORPUS: &[(&str, &str, &str)] = &[
+    (
+        "de-DE",
+        "gl0",
+        "Der schnelle braune Fuchs springt über den faulen Hund. Es ist 12:45 Uhr.",
+    ),
+    (
+        "en-GB",
+        "kh0",
+        "The quick brown fox jumps over the lazy dog. It is 12:45 pm.",
+    ),
+    (
+        "en-US",
+        "lh0",
+        "The quick brown fox jumps over the lazy dog. It is 12:45 pm.",
+    ),
+    (
+        "es-ES",
+        "zl0",
+        "El veloz murciélago hindú comía feliz cardillo y kiwi. Son las 12:45.",
+    ),
+    (
+        "fr-FR",
+        "nk0",
+        "Portez ce vieux whisky au juge blond qui fume. Il est 12 h 45.",
+    ),
+    (
+        "it-IT",
+        "cm0",
+        "Quel vituperabile xenofobo zelante assaggia il whisky ed esclama alleluia. Sono le 12:45.",
+    ),
+];
+
+/// Number of segments whose energy is fingerprinted.
+const NUM_SEGMENTS: usize = 16;
+/// Tolerated relative difference in the number of samples.
+const SAMPLES_TOLERANCE: f64 = 0.01;
+/// Tolerated difference in the RMS energy of each segment, relative to the loudest segment.
+const ENERGY_TOLERANCE: f64 = 0.05;
+
+fn rms(samples: &[i16]) -> f64 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    let sum_sq: f64 = samples.iter().map(|&s| (s as f64) * (s as f64)).sum();
+    (sum_sq / samples.len() as f64).sqrt()
+}
+
+/// Asserts that `samples` has the same length as `expected`, give or take `tolerance` (relative).
+fn assert_len_near(what: &str, samples: &[i16], expected: usize, tolerance: f64) {
+    let diff = (samples.len() as f64 - expected as f64).abs();
+    assert!(
+        diff <= tolerance * expected as f64,
+        "{}: {} samples, expected about {}",
+        what,
+        samples.len(),
+        expected
+    );
+}
+
+/// A coarse description of synthesized audio, which tolerates small numeric differences between builds.
+#[derive(Debug)]
+struct Fingerprint {
+    num_samples: usize,
+    /// RMS energy of each of [`NUM_SEGMENTS`] equal segments of the audio.
+    energies: Vec<f64>,
+}
+
+impl Fingerprint {
+    fn of(samples: &[i16]) -> Self {
+        let energies = (0..NUM_SEGMENTS)
+            .map(|i| {
+                let start = i * samples.len() / NUM_SEGMENTS;
+                let end = (i + 1) * samples.len() / NUM_SEGMENTS;
+                rms(&samples[start..end])
+            })
+            .collect();
+        Fingerprint {
+            num_samples: samples.len(),
+            energies,
+        }
+    }
+
+    fn read(path: &Path) -> Self {
+        let contents = fs::read_to_string(path)
+            .unwrap_or_else(|err| panic!("Failed to read {}: {}", path.display(), err));
+        let mut values = contents.split_whitespace();
+        let num_samples = values
+            .next()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or_else(|| panic!("Invalid fingerprint in {}", path.display()));
+        let energies: Vec<f64> = values
+            .map(|value| value.parse().expect("Invalid fingerprint energy"))
+            .collect();
+        assert_eq!(
+            energies.len(),
+            NUM_SEGMENTS,
+            "Invalid fingerprint in {}",
+            path.display()
+        );
+        Fingerprint {
+            num_samples,
+            energies,
+        }
+    }
+
+    fn write(&self, path: &Path) {
+        let mut contents = self.num_samples.to_string();
+        for energy in &self.energies {
+            contents += &format!(" {:.1}", energy);
+        }
+        contents.push('\n');
+        fs::write(path, contents)
+            .unwrap_or_else(|err| panic!("Failed to write {}: {}", path.display(), err));
+    }
+
+    /// Returns how `self` differs from the `expected` fingerprint beyond the tolerances, if it does.
+    fn mismatch(&self, expected: &Fingerprint) -> Option<String> {
+        let samples_diff = (self.num_samples as f64 - expected.num_samples as f64).abs();
+        if samples_diff > SAMPLES_TOLERANCE * expected.num_samples as f64 {
+            return Some(format!(
+                "{} samples, expected {}",
+                self.num_samples, expected.num_samples
+            ));
+        }
+        let max_energy = expected.energies.iter().cloned().fold(0.0, f64::max);
+        for (i, (energy, expected_energy)) in
+            self.energies.iter().zip(&expected.energies).enumerate()
+        {
+            if (energy - expected_energy).abs() > ENERGY_TOLERANCE * max_energy {
+                return Some(format!(
+                    "RMS energy {:.1} in segment {}, expected {:.1}",
+                    energy, i, expected_energy
+                ));
+            }
+        }
+        None
+    }
+}
+
+/// Speaks `text` with the reference build of Pico at `program` (see `tests/golden/reference/generate.sh`).
+fn speak_reference(program: &Path, lang: &str, speaker: &str, text: &str) -> Vec<i16> {
+    let (ta_path, sg_path) = language_pack(lang, speaker);
+    let out_path = std::env::temp_dir().join(format!(
+        "ttspico-golden-{}_{}-{}.raw",
+        lang,
+        speaker,
+        std::process::id()
+    ));
+    let status = Command::new(program)
+        .arg(&ta_path)
+        .arg(&sg_path)
+        .arg(text)
+        .arg(&out_path)
+        .status()
+        .unwrap_or_else(|err| panic!("Failed to run {}: {}", program.display(), err));
+    assert!(status.success(), "{}_{}: {}", lang, speaker, status);
+    let pcm = fs::read(&out_path).unwrap();
+    let _ = fs::remove_file(&out_path);
+    pcm.chunks_exact(2)
+        .map(|sample| i16::from_le_bytes([sample[0], sample[1]]))
+        .collect()
+}
+
+/// Compares the speech of each bundled voice to its reference fingerprint, or regenerates the references with the
+/// program in `TTSPICO_REFERENCE`.
+#[test]
+fn golden_audio() {
+    let reference = std::env::var_os("TTSPICO_REFERENCE");
+    let mut mismatches = Vec::new();
+    for &(lang, speaker, text) in CORPUS {
+        let golden_path = PathBuf::from(GOLDEN_DIR).join(format!("{}_{}.txt", lang, speaker));
+        if let Some(program) = &reference {
+            let samples = speak_reference(Path::new(program), lang, speaker, text);
+            Fingerprint::of(&samples).write(&golden_path);
+            continue;
+        }
+
+        let samples = synthesize(&mut create_engine(lang, speaker), text)
+            .unwrap_or_else(|err| panic!("{}_{}: {}", lang, speaker, err));
+        if let Some(mismatch) = Fingerprint::of(&samples).mismatch(&Fingerprint::read(&golden_path))
+        {
+            mismatches.push(format!("{}_{}: {}", lang, speaker, mismatch));
+        }
+    }
+    assert!(
+        mismatches.is_empty(),
+        "Synthesized audio does not match the output of the original engine:\n{}",
+        mismatches.join("\n")
+    );
+}
+
+/// Each voice speaks the same whatever its memory contained before, i.e. Pico reads no uninitialized memory (as
+/// `pam_initialize` did, only setting the diagonal of its silence weights).
+#[test]
+fn memory_contents() {
+    for &(lang, speaker, text) in CORPUS {
+        let speak = |fill: u8| {
+            let mem = Box::leak(vec![fill; MEMORY_SIZE].into_boxed_slice());
+            let sys = System::with_memory(mem).unwrap();
+            synthesize(&mut create_engine_in(sys, lang, speaker), text).unwrap()
+        };
+        assert!(speak(0) == speak(0xa5), "{}_{}", lang, speaker);
+    }
+}
+
+/// An engine that is fully reset speaks the same as a new one, whatever it synthesized before (i.e. the phase
+/// angles of the signal generator are reset as well).
+#[test]
+fn reused_engine() {
+    for &(lang, speaker, text) in CORPUS {
+        let fresh = synthesize(&mut create_engine(lang, speaker), text).unwrap();
+        let mut engine = create_engine(lang, speaker);
+        synthesize(&mut engine, TEXT).unwrap();
+        assert!(
+            synthesize(&mut engine, text).unwrap() == fresh,
+            "{}_{}",
+            lang,
+            speaker
+        );
+    }
+}
+
+#[test]
+fn markup_prosody() {
+    let mut engine = create_engine("en-US", "lh0");
+    let plain = synthesize(&mut engine, TEXT).unwrap();
+
+    let fast = synthesize(
+        &mut engine,
+        &format!("<speed level=\"200\">{}</speed>", TEXT),
+    )
+    .unwrap();
+    let slow = synthesize(
+        &mut engine,
+        &format!("<speed level=\"50\">{}</speed>", TEXT),
+    )
+    .unwrap();
+    assert!(fast.len() < plain.len() && slow.len() > plain.len());
+
+    let high = synthesize(
+        &mut engine,
+        &format!("<pitch level=\"200\">{}</pitch>", TEXT),
+    )
+    .unwrap();
+    assert!(!high.is_empty() && high != plain);
+
+    let quiet = synthesize(
+        &mut engine,
+        &format!("<volume level=\"20\">{}</volume>", TEXT),
+    )
+    .unwrap();
+    assert!(rms(&quiet) < rms(&plain) / 2.0);
+
+    let speaker = synthesize(
+        &mut engine,
+        &format!("<speaker level=\"150\">{}</speaker>", TEXT),
+    )
+    .unwrap();
+    assert!(!speaker.is_empty() && speaker != plain);
+}
+
+#[test]
+fn markup_structure() {
+    let mut engine = create_engine("en-US", "lh0");
+    let plain = synthesize(&mut engine, TEXT).unwrap();
+
+    let pause = synthesize(&mut engine, &format!("{}<break time=\"1s\"/>", TEXT)).unwrap();
+    assert!(pause.len() >= plain.len() + SAMPLE_RATE * 9 / 10);
+
+    let sentence = synthesize(&mut engine, &format!("<sentence>{}</sentence>", TEXT)).unwrap();
+    let s = synthesize(&mut engine, &format!("<s>{}</s>", TEXT)).unwrap();
+    assert!(!sentence.is_empty() && s == sentence);
+
+    let paragraph = synthesize(&mut engine, &format!("<paragraph>{}</paragraph>", TEXT)).unwrap();
+    let p = synthesize(&mut engine, &format!("<p>{}</p>", TEXT)).unwrap();
+    assert!(!paragraph.is_empty() && p == paragraph);
+
+    let ignored = synthesize(
+        &mut engine,
+        &format!(
+            "{}<ignore>And this is a much longer sentence, which is not spoken.</ignore>",
+            TEXT
+        ),
+    )
+    .unwrap();
+    assert_len_near("<ignore>", &ignored, plain.len(), 0.1);
+
+    let marked = synthesize(&mut engine, "This is <mark name=\"m1\"/>a test.").unwrap();
+    assert_len_near("<mark>", &marked, plain.len(), 0.1);
+
+    let context = synthesize(
+        &mut engine,
+        &format!("<preproccontext name=\"DEFAULT\">{}</preproccontext>", TEXT),
+    )
+    .unwrap();
+    assert_len_near("<preproccontext>", &context, plain.len(), 0.1);
+
+    let voice = synthesize(
+        &mut engine,
+        &format!("<voice name=\"TestVoice\">{}</voice>", TEXT),
+    )
+    .unwrap();
+    let item = synthesize(&mut engine, &format!("<item>{}</item>", TEXT)).unwrap();
+    assert!(!voice.is_empty() && !item.is_empty());
+}
+
+#[test]
+fn markup_marks() {
+    let mut engine = create_engine("en-US", "lh0");
+    let marked = synthesize(
+        &mut engine,
+        "This is <mark name=\"m1\"/>a test. <mark name=\"m2\"/>Another test.<mark name=\"end\"/>",
+    )
+    .unwrap();
+    let marks = engine.take_marks();
+    let names: Vec<&str> = marks.iter().map(|mark| mark.name.as_str()).collect();
+    assert_eq!(names, ["m1", "m2", "end"]);
+    assert!(marks[0].sample > 0);
+    assert!(marks.windows(2).all(|pair| pair[0].sample < pair[1].sample));
+    assert!(marks[2].sample <= marked.len());
+    assert!(engine.take_marks().is_empty());
+
+    // (Positions count from the reset before each synthesis)
+    synthesize(&mut engine, "This is <mark name=\"m1\"/>a test.").unwrap();
+    assert_eq!(engine.take_marks(), marks[..1]);
+}
+
+#[test]
+fn markup_pronunciation() {
+    let mut engine = create_engine("en-US", "lh0");
+
+    let word = synthesize(&mut engine, "test").unwrap();
+    let spelled = synthesize(&mut engine, "<spell>test</spell>").unwrap();
+    assert!(spelled.len() > word.len());
+
+    let phoneme = synthesize(
+        &mut engine,
+        "<phoneme alphabet=\"xsampa\" ph=\"t&quot;Est\"/>",
+    )
+    .unwrap();
+    assert!(!phoneme.is_empty());
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn markup_files() {
+    let mut engine = create_engine("en-US", "lh0");
+    let plain = synthesize(&mut engine, TEXT).unwrap();
+
+    let dir = std::env::temp_dir();
+    engine.set_file_policy(
+        FilePolicy::deny_all()
+            .allow_reading(&dir)
+            .allow_writing(&dir),
+    );
+    let clip_path = dir.join(format!("ttspico-golden-clip-{}.wav", std::process::id()));
+    let out_path = dir.join(format!("ttspico-golden-out-{}.wav", std::process::id()));
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: SAMPLE_RATE as u32,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let clip_len = SAMPLE_RATE / 2;
+    let mut clip = hound::WavWriter::create(&clip_path, spec).unwrap();
+    for i in 0..clip_len {
+        clip.write_sample(((i as f64 * 0.1).sin() * 10000.0) as i16)
+            .unwrap();
+    }
+    clip.finalize().unwrap();
+
+    // (The clip is played in place of the text in the tag, if any)
+    let played = synthesize(
+        &mut engine,
+        &format!("<play file=\"{}\">{}</play>", clip_path.display(), TEXT),
+    )
+    .unwrap();
+    assert!(played.len() >= clip_len && played.len() < plain.len());
+    let after_text = synthesize(
+        &mut engine,
+        &format!("{}<play file=\"{}\"/>", TEXT, clip_path.display()),
+    )
+    .unwrap();
+    assert!(after_text.len() >= plain.len() + clip_len);
+    let missing = synthesize(
+        &mut engine,
+        &format!(
+            "<play file=\"{}\">{}</play>",
+            dir.join("nonexistent.wav").display(),
+            TEXT
+        ),
+    )
+    .unwrap();
+    assert_len_near("<play> of a missing file", &missing, plain.len(), 0.1);
+
+    let signal = synthesize(
+        &mut engine,
+        &format!("<usesig file=\"{}\">{}</usesig>", clip_path.display(), TEXT),
+    )
+    .unwrap();
+    assert!(!signal.is_empty());
+
+    let generated = synthesize(
+        &mut engine,
+        &format!(
+            "<genfile file=\"{}\">{}</genfile>",
+            out_path.display(),
+            TEXT
+        ),
+    )
+    .unwrap();
+    let out = hound::WavReader::open(&out_path).expect("<genfile> did not write a WAV file");
+    assert_eq!(out.spec().sample_rate, SAMPLE_RATE as u32);
+    let out_len = out.len() as usize;
+    drop(out);
+    assert_len_near("<genfile>", &generated, out_len, 0.1);
+
+    let _ = fs::remove_file(&clip_path);
+    let _ = fs::remove_file(&out_path);
+}
+
+#[test]
+fn warnings() {
+    let mut engine = create_engine("en-US", "lh0");
+    engine.reset(EngineResetMode::Full).unwrap();
+    engine.put_text("Hello <play id=\"gong\"/> world").unwrap();
+    engine.flush().unwrap();
+    let mut pcm_buf = [0i16; 1024];
+    let mut warnings = Vec::new();
+    loop {
+        let (_, status) = engine.get_data(&mut pcm_buf[..]).unwrap();
+        warnings.extend(engine.warnings().unwrap());
+        if status == EngineStatus::Idle {
+            break;
+        }
+    }
+    // (Each warning is only returned after the call that raised it)
+    assert_eq!(warnings.len(), 1, "{:?}", warnings);
+    assert_eq!(warnings[0].code, native::PICO_EXC_CANT_OPEN_FILE);
+    assert!(warnings[0].descr.contains("gong"), "{}", warnings[0].descr);
+
+    synthesize(&mut engine, TEXT).unwrap();
+    assert_eq!(engine.warnings().unwrap(), []);
+}
+
+#[test]
+fn error_paths() {
+    let err = System::new(1000).unwrap_err();
+    assert_eq!(err.code, native::PICO_EXC_OUT_OF_MEM);
+
+    // (The `debug-pico` build of Pico allocates differently, so the arena too small for a resource is not the same)
+    #[cfg(not(feature = "debug-pico"))]
+    {
+        let sys = System::new(100 * 1024).unwrap();
+        let err = System::load_resource(sys, lang_file("en-US_ta.bin")).unwrap_err();
+        assert_eq!(err.code, native::PICO_EXC_OUT_OF_MEM);
+    }
+
+    let sys = System::new(MEMORY_SIZE).unwrap();
+
+    let err = System::load_resource(Rc::clone(&sys), lang_file("nonexistent.bin")).unwrap_err();
+    assert_eq!(err.code, native::PICO_EXC_CANT_OPEN_FILE);
+
+    let err = System::load_resource(Rc::clone(&sys), lang_file("en-US_ta.txt")).unwrap_err();
+    assert_eq!(err.code, native::PICO_EXC_UNEXPECTED_FILE_TYPE);
+    let err = System::load_resource_from_bytes(Rc::clone(&sys), "garbage.bin", &[0x42; 4096])
+        .unwrap_err();
+    assert_eq!(err.code, native::PICO_EXC_UNEXPECTED_FILE_TYPE);
+
+    let corrupt = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/fuzz/regressions/load_resource/resource-length-overflow.bin"
+    );
+    let err = System::load_resource(Rc::clone(&sys), corrupt).unwrap_err();
+    assert_eq!(err.code, native::PICO_EXC_FILE_CORRUPT);
+    assert_eq!(err.descr, "corrupt file: resource length out of range");
+
+    let ta_res = System::load_resource(Rc::clone(&sys), lang_file("en-US_ta.bin")).unwrap();
+    let err = System::load_resource(Rc::clone(&sys), lang_file("en-US_ta.bin")).unwrap_err();
+    assert_eq!(err.code, native::PICO_WARN_RESOURCE_DOUBLE_LOAD);
+
+    let long_name = "x".repeat(native::PICO_MAX_VOICE_NAME_SIZE);
+    let err = System::create_voice(Rc::clone(&sys), long_name).unwrap_err();
+    assert_eq!(err.code, native::PICO_EXC_NAME_ILLEGAL);
+
+    let voice = System::create_voice(Rc::clone(&sys), "TestVoice").unwrap();
+    let err = System::create_voice(Rc::clone(&sys), "TestVoice").unwrap_err();
+    assert_eq!(err.code, native::PICO_EXC_NAME_CONFLICT);
+
+    let mut voices = vec![Rc::clone(&voice)];
+    let err = loop {
+        match System::create_voice(Rc::clone(&sys), format!("TestVoice{}", voices.len())) {
+            Ok(voice) => voices.push(voice),
+            Err(err) => break err,
+        }
+    };
+    assert_eq!(err.code, native::PICO_EXC_MAX_NUM_EXCEED);
+    assert_eq!(
+        voices.len(),
+        native::PICO_MAX_NUM_VOICE_DEFINITIONS as usize
+    );
+
+    // (Each voice definition starts out with a default resource)
+    let err = loop {
+        if let Err(err) = voice.borrow_mut().add_resource(Rc::clone(&ta_res)) {
+            break err;
+        }
+    };
+    assert_eq!(err.code, native::PICO_EXC_MAX_NUM_EXCEED);
+}
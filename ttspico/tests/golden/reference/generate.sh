@@ -0,0 +1,30 @@
+#!/bin/sh
+# Regenerates the golden references of ttspico/tests/golden.rs from the original Pico engine: the vendored sources
+# as they were imported (REV, by default the baseline commit, before any change of this repository), built for i686
+# like the 32-bit Android builds of Pico, with SSE math so that rounding does not depend on the x87 precision.
+# There is no 32-bit C library: libc.c and include/ stand in for it, with the string functions of the wasm build.
+#
+# Usage: ttspico/tests/golden/reference/generate.sh [REV]
+# Needs git, gcc with -m32 support and cargo.
+set -eu
+
+here=$(cd "$(dirname "$0")" && pwd)
+repo=$(git -C "$here" rev-parse --show-toplevel)
+rev=${1:-594f253}
+work=$(mktemp -d)
+trap 'rm -rf "$work"' EXIT
+
+mkdir "$work/src" "$work/obj"
+git -C "$repo" archive "$rev" ttspico-sys/build/pico/lib | tar -x -C "$work/src" --strip-components=4
+
+cflags="-m32 -msse2 -mfpmath=sse -ffp-contract=off -O2 -ffreestanding -fno-builtin -fno-pie -nostdinc"
+cflags="$cflags -isystem $(gcc -m32 -print-file-name=include)"
+cflags="$cflags -I$here/include -I$repo/ttspico-sys/build/wasm/include -I$work/src"
+for src in "$work"/src/*.c "$here/main.c" "$here/libc.c"; do
+    gcc $cflags -c "$src" -o "$work/obj/$(basename "$src" .c).o"
+done
+gcc $cflags -c "$repo/ttspico-sys/build/wasm/libc.c" -o "$work/obj/wasm-libc.o"
+gcc -m32 -static -nostdlib -no-pie "$work"/obj/*.o -o "$work/pico-ref"
+
+cd "$repo"
+TTSPICO_REFERENCE="$work/pico-ref" cargo test -p ttspico --test golden golden_audio
@@ -0,0 +1,17 @@
+/*
+ * The part of <limits.h> needed by Pico and the golden reference generator (see ../generate.sh).
+ *
+ * Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+ * Licensed under the Apache License, Version 2.0.
+ */
+
+#ifndef TTSPICO_REFERENCE_LIMITS_H
+#define TTSPICO_REFERENCE_LIMITS_H
+
+#define CHAR_BIT 8
+#define SHRT_MAX 32767
+#define USHRT_MAX 65535
+#define INT_MAX 2147483647
+#define UINT_MAX 4294967295u
+
+#endif /* TTSPICO_REFERENCE_LIMITS_H */
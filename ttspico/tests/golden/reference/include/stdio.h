@@ -0,0 +1,36 @@
+/*
+ * The part of <stdio.h> needed by Pico and the golden reference generator (see ../generate.sh).
+ *
+ * Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+ * Licensed under the Apache License, Version 2.0.
+ */
+
+#ifndef TTSPICO_REFERENCE_STDIO_H
+#define TTSPICO_REFERENCE_STDIO_H
+
+#include <stddef.h>
+#include <stdarg.h>
+
+typedef struct FILE FILE;
+typedef long fpos_t;
+#define EOF (-1)
+#define SEEK_SET 0
+#define SEEK_CUR 1
+#define SEEK_END 2
+FILE *fopen(const char *path, const char *mode);
+int fclose(FILE *f);
+int fflush(FILE *f);
+size_t fread(void *ptr, size_t size, size_t n, FILE *f);
+size_t fwrite(const void *ptr, size_t size, size_t n, FILE *f);
+int fseek(FILE *f, long offset, int whence);
+long ftell(FILE *f);
+int feof(FILE *f);
+int fgetc(FILE *f);
+int fgetpos(FILE *f, fpos_t *pos);
+int fsetpos(FILE *f, const fpos_t *pos);
+void clearerr(FILE *f);
+int vsprintf(char *dst, const char *fmt, va_list args);
+int sprintf(char *dst, const char *fmt, ...);
+int printf(const char *fmt, ...);
+
+#endif /* TTSPICO_REFERENCE_STDIO_H */
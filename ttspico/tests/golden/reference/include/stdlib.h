@@ -0,0 +1,19 @@
+/*
+ * The part of <stdlib.h> needed by Pico and the golden reference generator (see ../generate.sh).
+ *
+ * Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+ * Licensed under the Apache License, Version 2.0.
+ */
+
+#ifndef TTSPICO_REFERENCE_STDLIB_H
+#define TTSPICO_REFERENCE_STDLIB_H
+
+#include <stddef.h>
+
+void *malloc(size_t size);
+void free(void *ptr);
+void exit(int status);
+int atoi(const char *s);
+int abs(int x);
+
+#endif /* TTSPICO_REFERENCE_STDLIB_H */
@@ -0,0 +1,15 @@
+/*
+ * The part of <time.h> needed by Pico and the golden reference generator (see ../generate.sh).
+ *
+ * Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+ * Licensed under the Apache License, Version 2.0.
+ */
+
+#ifndef TTSPICO_REFERENCE_TIME_H
+#define TTSPICO_REFERENCE_TIME_H
+
+typedef long clock_t;
+#define CLOCKS_PER_SEC 1000000l
+clock_t clock(void);
+
+#endif /* TTSPICO_REFERENCE_TIME_H */
@@ -0,0 +1,321 @@
+/*
+ * The parts of the C library needed by Pico and main.c on i686 Linux, without one: system calls, files, a
+ * zeroed heap, memory functions, x87/SSE math and Pico's printf formats. Built by generate.sh, together with
+ * the string functions of ttspico-sys/build/wasm/libc.c.
+ *
+ * Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+ * Licensed under the Apache License, Version 2.0.
+ */
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <math.h>
+#include <time.h>
+
+static long syscall3(long nr, long a, long b, long c) {
+    long ret;
+    __asm__ volatile("int $0x80" : "=a"(ret) : "a"(nr), "b"(a), "c"(b), "d"(c) : "memory");
+    return ret;
+}
+
+#define SYS_EXIT 1
+#define SYS_READ 3
+#define SYS_WRITE 4
+#define SYS_OPEN 5
+#define SYS_CLOSE 6
+#define SYS_LSEEK 19
+
+void exit(int status) {
+    for (;;) {
+        syscall3(SYS_EXIT, status, 0, 0);
+    }
+}
+
+int write_fd(int fd, const void *buf, size_t len) {
+    const char *p = buf;
+    while (len > 0) {
+        long n = syscall3(SYS_WRITE, fd, (long)p, (long)len);
+        if (n <= 0) {
+            return -1;
+        }
+        p += n;
+        len -= (size_t)n;
+    }
+    return 0;
+}
+
+struct FILE {
+    int fd;
+    int eof;
+};
+
+static FILE files[16];
+
+FILE *fopen(const char *path, const char *mode) {
+    int flags = (mode[0] == 'w') ? (01 | 0100 | 01000) : 0; /* O_WRONLY | O_CREAT | O_TRUNC */
+    long fd = syscall3(SYS_OPEN, (long)path, flags, 0644);
+    int i;
+    if (fd < 0) {
+        return NULL;
+    }
+    for (i = 0; i < 16; i++) {
+        if (files[i].fd == 0) {
+            files[i].fd = (int)fd;
+            files[i].eof = 0;
+            return &files[i];
+        }
+    }
+    syscall3(SYS_CLOSE, fd, 0, 0);
+    return NULL;
+}
+
+int fclose(FILE *f) {
+    syscall3(SYS_CLOSE, f->fd, 0, 0);
+    f->fd = 0;
+    return 0;
+}
+
+int fflush(FILE *f) {
+    (void)f;
+    return 0;
+}
+
+size_t fread(void *ptr, size_t size, size_t n, FILE *f) {
+    size_t total = size * n, done = 0;
+    while (done < total) {
+        long r = syscall3(SYS_READ, f->fd, (long)((char *)ptr + done), (long)(total - done));
+        if (r <= 0) {
+            f->eof = 1;
+            break;
+        }
+        done += (size_t)r;
+    }
+    return size ? done / size : 0;
+}
+
+size_t fwrite(const void *ptr, size_t size, size_t n, FILE *f) {
+    return write_fd(f->fd, ptr, size * n) == 0 ? n : 0;
+}
+
+int fseek(FILE *f, long offset, int whence) {
+    f->eof = 0;
+    return syscall3(SYS_LSEEK, f->fd, offset, whence) < 0 ? -1 : 0;
+}
+
+long ftell(FILE *f) {
+    return syscall3(SYS_LSEEK, f->fd, 0, SEEK_CUR);
+}
+
+int feof(FILE *f) {
+    return f->eof;
+}
+
+int fgetc(FILE *f) {
+    unsigned char c;
+    return fread(&c, 1, 1, f) == 1 ? c : EOF;
+}
+
+int fgetpos(FILE *f, fpos_t *pos) {
+    *pos = ftell(f);
+    return 0;
+}
+
+int fsetpos(FILE *f, const fpos_t *pos) {
+    return fseek(f, *pos, SEEK_SET);
+}
+
+void clearerr(FILE *f) {
+    f->eof = 0;
+}
+
+/* (Pico allocates its memory once, so a bump allocator is enough; the heap is zeroed like fresh pages) */
+static char heap[64 * 1024 * 1024] __attribute__((aligned(16)));
+static size_t heap_used;
+
+void *malloc(size_t size) {
+    void *p;
+    size = (size + 15) & ~(size_t)15;
+    if (size > sizeof heap - heap_used) {
+        return NULL;
+    }
+    p = heap + heap_used;
+    heap_used += size;
+    return p;
+}
+
+void free(void *ptr) {
+    (void)ptr;
+}
+
+int abs(int x) {
+    return x < 0 ? -x : x;
+}
+
+void *memcpy(void *dst, const void *src, size_t n) {
+    char *d = dst;
+    const char *s = src;
+    while (n--) {
+        *d++ = *s++;
+    }
+    return dst;
+}
+
+void *memmove(void *dst, const void *src, size_t n) {
+    char *d = dst;
+    const char *s = src;
+    if (d < s) {
+        while (n--) {
+            *d++ = *s++;
+        }
+    } else {
+        while (n--) {
+            d[n] = s[n];
+        }
+    }
+    return dst;
+}
+
+void *memset(void *dst, int c, size_t n) {
+    unsigned char *d = dst;
+    while (n--) {
+        *d++ = (unsigned char)c;
+    }
+    return dst;
+}
+
+int memcmp(const void *a, const void *b, size_t n) {
+    const unsigned char *x = a, *y = b;
+    for (; n > 0; n--, x++, y++) {
+        if (*x != *y) {
+            return *x - *y;
+        }
+    }
+    return 0;
+}
+
+/* (x87 instructions, with the accuracy of a C library's for the small arguments used by Pico) */
+double sin(double x) {
+    double r;
+    __asm__("fsin" : "=t"(r) : "0"(x));
+    return r;
+}
+
+double cos(double x) {
+    double r;
+    __asm__("fcos" : "=t"(r) : "0"(x));
+    return r;
+}
+
+double sqrt(double x) {
+    double r;
+    __asm__("sqrtsd %1, %0" : "=x"(r) : "x"(x));
+    return r;
+}
+
+double fabs(double x) {
+    return x < 0 ? -x : x;
+}
+
+double exp(double x) {
+    /* e^x = 2^(x*log2(e)) = 2^i * 2^f */
+    long double r, y = (long double)x;
+    __asm__("fldl2e\n\t"
+            "fmulp\n\t"
+            "fld %%st(0)\n\t"
+            "frndint\n\t"
+            "fsubr %%st(0), %%st(1)\n\t"
+            "fxch\n\t"
+            "f2xm1\n\t"
+            "fld1\n\t"
+            "faddp\n\t"
+            "fscale\n\t"
+            "fstp %%st(1)"
+            : "=t"(r)
+            : "0"(y));
+    return (double)r;
+}
+
+clock_t clock(void) {
+    return 0;
+}
+
+static char *put_uint(char *d, unsigned long v, unsigned base) {
+    char tmp[16];
+    int n = 0;
+    do {
+        tmp[n++] = "0123456789abcdef"[v % base];
+        v /= base;
+    } while (v);
+    while (n) {
+        *d++ = tmp[--n];
+    }
+    return d;
+}
+
+/* (Only for Pico's messages: %s, %c, %d/%i, %u, %x and %%, ignoring flags, width and precision) */
+int vsprintf(char *dst, const char *fmt, va_list args) {
+    char *d = dst;
+    for (; *fmt; fmt++) {
+        if (*fmt != '%') {
+            *d++ = *fmt;
+            continue;
+        }
+        fmt++;
+        while (*fmt == '-' || *fmt == '.' || *fmt == 'l' || (*fmt >= '0' && *fmt <= '9')) {
+            fmt++;
+        }
+        switch (*fmt) {
+        case 's': {
+            const char *s = va_arg(args, const char *);
+            while (*s) {
+                *d++ = *s++;
+            }
+            break;
+        }
+        case 'c':
+            *d++ = (char)va_arg(args, int);
+            break;
+        case 'd':
+        case 'i': {
+            long v = va_arg(args, long);
+            if (v < 0) {
+                *d++ = '-';
+                v = -v;
+            }
+            d = put_uint(d, (unsigned long)v, 10);
+            break;
+        }
+        case 'u':
+            d = put_uint(d, va_arg(args, unsigned long), 10);
+            break;
+        case 'x':
+            d = put_uint(d, va_arg(args, unsigned long), 16);
+            break;
+        default:
+            *d++ = *fmt;
+        }
+    }
+    *d = 0;
+    return (int)(d - dst);
+}
+
+int sprintf(char *dst, const char *fmt, ...) {
+    va_list args;
+    int n;
+    va_start(args, fmt);
+    n = vsprintf(dst, fmt, args);
+    va_end(args);
+    return n;
+}
+
+int printf(const char *fmt, ...) {
+    char buf[1024];
+    va_list args;
+    int n;
+    va_start(args, fmt);
+    n = vsprintf(buf, fmt, args);
+    va_end(args);
+    write_fd(1, buf, (size_t)n);
+    return n;
+}
@@ -0,0 +1,82 @@
+/*
+ * Speaks TEXT with the TA and SG resources given as arguments, writing its 16-bit samples to OUT, with the
+ * original Pico API. Built by generate.sh, to produce the golden references.
+ *
+ * Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+ * Licensed under the Apache License, Version 2.0.
+ */
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include "picoapi.h"
+
+#define MEMORY_SIZE (4 * 1024 * 1024)
+
+static void check(pico_Status status, const char *what) {
+    if (status != PICO_OK) {
+        printf("%s failed: %i\n", what, status);
+        exit(1);
+    }
+}
+
+static int run(int argc, char **argv) {
+    pico_System sys;
+    pico_Resource ta, sg;
+    pico_Char ta_name[PICO_MAX_RESOURCE_NAME_SIZE], sg_name[PICO_MAX_RESOURCE_NAME_SIZE];
+    pico_Engine engine;
+    const char *text;
+    pico_Int16 len, n_put, n_bytes, type;
+    pico_Int16 buf[1024];
+    pico_Status status;
+    FILE *out;
+
+    if (argc != 5) {
+        printf("usage: %s TA SG TEXT OUT\n", argv[0]);
+        return 2;
+    }
+    check(pico_initialize(malloc(MEMORY_SIZE), MEMORY_SIZE, &sys), "pico_initialize");
+    check(pico_loadResource(sys, (pico_Char *)argv[1], &ta), "loading the TA");
+    check(pico_loadResource(sys, (pico_Char *)argv[2], &sg), "loading the SG");
+    check(pico_getResourceName(sys, ta, (char *)ta_name), "pico_getResourceName");
+    check(pico_getResourceName(sys, sg, (char *)sg_name), "pico_getResourceName");
+    check(pico_createVoiceDefinition(sys, (pico_Char *)"RefVoice"), "pico_createVoiceDefinition");
+    check(pico_addResourceToVoiceDefinition(sys, (pico_Char *)"RefVoice", ta_name), "adding the TA");
+    check(pico_addResourceToVoiceDefinition(sys, (pico_Char *)"RefVoice", sg_name), "adding the SG");
+    check(pico_newEngine(sys, (pico_Char *)"RefVoice", &engine), "pico_newEngine");
+
+    out = fopen(argv[4], "wb");
+    if (!out) {
+        printf("cannot open %s\n", argv[4]);
+        return 1;
+    }
+    text = argv[3];
+    len = (pico_Int16)(strlen(text) + 1); /* (with the NUL, which flushes the engine) */
+    while (len > 0) {
+        check(pico_putTextUtf8(engine, (const pico_Char *)text, len, &n_put), "pico_putTextUtf8");
+        text += n_put;
+        len -= n_put;
+        do {
+            status = pico_getData(engine, buf, sizeof buf, &n_bytes, &type);
+            if (status != PICO_STEP_BUSY && status != PICO_STEP_IDLE) {
+                check(status, "pico_getData");
+            }
+            fwrite(buf, 1, (size_t)n_bytes, out);
+        } while (status == PICO_STEP_BUSY);
+    }
+    fclose(out);
+    return 0;
+}
+
+__attribute__((used)) static void start_c(int *sp) {
+    exit(run(sp[0], (char **)(sp + 1)));
+}
+
+__asm__(".globl _start\n"
+        "_start:\n"
+        "  xor %ebp, %ebp\n"
+        "  mov %esp, %eax\n"
+        "  and $-16, %esp\n"
+        "  sub $12, %esp\n"
+        "  push %eax\n"
+        "  call start_c\n");
@@ -0,0 +1,191 @@
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tests the identification of the language of text with the bundled [`LanguageModel`].
+
+#![cfg(feature = "langid")]
+
+use ttspico::{
+    Granularity, LanguageDetector, LanguageModel, MultiVoiceDocument, MultiVoiceSynthesizer, System,
+};
+
+mod common;
+use common::language_pack;
+
+/// Sentences that are not in the bundled corpora, by language.
+const SENTENCES: &[(&str, &str)] = &[
+    ("de", "Die Sitzung wurde auf morgen Nachmittag verschoben."),
+    ("de", "Vergessen Sie nicht, die Fenster zu schließen."),
+    (
+        "en",
+        "The parcel could not be delivered because nobody was at home.",
+    ),
+    ("en", "Remember to water the plants before you leave."),
+    ("es", "La reunión se ha aplazado hasta mañana por la tarde."),
+    ("es", "No olvides cerrar las ventanas antes de salir."),
+    ("fr", "La séance a été reportée à demain après-midi."),
+    (
+        "fr",
+        "N'oubliez pas de fermer les fenêtres avant de partir.",
+    ),
+    ("it", "La riunione è stata rinviata a domani pomeriggio."),
+    (
+        "it",
+        "Non dimenticare di chiudere le finestre prima di uscire.",
+    ),
+];
+
+#[test]
+fn identify() {
+    let model = LanguageModel::bundled();
+    assert_eq!(
+        model.languages().collect::<Vec<_>>(),
+        ["de", "en", "es", "fr", "it"]
+    );
+    for (language, sentence) in SENTENCES {
+        let detected = model.identify(sentence).unwrap();
+        assert_eq!(detected.language, *language, "{}", sentence);
+        assert!(detected.confidence > 0.9, "{}: {:?}", sentence, detected);
+
+        let scores = model.scores(sentence, |_| true);
+        assert_eq!(scores.len(), 5);
+        let total: f64 = scores.iter().map(|score| score.confidence).sum();
+        assert!((total - 1.0).abs() < 1e-9);
+    }
+    // (Markup is ignored, and text without letters has no language)
+    assert_eq!(
+        model
+            .identify("<break time=\"500ms\"/>Guten Morgen, wie geht es Ihnen?")
+            .unwrap()
+            .language,
+        "de"
+    );
+    assert_eq!(model.identify("<mark name=\"x\"/> 12:30 !"), None);
+}
+
+#[test]
+fn train() {
+    let mut model = LanguageModel::new();
+    assert_eq!(model.identify("Hello"), None);
+    model.train("tlh", "Qapla' nuqneH tlhIngan maH Heghlu'meH QaQ jajvam");
+    model.train(
+        "en",
+        "Success, hello, we are Klingons, today is a good day to die",
+    );
+    assert_eq!(model.identify("nuqneH tlhIngan").unwrap().language, "tlh");
+}
+
+#[test]
+fn threshold_and_overrides() {
+    let detector = LanguageDetector::new(LanguageModel::bundled());
+    let sentence = SENTENCES[8].1;
+    assert_eq!(detector.voice_for(sentence).as_deref(), Some("it"));
+    assert_eq!(detector.voice_for("..."), None);
+
+    // (Below the threshold, the fallback voice speaks)
+    let ambiguous = "Taxi";
+    let confidence = detector.detect(ambiguous).unwrap().confidence;
+    assert!(confidence < 0.9, "{}", confidence);
+    let detector = detector.fallback("en-US");
+    assert_eq!(detector.voice_for(ambiguous).as_deref(), Some("en-US"));
+    let detector = detector.threshold(0.0);
+    assert_ne!(detector.voice_for(ambiguous).as_deref(), Some("en-US"));
+
+    let detector = detector.map_language("it", "Carla");
+    assert_eq!(detector.voice_for(sentence).as_deref(), Some("Carla"));
+
+    // (Candidates are matched by base language)
+    let detector = detector.candidates(["en-GB", "de-DE"]);
+    let detected = detector.detect(sentence).unwrap();
+    assert!(detected.language == "en" || detected.language == "de");
+}
+
+#[test]
+fn split() {
+    let detector = LanguageDetector::new(LanguageModel::bundled()).map_language("en", "en-GB");
+    let text = format!(
+        "{} {}  <break time=\"1s\"/>{}\n{} 42! {}",
+        SENTENCES[2].1, SENTENCES[3].1, SENTENCES[0].1, SENTENCES[1].1, SENTENCES[9].1
+    );
+    let doc = detector.split(&text).unwrap();
+    let segments: Vec<(&str, &str)> = doc
+        .segments()
+        .iter()
+        .map(|segment| (segment.voice.as_str(), segment.text.as_str()))
+        .collect();
+    assert_eq!(
+        segments,
+        [
+            (
+                "en-GB",
+                &format!("{} {}  ", SENTENCES[2].1, SENTENCES[3].1)[..]
+            ),
+            (
+                "de",
+                &format!(
+                    "<break time=\"1s\"/>{}\n{} 42! ",
+                    SENTENCES[0].1, SENTENCES[1].1
+                )[..]
+            ),
+            ("it", SENTENCES[9].1),
+        ]
+    );
+
+    // (Paragraphs are identified as a whole, and <voice> elements keep their voice)
+    let text = format!(
+        "{} {}\n\n<voice name=\"Lisa\">{}</voice>{}",
+        SENTENCES[4].1, SENTENCES[2].1, SENTENCES[6].1, SENTENCES[7].1
+    );
+    let doc = detector
+        .clone()
+        .granularity(Granularity::Paragraph)
+        .split(&text)
+        .unwrap();
+    assert_eq!(
+        doc,
+        MultiVoiceDocument::new()
+            .segment("es", format!("{} {}\n\n", SENTENCES[4].1, SENTENCES[2].1))
+            .segment("Lisa", SENTENCES[6].1)
+            .segment("fr", SENTENCES[7].1)
+    );
+}
+
+#[test]
+fn speak_detected() {
+    let packs = [("en-GB", "kh0"), ("de-DE", "gl0"), ("it-IT", "cm0")];
+    let mut builder = System::builder();
+    for (language, speaker) in &packs {
+        let (ta_path, sg_path) = language_pack(language, speaker);
+        builder = builder.language_pack(ta_path, sg_path);
+    }
+    let mut synth = MultiVoiceSynthesizer::new(builder.build().unwrap());
+    for (language, speaker) in &packs {
+        let (ta_path, sg_path) = language_pack(language, speaker);
+        synth
+            .add_voice(*language, *language, ta_path, sg_path)
+            .unwrap();
+    }
+
+    let detector = LanguageDetector::new(LanguageModel::bundled()).candidates(synth.languages());
+    let text = format!("{} {} {}", SENTENCES[2].1, SENTENCES[0].1, SENTENCES[8].1);
+    let doc = detector.split(&text).unwrap();
+    let voices: Vec<&str> = doc
+        .segments()
+        .iter()
+        .map(|segment| synth.find_voice(&segment.voice).unwrap())
+        .collect();
+    assert_eq!(voices, ["en-GB", "de-DE", "it-IT"]);
+    let audio = synth.synthesize(&doc).unwrap();
+    assert!(audio.len() > 16000 * 3);
+}
@@ -0,0 +1,82 @@
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tests [`MockEngine`] through code written against [`SpeechEngine`], as downstream code would.
+
+use ttspico::{EngineResetMode, MockEngine, MockOperation, PicoError, SpeechEngine};
+use ttspico_sys as native;
+
+/// Speaks `text` to completion, resetting `engine` and giving up on the first error.
+fn speak(engine: &mut dyn SpeechEngine, text: &[u8]) -> Result<Vec<i16>, PicoError> {
+    let mut samples = Vec::new();
+    let mut pcm_buf = [0i16; 256];
+    let mut input = text;
+    let result = (|| loop {
+        let (n_samples, done) = engine.step(&mut input, &mut pcm_buf[..])?;
+        samples.extend_from_slice(&pcm_buf[..n_samples]);
+        if done {
+            return Ok(());
+        }
+    })();
+    if result.is_err() {
+        engine.reset(EngineResetMode::Full)?;
+    }
+    result.map(|()| samples)
+}
+
+fn error(code: native::pico_Status) -> PicoError {
+    PicoError {
+        code,
+        descr: "scripted".to_string(),
+    }
+}
+
+#[test]
+fn speaks_tone_per_byte() {
+    let mut engine = MockEngine::new().samples_per_byte(100).max_put_len(3);
+    let samples = speak(&mut engine, b"Hello, world").unwrap();
+    assert_eq!(samples.len(), 1200);
+    assert_eq!(engine.utterances(), &[b"Hello, world".to_vec()]);
+    assert!(engine.pending_text().is_empty());
+
+    let peak = samples.iter().map(|s| s.abs()).max().unwrap();
+    assert_eq!(peak, 8000);
+    // (Deterministic output)
+    assert_eq!(
+        speak(
+            &mut MockEngine::new().samples_per_byte(100),
+            b"Hello, world"
+        )
+        .unwrap(),
+        samples
+    );
+}
+
+#[test]
+fn scripted_errors() {
+    let mut engine = MockEngine::new();
+    engine.fail_next(MockOperation::GetData, error(native::PICO_EXC_BUF_OVERFLOW));
+    engine.fail_next(MockOperation::PutText, error(native::PICO_ERR_OTHER));
+
+    let err = speak(&mut engine, b"First").unwrap_err();
+    assert_eq!(err.code, native::PICO_ERR_OTHER);
+    let err = speak(&mut engine, b"Second").unwrap_err();
+    assert_eq!(err.code, native::PICO_EXC_BUF_OVERFLOW);
+    assert!(!speak(&mut engine, b"Third").unwrap().is_empty());
+
+    engine.fail_next(MockOperation::Reset, error(native::PICO_ERR_INVALID_HANDLE));
+    engine.fail_next(MockOperation::GetData, error(native::PICO_STEP_ERROR));
+    let err = speak(&mut engine, b"Fourth").unwrap_err();
+    assert_eq!(err.code, native::PICO_ERR_INVALID_HANDLE);
+}
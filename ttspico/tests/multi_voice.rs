@@ -0,0 +1,196 @@
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tests [`MultiVoiceSynthesizer`]s with the language packs bundled with Pico's sources.
+
+use std::time::Duration;
+use ttspico::{MultiVoiceDocument, MultiVoiceSynthesizer, System, VoiceSegment};
+
+mod common;
+use common::{language_pack, SAMPLE_RATE};
+
+/// The voices of the synthesizer: (name, language, speaker).
+const VOICES: &[(&str, &str, &str)] = &[
+    ("Kate", "en-GB", "kh0"),
+    ("Lisa", "en-US", "lh0"),
+    ("Gerd", "de-DE", "gl0"),
+    ("Carla", "it-IT", "cm0"),
+];
+
+fn create_synth() -> MultiVoiceSynthesizer {
+    let mut builder = System::builder();
+    for (_, language, speaker) in VOICES {
+        let (ta_path, sg_path) = language_pack(language, speaker);
+        builder = builder.language_pack(ta_path, sg_path);
+    }
+    let mut synth = MultiVoiceSynthesizer::new(builder.build().unwrap());
+    for (name, language, speaker) in VOICES {
+        let (ta_path, sg_path) = language_pack(language, speaker);
+        synth.add_voice(*name, *language, ta_path, sg_path).unwrap();
+    }
+    synth
+}
+
+#[test]
+fn find_voice() {
+    let synth = create_synth();
+    assert_eq!(
+        synth.voices().collect::<Vec<_>>(),
+        ["Kate", "Lisa", "Gerd", "Carla"]
+    );
+    assert_eq!(synth.find_voice("lisa"), Some("Lisa"));
+    assert_eq!(synth.find_voice("en-US"), Some("Lisa"));
+    assert_eq!(synth.find_voice("en_us"), Some("Lisa"));
+    assert_eq!(synth.find_voice("en"), Some("Kate"));
+    assert_eq!(synth.find_voice("it-CH"), Some("Carla"));
+    assert_eq!(synth.find_voice(""), Some("Kate"));
+    assert_eq!(synth.find_voice("fr"), None);
+}
+
+#[test]
+fn stitched() {
+    let mut synth = create_synth();
+    let speak = |synth: &mut MultiVoiceSynthesizer, segments: &[(&str, &str)]| {
+        let mut doc = MultiVoiceDocument::new();
+        for (voice, text) in segments {
+            doc.push(*voice, *text);
+        }
+        synth.synthesize(&doc).unwrap()
+    };
+    let dialogue = [
+        ("en-GB", "Shall we go?"),
+        ("en-US", "Sure, <mark name=\"here\"/>let's go."),
+        ("de", "Guten Tag."),
+        ("it", "Buongiorno."),
+    ];
+    let parts: Vec<Vec<i16>> = dialogue
+        .iter()
+        .map(|segment| speak(&mut synth, &[*segment]))
+        .collect();
+    assert!(parts.iter().all(|part| part.len() > SAMPLE_RATE / 4));
+    // (Each voice speaks differently)
+    assert_ne!(speak(&mut synth, &[("en-US", dialogue[0].1)]), parts[0]);
+    synth.take_marks();
+
+    // (Each segment is spoken the same as on its own, with the gap in between)
+    synth.set_gap(Duration::from_millis(250));
+    let gap = vec![0i16; SAMPLE_RATE / 4];
+    let audio = speak(&mut synth, &dialogue);
+    let expected: Vec<i16> = parts.join(&gap[..]);
+    assert_eq!(audio, expected);
+
+    let marks = synth.take_marks();
+    let second = parts[0].len() + gap.len();
+    #[cfg(not(feature = "system"))]
+    {
+        assert_eq!(marks.len(), 1);
+        assert_eq!(marks[0].name, "here");
+        assert!(marks[0].sample > second && marks[0].sample < second + parts[1].len());
+    }
+    #[cfg(feature = "system")]
+    assert_eq!(marks, []);
+
+    // (Chunks stream the same audio)
+    let mut streamed = Vec::new();
+    let doc = MultiVoiceDocument::new()
+        .segment("Kate", dialogue[0].1)
+        .segment("Lisa", dialogue[1].1);
+    synth
+        .speak(&doc, |chunk| streamed.extend_from_slice(chunk))
+        .unwrap();
+    assert_eq!(streamed, expected[..second + parts[1].len()]);
+}
+
+#[test]
+fn unknown_voice() {
+    let mut synth = create_synth();
+    let doc = MultiVoiceDocument::new()
+        .segment("en-GB", "Hello.")
+        .segment("fr-FR", "Bonjour.");
+    let mut n_samples = 0;
+    let err = synth
+        .speak(&doc, |chunk| n_samples += chunk.len())
+        .unwrap_err();
+    assert!(err.descr.contains("fr-FR"), "{}", err.descr);
+    assert_eq!(n_samples, 0);
+}
+
+/// A synthesizer with the voices of a [`VoiceCatalog`] speaks with each like a single engine would.
+#[cfg(feature = "std")]
+#[test]
+fn catalog() {
+    let catalog = ttspico::VoiceCatalog::new([common::LANG_DIR, "/nonexistent"]);
+    let voices: Vec<(&str, &str)> = catalog
+        .voices()
+        .iter()
+        .map(|voice| (voice.language.as_str(), voice.speaker.as_str()))
+        .collect();
+    assert_eq!(
+        voices,
+        [
+            ("de-DE", "gl0"),
+            ("en-GB", "kh0"),
+            ("en-US", "lh0"),
+            ("es-ES", "zl0"),
+            ("fr-FR", "nk0"),
+            ("it-IT", "cm0")
+        ]
+    );
+    assert_eq!(catalog.get("en-US").unwrap().speaker, "lh0");
+    assert_eq!(catalog.get("en-us"), None);
+    assert_eq!(catalog.find_voice("en_us").unwrap().language, "en-US");
+    assert_eq!(catalog.find_voice("en").unwrap().language, "en-GB");
+    assert_eq!(catalog.find_voice("it-CH").unwrap().language, "it-IT");
+    assert_eq!(catalog.find_voice("xx"), None);
+
+    let mut synth = MultiVoiceSynthesizer::new(catalog.system_builder().build().unwrap());
+    synth.add_catalog(&catalog).unwrap();
+    assert_eq!(synth.find_voice("fr"), Some("fr-FR"));
+    let speech = synth
+        .synthesize(&MultiVoiceDocument::new().segment("it", "Ciao mondo."))
+        .unwrap();
+    let engine = synth.engine("it").unwrap();
+    assert_eq!(common::synthesize(engine, "Ciao mondo.").unwrap(), speech);
+    assert!(synth.engine("xx").is_err());
+}
+
+#[test]
+fn parse() {
+    let doc = MultiVoiceDocument::parse(
+        "Hello. <VOICE name='en-US'>Hi, <emphasis>there</emphasis>!</VOICE>\n\
+         <voice name=\"it\" gender=\"female\">Ciao.</voice>",
+    )
+    .unwrap();
+    let segment = |voice: &str, text: &str| VoiceSegment {
+        voice: voice.to_string(),
+        text: text.to_string(),
+    };
+    assert_eq!(
+        doc.segments(),
+        [
+            segment("", "Hello. "),
+            segment("en-US", "Hi, <emphasis>there</emphasis>!"),
+            segment("it", "Ciao."),
+        ]
+    );
+
+    for markup in &[
+        "<voice name=\"en\">Hello",
+        "<voice>Hello</voice>",
+        "<voice name=\"en\"><voice name=\"de\">Hallo</voice></voice>",
+        "Hello</voice>",
+    ] {
+        assert!(MultiVoiceDocument::parse(markup).is_err(), "{}", markup);
+    }
+}
@@ -0,0 +1,92 @@
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tests the synthesis threads of [`SynthesizerPool`] and [`spawn_synthesis_thread`].
+
+#![cfg(feature = "std")]
+
+use std::sync::mpsc;
+use ttspico::{
+    spawn_synthesis_thread, MultiVoiceDocument, PicoError, SynthesizerPool, VoiceCatalog,
+};
+use ttspico_sys as native;
+
+mod common;
+use common::{create_engine, synthesize, LANG_DIR, TEXT};
+
+#[test]
+fn pool() {
+    let catalog = VoiceCatalog::new([LANG_DIR]);
+    let memsz = catalog.system_builder().measure().unwrap();
+    let (results_tx, results_rx) = mpsc::channel();
+    let pool = SynthesizerPool::start(2, memsz, &catalog, move |voice: &str, synth| {
+        let doc = MultiVoiceDocument::new().segment(voice, TEXT);
+        results_tx.send((voice, synth.synthesize(&doc))).unwrap();
+    })
+    .unwrap();
+    for voice in ["en-US", "de-DE", "en-US", "de-DE"] {
+        pool.send(voice).unwrap();
+    }
+    // (Dropping the pool waits for the jobs sent so far)
+    drop(pool);
+
+    let results: Vec<_> = results_rx.iter().collect();
+    assert_eq!(results.len(), 4);
+    for (voice, samples) in results {
+        let (lang, speaker) = match voice {
+            "en-US" => ("en-US", "lh0"),
+            _ => ("de-DE", "gl0"),
+        };
+        let expected = synthesize(&mut create_engine(lang, speaker), TEXT).unwrap();
+        assert!(samples.unwrap() == expected, "{}", voice);
+    }
+}
+
+#[test]
+fn pool_errors() {
+    let catalog = VoiceCatalog::new([LANG_DIR]);
+    let err = SynthesizerPool::<()>::start(2, 1000, &catalog, |_, _| {})
+        .err()
+        .unwrap();
+    assert_eq!(err.code, native::PICO_EXC_OUT_OF_MEM);
+}
+
+#[test]
+fn synthesis_thread() {
+    let (jobs_tx, jobs_rx) = mpsc::channel::<&str>();
+    let (results_tx, results_rx) = mpsc::channel();
+    let thread = spawn_synthesis_thread(
+        || Ok::<_, PicoError>(create_engine("en-US", "lh0")),
+        move |mut engine| {
+            for text in jobs_rx {
+                results_tx.send(synthesize(&mut engine, text)).unwrap();
+            }
+        },
+    )
+    .unwrap();
+    jobs_tx.send(TEXT).unwrap();
+    drop(jobs_tx);
+    thread.join().unwrap();
+    let expected = synthesize(&mut create_engine("en-US", "lh0"), TEXT).unwrap();
+    assert_eq!(results_rx.recv().unwrap(), Ok(expected));
+
+    let err = spawn_synthesis_thread(|| Err::<(), _>("no system"), |_| {}).unwrap_err();
+    assert_eq!(err, "no system");
+
+    // (Panics of the initialization are resumed on the calling thread)
+    let panicked = std::panic::catch_unwind(|| {
+        spawn_synthesis_thread(|| -> Result<(), ()> { panic!("no system") }, |_| {})
+    });
+    assert!(panicked.is_err());
+}
@@ -0,0 +1,100 @@
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tests that the conversion of SSML cannot be used to inject Pico markup.
+
+#![cfg(feature = "std")]
+
+use ttspico::ssml_to_markup;
+
+mod common;
+#[cfg(not(feature = "system"))]
+use common::{create_engine, synthesize};
+
+#[test]
+fn escaped_text() {
+    // (Escaped tags in text stay text)
+    assert_eq!(
+        ssml_to_markup("<speak>a &lt;play file=\"/etc/passwd\"/&gt; b</speak>").unwrap(),
+        "a  play file=\"/etc/passwd\"/  b"
+    );
+    assert_eq!(
+        ssml_to_markup("a &#60;speed level='500'&#x3E;b").unwrap(),
+        "a  speed level='500' b"
+    );
+    // (...and so do those in aliases, which are not decoded twice)
+    assert_eq!(
+        ssml_to_markup("<sub alias=\"&lt;speed level='500'&gt;\">fast</sub>").unwrap(),
+        " speed level='500' "
+    );
+    assert_eq!(
+        ssml_to_markup("<sub alias=\"&amp;lt;x\">x</sub>").unwrap(),
+        "&lt;x"
+    );
+    assert_eq!(ssml_to_markup("It's 3 &gt; 2").unwrap(), "It's 3   2");
+}
+
+#[test]
+fn escaped_attributes() {
+    assert_eq!(
+        ssml_to_markup("<mark name=\"a&gt;b\"/>").unwrap(),
+        "<mark name=\"ab\"/>"
+    );
+    // (Quotes and backslashes are escaped, so that they cannot end the value)
+    assert_eq!(
+        ssml_to_markup("<mark name='a\"b&apos;c&lt;d'/>").unwrap(),
+        "<mark name=\"a\\\"b\\'cd\"/>"
+    );
+    assert_eq!(
+        ssml_to_markup("<mark name=\"a\\\"/>Hi").unwrap(),
+        "<mark name=\"a\\\\\"/>Hi"
+    );
+    assert_eq!(
+        ssml_to_markup("<break time=\"1s&quot;/&gt;&lt;play file=&quot;x\"/>").unwrap(),
+        "<break time=\"1s\\\"/play file=\\\"x\"/>"
+    );
+    assert_eq!(
+        ssml_to_markup("<phoneme ph=\"h@'loU\">Hello</phoneme>").unwrap(),
+        "<phoneme alphabet=\"xsampa\" ph=\"h@\\'loU\"/>"
+    );
+    // (...but not in file names, where they are path separators)
+    assert_eq!(
+        ssml_to_markup("<audio src=\"C:\\chime.wav&quot;&gt;\">Ding</audio>").unwrap(),
+        "<play file=\"C:\\chime.wav\">Ding</play>"
+    );
+}
+
+/// Pico reads the converted markup as intended: tags in text are spoken, and escaped attribute values are unescaped.
+#[cfg(not(feature = "system"))]
+#[test]
+fn synthesized() {
+    let mut engine = create_engine("en-US", "lh0");
+    let speak = |engine: &mut ttspico::Engine, ssml: &str| {
+        synthesize(engine, &ssml_to_markup(ssml).unwrap()).unwrap()
+    };
+
+    // (Files are denied by default, so a <play> tag would fail the synthesis)
+    let text = speak(&mut engine, "a &lt;play file=\"/etc/passwd\"/&gt; b");
+    assert!(!text.is_empty());
+
+    speak(&mut engine, "<mark name='a\"b\\c&apos;d'/>Hi");
+    let marks = engine.take_marks();
+    assert_eq!(marks.len(), 1);
+    assert_eq!(marks[0].name, "a\"b\\c'd");
+
+    assert_eq!(
+        speak(&mut engine, "<phoneme ph=\"h@'loU\">Hello</phoneme>"),
+        synthesize(&mut engine, "<phoneme alphabet=\"xsampa\" ph=\"h@'loU\"/>").unwrap()
+    );
+}
@@ -0,0 +1,197 @@
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tests the memory and diagnostics of [`System`]s: Pico's version, tracing and memory usage, the sizing of memory
+//! by [`System::builder`], and systems in caller-provided memory with resources loaded from bytes (as without the
+//! `std` feature).
+//!
+//! Pico allows one system per thread, so each test drops its systems before creating the next one.
+
+use std::rc::Rc;
+use ttspico::{System, TraceLevel};
+use ttspico_sys as native;
+
+mod common;
+use common::{create_engine, create_engine_in, language_pack, synthesize, MEMORY_SIZE, TEXT};
+
+#[test]
+fn version() {
+    let version = System::version().unwrap();
+    // (The vendored sources do not track their version)
+    #[cfg(not(feature = "system"))]
+    assert_eq!(version, "invalid");
+    #[cfg(feature = "system")]
+    let _ = version;
+}
+
+#[test]
+fn set_trace() {
+    let sys = System::new(MEMORY_SIZE).unwrap();
+    let sys = sys.borrow();
+    sys.set_trace(TraceLevel::Trace, Some("picotok.c")).unwrap();
+    sys.set_trace(TraceLevel::Error, None).unwrap();
+    sys.set_trace(TraceLevel::Off, None).unwrap();
+    assert!(TraceLevel::Off < TraceLevel::Error && TraceLevel::Debug < TraceLevel::Trace);
+
+    // (Pico only keeps the first 16 bytes of a filter)
+    let err = sys
+        .set_trace(TraceLevel::Info, Some("picoextapi-long.c"))
+        .unwrap_err();
+    assert_eq!(err.code, -1);
+    assert!(sys
+        .set_trace(TraceLevel::Info, Some("pico\0tok.c"))
+        .is_err());
+
+    // (Targets name the file of their messages)
+    let target = sys.set_trace(TraceLevel::Info, Some("ttspico::pico::picoextapi"));
+    #[cfg(any(feature = "debug-pico", feature = "tracing"))]
+    target.unwrap();
+    #[cfg(not(any(feature = "debug-pico", feature = "tracing")))]
+    assert_eq!(target.unwrap_err().code, -1);
+}
+
+#[test]
+fn memory_usage() {
+    let sys = System::new(MEMORY_SIZE).unwrap();
+    assert_eq!(sys.borrow().memory_size(), MEMORY_SIZE);
+    let initial = sys.borrow().memory_usage(true).unwrap();
+    assert!(initial.used > 0 && initial.max_used >= initial.used);
+    assert_eq!(sys.borrow().memory_usage(false).unwrap().incremental, 0);
+
+    let mut engine = create_engine_in(Rc::clone(&sys), "en-US", "lh0");
+    let loaded = sys.borrow().memory_usage(false).unwrap();
+    assert!(loaded.used > initial.used && loaded.max_used >= loaded.used);
+    assert!(loaded.incremental > 0);
+
+    let created = engine.memory_usage(true).unwrap();
+    assert!(created.used > 0 && created.used < loaded.used);
+    assert_eq!(engine.memory_usage(false).unwrap().incremental, 0);
+    synthesize(&mut engine, TEXT).unwrap();
+    let spoken = engine.memory_usage(false).unwrap();
+    assert!(spoken.used > 0 && spoken.max_used >= created.max_used.max(spoken.used));
+
+    // (The engine's memory is the system's)
+    drop(engine);
+    assert!(sys.borrow().memory_usage(false).unwrap().used < loaded.used);
+}
+
+#[test]
+fn builder() {
+    let (ta_path, sg_path) = language_pack("en-US", "lh0");
+    let builder = System::builder().language_pack(ta_path, sg_path);
+    let measured = builder.measure().unwrap();
+    assert!(
+        measured > 64 * 1024 && measured < 32 * 1024 * 1024,
+        "{}",
+        measured
+    );
+    assert_eq!(
+        builder.clone().headroom(0).measure().unwrap(),
+        measured - 64 * 1024
+    );
+
+    // (The measured size fits the language pack and an engine speaking it)
+    let sys = builder.build().unwrap();
+    assert_eq!(sys.borrow().memory_size(), measured);
+    let speech = synthesize(&mut create_engine_in(sys, "en-US", "lh0"), TEXT).unwrap();
+    assert!(!speech.is_empty());
+
+    let sys = System::builder().memory_size(1024 * 1024).build().unwrap();
+    assert_eq!(sys.borrow().memory_size(), 1024 * 1024);
+    drop(sys);
+    let sys = System::builder().build().unwrap();
+    assert_eq!(sys.borrow().memory_size(), 4 * 1024 * 1024);
+    drop(sys);
+
+    let err = System::builder()
+        .language_pack(common::lang_file("nonexistent_ta.bin"), "")
+        .build()
+        .unwrap_err();
+    assert_eq!(err.code, native::PICO_EXC_CANT_OPEN_FILE);
+}
+
+/// Failing to load a resource for lack of memory says how much is missing.
+// (With `debug-pico`, Pico allocates resources outside of the system's memory)
+#[cfg(all(
+    not(feature = "debug-pico"),
+    any(feature = "std", not(feature = "system"))
+))]
+#[test]
+fn memory_shortfall() {
+    let (ta_path, _) = language_pack("en-US", "lh0");
+    let needed = std::fs::metadata(&ta_path).unwrap().len();
+    let assert_shortfall = |err: ttspico::PicoError| {
+        assert_eq!(err.code, native::PICO_EXC_OUT_OF_MEM);
+        assert!(
+            err.descr.contains(&format!("needs about {} bytes", needed))
+                && err.descr.contains("bytes short"),
+            "{}",
+            err.descr
+        );
+    };
+    let sys = System::new(256 * 1024).unwrap();
+    // (The size of a resource file is only known with `std`)
+    #[cfg(feature = "std")]
+    assert_shortfall(System::load_resource(Rc::clone(&sys), &ta_path).unwrap_err());
+    #[cfg(not(feature = "system"))]
+    assert_shortfall(
+        System::load_resource_from_bytes(sys, "en-US_ta.bin", &std::fs::read(&ta_path).unwrap())
+            .unwrap_err(),
+    );
+}
+
+#[test]
+fn caller_memory() {
+    let expected = synthesize(&mut create_engine("en-US", "lh0"), TEXT).unwrap();
+
+    // (The start of the memory is aligned, so a little of it can go unused)
+    let mem: &'static mut [u8] = &mut Box::leak(vec![0; MEMORY_SIZE + 1].into_boxed_slice())[1..];
+    let sys = System::with_memory(mem).unwrap();
+    let memsz = sys.borrow().memory_size();
+    assert!(
+        memsz <= MEMORY_SIZE && memsz > MEMORY_SIZE - 16,
+        "{}",
+        memsz
+    );
+    let speech = synthesize(&mut create_engine_in(sys, "en-US", "lh0"), TEXT).unwrap();
+    assert_eq!(speech, expected);
+
+    let err = System::with_memory(Box::leak(vec![0; 1000].into_boxed_slice())).unwrap_err();
+    assert_eq!(err.code, native::PICO_EXC_OUT_OF_MEM);
+}
+
+#[cfg(not(feature = "system"))]
+#[test]
+fn resources_from_bytes() {
+    let expected = synthesize(&mut create_engine("en-US", "lh0"), TEXT).unwrap();
+
+    let sys = System::with_memory(Box::leak(vec![0; MEMORY_SIZE].into_boxed_slice())).unwrap();
+    let (ta_path, sg_path) = language_pack("en-US", "lh0");
+    let load = |name: &str, path: &str| {
+        System::load_resource_from_bytes(Rc::clone(&sys), name, &std::fs::read(path).unwrap())
+    };
+    let ta_res = load("en-US_ta.bin", &ta_path).unwrap();
+    let sg_res = load("en-US_lh0_sg.bin", &sg_path).unwrap();
+    assert_eq!(
+        load("en-US_ta.bin", &ta_path).unwrap_err().code,
+        native::PICO_WARN_RESOURCE_DOUBLE_LOAD
+    );
+
+    let voice = System::create_voice(sys, "BytesVoice").unwrap();
+    voice.borrow_mut().add_resource(ta_res).unwrap();
+    voice.borrow_mut().add_resource(sg_res).unwrap();
+    // SAFETY: both a TA and a SG resource were added to the voice above.
+    let mut engine = unsafe { ttspico::Voice::create_engine(voice).unwrap() };
+    assert_eq!(synthesize(&mut engine, TEXT).unwrap(), expected);
+}
@@ -0,0 +1,159 @@
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tests the files opened by Pico through a [`PicoFileSystem`]: resources, `<play>` clips and `<genfile>` outputs.
+
+#![cfg(all(feature = "std", not(feature = "system")))]
+
+use std::cell::RefCell;
+use std::io::Cursor;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use ttspico::{
+    Engine, FilePolicy, MemoryFileSystem, PicoError, PicoFile, PicoFileSystem, System, Voice,
+};
+use ttspico_sys as native;
+
+mod common;
+use common::{lang_file, synthesize, MEMORY_SIZE, SAMPLE_RATE, TEXT};
+
+const TA: &str = "en-US_ta.bin";
+const SG: &str = "en-US_lh0_sg.bin";
+
+/// A file system serving the bundled en-US voice under the names [`TA`] and [`SG`].
+fn voice_fs() -> MemoryFileSystem {
+    let fs = MemoryFileSystem::new();
+    for name in &[TA, SG] {
+        fs.insert(*name, std::fs::read(lang_file(name)).unwrap());
+    }
+    fs
+}
+
+/// Creates an engine for the voice of the resources named [`TA`] and [`SG`] in `sys`.
+fn create_engine(sys: Rc<RefCell<System>>) -> Result<Engine, PicoError> {
+    let ta_res = System::load_resource(Rc::clone(&sys), TA)?;
+    let sg_res = System::load_resource(Rc::clone(&sys), SG)?;
+    let voice = System::create_voice(sys, "TestVoice")?;
+    voice.borrow_mut().add_resource(ta_res)?;
+    voice.borrow_mut().add_resource(sg_res)?;
+    // SAFETY: both a TA and a SG resource were added to the voice above.
+    unsafe { Voice::create_engine(voice) }
+}
+
+/// Returns a WAV file of `len` samples of a sine wave.
+fn wav_clip(len: usize) -> Vec<u8> {
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: SAMPLE_RATE as u32,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut wav = Cursor::new(Vec::new());
+    let mut writer = hound::WavWriter::new(&mut wav, spec).unwrap();
+    for i in 0..len {
+        writer
+            .write_sample(((i as f64 * 0.1).sin() * 10000.0) as i16)
+            .unwrap();
+    }
+    writer.finalize().unwrap();
+    wav.into_inner()
+}
+
+#[test]
+fn resources() {
+    let sys = System::new(MEMORY_SIZE).unwrap();
+    sys.borrow_mut()
+        .set_file_system(Some(Box::new(voice_fs())))
+        .unwrap();
+    let mut engine = create_engine(Rc::clone(&sys)).unwrap();
+    let audio = synthesize(&mut engine, TEXT).unwrap();
+    assert!(audio.len() > SAMPLE_RATE / 2);
+
+    // (Resources stay open, and keep their file system, after it is replaced)
+    sys.borrow_mut().set_file_system(None).unwrap();
+    assert_eq!(synthesize(&mut engine, TEXT).unwrap(), audio);
+    drop(engine);
+
+    let err = System::load_resource(Rc::clone(&sys), TA).unwrap_err();
+    assert_eq!(err.code, native::PICO_EXC_CANT_OPEN_FILE);
+    sys.borrow_mut()
+        .set_file_system(Some(Box::new(MemoryFileSystem::new())))
+        .unwrap();
+    let err = System::load_resource(sys, lang_file(TA)).unwrap_err();
+    assert_eq!(err.code, native::PICO_EXC_CANT_OPEN_FILE);
+}
+
+#[test]
+fn play_and_genfile() {
+    let fs = voice_fs();
+    let sys = System::new(MEMORY_SIZE).unwrap();
+    sys.borrow_mut()
+        .set_file_system(Some(Box::new(fs.clone())))
+        .unwrap();
+    let mut engine = create_engine(Rc::clone(&sys)).unwrap();
+    engine.set_file_policy(FilePolicy::allow_all());
+    let plain = synthesize(&mut engine, TEXT).unwrap();
+
+    let clip_len = SAMPLE_RATE / 2;
+    fs.insert("clip.wav", wav_clip(clip_len));
+    let played = synthesize(&mut engine, &format!("{}<play file=\"clip.wav\"/>", TEXT)).unwrap();
+    assert!(played.len() >= plain.len() + clip_len);
+
+    // (Writes are denied unless the file system allows them)
+    let genfile = format!("<genfile file=\"out.wav\">{}</genfile>", TEXT);
+    let generated = synthesize(&mut engine, &genfile).unwrap();
+    assert!(!generated.is_empty());
+    assert_eq!(fs.get("out.wav"), None);
+
+    // (A file system set after the engine was created is used by the engine as well)
+    let fs = fs.writable(true);
+    sys.borrow_mut()
+        .set_file_system(Some(Box::new(fs.clone())))
+        .unwrap();
+    let generated = synthesize(&mut engine, &genfile).unwrap();
+    let out = hound::WavReader::new(Cursor::new(fs.get("out.wav").unwrap()))
+        .expect("<genfile> did not write a WAV file");
+    assert_eq!(out.spec().sample_rate, SAMPLE_RATE as u32);
+    let out_len = out.len() as usize;
+    assert!(out_len > 0 && (out_len as f64 - generated.len() as f64).abs() <= 0.1 * out_len as f64);
+}
+
+/// A file system that records the files it opens, serving them from another one.
+struct Recorder {
+    inner: MemoryFileSystem,
+    opened: Arc<Mutex<Vec<String>>>,
+}
+
+impl PicoFileSystem for Recorder {
+    fn open(&mut self, name: &str) -> Option<Box<dyn PicoFile>> {
+        self.opened.lock().unwrap().push(name.to_string());
+        self.inner.open(name)
+    }
+}
+
+#[test]
+fn custom_file_system() {
+    let opened = Arc::new(Mutex::new(Vec::new()));
+    let sys = System::new(MEMORY_SIZE).unwrap();
+    sys.borrow_mut()
+        .set_file_system(Some(Box::new(Recorder {
+            inner: voice_fs(),
+            opened: Arc::clone(&opened),
+        })))
+        .unwrap();
+    let mut engine = create_engine(Rc::clone(&sys)).unwrap();
+    engine.set_file_policy(FilePolicy::allow_all());
+    synthesize(&mut engine, "<play file=\"missing.wav\"/>").unwrap();
+    assert_eq!(*opened.lock().unwrap(), [TA, SG, "missing.wav"]);
+}
@@ -0,0 +1,47 @@
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tests the WAV headers of [`wav_header`].
+
+use std::io::Cursor;
+use ttspico::{wav_header, SAMPLE_RATE, WAV_HEADER_LEN};
+
+#[test]
+fn wav_file() {
+    let samples = [0i16, 1000, -1000, i16::MAX, i16::MIN];
+    let mut wav = wav_header(Some(samples.len())).to_vec();
+    wav.extend(samples.iter().flat_map(|sample| sample.to_le_bytes()));
+
+    let mut reader = hound::WavReader::new(Cursor::new(wav)).unwrap();
+    let spec = reader.spec();
+    assert_eq!(spec.channels, 1);
+    assert_eq!(spec.sample_rate, SAMPLE_RATE);
+    assert_eq!(spec.bits_per_sample, 16);
+    assert_eq!(spec.sample_format, hound::SampleFormat::Int);
+    let read: Vec<i16> = reader.samples().map(Result::unwrap).collect();
+    assert_eq!(read, samples);
+}
+
+#[test]
+fn wav_stream() {
+    let header = wav_header(None);
+    assert_eq!(header.len(), WAV_HEADER_LEN);
+    assert_eq!(header[4..8], u32::MAX.to_le_bytes());
+    assert_eq!(header[40..44], u32::MAX.to_le_bytes());
+    // (Only the chunk sizes differ from the header of a file)
+    let mut file_header = wav_header(Some(0));
+    file_header[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+    file_header[40..44].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert_eq!(header, file_header);
+}
@@ -0,0 +1,87 @@
+// Copyright (c) 2019 Paolo Jovon <paolo.jovon@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tests [`mark_words`], and that the speech reaches its marks in order.
+
+use ttspico::{mark_words, word_index, Word};
+
+mod common;
+
+/// Returns the words of `text` found by [`mark_words`].
+fn words(text: &str) -> Vec<&str> {
+    mark_words(text)
+        .1
+        .iter()
+        .map(|word| &text[word.offset..word.offset + word.length])
+        .collect()
+}
+
+#[test]
+fn marked() {
+    assert_eq!(
+        words("\"Hello,\" she said -- twice...  Ça va?"),
+        ["Hello", "she", "said", "twice", "Ça", "va"]
+    );
+    // (Tags are not words, even with `>` in their attributes, and entities around words are trimmed)
+    assert_eq!(
+        words("<mark name=\"a>b\"/>&quot;Hi&quot;<break time='1s'/>there&amp;"),
+        ["Hi", "there"]
+    );
+    assert_eq!(
+        words(" ... <speed level=\"80\"> </speed>"),
+        Vec::<&str>::new()
+    );
+
+    let (marked, words) = mark_words("Hi, you");
+    assert_eq!(
+        marked,
+        "<mark name=\"ttspico-word-0\"/>Hi, <mark name=\"ttspico-word-1\"/>you"
+    );
+    assert_eq!(
+        words[1],
+        Word {
+            offset: 4,
+            length: 3
+        }
+    );
+    assert_eq!(word_index("ttspico-word-1"), Some(1));
+    assert_eq!(word_index("ttspico-word-x"), None);
+    assert_eq!(word_index("chapter-1"), None);
+}
+
+#[cfg(not(feature = "system"))]
+#[test]
+fn spoken() {
+    let mut engine = common::create_engine("en-US", "lh0");
+    let (marked, words) = mark_words("The quick, <mark name=\"fox\"/>brown fox.");
+    let samples = common::synthesize(&mut engine, &marked).unwrap();
+    let marks = engine.take_marks();
+    let names: Vec<&str> = marks.iter().map(|mark| mark.name.as_str()).collect();
+    assert_eq!(
+        names,
+        [
+            "ttspico-word-0",
+            "ttspico-word-1",
+            "fox",
+            "ttspico-word-2",
+            "ttspico-word-3"
+        ]
+    );
+    assert_eq!(words.len(), 4);
+    assert!(marks
+        .windows(2)
+        .all(|pair| pair[0].sample <= pair[1].sample));
+    assert!(marks[1].sample > marks[0].sample);
+    assert!(marks[4].sample < samples.len());
+}